@@ -4,7 +4,12 @@ extern crate criterion;
 // use dlt::dlt::*;
 use bytes::BytesMut;
 use criterion::Criterion;
-use dlt_core::{dlt::DltTimeStamp, parse::dlt_zero_terminated_string};
+use dlt_core::{
+    dlt::DltTimeStamp,
+    fibex::{extract_metadata, gather_fibex_data, FibexConfig},
+    filtering::IdMatcher,
+    parse::dlt_zero_terminated_string,
+};
 
 fn dlt_benchmark(c: &mut Criterion) {
     c.bench_function("format header", |b| {
@@ -73,5 +78,33 @@ fn dlt_parse_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, dlt_benchmark, dlt_parse_benchmark);
+fn dlt_filter_benchmark(c: &mut Criterion) {
+    c.bench_function("id matcher exact ids", |b| {
+        let matcher: IdMatcher = (0..64).map(|i| format!("A{:03}", i)).collect();
+        let ids = ["A000", "A031", "A063", "B000", "APP"];
+        b.iter(|| ids.iter().filter(|id| matcher.matches(id)).count())
+    });
+}
+
+fn dlt_fibex_benchmark(c: &mut Criterion) {
+    c.bench_function("fibex frame lookup", |b| {
+        let fibex = gather_fibex_data(FibexConfig {
+            fibex_file_paths: vec![format!(
+                "{}/tests/dlt-messages.xml",
+                env!("CARGO_MANIFEST_DIR")
+            )],
+            namespace_by_ecu: false,
+        })
+        .expect("fibex");
+        b.iter(|| extract_metadata(&fibex, 65, None).is_some())
+    });
+}
+
+criterion_group!(
+    benches,
+    dlt_benchmark,
+    dlt_parse_benchmark,
+    dlt_filter_benchmark,
+    dlt_fibex_benchmark
+);
 criterion_main!(benches);
@@ -0,0 +1,986 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Control messages
+//!
+//! Control messages configure the logging of an ECU. The payload of a
+//! request starts with the 32 bit service id followed by the parameters of
+//! the service, a response additionally carries a status byte after the
+//! service id. Numbers use the byte order of the message.
+//!
+//! `control` decodes these payloads into typed requests and responses.
+use crate::{
+    dlt::{ControlType, Endianness, LogLevel, Message, MessageType, PayloadContent},
+    service_id::ServiceId,
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use bytes::Bytes;
+use std::fmt;
+use thiserror::Error;
+
+/// Errors decoding a control message
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The payload ends before the parameters of the service
+    #[error("Not enough data for {what}: needed {needed} bytes, got {available}")]
+    Incomplete {
+        what: &'static str,
+        needed: usize,
+        available: usize,
+    },
+    /// The message type is neither request nor response
+    #[error("Unknown control type: {0}")]
+    UnknownControlType(u8),
+}
+
+pub const SET_LOG_LEVEL: u32 = 0x01;
+pub const SET_TRACE_STATUS: u32 = 0x02;
+pub const GET_LOG_INFO: u32 = 0x03;
+pub const GET_DEFAULT_LOG_LEVEL: u32 = 0x04;
+pub const STORE_CONFIGURATION: u32 = 0x05;
+pub const RESTORE_TO_FACTORY_DEFAULT: u32 = 0x06;
+pub const SET_MESSAGE_FILTERING: u32 = 0x0A;
+pub const SET_DEFAULT_LOG_LEVEL: u32 = 0x11;
+pub const SET_DEFAULT_TRACE_STATUS: u32 = 0x12;
+pub const GET_SOFTWARE_VERSION: u32 = 0x13;
+pub const GET_DEFAULT_TRACE_STATUS: u32 = 0x15;
+pub const GET_LOG_CHANNEL_NAMES: u32 = 0x17;
+pub const GET_MESSAGE_FILTERING_STATUS: u32 = 0x1A;
+pub const GET_TRACE_STATUS: u32 = 0x1F;
+pub const SET_LOG_CHANNEL_ASSIGNMENT: u32 = 0x20;
+pub const SET_LOG_CHANNEL_THRESHOLD: u32 = 0x21;
+pub const GET_LOG_CHANNEL_THRESHOLD: u32 = 0x22;
+pub const BUFFER_OVERFLOW_NOTIFICATION: u32 = 0x23;
+
+/// A log level as configured by control messages
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlLogLevel {
+    /// Use the default log level (-1)
+    Default,
+    /// Logging is disabled (0)
+    Off,
+    Level(LogLevel),
+}
+
+impl From<u8> for ControlLogLevel {
+    fn from(value: u8) -> Self {
+        match value {
+            0xFF => Self::Default,
+            0x00 => Self::Off,
+            level => Self::Level(LogLevel::from_value(level)),
+        }
+    }
+}
+
+impl From<ControlLogLevel> for u8 {
+    fn from(level: ControlLogLevel) -> Self {
+        match level {
+            ControlLogLevel::Default => 0xFF,
+            ControlLogLevel::Off => 0x00,
+            ControlLogLevel::Level(level) => level.value(),
+        }
+    }
+}
+
+/// Whether trace messages are enabled
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceStatus {
+    /// Use the default trace status (-1)
+    Default,
+    Off,
+    On,
+    Invalid(u8),
+}
+
+impl From<u8> for TraceStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0xFF => Self::Default,
+            0x00 => Self::Off,
+            0x01 => Self::On,
+            other => Self::Invalid(other),
+        }
+    }
+}
+
+impl From<TraceStatus> for u8 {
+    fn from(status: TraceStatus) -> Self {
+        match status {
+            TraceStatus::Default => 0xFF,
+            TraceStatus::Off => 0x00,
+            TraceStatus::On => 0x01,
+            TraceStatus::Invalid(other) => other,
+        }
+    }
+}
+
+/// A control request with the parameters of its service
+///
+/// Ids are 4 characters, trailing zeros are removed.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlRequest {
+    SetLogLevel {
+        application_id: String,
+        context_id: String,
+        log_level: ControlLogLevel,
+        com_interface: String,
+    },
+    SetTraceStatus {
+        application_id: String,
+        context_id: String,
+        trace_status: TraceStatus,
+        com_interface: String,
+    },
+    GetLogInfo {
+        options: u8,
+        application_id: String,
+        context_id: String,
+        com_interface: String,
+    },
+    GetDefaultLogLevel,
+    StoreConfiguration,
+    RestoreToFactoryDefault,
+    SetMessageFiltering {
+        enabled: bool,
+    },
+    SetDefaultLogLevel {
+        log_level: ControlLogLevel,
+        com_interface: String,
+    },
+    SetDefaultTraceStatus {
+        trace_status: TraceStatus,
+        com_interface: String,
+    },
+    GetSoftwareVersion,
+    GetDefaultTraceStatus,
+    GetLogChannelNames,
+    GetMessageFilteringStatus,
+    GetTraceStatus {
+        application_id: String,
+        context_id: String,
+    },
+    SetLogChannelAssignment {
+        application_id: String,
+        context_id: String,
+        log_channel: String,
+        /// Add (1) or remove (0) the log channel
+        add: bool,
+    },
+    SetLogChannelThreshold {
+        log_channel: String,
+        log_level: ControlLogLevel,
+        trace_status: TraceStatus,
+    },
+    GetLogChannelThreshold {
+        log_channel: String,
+    },
+    /// Requests of other services with their raw parameters
+    Other {
+        service_id: ServiceId,
+        parameters: Vec<u8>,
+    },
+}
+
+/// The service specific content of a control response
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseContent {
+    /// Responses that only carry the status
+    None,
+    LogLevel(ControlLogLevel),
+    TraceStatus(TraceStatus),
+    MessageFiltering(bool),
+    LogChannelNames(Vec<String>),
+    LogChannelThreshold {
+        log_level: ControlLogLevel,
+        trace_status: TraceStatus,
+    },
+    BufferOverflow {
+        counter: u32,
+    },
+    LogInfo(LogInfo),
+    /// The version string of `get_software_version`
+    SoftwareVersion(String),
+    /// Responses of other services with their raw parameters
+    Other(Vec<u8>),
+}
+
+/// The status of a control response
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseStatus {
+    Ok,
+    NotSupported,
+    Error,
+    PermissionDenied,
+    Warning,
+    /// Successful `get_log_info` response with the included fields, see `LOG_INFO_STATUS`
+    LogInfo(u8),
+    /// `get_log_info` found no matching contexts
+    NoMatchingContexts,
+    /// `get_log_info` response would exceed the maximum message size
+    Overflow,
+    Other(u8),
+}
+
+impl ResponseStatus {
+    /// Decode the status byte, some services define their own codes
+    pub fn new(service_id: u32, value: u8) -> Self {
+        match (service_id, value) {
+            (GET_LOG_INFO, status) if LOG_INFO_STATUS.contains(&status) => Self::LogInfo(status),
+            (GET_LOG_INFO, 8) => Self::NoMatchingContexts,
+            (GET_LOG_INFO, 9) => Self::Overflow,
+            (_, 0) => Self::Ok,
+            (_, 1) => Self::NotSupported,
+            (_, 2) => Self::Error,
+            (_, 3) => Self::PermissionDenied,
+            (_, 4) => Self::Warning,
+            (_, other) => Self::Other(other),
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        match *self {
+            Self::Ok => 0,
+            Self::NotSupported => 1,
+            Self::Error => 2,
+            Self::PermissionDenied => 3,
+            Self::Warning => 4,
+            Self::NoMatchingContexts => 8,
+            Self::Overflow => 9,
+            Self::LogInfo(status) | Self::Other(status) => status,
+        }
+    }
+
+    /// Whether the request succeeded and the response carries its content
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok | Self::LogInfo(_))
+    }
+}
+
+/// Displays the status like dlt-viewer (e.g. `not_supported`), codes
+/// without a name as hex value (e.g. `07`)
+impl fmt::Display for ResponseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => f.write_str("ok"),
+            Self::NotSupported => f.write_str("not_supported"),
+            Self::Error => f.write_str("error"),
+            Self::PermissionDenied => f.write_str("perm_denied"),
+            Self::Warning => f.write_str("warning"),
+            Self::NoMatchingContexts => f.write_str("no_matching_contexts"),
+            Self::Overflow => f.write_str("overflow"),
+            Self::LogInfo(status) | Self::Other(status) => write!(f, "{:02x}", status),
+        }
+    }
+}
+
+/// The status values of `get_log_info` responses that carry a `LogInfo`
+///
+/// The status tells which fields are included: 3 only has the ids, 4 adds log
+/// levels, 5 trace statuses, 6 both and 7 also the descriptions.
+pub const LOG_INFO_STATUS: std::ops::RangeInclusive<u8> = 3..=7;
+
+/// The registered applications and contexts reported by `get_log_info`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogInfo {
+    pub applications: Vec<ApplicationLogInfo>,
+    pub com_interface: String,
+}
+
+/// An application of a `LogInfo`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationLogInfo {
+    pub application_id: String,
+    pub contexts: Vec<ContextLogInfo>,
+    pub description: Option<String>,
+}
+
+/// A context of an `ApplicationLogInfo`, fields are `None` if the status excludes them
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextLogInfo {
+    pub context_id: String,
+    pub log_level: Option<ControlLogLevel>,
+    pub trace_status: Option<TraceStatus>,
+    pub description: Option<String>,
+}
+
+impl LogInfo {
+    fn parse(status: u8, p: &mut Parameters) -> Result<LogInfo, Error> {
+        let with_log_level = matches!(status, 4 | 6 | 7);
+        let with_trace_status = matches!(status, 5..=7);
+        let with_description = status == 7;
+        let application_count = p.u16()?;
+        let mut applications = Vec::with_capacity(application_count as usize);
+        for _ in 0..application_count {
+            let application_id = p.id()?;
+            let context_count = p.u16()?;
+            let mut contexts = Vec::with_capacity(context_count as usize);
+            for _ in 0..context_count {
+                contexts.push(ContextLogInfo {
+                    context_id: p.id()?,
+                    log_level: with_log_level.then(|| p.u8().map(Into::into)).transpose()?,
+                    trace_status: with_trace_status
+                        .then(|| p.u8().map(Into::into))
+                        .transpose()?,
+                    description: with_description.then(|| p.description()).transpose()?,
+                });
+            }
+            applications.push(ApplicationLogInfo {
+                application_id,
+                contexts,
+                description: with_description.then(|| p.description()).transpose()?,
+            });
+        }
+        Ok(LogInfo {
+            applications,
+            com_interface: p.id()?,
+        })
+    }
+
+    /// Write the fields that are included with `status`, see `LOG_INFO_STATUS`
+    fn write(&self, status: u8, w: &mut Writer) {
+        w.u16(self.applications.len() as u16);
+        for application in &self.applications {
+            w.id(&application.application_id);
+            w.u16(application.contexts.len() as u16);
+            for context in &application.contexts {
+                w.id(&context.context_id);
+                if matches!(status, 4 | 6 | 7) {
+                    w.u8(context.log_level.map(u8::from).unwrap_or_default());
+                }
+                if matches!(status, 5..=7) {
+                    w.u8(context.trace_status.map(u8::from).unwrap_or_default());
+                }
+                if status == 7 {
+                    w.description(context.description.as_deref().unwrap_or_default());
+                }
+            }
+            if status == 7 {
+                w.description(application.description.as_deref().unwrap_or_default());
+            }
+        }
+        w.id(&self.com_interface);
+    }
+}
+
+/// A control response
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlResponse {
+    pub service_id: ServiceId,
+    pub status: ResponseStatus,
+    pub content: ResponseContent,
+}
+
+/// The decoded payload of a control message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlPayload {
+    Request(ControlRequest),
+    Response(ControlResponse),
+}
+
+/// The control type and raw payload of a control message
+fn raw_payload(message: &Message) -> Option<(&ControlType, Vec<u8>)> {
+    let control_type = match &message.extended_header.as_ref()?.message_type {
+        MessageType::Control(control_type) => control_type,
+        _ => return None,
+    };
+    match &message.payload {
+        // the parser splits off the first byte of the service id
+        PayloadContent::ControlMsg(first, rest) => {
+            Some((control_type, [&[first.value()], &rest[..]].concat()))
+        }
+        _ => None,
+    }
+}
+
+/// The text of a control message as dlt-viewer displays it
+///
+/// The service name (or id) and the status of responses are put in brackets,
+/// followed by the remaining parameters as hex bytes, e.g.
+/// `[set_log_level ok]` or `[get_log_info] 07 41 50 50 00 ...`. Software
+/// versions are displayed as text. `None` if the message is no control message.
+pub fn control_text(message: &Message) -> Option<String> {
+    let (control_type, payload) = raw_payload(message)?;
+    let decoded = ControlPayload::parse(control_type, &payload, message.header.endianness);
+    let service_id = match &decoded {
+        Ok(decoded) => decoded.service_id(),
+        Err(_) => {
+            let mut p = Parameters {
+                input: &payload,
+                endianness: message.header.endianness,
+                what: "service id",
+            };
+            match p.u32() {
+                Ok(service_id) => ServiceId::from(service_id),
+                Err(_) => return Some(format!("[] {}", hex(&payload))),
+            }
+        }
+    };
+    let (mut text, parameters) = match (control_type, payload.get(4)) {
+        (ControlType::Response, Some(status)) => (
+            format!(
+                "[{} {}]",
+                service_id,
+                ResponseStatus::new(service_id.value(), *status)
+            ),
+            &payload[5..],
+        ),
+        _ => (format!("[{}]", service_id), &payload[4..]),
+    };
+    if let Ok(ControlPayload::Response(ControlResponse {
+        content: ResponseContent::SoftwareVersion(version),
+        ..
+    })) = &decoded
+    {
+        text.push(' ');
+        text.push_str(version);
+    } else if !parameters.is_empty() {
+        text.push(' ');
+        text.push_str(&hex(parameters));
+    }
+    Some(text)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads the parameters of a control payload
+struct Parameters<'a> {
+    input: &'a [u8],
+    endianness: Endianness,
+    what: &'static str,
+}
+
+impl<'a> Parameters<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if self.input.len() < length {
+            return Err(Error::Incomplete {
+                what: self.what,
+                needed: length,
+                available: self.input.len(),
+            });
+        }
+        let (taken, rest) = self.input.split_at(length);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(match self.endianness {
+            Endianness::Big => BigEndian::read_u16(bytes),
+            Endianness::Little => LittleEndian::read_u16(bytes),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.take(4)?;
+        Ok(match self.endianness {
+            Endianness::Big => BigEndian::read_u32(bytes),
+            Endianness::Little => LittleEndian::read_u32(bytes),
+        })
+    }
+
+    /// A 4 character id without trailing zeros
+    fn id(&mut self) -> Result<String, Error> {
+        let bytes = self.take(4)?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// A string with a 16 bit length prefix
+    fn description(&mut self) -> Result<String, Error> {
+        let length = self.u16()?;
+        let bytes = self.take(length as usize)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn rest(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.input).to_vec()
+    }
+}
+
+/// Writes the parameters of a control payload
+struct Writer {
+    buf: Vec<u8>,
+    endianness: Endianness,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        match self.endianness {
+            Endianness::Big => self.buf.extend_from_slice(&value.to_be_bytes()),
+            Endianness::Little => self.buf.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        match self.endianness {
+            Endianness::Big => self.buf.extend_from_slice(&value.to_be_bytes()),
+            Endianness::Little => self.buf.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    /// A string with a 16 bit length prefix
+    fn description(&mut self, description: &str) {
+        self.u16(description.len() as u16);
+        self.buf.extend_from_slice(description.as_bytes());
+    }
+
+    fn id(&mut self, id: &str) {
+        let mut bytes = [0u8; 4];
+        let length = id.len().min(4);
+        bytes[..length].copy_from_slice(&id.as_bytes()[..length]);
+        self.buf.extend_from_slice(&bytes);
+    }
+}
+
+impl ControlRequest {
+    /// The service id of the request
+    pub fn service_id(&self) -> ServiceId {
+        ServiceId::from(match self {
+            ControlRequest::SetLogLevel { .. } => SET_LOG_LEVEL,
+            ControlRequest::SetTraceStatus { .. } => SET_TRACE_STATUS,
+            ControlRequest::GetLogInfo { .. } => GET_LOG_INFO,
+            ControlRequest::GetDefaultLogLevel => GET_DEFAULT_LOG_LEVEL,
+            ControlRequest::StoreConfiguration => STORE_CONFIGURATION,
+            ControlRequest::RestoreToFactoryDefault => RESTORE_TO_FACTORY_DEFAULT,
+            ControlRequest::SetMessageFiltering { .. } => SET_MESSAGE_FILTERING,
+            ControlRequest::SetDefaultLogLevel { .. } => SET_DEFAULT_LOG_LEVEL,
+            ControlRequest::SetDefaultTraceStatus { .. } => SET_DEFAULT_TRACE_STATUS,
+            ControlRequest::GetSoftwareVersion => GET_SOFTWARE_VERSION,
+            ControlRequest::GetDefaultTraceStatus => GET_DEFAULT_TRACE_STATUS,
+            ControlRequest::GetLogChannelNames => GET_LOG_CHANNEL_NAMES,
+            ControlRequest::GetMessageFilteringStatus => GET_MESSAGE_FILTERING_STATUS,
+            ControlRequest::GetTraceStatus { .. } => GET_TRACE_STATUS,
+            ControlRequest::SetLogChannelAssignment { .. } => SET_LOG_CHANNEL_ASSIGNMENT,
+            ControlRequest::SetLogChannelThreshold { .. } => SET_LOG_CHANNEL_THRESHOLD,
+            ControlRequest::GetLogChannelThreshold { .. } => GET_LOG_CHANNEL_THRESHOLD,
+            ControlRequest::Other { service_id, .. } => service_id.value(),
+        })
+    }
+
+    /// The raw payload of the request, starting with the service id
+    ///
+    /// Ids are padded with zeros to 4 bytes, longer ids are truncated.
+    pub fn as_bytes(&self, endianness: Endianness) -> Vec<u8> {
+        let mut w = Writer {
+            buf: Vec::with_capacity(20),
+            endianness,
+        };
+        w.u32(self.service_id().value());
+        match self {
+            ControlRequest::SetLogLevel {
+                application_id,
+                context_id,
+                log_level,
+                com_interface,
+            } => {
+                w.id(application_id);
+                w.id(context_id);
+                w.u8((*log_level).into());
+                w.id(com_interface);
+            }
+            ControlRequest::SetTraceStatus {
+                application_id,
+                context_id,
+                trace_status,
+                com_interface,
+            } => {
+                w.id(application_id);
+                w.id(context_id);
+                w.u8((*trace_status).into());
+                w.id(com_interface);
+            }
+            ControlRequest::GetLogInfo {
+                options,
+                application_id,
+                context_id,
+                com_interface,
+            } => {
+                w.u8(*options);
+                w.id(application_id);
+                w.id(context_id);
+                w.id(com_interface);
+            }
+            ControlRequest::SetMessageFiltering { enabled } => w.u8(*enabled as u8),
+            ControlRequest::SetDefaultLogLevel {
+                log_level,
+                com_interface,
+            } => {
+                w.u8((*log_level).into());
+                w.id(com_interface);
+            }
+            ControlRequest::SetDefaultTraceStatus {
+                trace_status,
+                com_interface,
+            } => {
+                w.u8((*trace_status).into());
+                w.id(com_interface);
+            }
+            ControlRequest::GetTraceStatus {
+                application_id,
+                context_id,
+            } => {
+                w.id(application_id);
+                w.id(context_id);
+            }
+            ControlRequest::SetLogChannelAssignment {
+                application_id,
+                context_id,
+                log_channel,
+                add,
+            } => {
+                w.id(application_id);
+                w.id(context_id);
+                w.id(log_channel);
+                w.u8(*add as u8);
+            }
+            ControlRequest::SetLogChannelThreshold {
+                log_channel,
+                log_level,
+                trace_status,
+            } => {
+                w.id(log_channel);
+                w.u8((*log_level).into());
+                w.u8((*trace_status).into());
+            }
+            ControlRequest::GetLogChannelThreshold { log_channel } => w.id(log_channel),
+            ControlRequest::Other { parameters, .. } => w.buf.extend_from_slice(parameters),
+            ControlRequest::GetDefaultLogLevel
+            | ControlRequest::StoreConfiguration
+            | ControlRequest::RestoreToFactoryDefault
+            | ControlRequest::GetSoftwareVersion
+            | ControlRequest::GetDefaultTraceStatus
+            | ControlRequest::GetLogChannelNames
+            | ControlRequest::GetMessageFilteringStatus => {}
+        }
+        w.buf
+    }
+
+    /// The payload of a message that sends the request, see `Message::new`
+    pub fn payload(&self, endianness: Endianness) -> PayloadContent {
+        let bytes = self.as_bytes(endianness);
+        // the parser splits off the first byte of the service id
+        PayloadContent::ControlMsg(
+            ControlType::from_value(bytes[0]),
+            Bytes::copy_from_slice(&bytes[1..]),
+        )
+    }
+
+    fn parse(service_id: u32, p: &mut Parameters) -> Result<ControlRequest, Error> {
+        Ok(match service_id {
+            SET_LOG_LEVEL => ControlRequest::SetLogLevel {
+                application_id: p.id()?,
+                context_id: p.id()?,
+                log_level: p.u8()?.into(),
+                com_interface: p.id()?,
+            },
+            SET_TRACE_STATUS => ControlRequest::SetTraceStatus {
+                application_id: p.id()?,
+                context_id: p.id()?,
+                trace_status: p.u8()?.into(),
+                com_interface: p.id()?,
+            },
+            GET_LOG_INFO => ControlRequest::GetLogInfo {
+                options: p.u8()?,
+                application_id: p.id()?,
+                context_id: p.id()?,
+                com_interface: p.id()?,
+            },
+            GET_DEFAULT_LOG_LEVEL => ControlRequest::GetDefaultLogLevel,
+            STORE_CONFIGURATION => ControlRequest::StoreConfiguration,
+            RESTORE_TO_FACTORY_DEFAULT => ControlRequest::RestoreToFactoryDefault,
+            SET_MESSAGE_FILTERING => ControlRequest::SetMessageFiltering {
+                enabled: p.u8()? != 0,
+            },
+            SET_DEFAULT_LOG_LEVEL => ControlRequest::SetDefaultLogLevel {
+                log_level: p.u8()?.into(),
+                com_interface: p.id()?,
+            },
+            SET_DEFAULT_TRACE_STATUS => ControlRequest::SetDefaultTraceStatus {
+                trace_status: p.u8()?.into(),
+                com_interface: p.id()?,
+            },
+            GET_SOFTWARE_VERSION => ControlRequest::GetSoftwareVersion,
+            GET_DEFAULT_TRACE_STATUS => ControlRequest::GetDefaultTraceStatus,
+            GET_LOG_CHANNEL_NAMES => ControlRequest::GetLogChannelNames,
+            GET_MESSAGE_FILTERING_STATUS => ControlRequest::GetMessageFilteringStatus,
+            GET_TRACE_STATUS => ControlRequest::GetTraceStatus {
+                application_id: p.id()?,
+                context_id: p.id()?,
+            },
+            SET_LOG_CHANNEL_ASSIGNMENT => ControlRequest::SetLogChannelAssignment {
+                application_id: p.id()?,
+                context_id: p.id()?,
+                log_channel: p.id()?,
+                add: p.u8()? != 0,
+            },
+            SET_LOG_CHANNEL_THRESHOLD => ControlRequest::SetLogChannelThreshold {
+                log_channel: p.id()?,
+                log_level: p.u8()?.into(),
+                trace_status: p.u8()?.into(),
+            },
+            GET_LOG_CHANNEL_THRESHOLD => ControlRequest::GetLogChannelThreshold {
+                log_channel: p.id()?,
+            },
+            _ => ControlRequest::Other {
+                service_id: service_id.into(),
+                parameters: p.rest(),
+            },
+        })
+    }
+}
+
+impl ControlResponse {
+    /// The raw payload of the response, starting with the service id
+    ///
+    /// Ids are padded with zeros to 4 bytes, longer ids are truncated.
+    pub fn as_bytes(&self, endianness: Endianness) -> Vec<u8> {
+        let mut w = Writer {
+            buf: Vec::with_capacity(20),
+            endianness,
+        };
+        w.u32(self.service_id.value());
+        w.u8(self.status.value());
+        match &self.content {
+            ResponseContent::None => {}
+            ResponseContent::LogLevel(log_level) => w.u8((*log_level).into()),
+            ResponseContent::TraceStatus(trace_status) => w.u8((*trace_status).into()),
+            ResponseContent::MessageFiltering(enabled) => w.u8(*enabled as u8),
+            ResponseContent::LogChannelNames(names) => {
+                w.u8(names.len() as u8);
+                names.iter().for_each(|name| w.id(name));
+            }
+            ResponseContent::LogChannelThreshold {
+                log_level,
+                trace_status,
+            } => {
+                w.u8((*log_level).into());
+                w.u8((*trace_status).into());
+            }
+            ResponseContent::BufferOverflow { counter } => w.u32(*counter),
+            ResponseContent::LogInfo(log_info) => log_info.write(self.status.value(), &mut w),
+            ResponseContent::SoftwareVersion(version) => {
+                w.u32(version.len() as u32);
+                w.buf.extend_from_slice(version.as_bytes());
+            }
+            ResponseContent::Other(parameters) => w.buf.extend_from_slice(parameters),
+        }
+        w.buf
+    }
+}
+
+impl ResponseContent {
+    fn parse(
+        service_id: u32,
+        status: ResponseStatus,
+        p: &mut Parameters,
+    ) -> Result<ResponseContent, Error> {
+        match status {
+            ResponseStatus::LogInfo(status) => {
+                return Ok(ResponseContent::LogInfo(LogInfo::parse(status, p)?))
+            }
+            ResponseStatus::Ok => {}
+            _ => return Ok(ResponseContent::None),
+        }
+        Ok(match service_id {
+            SET_LOG_LEVEL
+            | SET_TRACE_STATUS
+            | STORE_CONFIGURATION
+            | RESTORE_TO_FACTORY_DEFAULT
+            | SET_MESSAGE_FILTERING
+            | SET_DEFAULT_LOG_LEVEL
+            | SET_DEFAULT_TRACE_STATUS
+            | SET_LOG_CHANNEL_ASSIGNMENT
+            | SET_LOG_CHANNEL_THRESHOLD
+            | GET_LOG_INFO => ResponseContent::None,
+            GET_DEFAULT_LOG_LEVEL => ResponseContent::LogLevel(p.u8()?.into()),
+            GET_DEFAULT_TRACE_STATUS | GET_TRACE_STATUS => {
+                ResponseContent::TraceStatus(p.u8()?.into())
+            }
+            GET_MESSAGE_FILTERING_STATUS => ResponseContent::MessageFiltering(p.u8()? != 0),
+            GET_LOG_CHANNEL_NAMES => {
+                let count = p.u8()?;
+                let names = (0..count).map(|_| p.id()).collect::<Result<_, _>>()?;
+                ResponseContent::LogChannelNames(names)
+            }
+            GET_LOG_CHANNEL_THRESHOLD => ResponseContent::LogChannelThreshold {
+                log_level: p.u8()?.into(),
+                trace_status: p.u8()?.into(),
+            },
+            BUFFER_OVERFLOW_NOTIFICATION => ResponseContent::BufferOverflow { counter: p.u32()? },
+            GET_SOFTWARE_VERSION => {
+                let length = p.u32()?;
+                let version = p.take(length as usize)?;
+                // some ECUs include the terminating zero in the length
+                let end = version
+                    .iter()
+                    .position(|b| *b == 0)
+                    .unwrap_or(version.len());
+                ResponseContent::SoftwareVersion(
+                    String::from_utf8_lossy(&version[..end]).into_owned(),
+                )
+            }
+            _ => ResponseContent::Other(p.rest()),
+        })
+    }
+}
+
+/// Convert a raw control payload from one byte order to another
+///
+/// Bytes after the decoded parameters are kept as they are. A payload that
+/// can't be decoded, or not be encoded again without loss, only gets its
+/// service id swapped.
+pub(crate) fn convert_payload(
+    control_type: &ControlType,
+    payload: &[u8],
+    from: Endianness,
+    to: Endianness,
+) -> Vec<u8> {
+    if let Ok(decoded) = ControlPayload::parse(control_type, payload, from) {
+        let original = decoded.as_bytes(from);
+        if let Some(rest) = payload.strip_prefix(original.as_slice()) {
+            return [decoded.as_bytes(to), rest.to_vec()].concat();
+        }
+    }
+    let mut converted = payload.to_vec();
+    if let Some(service_id) = converted.get_mut(..4) {
+        service_id.reverse();
+    }
+    converted
+}
+
+impl ControlPayload {
+    /// Decode the raw payload of a control message, starting with the service id
+    ///
+    /// Parameters of a response are only decoded if its status is ok,
+    /// otherwise the content is `ResponseContent::None`.
+    pub fn parse(
+        control_type: &ControlType,
+        payload: &[u8],
+        endianness: Endianness,
+    ) -> Result<ControlPayload, Error> {
+        let mut p = Parameters {
+            input: payload,
+            endianness,
+            what: "service id",
+        };
+        let service_id = p.u32()?;
+        p.what = "control parameters";
+        match control_type {
+            ControlType::Request => Ok(ControlPayload::Request(ControlRequest::parse(
+                service_id, &mut p,
+            )?)),
+            ControlType::Response => {
+                let status = ResponseStatus::new(service_id, p.u8()?);
+                let content = ResponseContent::parse(service_id, status, &mut p)?;
+                Ok(ControlPayload::Response(ControlResponse {
+                    service_id: service_id.into(),
+                    status,
+                    content,
+                }))
+            }
+            ControlType::Unknown(n) => Err(Error::UnknownControlType(*n)),
+        }
+    }
+
+    /// Decode the payload of a message, `Ok(None)` if it is no control message
+    pub fn decode(message: &Message) -> Result<Option<ControlPayload>, Error> {
+        match raw_payload(message) {
+            Some((control_type, payload)) => {
+                Self::parse(control_type, &payload, message.header.endianness).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn service_id(&self) -> ServiceId {
+        match self {
+            ControlPayload::Request(request) => request.service_id(),
+            ControlPayload::Response(response) => response.service_id,
+        }
+    }
+
+    /// The raw payload, starting with the service id
+    pub fn as_bytes(&self, endianness: Endianness) -> Vec<u8> {
+        match self {
+            ControlPayload::Request(request) => request.as_bytes(endianness),
+            ControlPayload::Response(response) => response.as_bytes(endianness),
+        }
+    }
+
+    /// The version string if this is a successful `get_software_version` response
+    pub fn software_version(&self) -> Option<&str> {
+        match self {
+            ControlPayload::Response(ControlResponse {
+                content: ResponseContent::SoftwareVersion(version),
+                ..
+            }) => Some(version),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,638 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Support for dlt-viewer filter files
+//!
+//! `dlf` reads and writes the XML filter files (`.dlf`) of the dlt-viewer
+//! and converts them from and to a `DltFilterConfig`.
+//!
+//! A filter file contains a list of filters, a message is selected if it matches
+//! any of the (enabled, positive) filters. Within one filter all enabled
+//! criteria have to match.
+use crate::{
+    dlt::{LogLevel, Message, MessageType},
+    filtering::{header_text, DltFilterConfig},
+};
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event as XmlEvent},
+    Reader as XmlReader, Writer as XmlWriter,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+use thiserror::Error;
+
+/// dlt-viewer filter file related error types
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Problems parsing the filter file
+    #[error("Problems parsing: {0}")]
+    Parse(String),
+    /// The filter configuration can't be expressed with dlt-viewer filters
+    #[error("Unsupported filter: {0}")]
+    Unsupported(String),
+    /// Reading or writing the xml failed
+    #[error("XML error: {0:?}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("IO error: {0:?}")]
+    Io(#[from] std::io::Error),
+}
+
+const FILTER_TYPE_POSITIVE: u8 = 0;
+const FILTER_TYPE_NEGATIVE: u8 = 1;
+const FILTER_TYPE_MARKER: u8 = 2;
+
+/// The kind of a dlt-viewer filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DlfFilterType {
+    /// select matching messages
+    #[default]
+    Positive,
+    /// exclude matching messages
+    Negative,
+    /// highlight matching messages
+    Marker,
+}
+
+impl DlfFilterType {
+    pub fn value(&self) -> u8 {
+        match self {
+            DlfFilterType::Positive => FILTER_TYPE_POSITIVE,
+            DlfFilterType::Negative => FILTER_TYPE_NEGATIVE,
+            DlfFilterType::Marker => FILTER_TYPE_MARKER,
+        }
+    }
+    pub fn from_value(v: u8) -> Option<Self> {
+        match v {
+            FILTER_TYPE_POSITIVE => Some(DlfFilterType::Positive),
+            FILTER_TYPE_NEGATIVE => Some(DlfFilterType::Negative),
+            FILTER_TYPE_MARKER => Some(DlfFilterType::Marker),
+            _ => None,
+        }
+    }
+}
+
+/// A single filter of a dlt-viewer filter file
+///
+/// Criteria that are `None` are disabled in the filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DlfFilter {
+    pub filter_type: DlfFilterType,
+    pub name: String,
+    pub enabled: bool,
+    pub ecu_id: Option<String>,
+    pub app_id: Option<String>,
+    pub context_id: Option<String>,
+    /// only match messages whose header text contains this text
+    pub header_text: Option<String>,
+    /// only match messages whose payload text contains this text
+    pub payload_text: Option<String>,
+    /// only match messages with this level and more severe
+    pub log_level_max: Option<u8>,
+    /// only match messages with this level and less severe
+    pub log_level_min: Option<u8>,
+    /// only match control messages
+    pub control_messages: bool,
+}
+
+impl Default for DlfFilter {
+    fn default() -> Self {
+        DlfFilter {
+            filter_type: DlfFilterType::Positive,
+            name: String::new(),
+            enabled: true,
+            ecu_id: None,
+            app_id: None,
+            context_id: None,
+            header_text: None,
+            payload_text: None,
+            log_level_max: None,
+            log_level_min: None,
+            control_messages: false,
+        }
+    }
+}
+
+impl DlfFilter {
+    /// Check if all enabled criteria of the filter match the message
+    ///
+    /// Log level criteria only match log messages, the control message criteria
+    /// only matches control messages.
+    pub fn matches(&self, message: &Message) -> bool {
+        let ext = message.extended_header.as_ref();
+        let id_matches = |expected: &Option<String>, actual: Option<&str>| match expected {
+            Some(expected) => actual == Some(expected.as_str()),
+            None => true,
+        };
+        let level = match ext.map(|h| &h.message_type) {
+            Some(MessageType::Log(level)) => Some(level.value()),
+            _ => None,
+        };
+        let text_matches =
+            |expected: &Option<String>, text: &dyn Fn() -> Option<String>| match expected {
+                Some(expected) => matches!(text(), Some(text) if text.contains(expected.as_str())),
+                None => true,
+            };
+        let level_matches = |limit: Option<u8>, in_range: fn(u8, u8) -> bool| match limit {
+            Some(limit) => matches!(level, Some(level) if in_range(level, limit)),
+            None => true,
+        };
+        id_matches(&self.ecu_id, message.header.ecu_id.as_deref())
+            && id_matches(&self.app_id, ext.map(|h| h.application_id.as_str()))
+            && id_matches(&self.context_id, ext.map(|h| h.context_id.as_str()))
+            && level_matches(self.log_level_max, |level, max| level <= max)
+            && level_matches(self.log_level_min, |level, min| level >= min)
+            && text_matches(&self.header_text, &|| {
+                Some(header_text(&message.header, ext))
+            })
+            && text_matches(&self.payload_text, &|| message.payload.verbose_text())
+            && (!self.control_messages
+                || matches!(ext.map(|h| &h.message_type), Some(MessageType::Control(_))))
+    }
+}
+
+/// The filters of a dlt-viewer filter file grouped by their type
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DlfFilters {
+    pub positive: Vec<DlfFilter>,
+    pub negative: Vec<DlfFilter>,
+    pub markers: Vec<DlfFilter>,
+}
+
+impl From<Vec<DlfFilter>> for DlfFilters {
+    fn from(filters: Vec<DlfFilter>) -> Self {
+        let mut grouped = DlfFilters::default();
+        for filter in filters {
+            match filter.filter_type {
+                DlfFilterType::Positive => grouped.positive.push(filter),
+                DlfFilterType::Negative => grouped.negative.push(filter),
+                DlfFilterType::Marker => grouped.markers.push(filter),
+            }
+        }
+        grouped
+    }
+}
+
+impl DlfFilters {
+    /// All filters, positive filters first, followed by negative filters and markers
+    pub fn all(&self) -> impl Iterator<Item = &DlfFilter> {
+        self.positive
+            .iter()
+            .chain(self.negative.iter())
+            .chain(self.markers.iter())
+    }
+
+    /// Check if a message is selected like dlt-viewer does
+    ///
+    /// A message is selected if it matches any enabled positive filter (or there are
+    /// none) and does not match any enabled negative filter.
+    pub fn selects(&self, message: &Message) -> bool {
+        let mut positive = self.positive.iter().filter(|f| f.enabled).peekable();
+        (positive.peek().is_none() || positive.any(|f| f.matches(message)))
+            && !self
+                .negative
+                .iter()
+                .any(|f| f.enabled && f.matches(message))
+    }
+
+    /// Convert the positive filters into a `DltFilterConfig`
+    ///
+    /// Only enabled positive filters are considered, negative filters and markers
+    /// have to be applied using `DlfFilters::selects`. The exception are negative
+    /// filters that exclude all control messages. Since a `DltFilterConfig` can't
+    /// express arbitrary combinations of filters, the result can select more messages
+    /// than the original filters: all ids used in any filter are allowed, log levels
+    /// are mapped to per-id log levels (context-id before app-id) unless all filters
+    /// use the same level.
+    pub fn to_filter_config(&self) -> DltFilterConfig {
+        let positive: Vec<&DlfFilter> = self.positive.iter().filter(|f| f.enabled).collect();
+        // collects the values of one criteria, `None` if any filter doesn't use it
+        let collect_ids = |id: fn(&DlfFilter) -> &Option<String>| -> Option<Vec<String>> {
+            if positive.is_empty() {
+                // without positive filters all messages are selected
+                return None;
+            }
+            let mut ids: Vec<String> = vec![];
+            for filter in &positive {
+                match id(filter) {
+                    Some(id) if !ids.contains(id) => ids.push(id.clone()),
+                    Some(_) => (),
+                    None => return None,
+                }
+            }
+            Some(ids)
+        };
+        let mut config = DltFilterConfig {
+            app_ids: collect_ids(|f| &f.app_id),
+            ecu_ids: collect_ids(|f| &f.ecu_id),
+            context_ids: collect_ids(|f| &f.context_id),
+            header_texts: collect_ids(|f| &f.header_text),
+            payload_texts: collect_ids(|f| &f.payload_text),
+            ..Default::default()
+        };
+        if !positive.is_empty() && positive.iter().all(|f| f.control_messages) {
+            config.control_messages = Some(true);
+        } else if self
+            .negative
+            .iter()
+            .any(|f| f.enabled && is_control_message_filter(f))
+        {
+            config.control_messages = Some(false);
+        }
+        let common_level = positive.first().and_then(|f| f.log_level_max);
+        if positive.iter().all(|f| f.log_level_max == common_level) {
+            config.min_log_level = common_level;
+            return config;
+        }
+        let verbose = LogLevel::Verbose.value();
+        let mut app_id_log_levels = vec![];
+        let mut context_id_log_levels = vec![];
+        for filter in &positive {
+            let level = filter.log_level_max.unwrap_or(verbose);
+            match (&filter.context_id, &filter.app_id) {
+                (Some(context_id), _) => {
+                    context_id_log_levels.push((context_id.clone(), level));
+                }
+                (None, Some(app_id)) => app_id_log_levels.push((app_id.clone(), level)),
+                (None, None) => {
+                    config.min_log_level = Some(config.min_log_level.unwrap_or(0).max(level));
+                }
+            }
+        }
+        config.app_id_log_levels = (!app_id_log_levels.is_empty()).then_some(app_id_log_levels);
+        config.context_id_log_levels =
+            (!context_id_log_levels.is_empty()).then_some(context_id_log_levels);
+        config
+    }
+
+    /// Check if a message matches any enabled marker filter
+    pub fn is_marked(&self, message: &Message) -> bool {
+        self.markers.iter().any(|f| f.enabled && f.matches(message))
+    }
+}
+
+/// Reads dlt-viewer filter files
+pub struct DlfParser;
+
+impl DlfParser {
+    /// Read a filter file, see `DlfParser::parse`
+    pub fn parse_file(path: &Path) -> Result<DlfFilters, Error> {
+        DlfParser::parse(BufReader::new(File::open(path)?))
+    }
+
+    /// Read filters in the dlt-viewer format grouped by their type
+    ///
+    /// Use `DlfFilters::to_filter_config` to get a `DltFilterConfig` that can be used
+    /// for parsing.
+    pub fn parse<B: BufRead>(input: B) -> Result<DlfFilters, Error> {
+        Ok(DlfParser::parse_filters(input)?.into())
+    }
+
+    /// Read all filters of a filter file
+    pub fn parse_filters<B: BufRead>(input: B) -> Result<Vec<DlfFilter>, Error> {
+        let mut reader = XmlReader::from_reader(input);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        let mut filters = vec![];
+        let mut fields: Option<HashMap<String, String>> = None;
+        let mut current_element: Option<String> = None;
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                XmlEvent::Start(ref e) => {
+                    let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    if name == "filter" {
+                        fields = Some(HashMap::new());
+                    } else if fields.is_some() {
+                        current_element = Some(name);
+                    }
+                }
+                XmlEvent::Text(ref e) => {
+                    if let (Some(fields), Some(element)) = (&mut fields, &current_element) {
+                        fields.insert(element.clone(), e.unescape()?.into_owned());
+                    }
+                }
+                XmlEvent::End(ref e) => {
+                    if e.name().as_ref() == b"filter" {
+                        if let Some(fields) = fields.take() {
+                            filters.push(filter_from_fields(&fields)?);
+                        }
+                    }
+                    current_element = None;
+                }
+                XmlEvent::Eof => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(filters)
+    }
+}
+
+/// A filter that only matches control messages
+fn is_control_message_filter(filter: &DlfFilter) -> bool {
+    *filter
+        == DlfFilter {
+            filter_type: filter.filter_type,
+            name: filter.name.clone(),
+            enabled: filter.enabled,
+            control_messages: true,
+            ..Default::default()
+        }
+}
+
+fn filter_from_fields(fields: &HashMap<String, String>) -> Result<DlfFilter, Error> {
+    let flag = |name: &str| fields.get(name).map(|v| v.trim() == "1").unwrap_or(false);
+    let number = |name: &str| -> Result<Option<u8>, Error> {
+        fields
+            .get(name)
+            .map(|v| {
+                v.trim()
+                    .parse::<u8>()
+                    .map_err(|e| Error::Parse(format!("invalid value for {}: {}", name, e)))
+            })
+            .transpose()
+    };
+    let enabled_text = |enable: &str, name: &str| {
+        if flag(enable) {
+            fields.get(name).cloned()
+        } else {
+            None
+        }
+    };
+    let enabled_number = |enable: &str, name: &str| -> Result<Option<u8>, Error> {
+        if flag(enable) {
+            number(name)
+        } else {
+            Ok(None)
+        }
+    };
+    for (enable, name) in [
+        ("enableregexp_Header", "headertext"),
+        ("enableregexp_Payload", "payloadtext"),
+    ] {
+        if flag(enable) {
+            warn!(
+                "regular expressions are not supported, {} used as plain text",
+                name
+            );
+        }
+    }
+    let filter_type = match number("type")? {
+        Some(v) => DlfFilterType::from_value(v)
+            .ok_or_else(|| Error::Parse(format!("unknown filter type: {}", v)))?,
+        None => DlfFilterType::Positive,
+    };
+    Ok(DlfFilter {
+        filter_type,
+        name: fields.get("name").cloned().unwrap_or_default(),
+        enabled: fields.get("enablefilter").is_none() || flag("enablefilter"),
+        ecu_id: enabled_text("enableecuid", "ecuid"),
+        app_id: enabled_text("enableapplicationid", "applicationid"),
+        context_id: enabled_text("enablecontextid", "contextid"),
+        header_text: enabled_text("enableheadertext", "headertext"),
+        payload_text: enabled_text("enablepayloadtext", "payloadtext"),
+        log_level_max: enabled_number("enableLogLevelMax", "logLevelMax")?,
+        log_level_min: enabled_number("enableLogLevelMin", "logLevelMin")?,
+        control_messages: flag("enablectrlmsgs") || flag("ctrlmsgs"),
+    })
+}
+
+/// Writes dlt-viewer filter files
+pub struct DlfWriter<W: Write> {
+    writer: XmlWriter<W>,
+}
+
+impl<W: Write> DlfWriter<W> {
+    pub fn new(output: W) -> Self {
+        DlfWriter {
+            writer: XmlWriter::new_with_indent(output, b' ', 4),
+        }
+    }
+
+    /// Write a `DltFilterConfig` as dlt-viewer filters, see `DlfWriter::to_filters`
+    pub fn write_config(&mut self, config: &DltFilterConfig) -> Result<(), Error> {
+        self.write_filters(&DlfWriter::<W>::to_filters(config)?)
+    }
+
+    /// Write all filters of a filter set, see `DlfFilters::all`
+    pub fn write_filter_set(&mut self, filters: &DlfFilters) -> Result<(), Error> {
+        let all: Vec<DlfFilter> = filters.all().cloned().collect();
+        self.write_filters(&all)
+    }
+
+    /// Write a complete filter file containing the given filters
+    pub fn write_filters(&mut self, filters: &[DlfFilter]) -> Result<(), Error> {
+        self.writer
+            .write_event(XmlEvent::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        self.writer
+            .create_element("dltfilter")
+            .write_inner_content(|writer| {
+                for filter in filters {
+                    write_filter(writer, filter)?;
+                }
+                Ok(())
+            })?;
+        self.writer.get_mut().write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    /// Convert a `DltFilterConfig` into dlt-viewer filters
+    ///
+    /// Every combination of the configured ecu, app and context ids and header and
+    /// payload texts becomes one positive filter. Per-id log levels are written as additional positive filters
+    /// and, where they are stricter than the general level, as negative filters that
+    /// exclude the more verbose levels.
+    ///
+    /// Time ranges, session ids, message ids and argument conditions can't be expressed in dlt-viewer
+    /// filters and result in an `Error::Unsupported`.
+    pub fn to_filters(config: &DltFilterConfig) -> Result<Vec<DlfFilter>, Error> {
+        if config.min_timestamp.is_some() || config.max_timestamp.is_some() {
+            return Err(Error::Unsupported("time range".to_string()));
+        }
+        if config.session_ids.is_some() || config.excluded_session_ids.is_some() {
+            return Err(Error::Unsupported("session ids".to_string()));
+        }
+        if config.message_ids.is_some() || config.excluded_message_ids.is_some() {
+            return Err(Error::Unsupported("message ids".to_string()));
+        }
+        if config.argument_conditions.is_some() {
+            return Err(Error::Unsupported("argument conditions".to_string()));
+        }
+        let options = |ids: &Option<Vec<String>>| -> Vec<Option<String>> {
+            match ids {
+                Some(ids) => ids.iter().cloned().map(Some).collect(),
+                None => vec![None],
+            }
+        };
+        let level_of = |levels: &Option<Vec<(String, u8)>>, id: &Option<String>| {
+            levels.as_ref().and_then(|levels| {
+                levels
+                    .iter()
+                    .find(|(level_id, _)| Some(level_id) == id.as_ref())
+                    .map(|(_, level)| *level)
+            })
+        };
+        let ecu_ids = options(&config.ecu_ids);
+        let app_ids = options(&config.app_ids);
+        let context_ids = options(&config.context_ids);
+        let header_texts = options(&config.header_texts);
+        let payload_texts = options(&config.payload_texts);
+
+        let mut filters = vec![];
+        let mut add_filters = |app_ids: &[Option<String>], context_ids: &[Option<String>]| {
+            for ecu_id in &ecu_ids {
+                for app_id in app_ids {
+                    for context_id in context_ids {
+                        let log_level_max = level_of(&config.context_id_log_levels, context_id)
+                            .or_else(|| level_of(&config.app_id_log_levels, app_id))
+                            .or(config.min_log_level);
+                        for header_text in &header_texts {
+                            for payload_text in &payload_texts {
+                                filters.push(DlfFilter {
+                                    ecu_id: ecu_id.clone(),
+                                    app_id: app_id.clone(),
+                                    context_id: context_id.clone(),
+                                    header_text: header_text.clone(),
+                                    payload_text: payload_text.clone(),
+                                    log_level_max,
+                                    control_messages: config.control_messages == Some(true),
+                                    ..Default::default()
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        add_filters(&app_ids, &context_ids);
+        // per-id levels for ids that are not part of an explicit id list
+        if config.app_ids.is_none() {
+            if let Some(levels) = &config.app_id_log_levels {
+                let ids: Vec<Option<String>> =
+                    levels.iter().map(|(id, _)| Some(id.clone())).collect();
+                add_filters(&ids, &context_ids);
+            }
+        }
+        if config.context_ids.is_none() {
+            if let Some(levels) = &config.context_id_log_levels {
+                let ids: Vec<Option<String>> =
+                    levels.iter().map(|(id, _)| Some(id.clone())).collect();
+                add_filters(&app_ids, &ids);
+            }
+        }
+        // exclude more verbose levels where per-id levels are stricter
+        let general_level = config.min_log_level.unwrap_or(LogLevel::Verbose.value());
+        let stricter = |levels: &Option<Vec<(String, u8)>>| -> Vec<(String, u8)> {
+            levels
+                .iter()
+                .flatten()
+                .filter(|(_, level)| *level < general_level)
+                .cloned()
+                .collect()
+        };
+        for (app_id, level) in stricter(&config.app_id_log_levels) {
+            filters.push(DlfFilter {
+                filter_type: DlfFilterType::Negative,
+                app_id: Some(app_id),
+                log_level_min: Some(level + 1),
+                ..Default::default()
+            });
+        }
+        for (context_id, level) in stricter(&config.context_id_log_levels) {
+            filters.push(DlfFilter {
+                filter_type: DlfFilterType::Negative,
+                context_id: Some(context_id),
+                log_level_min: Some(level + 1),
+                ..Default::default()
+            });
+        }
+        if config.control_messages == Some(false) {
+            filters.push(DlfFilter {
+                filter_type: DlfFilterType::Negative,
+                control_messages: true,
+                ..Default::default()
+            });
+        }
+        Ok(filters)
+    }
+}
+
+fn write_filter<W: Write>(writer: &mut XmlWriter<W>, filter: &DlfFilter) -> quick_xml::Result<()> {
+    let flag = |enabled: bool| if enabled { "1" } else { "0" };
+    let fields: Vec<(&str, String)> = vec![
+        ("type", filter.filter_type.value().to_string()),
+        ("name", filter.name.clone()),
+        ("ecuid", filter.ecu_id.clone().unwrap_or_default()),
+        ("applicationid", filter.app_id.clone().unwrap_or_default()),
+        ("contextid", filter.context_id.clone().unwrap_or_default()),
+        ("headertext", filter.header_text.clone().unwrap_or_default()),
+        (
+            "payloadtext",
+            filter.payload_text.clone().unwrap_or_default(),
+        ),
+        ("enablefilter", flag(filter.enabled).to_string()),
+        ("enableecuid", flag(filter.ecu_id.is_some()).to_string()),
+        (
+            "enableapplicationid",
+            flag(filter.app_id.is_some()).to_string(),
+        ),
+        (
+            "enablecontextid",
+            flag(filter.context_id.is_some()).to_string(),
+        ),
+        (
+            "enableheadertext",
+            flag(filter.header_text.is_some()).to_string(),
+        ),
+        (
+            "enablepayloadtext",
+            flag(filter.payload_text.is_some()).to_string(),
+        ),
+        ("enablectrlmsgs", flag(filter.control_messages).to_string()),
+        (
+            "enableLogLevelMax",
+            flag(filter.log_level_max.is_some()).to_string(),
+        ),
+        (
+            "enableLogLevelMin",
+            flag(filter.log_level_min.is_some()).to_string(),
+        ),
+        (
+            "logLevelMax",
+            filter.log_level_max.unwrap_or_default().to_string(),
+        ),
+        (
+            "logLevelMin",
+            filter.log_level_min.unwrap_or_default().to_string(),
+        ),
+    ];
+    writer
+        .create_element("filter")
+        .write_inner_content(|writer| {
+            for (name, value) in &fields {
+                writer
+                    .create_element(*name)
+                    .write_text_content(BytesText::new(value))?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
@@ -18,8 +18,8 @@
 #![allow(clippy::unit_arg)]
 
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
-use bytes::{BufMut, BytesMut};
-use std::{convert::TryFrom, str};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::{borrow::Cow, convert::TryFrom, fmt, str};
 use thiserror::Error;
 
 #[cfg(test)]
@@ -76,8 +76,11 @@ pub struct Message {
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct StorageHeader {
     pub timestamp: DltTimeStamp,
-    #[cfg_attr(test, proptest(strategy = "\"[a-zA-Z 0-9]{4}\""))]
-    pub ecu_id: String,
+    #[cfg_attr(
+        test,
+        proptest(strategy = "\"[a-zA-Z 0-9]{4}\".prop_map(|id| DltId::new(&id))")
+    )]
+    pub ecu_id: DltId,
 }
 
 /// The Standard Header shall be in big endian format
@@ -91,7 +94,7 @@ pub struct StandardHeader {
     pub endianness: Endianness,
     pub has_extended_header: bool,
     pub message_counter: u8,
-    pub ecu_id: Option<String>,
+    pub ecu_id: Option<DltId>,
     pub session_id: Option<u32>,
     pub timestamp: Option<u32>,
     pub payload_length: u16,
@@ -118,10 +121,159 @@ pub struct ExtendedHeader {
     pub argument_count: u8,
     pub message_type: MessageType,
 
-    #[cfg_attr(test, proptest(strategy = "\"[a-zA-Z]{1,3}\""))]
-    pub application_id: String,
-    #[cfg_attr(test, proptest(strategy = "\"[a-zA-Z]{1,3}\""))]
-    pub context_id: String,
+    #[cfg_attr(
+        test,
+        proptest(strategy = "\"[a-zA-Z]{1,3}\".prop_map(|id| DltId::new(&id))")
+    )]
+    pub application_id: DltId,
+    #[cfg_attr(
+        test,
+        proptest(strategy = "\"[a-zA-Z]{1,3}\".prop_map(|id| DltId::new(&id))")
+    )]
+    pub context_id: DltId,
+}
+
+/// Fixed-size representation of the ids used for ECU, application and context
+///
+/// DLT ids consist of up to 4 bytes, shorter ids are padded with `\0`.
+/// Other than a `String`, a `DltId` does not need any heap allocation and
+/// can be compared and hashed cheaply, which is why the headers of a
+/// `Message` hold their ids as `DltId`.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DltId([u8; 4]);
+
+impl DltId {
+    /// Create an id from a string, cut to 4 bytes like when it is written
+    ///
+    /// ```
+    /// # use dlt_core::dlt::DltId;
+    /// assert_eq!(DltId::new("APP"), "APP");
+    /// assert_eq!(DltId::new("CONTEXT"), "CONT");
+    /// ```
+    pub fn new(id: &str) -> Self {
+        let bytes = id.as_bytes();
+        let length = bytes.len().min(4);
+        let mut raw = [0u8; 4];
+        raw[..length].copy_from_slice(&bytes[..length]);
+        DltId(raw)
+    }
+
+    /// Create an id from its raw (zero padded) representation
+    pub const fn from_bytes(bytes: [u8; 4]) -> Self {
+        DltId(bytes)
+    }
+
+    /// The raw (zero padded) representation of the id
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+
+    /// The id as string slice without the `\0` padding
+    ///
+    /// Invalid UTF-8 content is cut off at the first invalid byte.
+    pub fn as_str(&self) -> &str {
+        let content = &self.0[..self.len()];
+        match str::from_utf8(content) {
+            Ok(s) => s,
+            Err(e) => {
+                // the slice up to `valid_up_to` was verified to be valid UTF-8
+                str::from_utf8(&content[..e.valid_up_to()]).unwrap_or_default()
+            }
+        }
+    }
+
+    /// Number of bytes up to the first `\0`
+    pub fn len(&self) -> usize {
+        self.0.iter().position(|b| *b == 0).unwrap_or(4)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0[0] == 0
+    }
+}
+
+impl TryFrom<&str> for DltId {
+    type Error = Error;
+    fn try_from(id: &str) -> Result<DltId, Error> {
+        let bytes = id.as_bytes();
+        if bytes.len() > 4 {
+            return Err(Error::InvalidData(format!(
+                "DLT id can have at most 4 bytes: {}",
+                id
+            )));
+        }
+        let mut raw = [0u8; 4];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        Ok(DltId(raw))
+    }
+}
+
+impl TryFrom<String> for DltId {
+    type Error = Error;
+    fn try_from(id: String) -> Result<DltId, Error> {
+        DltId::try_from(id.as_str())
+    }
+}
+
+impl str::FromStr for DltId {
+    type Err = Error;
+    fn from_str(id: &str) -> Result<DltId, Error> {
+        DltId::try_from(id)
+    }
+}
+
+impl From<DltId> for String {
+    fn from(id: DltId) -> String {
+        id.as_str().to_string()
+    }
+}
+
+impl AsRef<str> for DltId {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::ops::Deref for DltId {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for DltId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for DltId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DltId({:?})", self.as_str())
+    }
+}
+
+impl PartialEq<str> for DltId {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for DltId {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for DltId {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
 }
 
 /// There are 3 different types of payload:
@@ -143,44 +295,49 @@ pub struct ExtendedHeader {
 /// Control messages are normal Dlt messages with a Standard Header, an Extended Header,
 /// and payload. The payload contains of the Service ID and the contained parameters.
 ///
+/// Raw data is held as `Bytes`, so a message parsed with
+/// `parse::dlt_message_bytes` refers to the input buffer instead of a copy.
+///
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
+// the inline arguments are the point of the `smallvec` feature
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
 pub enum PayloadContent {
     #[cfg_attr(
         test,
         proptest(strategy = "argument_vector_strategy().prop_map(PayloadContent::Verbose)")
     )]
-    Verbose(Vec<Argument>),
+    Verbose(Arguments),
     #[cfg_attr(
         test,
         proptest(
-            strategy = "(0..10u32, prop::collection::vec(any::<u8>(), 0..5)).prop_map(|(a, b)| PayloadContent::NonVerbose(a,b))"
+            strategy = "(0..10u32, prop::collection::vec(any::<u8>(), 0..5)).prop_map(|(a, b)| PayloadContent::NonVerbose(a,b.into()))"
         )
     )]
-    NonVerbose(u32, Vec<u8>), // (message_id, payload)
+    NonVerbose(u32, Bytes), // (message_id, payload)
     #[cfg_attr(
         test,
         proptest(
-            strategy = "(any::<ControlType>(), prop::collection::vec(any::<u8>(), 0..5)).prop_map(|(a, b)| PayloadContent::ControlMsg(a,b))"
+            strategy = "(any::<ControlType>(), prop::collection::vec(any::<u8>(), 0..5)).prop_map(|(a, b)| PayloadContent::ControlMsg(a,b.into()))"
         )
     )]
-    ControlMsg(ControlType, Vec<u8>),
+    ControlMsg(ControlType, Bytes),
     #[cfg_attr(
         test,
         proptest(strategy = "vec_of_vec().prop_map(PayloadContent::NetworkTrace)")
     )]
-    NetworkTrace(Vec<Vec<u8>>),
+    NetworkTrace(Vec<Bytes>),
 }
 
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct DltTimeStamp {
     pub seconds: u32,
@@ -198,23 +355,12 @@ impl DltTimeStamp {
     pub fn from_us(us: u64) -> Self {
         DltTimeStamp {
             seconds: (us / (1000 * 1000)) as u32,
-            microseconds: (us % (1000 * 1000)) as u32 * 1000 * 1000,
+            microseconds: (us % (1000 * 1000)) as u32,
         }
     }
-}
-
-trait BytesMutExt {
-    fn put_zero_terminated_string(&mut self, s: &str, max: usize);
-}
-
-impl BytesMutExt for BytesMut {
-    fn put_zero_terminated_string(&mut self, s: &str, max: usize) {
-        self.extend_from_slice(s.as_bytes());
-        if max > s.len() {
-            for _ in 0..(max - s.len()) {
-                self.put_u8(0x0);
-            }
-        }
+    /// The timestamp in microseconds
+    pub fn as_us(&self) -> u64 {
+        self.seconds as u64 * 1000 * 1000 + self.microseconds as u64
     }
 }
 
@@ -226,7 +372,7 @@ impl StorageHeader {
         buf.put_u8(0x01);
         buf.put_u32_le(self.timestamp.seconds);
         buf.put_u32_le(self.timestamp.microseconds);
-        buf.put_zero_terminated_string(&self.ecu_id[..], 4);
+        buf.extend_from_slice(self.ecu_id.as_bytes());
         buf.to_vec()
     }
 }
@@ -302,7 +448,7 @@ impl StandardHeader {
         message_counter: u8,
         has_extended_header: bool,
         payload_length: u16,
-        ecu_id: Option<String>,
+        ecu_id: Option<DltId>,
         session_id: Option<u32>,
         timestamp: Option<u32>,
     ) -> Self {
@@ -329,7 +475,7 @@ impl StandardHeader {
         buf.put_u8(self.message_counter);
         buf.put_u16(self.overall_length());
         if let Some(id) = &self.ecu_id {
-            buf.put_zero_terminated_string(&id[..], 4);
+            buf.extend_from_slice(id.as_bytes());
         }
         if let Some(id) = &self.session_id {
             buf.put_u32(*id);
@@ -346,7 +492,10 @@ impl StandardHeader {
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+///
+/// Log levels are ordered by verbosity, so `LogLevel::Fatal < LogLevel::Warn`.
+/// Invalid levels are ordered after all valid levels.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum LogLevel {
     Fatal,
@@ -373,6 +522,73 @@ impl AsRef<str> for LogLevel {
     }
 }
 
+impl LogLevel {
+    /// The numeric value of the log level as used in the DLT protocol (1 = fatal ... 6 = verbose)
+    pub fn value(&self) -> u8 {
+        match *self {
+            LogLevel::Fatal => LEVEL_FATAL,
+            LogLevel::Error => LEVEL_ERROR,
+            LogLevel::Warn => LEVEL_WARN,
+            LogLevel::Info => LEVEL_INFO,
+            LogLevel::Debug => LEVEL_DEBUG,
+            LogLevel::Verbose => LEVEL_VERBOSE,
+            LogLevel::Invalid(v) => v,
+        }
+    }
+    /// Get the log level for a numeric value as used in the DLT protocol
+    ///
+    /// `LogLevel::from_value(level.value()) == level` for all log levels.
+    pub fn from_value(v: u8) -> Self {
+        u8_to_log_level(v).unwrap_or(LogLevel::Invalid(v))
+    }
+    /// Check if a message with this level is skipped when filtering with `level`
+    /// as the most verbose level that passes
+    pub fn skip_with_level(self, level: LogLevel) -> bool {
+        match (self, level) {
+            (LogLevel::Invalid(a), LogLevel::Invalid(b)) => a < b,
+            (LogLevel::Invalid(_), _) => false,
+            (_, LogLevel::Invalid(_)) => true,
+            _ => level < self,
+        }
+    }
+}
+
+/// Displays the log level the way dlt-viewer does (e.g. `warn`),
+/// invalid levels are displayed with their numeric value
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fatal => f.write_str("fatal"),
+            Self::Error => f.write_str("error"),
+            Self::Warn => f.write_str("warn"),
+            Self::Info => f.write_str("info"),
+            Self::Debug => f.write_str("debug"),
+            Self::Verbose => f.write_str("verbose"),
+            Self::Invalid(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Parses a log level from its name (case-insensitive, e.g. `warn` or `WARNING`)
+/// or from its numeric value (e.g. `3`)
+impl str::FromStr for LogLevel {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<LogLevel, Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fatal" => Ok(LogLevel::Fatal),
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "verbose" => Ok(LogLevel::Verbose),
+            other => match other.parse::<u8>() {
+                Ok(v) if v <= 0b1111 => Ok(LogLevel::from_value(v)),
+                _ => Err(Error::UnexpectedValue(format!("unknown log level: {}", s))),
+            },
+        }
+    }
+}
+
 /// Represents the kind of a `DLT Trace Message`
 ///
 /// In case the dlt message contains tracing information, the Trace-Type
@@ -381,7 +597,7 @@ impl AsRef<str> for LogLevel {
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum ApplicationTraceType {
     Variable,
@@ -417,7 +633,7 @@ impl AsRef<str> for ApplicationTraceType {
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum NetworkTraceType {
     Ipc,
@@ -460,7 +676,7 @@ const CTRL_TYPE_RESPONSE: u8 = 0x2;
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum ControlType {
     Request,  // represented by 0x1
@@ -501,7 +717,7 @@ impl ControlType {
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum MessageType {
     Log(LogLevel),
@@ -528,6 +744,149 @@ impl AsRef<str> for MessageType {
     }
 }
 
+/// Parses a numeric message sub-type that has no name
+fn parse_sub_type_value(s: &str, kind: &str) -> Result<u8, Error> {
+    match s.parse::<u8>() {
+        Ok(v) if v <= 0b1111 => Ok(v),
+        _ => Err(Error::UnexpectedValue(format!("unknown {}: {}", kind, s))),
+    }
+}
+
+impl fmt::Display for ApplicationTraceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Variable => f.write_str("variable"),
+            Self::FunctionIn => f.write_str("func_in"),
+            Self::FunctionOut => f.write_str("func_out"),
+            Self::State => f.write_str("state"),
+            Self::Vfb => f.write_str("vfb"),
+            Self::Invalid(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl str::FromStr for ApplicationTraceType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<ApplicationTraceType, Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "variable" => Ok(ApplicationTraceType::Variable),
+            "func_in" | "function_in" => Ok(ApplicationTraceType::FunctionIn),
+            "func_out" | "function_out" => Ok(ApplicationTraceType::FunctionOut),
+            "state" => Ok(ApplicationTraceType::State),
+            "vfb" => Ok(ApplicationTraceType::Vfb),
+            other => ApplicationTraceType::try_from(
+                parse_sub_type_value(other, "application trace type")? << 4,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for NetworkTraceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ipc => f.write_str("ipc"),
+            Self::Can => f.write_str("can"),
+            Self::Flexray => f.write_str("flexray"),
+            Self::Most => f.write_str("most"),
+            Self::Ethernet => f.write_str("ethernet"),
+            Self::Someip => f.write_str("someip"),
+            Self::Invalid => f.write_str("invalid"),
+            Self::UserDefined(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl str::FromStr for NetworkTraceType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<NetworkTraceType, Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ipc" => Ok(NetworkTraceType::Ipc),
+            "can" => Ok(NetworkTraceType::Can),
+            "flexray" => Ok(NetworkTraceType::Flexray),
+            "most" => Ok(NetworkTraceType::Most),
+            "ethernet" => Ok(NetworkTraceType::Ethernet),
+            "someip" => Ok(NetworkTraceType::Someip),
+            "invalid" => Ok(NetworkTraceType::Invalid),
+            other => {
+                NetworkTraceType::try_from(parse_sub_type_value(other, "network trace type")? << 4)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ControlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request => f.write_str("request"),
+            Self::Response => f.write_str("response"),
+            Self::Unknown(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl str::FromStr for ControlType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<ControlType, Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "request" => Ok(ControlType::Request),
+            "response" => Ok(ControlType::Response),
+            other => Ok(ControlType::from_value(parse_sub_type_value(
+                other,
+                "control type",
+            )?)),
+        }
+    }
+}
+
+/// Displays the message type as type and sub-type separated by a space,
+/// e.g. `log warn`, `app_trace state`, `nw_trace can` or `control request`
+///
+/// Unknown message types are displayed as `unknown <type> <sub-type>`.
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Log(level) => write!(f, "log {}", level),
+            Self::ApplicationTrace(t) => write!(f, "app_trace {}", t),
+            Self::NetworkTrace(t) => write!(f, "nw_trace {}", t),
+            Self::Control(t) => write!(f, "control {}", t),
+            Self::Unknown((mstp, mtin)) => write!(f, "unknown {} {}", mstp, mtin),
+        }
+    }
+}
+
+impl str::FromStr for MessageType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<MessageType, Error> {
+        let mut parts = s.split_whitespace();
+        let (kind, sub_type) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(kind), Some(sub_type), None) => (kind, sub_type),
+            (Some(kind), Some(mstp), Some(mtin))
+                if kind.eq_ignore_ascii_case("unknown") && parts.next().is_none() =>
+            {
+                let mstp = parse_sub_type_value(mstp, "message type")?;
+                let mtin = parse_sub_type_value(mtin, "message type info")?;
+                return Ok(MessageType::Unknown((mstp, mtin)));
+            }
+            _ => {
+                return Err(Error::UnexpectedValue(format!(
+                    "unknown message type: {}",
+                    s
+                )))
+            }
+        };
+        match kind.to_ascii_lowercase().as_str() {
+            "log" => Ok(MessageType::Log(sub_type.parse()?)),
+            "app_trace" => Ok(MessageType::ApplicationTrace(sub_type.parse()?)),
+            "nw_trace" => Ok(MessageType::NetworkTrace(sub_type.parse()?)),
+            "control" => Ok(MessageType::Control(sub_type.parse()?)),
+            _ => Err(Error::UnexpectedValue(format!(
+                "unknown message type: {}",
+                s
+            ))),
+        }
+    }
+}
+
 pub(crate) const DLT_TYPE_LOG: u8 = 0b000;
 pub(crate) const DLT_TYPE_APP_TRACE: u8 = 0b001;
 pub(crate) const DLT_TYPE_NW_TRACE: u8 = 0b010;
@@ -539,18 +898,13 @@ impl ExtendedHeader {
         let mut buf = BytesMut::with_capacity(EXTENDED_HEADER_LENGTH as usize);
         buf.put_u8(u8::from(&self.message_type) | u8::from(self.verbose));
         buf.put_u8(self.argument_count);
-        buf.put_zero_terminated_string(&self.application_id[..], 4);
-        buf.put_zero_terminated_string(&self.context_id[..], 4);
+        buf.extend_from_slice(self.application_id.as_bytes());
+        buf.extend_from_slice(self.context_id.as_bytes());
         buf.to_vec()
     }
     pub fn skip_with_level(self: &ExtendedHeader, level: LogLevel) -> bool {
         match self.message_type {
-            MessageType::Log(n) => match (n, level) {
-                (LogLevel::Invalid(a), LogLevel::Invalid(b)) => a < b,
-                (LogLevel::Invalid(_), _) => false,
-                (_, LogLevel::Invalid(_)) => true,
-                _ => level < n,
-            },
+            MessageType::Log(n) => n.skip_with_level(level),
             _ => false,
         }
     }
@@ -600,6 +954,37 @@ pub enum Value {
     Raw(Vec<u8>),
 }
 
+/// Displays the value like dlt-viewer does, raw values as hex bytes (e.g. `0a 1f`)
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{}", *v != 0),
+            Value::U8(v) => write!(f, "{}", v),
+            Value::U16(v) => write!(f, "{}", v),
+            Value::U32(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::U128(v) => write!(f, "{}", v),
+            Value::I8(v) => write!(f, "{}", v),
+            Value::I16(v) => write!(f, "{}", v),
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::I128(v) => write!(f, "{}", v),
+            Value::F32(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+            Value::StringVal(v) => f.write_str(v),
+            Value::Raw(bytes) => {
+                for (i, b) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 /// Defines what string type is used, `ASCII` or `UTF8`
 #[allow(clippy::upper_case_acronyms)]
 #[cfg_attr(
@@ -901,9 +1286,9 @@ impl TryFrom<u32> for TypeInfo {
 ///
 /// * phy_v is what we received in the dlt message
 /// * log_v is the real value
-///     example: the degree celcius is transmitted,
-///     quantization = 0.01, offset = -50
-///     now the transmitted value phy_v = (log_v - offset)/quantization = 7785
+///   example: the degree celcius is transmitted,
+///   quantization = 0.01, offset = -50
+///   now the transmitted value phy_v = (log_v - offset)/quantization = 7785
 ///
 /// The width depends on the TYLE value
 ///     * i32 bit if Type Length (TYLE) equals 1,2 or 3
@@ -926,20 +1311,55 @@ pub struct FixedPoint {
 /// information of an application). In addition to the variable value
 /// itself, it is needed to provide information like size and type
 /// of the variable. This information is contained in the `type_info` field.
+///
+/// Name and unit are owned `String`s by default, see `BorrowedArgument` for
+/// arguments that refer to the parsed data instead.
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
 #[derive(Debug, Clone, PartialEq)]
-pub struct Argument {
+pub struct Argument<S = String> {
     pub type_info: TypeInfo,
-    pub name: Option<String>,
-    pub unit: Option<String>,
+    pub name: Option<S>,
+    pub unit: Option<S>,
     pub fixed_point: Option<FixedPoint>,
     pub value: Value,
 }
 
-impl Argument {
+/// The arguments of a verbose payload
+///
+/// With the `smallvec` feature up to four arguments are stored inline, which
+/// saves an allocation for most verbose messages.
+#[cfg(feature = "smallvec")]
+pub type Arguments = smallvec::SmallVec<[Argument; 4]>;
+
+/// The arguments of a verbose payload
+#[cfg(not(feature = "smallvec"))]
+pub type Arguments = Vec<Argument>;
+
+/// An argument whose name and unit are borrowed from the parsed data
+///
+/// Returned by `parse::dlt_borrowed_arguments`, it saves the allocations of
+/// name and unit for payloads that are inspected without being kept. Messages
+/// parsed with `parse::dlt_message` own their arguments, so names and units
+/// are still allocated there.
+pub type BorrowedArgument<'a> = Argument<Cow<'a, str>>;
+
+impl BorrowedArgument<'_> {
+    /// Converts the argument into one that owns its name and unit
+    pub fn into_owned(self) -> Argument {
+        Argument {
+            type_info: self.type_info,
+            name: self.name.map(Cow::into_owned),
+            unit: self.unit.map(Cow::into_owned),
+            fixed_point: self.fixed_point,
+            value: self.value,
+        }
+    }
+}
+
+impl<S> Argument<S> {
     fn value_as_f64(&self) -> Option<f64> {
         match self.value {
             Value::I8(v) => Some(v as f64),
@@ -977,6 +1397,42 @@ impl Argument {
         }
     }
 
+    /// The numeric value of the argument, with quantization and offset applied
+    /// for fixed point values
+    ///
+    /// `None` for strings and raw values.
+    pub fn numeric_value(&self) -> Option<f64> {
+        let value = match &self.value {
+            Value::Bool(v) => *v as f64,
+            Value::U8(v) => *v as f64,
+            Value::U16(v) => *v as f64,
+            Value::U32(v) => *v as f64,
+            Value::U64(v) => *v as f64,
+            Value::U128(v) => *v as f64,
+            Value::I8(v) => *v as f64,
+            Value::I16(v) => *v as f64,
+            Value::I32(v) => *v as f64,
+            Value::I64(v) => *v as f64,
+            Value::I128(v) => *v as f64,
+            Value::F32(v) => *v as f64,
+            Value::F64(v) => *v,
+            Value::StringVal(_) | Value::Raw(_) => return None,
+        };
+        Some(match &self.fixed_point {
+            Some(FixedPoint {
+                quantization,
+                offset,
+            }) => {
+                let offset = match offset {
+                    FixedPointValue::I32(v) => *v as f64,
+                    FixedPointValue::I64(v) => *v as f64,
+                };
+                value * *quantization as f64 + offset
+            }
+            None => value,
+        })
+    }
+
     pub fn to_real_value(&self) -> Option<u64> {
         match (&self.type_info.kind, &self.fixed_point) {
             (TypeInfoKind::SignedFixedPoint(_), Some(_)) => self.log_v(),
@@ -1005,7 +1461,9 @@ impl Argument {
         }
         valid
     }
+}
 
+impl Argument {
     fn fixed_point_capacity(&self, float_width: FloatWidth) -> usize {
         let mut capacity = float_width.width_in_bytes();
         if let Some(fp) = &self.fixed_point {
@@ -1446,11 +1904,27 @@ fn put_signed_value<T: ByteOrder>(value: &Value, buf: &mut BytesMut) {
 impl PayloadContent {
     pub fn arg_count(&self) -> u8 {
         match &self {
-            PayloadContent::Verbose(args) => std::cmp::min(args.len() as u8, u8::MAX),
+            PayloadContent::Verbose(args) => std::cmp::min(args.len(), u8::MAX as usize) as u8,
+            PayloadContent::NetworkTrace(slices) => {
+                std::cmp::min(slices.len(), u8::MAX as usize) as u8
+            }
             _ => 0,
         }
     }
 
+    /// The values of verbose arguments separated by spaces, `None` for other payloads
+    pub(crate) fn verbose_text(&self) -> Option<String> {
+        match self {
+            PayloadContent::Verbose(args) => Some(
+                args.iter()
+                    .map(|arg| arg.value.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            ),
+            _ => None,
+        }
+    }
+
     #[allow(dead_code)]
     pub(crate) fn is_verbose(&self) -> bool {
         matches!(self, PayloadContent::Verbose(_))
@@ -1493,8 +1967,9 @@ impl PayloadContent {
             PayloadContent::NetworkTrace(slices) => {
                 for slice in slices {
                     // type-info (rawd)
-                    const TYPE_INFO_BYTES: &[u8] = &[0x00, 0x04, 0x00, 0x00];
-                    buf.extend_from_slice(TYPE_INFO_BYTES);
+                    let mut tmp_buf = [0; 4];
+                    T::write_u32(&mut tmp_buf, TYPE_INFO_RAW_FLAG);
+                    buf.extend_from_slice(&tmp_buf);
 
                     // len (16bit)
                     let mut tmp_buf = [0; 2];
@@ -1574,6 +2049,27 @@ fn dbg_bytes(_name: &str, _bytes: &[u8]) {
     dbg_bytes_with_info(_name, _bytes, None);
 }
 
+/// An empty message without ids, e.g. as scratch message for `parse::dlt_message_into`
+impl Default for Message {
+    fn default() -> Self {
+        Message {
+            storage_header: None,
+            header: StandardHeader {
+                version: 1,
+                endianness: Endianness::Big,
+                has_extended_header: false,
+                message_counter: 0,
+                ecu_id: None,
+                session_id: None,
+                timestamp: None,
+                payload_length: 0,
+            },
+            extended_header: None,
+            payload: PayloadContent::Verbose(Arguments::new()),
+        }
+    }
+}
+
 impl Message {
     pub fn new(conf: MessageConfig, storage_header: Option<StorageHeader>) -> Self {
         let payload_length = if conf.endianness == Endianness::Big {
@@ -1586,7 +2082,7 @@ impl Message {
                 version: conf.version,
                 endianness: conf.endianness,
                 message_counter: conf.counter,
-                ecu_id: conf.ecu_id,
+                ecu_id: conf.ecu_id.as_deref().map(DltId::new),
                 session_id: conf.session_id,
                 timestamp: conf.timestamp,
                 has_extended_header: conf.extended_header_info.is_some(),
@@ -1594,11 +2090,13 @@ impl Message {
             },
             extended_header: match conf.extended_header_info {
                 Some(ext_info) => Some(ExtendedHeader {
-                    verbose: conf.payload.is_verbose(),
+                    // network traces are sent as verbose raw arguments
+                    verbose: conf.payload.is_verbose()
+                        || matches!(conf.payload, PayloadContent::NetworkTrace(_)),
                     argument_count: conf.payload.arg_count(),
                     message_type: ext_info.message_type,
-                    application_id: ext_info.app_id,
-                    context_id: ext_info.context_id,
+                    application_id: DltId::new(&ext_info.app_id),
+                    context_id: DltId::new(&ext_info.context_id),
                 }),
                 None => None,
             },
@@ -1658,14 +2156,69 @@ impl Message {
         let ecu_id = self
             .header
             .ecu_id
-            .clone()
-            .unwrap_or_else(|| DEFAULT_ECU_ID.into());
+            .unwrap_or_else(|| DltId::new(DEFAULT_ECU_ID));
         self = Message {
             storage_header: Some(StorageHeader { timestamp, ecu_id }),
             ..self
         };
         self
     }
+
+    /// Convert the message to the given byte order
+    ///
+    /// The MSBF flag of the standard header is updated accordingly, so all
+    /// arguments of a verbose payload, the message id of a non-verbose payload
+    /// and the slice lengths of a network trace are written in the new byte
+    /// order by `as_bytes`.
+    /// The service id and the parameters of a control message are encoded
+    /// again in the new byte order.
+    /// The raw data of non-verbose messages cannot be interpreted without
+    /// further knowledge and is kept unchanged.
+    #[must_use]
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        if self.header.endianness != endianness {
+            if let (
+                Some(ExtendedHeader {
+                    message_type: MessageType::Control(control_type),
+                    ..
+                }),
+                PayloadContent::ControlMsg(first, rest),
+            ) = (&self.extended_header, &self.payload)
+            {
+                // the parser splits off the first byte of the service id
+                let payload = [&[first.value()], &rest[..]].concat();
+                let converted = crate::control::convert_payload(
+                    control_type,
+                    &payload,
+                    self.header.endianness,
+                    endianness,
+                );
+                if let Some((first, rest)) = converted.split_first() {
+                    self.payload = PayloadContent::ControlMsg(
+                        ControlType::from_value(*first),
+                        Bytes::copy_from_slice(rest),
+                    );
+                }
+            }
+        }
+        self.header.endianness = endianness;
+        self
+    }
+
+    /// The payload as text, whatever kind of payload the message has
+    ///
+    /// See `fmt::payload_text` for how the kinds of payload are rendered.
+    pub fn payload_text(&self) -> String {
+        crate::fmt::payload_text(self)
+    }
+
+    /// The arguments of a verbose message, other messages have none
+    pub fn arguments(&self) -> std::slice::Iter<'_, Argument> {
+        match &self.payload {
+            PayloadContent::Verbose(arguments) => arguments.iter(),
+            _ => [].iter(),
+        }
+    }
 }
 
 impl From<&LogLevel> for u8 {
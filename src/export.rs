@@ -0,0 +1,749 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Exporting dlt messages into new files
+//!
+//! `export` contains batch operations that stream the messages of a source
+//! into an output, e.g. to store only the messages that match a filter. The
+//! bytes of exported messages are written as they were read.
+use crate::{
+    dlt::{DltId, DltTimeStamp, Message, StandardHeader, STORAGE_HEADER_LENGTH},
+    filtering::{self, DltFilterConfig, ProcessedDltFilterConfig},
+    fmt::{column_value, csv_field, dlt_viewer_line, Column},
+    parse::{dlt_message, dlt_standard_header, dlt_storage_header, DltParseError, ParsedMessage},
+    progress::{CancellationToken, Progress},
+    read::DltMessageReader,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Parse error: {0}")]
+    Parse(#[from] DltParseError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid filter: {0}")]
+    Filter(#[from] filtering::Error),
+    #[error("Input has no storage headers")]
+    MissingStorageHeaders,
+}
+
+/// Settings of an export that are independent of the operation
+///
+/// ```
+/// # use dlt_core::export::ExportOptions;
+/// let options = ExportOptions::new()
+///     .recover(true)
+///     .progress(Some(1024), |progress| println!("{:?}", progress.fraction()));
+/// ```
+#[derive(Default)]
+pub struct ExportOptions {
+    total: Option<u64>,
+    progress: Option<Box<dyn FnMut(Progress) + Send>>,
+    cancel: Option<CancellationToken>,
+    recover: bool,
+    rewriter: Rewriter,
+}
+
+/// New storage header timestamps for the exported messages
+pub enum TimestampRewrite {
+    /// The base time plus the timestamp of the standard header (the time
+    /// since the start of the ECU), messages without one keep their timestamp
+    FromHeaderTimestamp(DltTimeStamp),
+    /// Computed from the original storage timestamp and the standard header
+    /// timestamp in 0.1 ms
+    Map(TimestampMap),
+}
+
+/// Function of `TimestampRewrite::Map`
+pub type TimestampMap = Box<dyn FnMut(&DltTimeStamp, Option<u32>) -> DltTimeStamp + Send>;
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the progress of the export, see `DltMessageReader::set_progress_hook`
+    pub fn progress(
+        mut self,
+        total: Option<u64>,
+        hook: impl FnMut(Progress) + Send + 'static,
+    ) -> Self {
+        self.total = total;
+        self.progress = Some(Box::new(hook));
+        self
+    }
+
+    /// Stop the export with `DltParseError::Cancelled` once the token is cancelled
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Skip corrupted bytes between messages, see `DltMessageReader::set_recovery`
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Replace the storage header timestamps of the exported messages
+    ///
+    /// Other bytes of the messages are not changed, messages without storage
+    /// header are written as they are.
+    pub fn rewrite_timestamps(mut self, rewrite: TimestampRewrite) -> Self {
+        self.rewriter.timestamps = Some(rewrite);
+        self
+    }
+
+    /// Number the exported messages of each ECU and session consecutively
+    ///
+    /// The message counters of the input have gaps when messages are filtered
+    /// out, which tools that check for lost messages would report.
+    pub fn renumber_counters(mut self, renumber: bool) -> Self {
+        self.rewriter.counters = renumber.then(HashMap::new);
+        self
+    }
+
+    /// Drop messages that are identical to a message at most `window` earlier or later
+    ///
+    /// Messages are compared without their storage headers, so the same
+    /// message recorded by two loggers is exported once. The storage header
+    /// timestamps are used for the window (or the standard header timestamps
+    /// if the input has no storage headers).
+    pub fn deduplicate(mut self, window: Duration) -> Self {
+        self.rewriter.duplicates = Some(Deduplicator {
+            window: window.as_micros() as u64,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        });
+        self
+    }
+
+    fn reader<S: Read>(self, input: S) -> Result<(DltMessageReader<S>, Rewriter), Error> {
+        let mut reader = DltMessageReader::auto_detect(input)?;
+        if let Some(hook) = self.progress {
+            reader.set_progress_hook(self.total, hook);
+        }
+        if let Some(token) = self.cancel {
+            reader.set_cancellation_token(token);
+        }
+        reader.set_recovery(self.recover);
+        Ok((reader, self.rewriter))
+    }
+}
+
+/// ECU id and session id of a message
+type CounterKey = (Option<DltId>, Option<u32>);
+
+/// Changes the bytes of messages before they are written
+#[derive(Default)]
+struct Rewriter {
+    timestamps: Option<TimestampRewrite>,
+    /// next counter per ECU id and session id if counters are renumbered
+    counters: Option<HashMap<CounterKey, u8>>,
+    duplicates: Option<Deduplicator>,
+    buffer: Vec<u8>,
+}
+
+impl Rewriter {
+    /// Whether the message is dropped as a duplicate of an earlier message
+    fn is_duplicate(&mut self, message: &[u8], with_storage_header: bool) -> bool {
+        let duplicates = match self.duplicates.as_mut() {
+            Some(duplicates) => duplicates,
+            None => return false,
+        };
+        let (time, content) = if with_storage_header {
+            let time = match dlt_storage_header(message) {
+                Ok((_, Some((storage_header, _)))) => storage_header.timestamp.as_us(),
+                _ => 0,
+            };
+            (time, &message[STORAGE_HEADER_LENGTH as usize..])
+        } else {
+            let time = match dlt_standard_header(message) {
+                Ok((_, header)) => header.timestamp.unwrap_or_default() as u64 * 100,
+                Err(_) => 0,
+            };
+            (time, message)
+        };
+        duplicates.is_duplicate(time, content)
+    }
+
+    /// Whether `apply` changes the bytes of messages
+    fn rewrites(&self) -> bool {
+        self.timestamps.is_some() || self.counters.is_some()
+    }
+
+    /// The message with all changes applied, or the message itself if nothing changes
+    fn apply<'a>(&'a mut self, message: &'a [u8], with_storage_header: bool) -> &'a [u8] {
+        if !self.rewrites() {
+            return message;
+        }
+        let header_offset = if with_storage_header {
+            STORAGE_HEADER_LENGTH as usize
+        } else {
+            0
+        };
+        let header = match dlt_standard_header(&message[header_offset..]) {
+            Ok((_, header)) => header,
+            Err(_) => return message,
+        };
+        let timestamp = match self.timestamps.as_mut() {
+            Some(rewrite) if with_storage_header => new_timestamp(rewrite, message, &header),
+            _ => None,
+        };
+        let counter = self.counters.as_mut().map(|counters| {
+            let next = counters
+                .entry((header.ecu_id, header.session_id))
+                .or_default();
+            let counter = *next;
+            *next = next.wrapping_add(1);
+            counter
+        });
+        self.buffer.clear();
+        self.buffer.extend_from_slice(message);
+        if let Some(timestamp) = timestamp {
+            self.buffer[4..8].copy_from_slice(&timestamp.seconds.to_le_bytes());
+            self.buffer[8..12].copy_from_slice(&timestamp.microseconds.to_le_bytes());
+        }
+        if let Some(counter) = counter {
+            self.buffer[header_offset + 1] = counter;
+        }
+        &self.buffer
+    }
+}
+
+/// Remembers the messages of the deduplication window
+struct Deduplicator {
+    /// in microseconds
+    window: u64,
+    /// time of the first occurrence of each message in the window
+    seen: HashMap<Vec<u8>, u64>,
+    order: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl Deduplicator {
+    fn is_duplicate(&mut self, time: u64, content: &[u8]) -> bool {
+        while let Some((first_time, _)) = self.order.front() {
+            if first_time + self.window >= time {
+                break;
+            }
+            if let Some((first_time, content)) = self.order.pop_front() {
+                if self.seen.get(&content) == Some(&first_time) {
+                    self.seen.remove(&content);
+                }
+            }
+        }
+        match self.seen.get(content) {
+            Some(first_time) if first_time.abs_diff(time) <= self.window => true,
+            _ => {
+                self.seen.insert(content.to_vec(), time);
+                self.order.push_back((time, content.to_vec()));
+                false
+            }
+        }
+    }
+}
+
+/// The rewritten storage header timestamp, `None` to keep it
+fn new_timestamp(
+    rewrite: &mut TimestampRewrite,
+    message: &[u8],
+    header: &StandardHeader,
+) -> Option<DltTimeStamp> {
+    let storage_timestamp = match dlt_storage_header(message) {
+        Ok((_, Some((storage_header, _)))) => storage_header.timestamp,
+        _ => return None,
+    };
+    match rewrite {
+        TimestampRewrite::FromHeaderTimestamp(base) => header
+            .timestamp
+            .map(|ticks| DltTimeStamp::from_us(base.as_us() + ticks as u64 * 100)),
+        TimestampRewrite::Map(map) => Some(map(&storage_timestamp, header.timestamp)),
+    }
+}
+
+/// Counters of an export
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportStats {
+    /// messages that were read from the input
+    pub read_messages: usize,
+    /// messages that were written to the output
+    pub written_messages: usize,
+    /// bytes that were written to the output
+    pub written_bytes: u64,
+    /// messages that were filtered out
+    pub filtered_messages: usize,
+    /// messages that could not be parsed or were too large, they are not exported
+    pub invalid_messages: usize,
+    /// messages that were dropped as duplicates, see `ExportOptions::deduplicate`
+    pub duplicate_messages: usize,
+    /// corrupted bytes of the input that were skipped
+    pub bytes_skipped: u64,
+}
+
+/// Copy the messages of `input` that match the filter to `output`
+///
+/// Whether the input contains storage headers is detected from its first
+/// bytes. The matching messages are written unchanged, so the output has
+/// the same format as the input.
+pub fn filter_copy<R: Read, W: Write>(
+    input: R,
+    output: W,
+    filter_config: &DltFilterConfig,
+    options: ExportOptions,
+) -> Result<ExportStats, Error> {
+    export_messages(
+        input,
+        output,
+        Some(filter_config),
+        options,
+        |output, _, slice, _| {
+            output.write_all(slice)?;
+            Ok(slice.len() as u64)
+        },
+    )
+}
+
+/// Write the messages of `input` that match the filter as CSV
+///
+/// The first line names the `columns`, followed by one line per message with
+/// the values rendered like [`crate::fmt::MessageFormatter`] does. Fields that
+/// contain a comma, a quote or a line break are quoted. The index column
+/// counts the exported messages from 0.
+///
+/// ```
+/// # use dlt_core::{export::{to_csv, ExportOptions}, fmt::Column};
+/// let mut output = vec![];
+/// let columns = [Column::Application, Column::Context, Column::Payload];
+/// to_csv(&[][..], &mut output, &columns, None, ExportOptions::new()).expect("export");
+/// assert_eq!(output, b"app,ctx,payload\n");
+/// ```
+pub fn to_csv<R: Read, W: Write>(
+    input: R,
+    mut output: W,
+    columns: &[Column],
+    filter_config: Option<&DltFilterConfig>,
+    options: ExportOptions,
+) -> Result<ExportStats, Error> {
+    let header = columns
+        .iter()
+        .map(|column| column.name())
+        .collect::<Vec<_>>()
+        .join(",")
+        + "\n";
+    output.write_all(header.as_bytes())?;
+    let mut index = 0;
+    let mut line = String::new();
+    let mut stats = export_messages(
+        input,
+        output,
+        filter_config,
+        options,
+        |output, _, _, message| {
+            line.clear();
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    line.push(',');
+                }
+                line.push_str(&csv_field(&column_value(*column, index, message)));
+            }
+            line.push('\n');
+            index += 1;
+            output.write_all(line.as_bytes())?;
+            Ok(line.len() as u64)
+        },
+    )?;
+    stats.written_bytes += header.len() as u64;
+    Ok(stats)
+}
+
+/// Write the messages of `input` that match the filter like the "export
+/// ASCII" of dlt-viewer does
+///
+/// Each message is one line, see [`dlt_viewer_line`], with its index in the
+/// input and the storage time at `offset_minutes` from UTC. dlt-viewer shows
+/// the local time, so the offset of its time zone gives the same output.
+pub fn to_ascii<R: Read, W: Write>(
+    input: R,
+    output: W,
+    filter_config: Option<&DltFilterConfig>,
+    offset_minutes: i32,
+    options: ExportOptions,
+) -> Result<ExportStats, Error> {
+    export_messages(
+        input,
+        output,
+        filter_config,
+        options,
+        |output, index, _, message| {
+            let line = dlt_viewer_line(index, message, offset_minutes) + "\n";
+            output.write_all(line.as_bytes())?;
+            Ok(line.len() as u64)
+        },
+    )
+}
+
+/// Write each message of `input` that matches the filter as one line of JSON
+///
+/// Every line is a flat object of the header fields (e.g. `ecu`, `app`,
+/// `ctx`, `type`, `subtype`) and the payload, which makes the output easy to
+/// ingest into log pipelines like Elasticsearch or Splunk. Verbose arguments
+/// are listed in `arguments` as objects with `name`, `unit` and `value`, see
+/// [`message_json`].
+#[cfg(feature = "serde-support")]
+pub fn to_json_lines<R: Read, W: Write>(
+    input: R,
+    output: W,
+    filter_config: Option<&DltFilterConfig>,
+    options: ExportOptions,
+) -> Result<ExportStats, Error> {
+    export_messages(
+        input,
+        output,
+        filter_config,
+        options,
+        |output, _, _, message| {
+            let mut line = serde_json::to_vec(&message_json(message))?;
+            line.push(b'\n');
+            output.write_all(&line)?;
+            Ok(line.len() as u64)
+        },
+    )
+}
+
+/// The message as a flat JSON object
+///
+/// Fields that the message does not contain are left out. Integers and
+/// floats are written as JSON numbers (floats that are not finite as `null`),
+/// raw data as a string of hex bytes.
+///
+/// ```json
+/// {"storage_seconds":1,"storage_microseconds":0,"storage_ecu":"ECU1","ecu":"ECU1",
+///  "counter":0,"timestamp":5,"app":"APP","ctx":"CTX","type":"log","subtype":"info",
+///  "verbose":true,"arguments":[{"name":"speed","unit":"km/h","value":42}]}
+/// ```
+#[cfg(feature = "serde-support")]
+pub fn message_json(message: &Message) -> serde_json::Value {
+    use crate::{
+        dlt::{PayloadContent, Value},
+        fmt::payload_text,
+    };
+    use serde_json::{json, Map, Value as Json};
+    let mut object = Map::new();
+    if let Some(storage_header) = &message.storage_header {
+        object.insert(
+            "storage_seconds".into(),
+            storage_header.timestamp.seconds.into(),
+        );
+        object.insert(
+            "storage_microseconds".into(),
+            storage_header.timestamp.microseconds.into(),
+        );
+        object.insert("storage_ecu".into(), storage_header.ecu_id.as_str().into());
+    }
+    let header = &message.header;
+    if let Some(ecu_id) = &header.ecu_id {
+        object.insert("ecu".into(), ecu_id.as_str().into());
+    }
+    if let Some(session_id) = header.session_id {
+        object.insert("session_id".into(), session_id.into());
+    }
+    object.insert("counter".into(), header.message_counter.into());
+    if let Some(timestamp) = header.timestamp {
+        object.insert("timestamp".into(), timestamp.into());
+    }
+    if let Some(extended_header) = &message.extended_header {
+        object.insert("app".into(), extended_header.application_id.as_str().into());
+        object.insert("ctx".into(), extended_header.context_id.as_str().into());
+        let message_type = extended_header.message_type.to_string();
+        let (kind, subtype) = message_type
+            .split_once(' ')
+            .unwrap_or((message_type.as_str(), ""));
+        object.insert("type".into(), kind.into());
+        object.insert("subtype".into(), subtype.into());
+        object.insert("verbose".into(), extended_header.verbose.into());
+    }
+    match &message.payload {
+        PayloadContent::Verbose(arguments) => {
+            let arguments = arguments
+                .iter()
+                .map(|argument| {
+                    let value = match &argument.value {
+                        Value::Bool(v) => Json::from(*v != 0),
+                        Value::U8(v) => Json::from(*v),
+                        Value::U16(v) => Json::from(*v),
+                        Value::U32(v) => Json::from(*v),
+                        Value::U64(v) => Json::from(*v),
+                        Value::I8(v) => Json::from(*v),
+                        Value::I16(v) => Json::from(*v),
+                        Value::I32(v) => Json::from(*v),
+                        Value::I64(v) => Json::from(*v),
+                        // wider than JSON numbers are read by most consumers
+                        Value::U128(v) => Json::from(v.to_string()),
+                        Value::I128(v) => Json::from(v.to_string()),
+                        Value::F32(v) => Json::from(*v),
+                        Value::F64(v) => Json::from(*v),
+                        Value::StringVal(v) => Json::from(v.as_str()),
+                        Value::Raw(_) => Json::from(argument.value.to_string()),
+                    };
+                    json!({"name": argument.name, "unit": argument.unit, "value": value})
+                })
+                .collect::<Vec<_>>();
+            object.insert("arguments".into(), arguments.into());
+        }
+        PayloadContent::NonVerbose(message_id, data) => {
+            object.insert("message_id".into(), (*message_id).into());
+            object.insert(
+                "payload".into(),
+                Value::Raw(data.to_vec()).to_string().into(),
+            );
+        }
+        PayloadContent::ControlMsg(_, _) | PayloadContent::NetworkTrace(_) => {
+            object.insert("payload".into(), payload_text(message).into());
+        }
+    }
+    Json::Object(object)
+}
+
+/// Stream the messages of `input` that match the filter into `output`
+///
+/// `write` is called with the index of each message in the input, its bytes
+/// with all changes of the options applied and the message parsed from them.
+/// It returns the number of bytes it wrote.
+fn export_messages<R: Read, W: Write>(
+    input: R,
+    output: W,
+    filter_config: Option<&DltFilterConfig>,
+    options: ExportOptions,
+    mut write: impl FnMut(&mut BufWriter<W>, usize, &[u8], &Message) -> std::io::Result<u64>,
+) -> Result<ExportStats, Error> {
+    if let Some(filter_config) = filter_config {
+        filter_config.validate()?;
+    }
+    let filter = filter_config.map(ProcessedDltFilterConfig::from);
+    let (mut reader, mut rewriter) = options.reader(input)?;
+    let with_storage_header = reader.with_storage_header();
+    let mut output = BufWriter::new(output);
+    let mut stats = ExportStats::default();
+    loop {
+        let slice = match reader.next_message_slice() {
+            Ok([]) => break,
+            Ok(slice) => slice,
+            Err(DltParseError::MessageTooLarge { .. }) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        stats.read_messages += 1;
+        match dlt_message(slice, filter.as_ref(), with_storage_header) {
+            Ok((_, ParsedMessage::Item(_)))
+                if rewriter.is_duplicate(slice, with_storage_header) =>
+            {
+                stats.duplicate_messages += 1
+            }
+            Ok((_, ParsedMessage::Item(message))) => {
+                let rewrites = rewriter.rewrites();
+                let slice = rewriter.apply(slice, with_storage_header);
+                let message = match rewrites {
+                    true => match dlt_message(slice, None, with_storage_header) {
+                        Ok((_, ParsedMessage::Item(message))) => message,
+                        _ => message,
+                    },
+                    false => message,
+                };
+                let index = stats.read_messages - 1;
+                stats.written_bytes += write(&mut output, index, slice, &message)?;
+                stats.written_messages += 1;
+            }
+            Ok((_, ParsedMessage::FilteredOut(_))) => stats.filtered_messages += 1,
+            Ok((_, ParsedMessage::Invalid)) | Err(DltParseError::ParsingHickup(_)) => {
+                stats.invalid_messages += 1
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    output.flush()?;
+    stats.invalid_messages += reader.stats().invalid_messages;
+    stats.bytes_skipped = reader.stats().bytes_skipped;
+    Ok(stats)
+}
+
+/// How a file is split into parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// parts of at most this many bytes, unless a single message is larger
+    Size(u64),
+    /// parts that cover at most this duration of storage timestamps
+    Duration(Duration),
+    /// one part per ECU id of the storage headers
+    Ecu,
+}
+
+/// Identifies a part of a split
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SplitPart {
+    /// number of the part, counted from 0, when splitting by size or duration
+    Index(usize),
+    /// ECU id of the messages when splitting by ECU
+    Ecu(DltId),
+}
+
+/// Split the messages of a storage file into several outputs
+///
+/// `create_output` is called once for each new part. Messages are written
+/// unchanged, each with its storage header, so every part is a valid
+/// storage file on its own.
+pub fn split<R: Read, W: Write>(
+    input: R,
+    split_by: SplitBy,
+    mut create_output: impl FnMut(&SplitPart) -> std::io::Result<W>,
+    options: ExportOptions,
+) -> Result<ExportStats, Error> {
+    let (mut reader, mut rewriter) = options.reader(input)?;
+    let with_storage_header = reader.with_storage_header();
+    let mut outputs: HashMap<SplitPart, BufWriter<W>> = HashMap::new();
+    let mut index = 0;
+    let mut part_size = 0;
+    let mut part_start = None;
+    let mut stats = ExportStats::default();
+    loop {
+        let slice = match reader.next_message_slice() {
+            Ok([]) => break,
+            Ok(slice) => slice,
+            Err(DltParseError::MessageTooLarge { .. }) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if !with_storage_header {
+            return Err(Error::MissingStorageHeaders);
+        }
+        stats.read_messages += 1;
+        if rewriter.is_duplicate(slice, with_storage_header) {
+            stats.duplicate_messages += 1;
+            continue;
+        }
+        let slice = rewriter.apply(slice, with_storage_header);
+        let storage_header = match dlt_storage_header(slice) {
+            Ok((_, Some((storage_header, _)))) => storage_header,
+            _ => {
+                stats.invalid_messages += 1;
+                continue;
+            }
+        };
+        let length = slice.len() as u64;
+        let next_part = match split_by {
+            SplitBy::Ecu => false,
+            SplitBy::Size(max) => {
+                let next_part = part_size > 0 && part_size + length > max;
+                if next_part {
+                    part_size = 0;
+                }
+                part_size += length;
+                next_part
+            }
+            SplitBy::Duration(duration) => {
+                let time = storage_header.timestamp.as_us();
+                match part_start {
+                    Some(start) if time < start + duration.as_micros() as u64 => false,
+                    start => {
+                        part_start = Some(time);
+                        start.is_some()
+                    }
+                }
+            }
+        };
+        if next_part {
+            // parts are written one after the other, the finished one can be closed
+            if let Some(mut finished) = outputs.remove(&SplitPart::Index(index)) {
+                finished.flush()?;
+            }
+            index += 1;
+        }
+        let part = match split_by {
+            SplitBy::Ecu => SplitPart::Ecu(storage_header.ecu_id),
+            _ => SplitPart::Index(index),
+        };
+        let output = match outputs.entry(part) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let output = BufWriter::new(create_output(entry.key())?);
+                entry.insert(output)
+            }
+        };
+        output.write_all(slice)?;
+        stats.written_messages += 1;
+        stats.written_bytes += length;
+    }
+    for output in outputs.values_mut() {
+        output.flush()?;
+    }
+    stats.invalid_messages += reader.stats().invalid_messages;
+    stats.bytes_skipped = reader.stats().bytes_skipped;
+    Ok(stats)
+}
+
+/// Split a storage file into files in `output_dir`
+///
+/// The parts are named after the input file, e.g. `trace_0.dlt`, `trace_1.dlt`
+/// or `trace_ECU1.dlt`. Bytes of ECU ids other than ASCII letters, digits, `_`
+/// and `-` are written as `%XX`, e.g. `trace_EC%2F1.dlt` for `EC/1`, an
+/// empty id as `%00`. Returns the paths of the created files.
+pub fn split_file(
+    input: &Path,
+    output_dir: &Path,
+    split_by: SplitBy,
+    options: ExportOptions,
+) -> Result<Vec<PathBuf>, Error> {
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut paths = vec![];
+    split(
+        File::open(input)?,
+        split_by,
+        |part| {
+            let suffix = match part {
+                SplitPart::Index(n) => n.to_string(),
+                SplitPart::Ecu(ecu_id) => file_name_id(ecu_id),
+            };
+            let path = output_dir.join(format!("{}_{}.dlt", stem, suffix));
+            let file = File::create(&path)?;
+            paths.push(path);
+            Ok(file)
+        },
+        options,
+    )?;
+    Ok(paths)
+}
+
+/// An id that can safely be used in a file name
+fn file_name_id(id: &DltId) -> String {
+    let bytes = &id.as_bytes()[..id.len().max(1)];
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' => char::from(*byte).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -16,7 +16,14 @@
 //!
 //! `fibex` contains support for non-verbose message information
 //! that is stored in FIBEX files (Field Bus Exchange Format)
-use crate::dlt::{ExtendedHeader, FloatWidth, StringCoding, TypeInfo, TypeInfoKind, TypeLength};
+use crate::{
+    dlt::{
+        Argument, ExtendedHeader, FloatWidth, LogLevel, Message, PayloadContent, StringCoding,
+        TypeInfo, TypeInfoKind, TypeLength,
+    },
+    parse::construct_arguments,
+    progress::CancellationToken,
+};
 use quick_xml::{
     events::{
         attributes::{AttrError, Attributes},
@@ -25,12 +32,13 @@ use quick_xml::{
     Reader as XmlReader,
 };
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, BTreeMap, HashMap},
     fs::File,
     hash::Hash,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Seek, SeekFrom},
     mem,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 use thiserror::Error;
 
@@ -51,6 +59,9 @@ pub enum Error {
     Attribute(#[from] AttrError),
     #[error("IO error: {0:?}")]
     Io(#[from] std::io::Error),
+    /// Reading was aborted with a `CancellationToken`
+    #[error("Reading was cancelled")]
+    Cancelled,
 }
 
 /// Contains all the paths of fibex files that should be combined into the model
@@ -61,6 +72,24 @@ pub enum Error {
 #[derive(Debug)]
 pub struct FibexConfig {
     pub fibex_file_paths: Vec<String>,
+    /// put the frames of each file into the namespace of its `ECU` element,
+    /// see `FibexNamespace::FromFile`
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub namespace_by_ecu: bool,
+}
+
+/// The ECU namespace the frames of a FIBEX file are put into
+///
+/// Frames of an ECU namespace are only used for messages of that ECU, so
+/// identical frame ids of different ECUs don't replace each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FibexNamespace {
+    /// frames are used for messages of all ECUs
+    Global,
+    /// frames are only used for messages of this ECU
+    Ecu(String),
+    /// use the id of the first `ECU` element of the file, `Global` if there is none
+    FromFile,
 }
 
 #[derive(Debug, PartialEq, Hash, Clone, Eq)]
@@ -72,9 +101,103 @@ pub struct FrameMetadataIdentification {
 
 /// The model represented by the FIBEX data
 #[derive(Debug, PartialEq, Clone)]
+///
+/// Lookups by message id use indices keyed by the numeric frame id, create the
+/// model with `FibexMetadata::new` to keep them in sync with the maps.
 pub struct FibexMetadata {
     pub frame_map_with_key: HashMap<FrameMetadataIdentification, FrameMetadata>, // TODO: avoid cloning on .get
     pub frame_map: HashMap<FrameId, FrameMetadata>,
+    frames_by_id: HashMap<u32, FrameMetadata>,
+    /// frames with application and context id, sorted by these ids
+    frames_by_id_with_key: HashMap<u32, Vec<FrameMetadata>>,
+    /// models of the ECU namespaces
+    ecus: HashMap<String, FibexMetadata>,
+}
+
+impl FibexMetadata {
+    pub fn new(
+        frame_map_with_key: HashMap<FrameMetadataIdentification, FrameMetadata>,
+        frame_map: HashMap<FrameId, FrameMetadata>,
+    ) -> Self {
+        let frames_by_id = frame_map
+            .iter()
+            .filter_map(|(id, frame)| Some((numeric_frame_id(id)?, frame.clone())))
+            .collect();
+        let mut frames_by_id_with_key: HashMap<u32, Vec<FrameMetadata>> = HashMap::new();
+        for (key, frame) in &frame_map_with_key {
+            if let Some(id) = numeric_frame_id(&key.frame_id) {
+                frames_by_id_with_key
+                    .entry(id)
+                    .or_default()
+                    .push(frame.clone());
+            }
+        }
+        for frames in frames_by_id_with_key.values_mut() {
+            frames.sort_by(|a, b| {
+                (&a.application_id, &a.context_id).cmp(&(&b.application_id, &b.context_id))
+            });
+        }
+        FibexMetadata {
+            frame_map_with_key,
+            frame_map,
+            frames_by_id,
+            frames_by_id_with_key,
+            ecus: HashMap::new(),
+        }
+    }
+
+    /// Add the model of an ECU namespace, replacing a previous one
+    pub fn insert_ecu(&mut self, ecu_id: String, fibex: FibexMetadata) {
+        self.ecus.insert(ecu_id, fibex);
+    }
+
+    /// The model of an ECU namespace
+    pub fn ecu(&self, ecu_id: &str) -> Option<&FibexMetadata> {
+        self.ecus.get(ecu_id)
+    }
+
+    /// The ids of all ECU namespaces
+    pub fn ecu_ids(&self) -> impl Iterator<Item = &String> {
+        self.ecus.keys()
+    }
+
+    /// Lookup a frame by the numeric message id of a non-verbose message
+    pub fn frame(&self, id: u32) -> Option<&FrameMetadata> {
+        self.frames_by_id.get(&id)
+    }
+
+    /// Lookup a frame by the numeric message id, application id and context id
+    pub fn frame_with_key(
+        &self,
+        id: u32,
+        application_id: &str,
+        context_id: &str,
+    ) -> Option<&FrameMetadata> {
+        self.frames_by_id_with_key.get(&id)?.iter().find(|frame| {
+            frame.application_id.as_deref() == Some(application_id)
+                && frame.context_id.as_deref() == Some(context_id)
+        })
+    }
+
+    /// The sorted numeric message ids of all frames in the model, including
+    /// the ECU namespaces
+    pub fn frame_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .frames_by_id
+            .keys()
+            .chain(self.frames_by_id_with_key.keys())
+            .copied()
+            .chain(self.ecus.values().flat_map(|ecu| ecu.frame_ids()))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// The numeric message id of frame ids like `ID_42`
+fn numeric_frame_id(frame_id: &str) -> Option<u32> {
+    frame_id.strip_prefix("ID_")?.parse().ok()
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -85,12 +208,259 @@ pub struct FrameMetadata {
     pub context_id: Option<ContextId>,
     pub message_type: Option<String>,
     pub message_info: Option<String>,
+    /// declared length of the payload (without the message id) in bytes
+    pub byte_length: usize,
+}
+
+impl FrameMetadata {
+    /// Decode the payload of a non-verbose message of this frame into typed
+    /// arguments, `None` if the payload does not match the signal types
+    pub fn decode(&self, message: &Message) -> Option<Vec<Argument>> {
+        let (id, data) = match &message.payload {
+            PayloadContent::NonVerbose(id, data) => (*id, data),
+            _ => return None,
+        };
+        let signal_types: Vec<TypeInfo> = self
+            .pdus
+            .iter()
+            .flat_map(|pdu| pdu.signal_types.iter().cloned())
+            .collect();
+        match construct_arguments(message.header.endianness, &signal_types, data) {
+            Ok(arguments) => Some(arguments),
+            Err(e) => {
+                warn!("could not decode non-verbose message {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Render a non-verbose message of this frame as text the way dlt-viewer shows it
+    ///
+    /// The static descriptions of PDUs without signals are interleaved with the
+    /// decoded signal values, all parts are separated by a space. Signal values
+    /// are converted with the compu-methods of their codings where available.
+    pub fn text(&self, message: &Message) -> Option<String> {
+        let mut arguments = self.decode(message)?.into_iter();
+        let mut parts: Vec<String> = vec![];
+        for pdu in &self.pdus {
+            if pdu.signal_types.is_empty() {
+                if let Some(description) = &pdu.description {
+                    parts.push(description.clone());
+                }
+            } else {
+                parts.extend(
+                    arguments
+                        .by_ref()
+                        .take(pdu.signal_types.len())
+                        .zip(pdu.compu_methods.iter())
+                        .map(|(arg, compu_method)| {
+                            match compu_method.as_ref().and_then(|c| c.apply_to(&arg)) {
+                                Some(physical) => physical.to_string(),
+                                None => arg.value.to_string(),
+                            }
+                        }),
+                );
+            }
+        }
+        Some(parts.join(" "))
+    }
+
+    /// The log level of the frame, taken from `MESSAGE_INFO` (e.g. `DLT_LOG_WARN`)
+    pub fn log_level(&self) -> Option<LogLevel> {
+        self.message_info
+            .as_deref()?
+            .strip_prefix("DLT_LOG_")?
+            .parse()
+            .ok()
+    }
+
+    /// Check the payload length of a non-verbose message of this frame against
+    /// the declared `BYTE-LENGTH`
+    ///
+    /// Frames with string or raw signals have no fixed length and are not checked.
+    pub fn check_payload_length(&self, message: &Message) -> Option<LengthMismatch> {
+        let (id, data) = match &message.payload {
+            PayloadContent::NonVerbose(id, data) => (*id, data),
+            _ => return None,
+        };
+        let has_fixed_length = self
+            .pdus
+            .iter()
+            .flat_map(|pdu| pdu.signal_types.iter())
+            .all(|signal_type| fixed_width_in_bytes(signal_type).is_some());
+        (has_fixed_length && data.len() != self.byte_length).then_some(LengthMismatch {
+            message_id: id,
+            declared: self.byte_length,
+            actual: data.len(),
+        })
+    }
+}
+
+/// The size of a signal in a non-verbose payload, `None` if it has variable length
+fn fixed_width_in_bytes(type_info: &TypeInfo) -> Option<usize> {
+    match type_info.kind {
+        TypeInfoKind::Bool => Some(1),
+        TypeInfoKind::Signed(len) | TypeInfoKind::Unsigned(len) => Some(len.width_in_bytes()),
+        TypeInfoKind::Float(width) => Some(width.width_in_bytes()),
+        TypeInfoKind::SignedFixedPoint(_)
+        | TypeInfoKind::UnsignedFixedPoint(_)
+        | TypeInfoKind::StringType
+        | TypeInfoKind::Raw => None,
+    }
+}
+
+/// A non-verbose payload whose length differs from the length declared in the fibex model
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LengthMismatch {
+    pub message_id: u32,
+    /// `BYTE-LENGTH` of the frame
+    pub declared: usize,
+    /// length of the payload without the message id
+    pub actual: usize,
+}
+
+impl std::fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "non-verbose message {}: payload has {} bytes, fibex declares {}",
+            self.message_id, self.actual, self.declared
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct PduMetadata {
     pub description: Option<String>,
     pub signal_types: Vec<TypeInfo>,
+    /// compu-methods of the signals' codings, one entry for each signal type
+    pub compu_methods: Vec<Option<CompuMethod>>,
+    /// declared length of the PDU in bytes
+    pub byte_length: usize,
+}
+
+/// Conversion of internal signal values to physical values or texts,
+/// read from the `COMPU-METHODS` of a coding
+///
+/// All categories (`TEXTTABLE`, `LINEAR`, `SCALE_LINEAR`, ...) are represented
+/// as a list of scales, the first scale that contains the value is applied.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompuMethod {
+    pub scales: Vec<CompuScale>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompuScale {
+    pub lower_limit: Option<f64>,
+    pub upper_limit: Option<f64>,
+    pub value: CompuValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CompuValue {
+    /// a symbolic name, e.g. for enumerations
+    Text(String),
+    /// `numerator(x) / denominator(x)`, with the coefficients of the polynomials
+    /// in ascending order (e.g. `[offset, factor]` for linear scalings)
+    Rational {
+        numerator: Vec<f64>,
+        denominator: Vec<f64>,
+    },
+}
+
+/// The result of applying a compu-method to a signal value
+#[derive(Debug, PartialEq, Clone)]
+pub enum PhysicalValue {
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for PhysicalValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhysicalValue::Number(v) => write!(f, "{}", v),
+            PhysicalValue::Text(t) => f.write_str(t),
+        }
+    }
+}
+
+impl CompuScale {
+    fn contains(&self, value: f64) -> bool {
+        !matches!(self.lower_limit, Some(lower) if value < lower)
+            && !matches!(self.upper_limit, Some(upper) if value > upper)
+    }
+}
+
+impl CompuMethod {
+    /// Convert an internal value, `None` if no scale applies
+    pub fn apply(&self, value: f64) -> Option<PhysicalValue> {
+        let polynomial = |coefficients: &[f64]| {
+            coefficients
+                .iter()
+                .rev()
+                .fold(0.0, |acc, coefficient| acc * value + coefficient)
+        };
+        match &self.scales.iter().find(|s| s.contains(value))?.value {
+            CompuValue::Text(text) => Some(PhysicalValue::Text(text.clone())),
+            CompuValue::Rational {
+                numerator,
+                denominator,
+            } => {
+                let denominator = if denominator.is_empty() {
+                    1.0
+                } else {
+                    polynomial(denominator)
+                };
+                if denominator == 0.0 {
+                    None
+                } else {
+                    Some(PhysicalValue::Number(polynomial(numerator) / denominator))
+                }
+            }
+        }
+    }
+
+    /// Convert the value of a decoded argument, `None` for non-numeric arguments
+    pub fn apply_to(&self, argument: &Argument) -> Option<PhysicalValue> {
+        self.apply(argument.numeric_value()?)
+    }
+}
+
+/// The kind of FIBEX element that was defined more than once
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FibexItemKind {
+    Frame,
+    Pdu,
+    Signal,
+    Coding,
+}
+
+/// An id that was defined in more than one place
+///
+/// Files are `None` for FIBEX data that was not read from a file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FibexConflict {
+    pub kind: FibexItemKind,
+    pub id: String,
+    /// where the definition comes from that is used in the model
+    pub used_from: Option<PathBuf>,
+    /// where the ignored definition comes from
+    pub ignored_from: Option<PathBuf>,
+}
+
+/// Conflicts found when combining FIBEX data
+///
+/// The first definition of a frame or PDU is used, while signals and codings
+/// are replaced by later definitions.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FibexMergeReport {
+    pub conflicts: Vec<FibexConflict>,
+}
+
+impl FibexMergeReport {
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
 }
 
 pub type FrameId = String;
@@ -279,43 +649,203 @@ pub fn gather_fibex_data(fibex: FibexConfig) -> Option<FibexMetadata> {
             .into_iter()
             .map(PathBuf::from)
             .collect();
-        match read_fibexes(paths) {
-            Ok(res) => Some(res),
-            Err(e) => {
-                warn!("error reading fibex {}", e);
-                None
-            }
+        let namespace = if fibex.namespace_by_ecu {
+            FibexNamespace::FromFile
+        } else {
+            FibexNamespace::Global
+        };
+        let file_count = paths.len();
+        let result = read_fibexes_recovering(
+            paths
+                .into_iter()
+                .map(|path| (path, namespace.clone()))
+                .collect(),
+            |_| {},
+        );
+        for (path, e) in &result.errors {
+            warn!("error reading fibex {:?}: {}", path, e);
+        }
+        if result.failed_files == file_count {
+            None
+        } else {
+            Some(result.fibex)
         }
     }
 }
 
-pub(crate) fn read_fibexes(files: Vec<PathBuf>) -> Result<FibexMetadata, Error> {
-    let mut frames = vec![];
-    let mut frame_map_with_key: HashMap<FrameMetadataIdentification, FrameMetadata> =
-        HashMap::new();
-    let mut frame_map: HashMap<FrameId, FrameMetadata> = HashMap::new();
-    let mut pdu_by_id = HashMap::new();
-    let mut signals_map = HashMap::new();
-    let mut codings_map = HashMap::new();
-    let mut pdus = vec![];
+/// Combine multiple FIBEX files into one model, fails if any file can't be read
+pub fn read_fibexes(files: Vec<PathBuf>) -> Result<FibexMetadata, Error> {
+    read_fibexes_with_report(files).map(|(fibex, _)| fibex)
+}
+
+/// Combine multiple FIBEX files and report the ids that are defined more than once
+pub fn read_fibexes_with_report(
+    files: Vec<PathBuf>,
+) -> Result<(FibexMetadata, FibexMergeReport), Error> {
+    let mut data = FibexData::default();
     for f in files {
         debug!("read_fibexe from {:?}", f);
-        let mut reader = Reader::from_file(f)?;
+        data.read(&mut Reader::from_file(&f)?, Some(f))?;
+    }
+    data.into_metadata()
+}
+
+/// Combine multiple FIBEX files, putting the frames of each file into its ECU namespace
+///
+/// PDUs, signals and codings are only shared between files of the same
+/// namespace, conflicts are reported per namespace.
+pub fn read_fibexes_namespaced(
+    files: Vec<(PathBuf, FibexNamespace)>,
+) -> Result<(FibexMetadata, FibexMergeReport), Error> {
+    let mut data = NamespacedData::default();
+    for (f, namespace) in files {
+        debug!("read_fibexe from {:?} into {:?}", f, namespace);
+        let source = FibexSourceData::read(&mut Reader::from_file(&f)?, Some(f))?;
+        data.add(source, namespace);
+    }
+    let mut errors = vec![];
+    let result = data.into_metadata_skipping(&mut errors);
+    match errors.into_iter().next() {
+        Some((_, e)) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// Progress of reading multiple FIBEX files, reported after each file
+#[derive(Debug)]
+pub struct FibexProgress<'a> {
+    pub path: &'a Path,
+    /// number of files read so far, including this one
+    pub done: usize,
+    pub total: usize,
+    /// the error if the file could not be read
+    pub error: Option<&'a Error>,
+}
+
+/// The model combined from the files that could be read, see `read_fibexes_recovering`
+#[derive(Debug)]
+pub struct FibexReadResult {
+    pub fibex: FibexMetadata,
+    pub report: FibexMergeReport,
+    /// files that could not be read, and frames that were skipped because of
+    /// missing PDUs (with the file of the frame)
+    pub errors: Vec<(PathBuf, Error)>,
+    /// number of files that could not be read at all
+    pub failed_files: usize,
+}
+
+/// Like `read_fibexes_namespaced`, but files that can't be read are skipped
+/// instead of failing the whole model
+///
+/// The `progress` callback is invoked after each file.
+pub fn read_fibexes_recovering(
+    files: Vec<(PathBuf, FibexNamespace)>,
+    progress: impl FnMut(FibexProgress),
+) -> FibexReadResult {
+    read_fibexes_cancellable(files, progress, &CancellationToken::new())
+        .expect("a new token is never cancelled")
+}
+
+/// Like `read_fibexes_recovering`, but fails with `Error::Cancelled` once the
+/// token is cancelled
+///
+/// The token is checked before each file.
+pub fn read_fibexes_cancellable(
+    files: Vec<(PathBuf, FibexNamespace)>,
+    mut progress: impl FnMut(FibexProgress),
+    cancel: &CancellationToken,
+) -> Result<FibexReadResult, Error> {
+    let total = files.len();
+    let mut errors = vec![];
+    let mut data = NamespacedData::default();
+    for (index, (f, namespace)) in files.into_iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        debug!("read_fibexe from {:?} into {:?}", f, namespace);
+        let result = Reader::from_file(&f)
+            .and_then(|mut reader| FibexSourceData::read(&mut reader, Some(f.clone())));
+        progress(FibexProgress {
+            path: &f,
+            done: index + 1,
+            total,
+            error: result.as_ref().err(),
+        });
+        match result {
+            Ok(source) => data.add(source, namespace),
+            Err(e) => errors.push((f, e)),
+        }
+    }
+    let failed_files = errors.len();
+    let mut frame_errors = vec![];
+    let (fibex, report) = data.into_metadata_skipping(&mut frame_errors);
+    errors.extend(
+        frame_errors
+            .into_iter()
+            .map(|(path, e)| (path.unwrap_or_default(), e)),
+    );
+    Ok(FibexReadResult {
+        fibex,
+        report,
+        errors,
+        failed_files,
+    })
+}
+
+/// Read the FIBEX model from any buffered reader, e.g. a network stream or an archive entry
+pub fn read_fibex_from_reader<R: BufRead>(reader: R) -> Result<FibexMetadata, Error> {
+    let mut data = FibexData::default();
+    data.read(&mut Reader::from_reader(reader), None)?;
+    data.into_metadata().map(|(fibex, _)| fibex)
+}
+
+/// Read the FIBEX model from a FIBEX file that is held in memory
+pub fn read_fibex_from_bytes(bytes: &[u8]) -> Result<FibexMetadata, Error> {
+    read_fibex_from_reader(bytes)
+}
+
+/// The raw data read from a single FIBEX source
+#[derive(Default)]
+struct FibexSourceData {
+    path: Option<PathBuf>,
+    /// id of the first `ECU` element
+    ecu_id: Option<String>,
+    frames: Vec<(FrameId, FrameReadData)>,
+    pdus: Vec<(String, PduReadData)>,
+    signals: Vec<(String, String)>,
+    codings: Vec<(String, String, Option<CompuMethod>)>,
+}
+
+impl FibexSourceData {
+    fn read<B: BufRead>(reader: &mut Reader<B>, path: Option<PathBuf>) -> Result<Self, Error> {
+        let mut data = FibexSourceData {
+            path,
+            ..Default::default()
+        };
         loop {
             match reader.read_event()? {
                 Event::PduStart { id } => {
-                    pdus.push((id, read_pdu(&mut reader)?));
+                    data.pdus.push((id, read_pdu(reader)?));
                 }
                 Event::FrameStart { id } => {
-                    frames.push((id, read_frame(&mut reader)?));
+                    data.frames.push((id, read_frame(reader)?));
+                }
+                Event::Eof => return Ok(data),
+                Event::Ecu { id } => {
+                    if data.ecu_id.is_none() {
+                        data.ecu_id = Some(id);
+                    }
                 }
-                Event::Eof => break,
                 Event::Signal { id, coding_ref } => {
                     trace!("found signal {} (coding_ref={})", id, coding_ref);
-                    signals_map.insert(id, coding_ref);
+                    data.signals.push((id, coding_ref));
                 }
-                Event::Coding { id, base_data_type } => {
-                    codings_map.insert(id, base_data_type);
+                Event::Coding {
+                    id,
+                    base_data_type,
+                    compu_method,
+                } => {
+                    data.codings.push((id, base_data_type, compu_method));
                 }
                 x => {
                     debug!("read_fibex some other event: {:?}", x);
@@ -323,87 +853,414 @@ pub(crate) fn read_fibexes(files: Vec<PathBuf>) -> Result<FibexMetadata, Error>
             }
         }
     }
-    for (id, (description, signal_refs)) in pdus {
-        match pdu_by_id.entry(id) {
-            Entry::Occupied(e) => warn!("duplicate PDU ID {} found in fibexes", e.key()),
-            Entry::Vacant(v) => {
-                v.insert(PduMetadata {
-                    description,
-                    signal_types: signal_refs
-                        .into_iter()
-                        .filter_map(|type_ref| {
-                            type_info_for_signal_ref(type_ref, &signals_map, &codings_map)
-                        })
-                        .collect(),
-                });
-            }
+}
+
+/// The raw data of FIBEX sources, separated by ECU namespace
+#[derive(Default)]
+struct NamespacedData {
+    global: FibexData,
+    ecus: BTreeMap<String, FibexData>,
+}
+
+impl NamespacedData {
+    fn add(&mut self, source: FibexSourceData, namespace: FibexNamespace) {
+        let ecu_id = match namespace {
+            FibexNamespace::Global => None,
+            FibexNamespace::Ecu(ecu_id) => Some(ecu_id),
+            FibexNamespace::FromFile => source.ecu_id.clone(),
+        };
+        match ecu_id {
+            Some(ecu_id) => self.ecus.entry(ecu_id).or_default().add(source),
+            None => self.global.add(source),
         }
     }
-    for (
-        id,
-        FrameReadData {
-            short_name,
-            context_id,
-            application_id,
-            message_type,
-            message_info,
-            pdu_refs,
-        },
-    ) in frames
-    {
-        let frame = FrameMetadata {
-            short_name,
-            pdus: pdu_refs
+
+    /// See `FibexData::into_metadata_skipping`, conflicts of all namespaces are reported
+    fn into_metadata_skipping(
+        self,
+        errors: &mut Vec<(Option<PathBuf>, Error)>,
+    ) -> (FibexMetadata, FibexMergeReport) {
+        let (mut fibex, mut report) = self.global.into_metadata_skipping(errors);
+        for (ecu_id, data) in self.ecus {
+            let (ecu_fibex, ecu_report) = data.into_metadata_skipping(errors);
+            report.conflicts.extend(ecu_report.conflicts);
+            fibex.insert_ecu(ecu_id, ecu_fibex);
+        }
+        (fibex, report)
+    }
+}
+
+/// The raw data collected from one or more FIBEX sources
+#[derive(Default)]
+struct FibexData {
+    /// the file of each source that was read (`None` for other readers)
+    sources: Vec<Option<PathBuf>>,
+    frames: Vec<(FrameId, FrameReadData, usize)>,
+    pdus: Vec<(String, PduReadData, usize)>,
+    codings: Codings,
+    signal_sources: HashMap<String, usize>,
+    coding_sources: HashMap<String, usize>,
+    conflicts: Vec<FibexConflict>,
+}
+
+impl FibexData {
+    fn conflict(
+        &self,
+        kind: FibexItemKind,
+        id: &str,
+        used: usize,
+        ignored: usize,
+    ) -> FibexConflict {
+        FibexConflict {
+            kind,
+            id: id.to_string(),
+            used_from: self.sources[used].clone(),
+            ignored_from: self.sources[ignored].clone(),
+        }
+    }
+
+    fn read<B: BufRead>(
+        &mut self,
+        reader: &mut Reader<B>,
+        path: Option<PathBuf>,
+    ) -> Result<(), Error> {
+        self.add(FibexSourceData::read(reader, path)?);
+        Ok(())
+    }
+
+    fn add(&mut self, data: FibexSourceData) {
+        let source = self.sources.len();
+        self.sources.push(data.path);
+        self.pdus
+            .extend(data.pdus.into_iter().map(|(id, pdu)| (id, pdu, source)));
+        self.frames.extend(
+            data.frames
                 .into_iter()
-                .map(|r| {
-                    pdu_by_id
-                        .get(&r)
-                        .cloned()
-                        .ok_or_else(|| Error::FibexStructure(format!("pdu {} not found", &r)))
-                })
-                .collect::<Result<Vec<_>, Error>>()?,
-            application_id,
-            context_id,
-            message_type,
-            message_info,
-        };
-        if let (Some(context_id), Some(application_id)) =
-            (frame.context_id.as_ref(), frame.application_id.as_ref())
-        {
-            let key = FrameMetadataIdentification {
-                context_id: context_id.clone(),
-                app_id: application_id.clone(),
-                frame_id: id.clone(),
+                .map(|(id, frame)| (id, frame, source)),
+        );
+        // signals and codings that are defined more than once are replaced
+        for (id, coding_ref) in data.signals {
+            if let Some(previous) = self.signal_sources.insert(id.clone(), source) {
+                warn!("duplicate Signal id={}", id);
+                let conflict = self.conflict(FibexItemKind::Signal, &id, source, previous);
+                self.conflicts.push(conflict);
+            }
+            self.codings.signals.insert(id, coding_ref);
+        }
+        for (id, base_data_type, compu_method) in data.codings {
+            if let Some(previous) = self.coding_sources.insert(id.clone(), source) {
+                warn!("duplicate Coding id={}", id);
+                let conflict = self.conflict(FibexItemKind::Coding, &id, source, previous);
+                self.conflicts.push(conflict);
+            }
+            self.codings.insert(id, base_data_type, compu_method);
+        }
+    }
+
+    /// Combine the collected data, the first definition of a PDU or frame is used
+    fn into_metadata(self) -> Result<(FibexMetadata, FibexMergeReport), Error> {
+        let mut errors = vec![];
+        let result = self.into_metadata_skipping(&mut errors);
+        match errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    /// Like `into_metadata`, but frames that refer to missing PDUs are skipped
+    /// and added to the errors (with the file of the frame)
+    fn into_metadata_skipping(
+        mut self,
+        errors: &mut Vec<(Option<PathBuf>, Error)>,
+    ) -> (FibexMetadata, FibexMergeReport) {
+        let frames = mem::take(&mut self.frames);
+        let pdus = mem::take(&mut self.pdus);
+        let mut frame_map_with_key: HashMap<FrameMetadataIdentification, FrameMetadata> =
+            HashMap::new();
+        let mut frame_map: HashMap<FrameId, FrameMetadata> = HashMap::new();
+        let mut frame_sources: HashMap<FrameId, usize> = HashMap::new();
+        let mut pdu_by_id = HashMap::new();
+        let mut pdu_sources: HashMap<String, usize> = HashMap::new();
+        for (id, pdu_data, source) in pdus {
+            match pdu_by_id.entry(id) {
+                Entry::Occupied(e) => {
+                    warn!("duplicate PDU ID {} found in fibexes", e.key());
+                    let conflict =
+                        self.conflict(FibexItemKind::Pdu, e.key(), pdu_sources[e.key()], source);
+                    self.conflicts.push(conflict);
+                }
+                Entry::Vacant(v) => {
+                    pdu_sources.insert(v.key().clone(), source);
+                    v.insert(self.codings.pdu_metadata(pdu_data));
+                }
+            }
+        }
+        for (id, frame_data, source) in frames {
+            let frame = match frame_data.into_frame(|r| {
+                pdu_by_id
+                    .get(r)
+                    .cloned()
+                    .ok_or_else(|| Error::FibexStructure(format!("pdu {} not found", r)))
+            }) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    errors.push((self.sources[source].clone(), e));
+                    continue;
+                }
             };
+            if let (Some(context_id), Some(application_id)) =
+                (frame.context_id.as_ref(), frame.application_id.as_ref())
+            {
+                let key = FrameMetadataIdentification {
+                    context_id: context_id.clone(),
+                    app_id: application_id.clone(),
+                    frame_id: id.clone(),
+                };
 
-            match frame_map_with_key.entry(key) {
+                match frame_map_with_key.entry(key) {
+                    Entry::Occupied(e) => {
+                        let key = e.key();
+                        warn!(
+                            "duplicate Frame context_id={} application_id={} id={}",
+                            key.context_id, key.app_id, key.frame_id
+                        )
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(frame.clone());
+                    }
+                }
+            } // else error?
+            match frame_map.entry(id) {
                 Entry::Occupied(e) => {
-                    let key = e.key();
-                    warn!(
-                        "duplicate Frame context_id={} application_id={} id={}",
-                        key.context_id, key.app_id, key.frame_id
-                    )
+                    warn!("duplicate Frame id={}", e.key());
+                    let conflict = self.conflict(
+                        FibexItemKind::Frame,
+                        e.key(),
+                        frame_sources[e.key()],
+                        source,
+                    );
+                    self.conflicts.push(conflict);
                 }
                 Entry::Vacant(entry) => {
-                    entry.insert(frame.clone());
+                    frame_sources.insert(entry.key().clone(), source);
+                    entry.insert(frame);
                 }
             }
-        } // else error?
-        match frame_map.entry(id) {
-            Entry::Occupied(e) => warn!("duplicate Frame id={}", e.key()),
-            Entry::Vacant(entry) => {
-                entry.insert(frame);
+        }
+        debug!("parsed fibex data OK");
+        (
+            FibexMetadata::new(frame_map_with_key, frame_map),
+            FibexMergeReport {
+                conflicts: self.conflicts,
+            },
+        )
+    }
+}
+
+/// Location of a frame or PDU definition: index of the file and byte offset
+type Location = (usize, u64);
+
+/// A FIBEX model that is loaded on demand, for huge FIBEX sets
+///
+/// Creating the index reads all files once, but only keeps the byte offsets of
+/// frames and PDUs together with the signals and codings (needed to determine the
+/// signal types). Frames are parsed from the files when they are first looked up
+/// and cached afterwards.
+pub struct LazyFibex {
+    files: Vec<PathBuf>,
+    frames: HashMap<u32, Vec<Location>>,
+    pdus: HashMap<String, Location>,
+    codings: Codings,
+    cache: Mutex<HashMap<Location, Option<Arc<FrameMetadata>>>>,
+}
+
+impl LazyFibex {
+    /// Index the frames and PDUs of the FIBEX files
+    ///
+    /// Like for `read_fibexes`, the first definition of a PDU is used.
+    pub fn index(files: Vec<PathBuf>) -> Result<Self, Error> {
+        let mut frames: HashMap<u32, Vec<Location>> = HashMap::new();
+        let mut pdus = HashMap::new();
+        let mut codings = Codings::default();
+        for (index, path) in files.iter().enumerate() {
+            debug!("index fibex {:?}", path);
+            let mut reader = Reader::from_file(path)?;
+            loop {
+                match reader.read_event()? {
+                    Event::FrameStart { id } => match numeric_frame_id(&id) {
+                        Some(id) => frames
+                            .entry(id)
+                            .or_default()
+                            .push((index, reader.event_offset as u64)),
+                        None => debug!("skip frame with non-numeric id {}", id),
+                    },
+                    Event::PduStart { id } => {
+                        pdus.entry(id)
+                            .or_insert((index, reader.event_offset as u64));
+                    }
+                    Event::Signal { id, coding_ref } => {
+                        codings.signals.insert(id, coding_ref);
+                    }
+                    Event::Coding {
+                        id,
+                        base_data_type,
+                        compu_method,
+                    } => codings.insert(id, base_data_type, compu_method),
+                    Event::Eof => break,
+                    _ => {}
+                }
             }
         }
+        Ok(LazyFibex {
+            files,
+            frames,
+            pdus,
+            codings,
+            cache: Mutex::new(HashMap::new()),
+        })
     }
-    debug!("parsed fibex data OK");
-    Ok(FibexMetadata {
-        frame_map_with_key,
-        frame_map,
-    })
+
+    /// Number of indexed frame ids
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Lookup the frame for a message id, see `extract_metadata`
+    ///
+    /// Frames that can't be parsed are logged and treated as missing.
+    pub fn frame(
+        &self,
+        id: u32,
+        extended_header: Option<&ExtendedHeader>,
+    ) -> Option<Arc<FrameMetadata>> {
+        let locations = self.frames.get(&id)?;
+        locations.iter().find_map(|location| {
+            let frame = self.cached_frame(*location)?;
+            match extended_header {
+                Some(h) => (frame.application_id.as_deref() == Some(h.application_id.as_str())
+                    && frame.context_id.as_deref() == Some(h.context_id.as_str()))
+                .then_some(frame),
+                None => Some(frame),
+            }
+        })
+    }
+
+    /// See `decode_nonverbose`
+    pub fn decode_nonverbose(&self, message: &Message) -> Option<Vec<Argument>> {
+        self.frame(nonverbose_id(message)?, message.extended_header.as_ref())?
+            .decode(message)
+    }
+
+    /// See `nonverbose_text`
+    pub fn nonverbose_text(&self, message: &Message) -> Option<String> {
+        self.frame(nonverbose_id(message)?, message.extended_header.as_ref())?
+            .text(message)
+    }
+
+    /// See `check_payload_length`
+    pub fn check_payload_length(&self, message: &Message) -> Option<LengthMismatch> {
+        self.frame(nonverbose_id(message)?, message.extended_header.as_ref())?
+            .check_payload_length(message)
+    }
+
+    fn cached_frame(&self, location: Location) -> Option<Arc<FrameMetadata>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .entry(location)
+            .or_insert_with(|| match self.load_frame(location) {
+                Ok(frame) => Some(Arc::new(frame)),
+                Err(e) => {
+                    warn!("could not load frame at {:?}: {}", location, e);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    fn load_frame(&self, location: Location) -> Result<FrameMetadata, Error> {
+        let frame_data = self.read_at(location, |reader| match reader.read_event()? {
+            Event::FrameStart { .. } => read_frame(reader),
+            e => Err(Error::FibexStructure(format!(
+                "expected frame, got {:?}",
+                e
+            ))),
+        })?;
+        frame_data.into_frame(|r| {
+            let location = *self
+                .pdus
+                .get(r)
+                .ok_or_else(|| Error::FibexStructure(format!("pdu {} not found", r)))?;
+            let pdu_data = self.read_at(location, |reader| match reader.read_event()? {
+                Event::PduStart { .. } => read_pdu(reader),
+                e => Err(Error::FibexStructure(format!("expected pdu, got {:?}", e))),
+            })?;
+            Ok(self.codings.pdu_metadata(pdu_data))
+        })
+    }
+
+    fn read_at<T, F>(&self, (index, offset): Location, read: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Reader<BufReader<File>>) -> Result<T, Error>,
+    {
+        let mut file = File::open(&self.files[index])?;
+        file.seek(SeekFrom::Start(offset))?;
+        read(&mut Reader::from_reader(BufReader::new(file)))
+    }
+}
+
+/// Signals and codings, needed to determine the types of PDU signals
+#[derive(Default)]
+struct Codings {
+    /// coding ref by signal id
+    signals: HashMap<String, String>,
+    /// base data type by coding id
+    base_types: HashMap<String, String>,
+    compu_methods: HashMap<String, CompuMethod>,
 }
 
-fn read_pdu(reader: &mut Reader<BufReader<File>>) -> Result<(Option<String>, Vec<String>), Error> {
+impl Codings {
+    fn insert(&mut self, id: String, base_data_type: String, compu_method: Option<CompuMethod>) {
+        match compu_method {
+            Some(compu_method) => self.compu_methods.insert(id.clone(), compu_method),
+            None => self.compu_methods.remove(&id),
+        };
+        self.base_types.insert(id, base_data_type);
+    }
+
+    fn pdu_metadata(&self, pdu_data: PduReadData) -> PduMetadata {
+        let (signal_types, compu_methods) = pdu_data
+            .signal_refs
+            .into_iter()
+            .filter_map(|type_ref| {
+                let compu_method = self
+                    .signals
+                    .get(&type_ref)
+                    .and_then(|coding| self.compu_methods.get(coding))
+                    .cloned();
+                type_info_for_signal_ref(type_ref, &self.signals, &self.base_types)
+                    .map(|type_info| (type_info, compu_method))
+            })
+            .unzip();
+        PduMetadata {
+            description: pdu_data.description,
+            signal_types,
+            compu_methods,
+            byte_length: pdu_data.byte_length,
+        }
+    }
+}
+
+struct PduReadData {
+    description: Option<String>,
+    signal_refs: Vec<String>,
+    byte_length: usize,
+}
+
+fn read_pdu<B: BufRead>(reader: &mut Reader<B>) -> Result<PduReadData, Error> {
     let mut signal_refs = vec![];
     loop {
         match reader.read_event()? {
@@ -414,9 +1271,17 @@ fn read_pdu(reader: &mut Reader<BufReader<File>>) -> Result<(Option<String>, Vec
             } => {
                 signal_refs.push((sequence_number, signal_ref));
             }
-            Event::PduEnd { description, .. } => {
+            Event::PduEnd {
+                description,
+                byte_length,
+                ..
+            } => {
                 signal_refs.sort_by_key(|s| s.0);
-                return Ok((description, signal_refs.into_iter().map(|v| v.1).collect()));
+                return Ok(PduReadData {
+                    description,
+                    signal_refs: signal_refs.into_iter().map(|v| v.1).collect(),
+                    byte_length,
+                });
             }
             _ => {}
         }
@@ -430,9 +1295,31 @@ struct FrameReadData {
     message_type: Option<String>,
     message_info: Option<String>,
     pdu_refs: Vec<String>,
+    byte_length: usize,
 }
 
-fn read_frame(reader: &mut Reader<BufReader<File>>) -> Result<FrameReadData, Error> {
+impl FrameReadData {
+    fn into_frame<F>(self, mut pdu: F) -> Result<FrameMetadata, Error>
+    where
+        F: FnMut(&str) -> Result<PduMetadata, Error>,
+    {
+        Ok(FrameMetadata {
+            short_name: self.short_name,
+            pdus: self
+                .pdu_refs
+                .iter()
+                .map(|r| pdu(r))
+                .collect::<Result<Vec<_>, Error>>()?,
+            application_id: self.application_id,
+            context_id: self.context_id,
+            message_type: self.message_type,
+            message_info: self.message_info,
+            byte_length: self.byte_length,
+        })
+    }
+}
+
+fn read_frame<B: BufRead>(reader: &mut Reader<B>) -> Result<FrameReadData, Error> {
     let mut pdus = vec![];
     let mut frame_context_id = None;
     let mut frame_application_id = None;
@@ -459,9 +1346,13 @@ fn read_frame(reader: &mut Reader<BufReader<File>>) -> Result<FrameReadData, Err
                 frame_message_type = message_type;
                 frame_message_info = message_info;
             }
-            Event::FrameEnd { short_name, .. } => {
+            Event::FrameEnd {
+                short_name,
+                byte_length,
+            } => {
                 pdus.sort_by_key(|p| p.0);
                 return Ok(FrameReadData {
+                    byte_length,
                     short_name,
                     context_id: frame_context_id,
                     application_id: frame_application_id,
@@ -479,6 +1370,7 @@ const B_SHORT_NAME: &[u8] = b"SHORT-NAME";
 const B_ID_REF: &[u8] = b"ID-REF";
 const B_ID: &[u8] = b"ID";
 const B_XSI_TYPE: &[u8] = b"xsi:type";
+const B_ECU: &[u8] = b"ECU";
 const B_PDU: &[u8] = b"PDU";
 const B_BYTE_LENGTH: &[u8] = b"BYTE-LENGTH";
 const B_PDU_TYPE: &[u8] = b"PDU-TYPE";
@@ -500,10 +1392,21 @@ const B_SIGNAL: &[u8] = b"SIGNAL";
 const B_CODING_REF: &[u8] = b"CODING-REF";
 const B_BASE_DATA_TYPE: &[u8] = b"BASE-DATA-TYPE";
 const B_CODED_TYPE: &[u8] = b"CODED-TYPE";
+const B_COMPU_SCALE: &[u8] = b"COMPU-SCALE";
+const B_COMPU_PHYS_TO_INTERNAL: &[u8] = b"COMPU-PHYS-TO-INTERNAL";
+const B_LOWER_LIMIT: &[u8] = b"LOWER-LIMIT";
+const B_UPPER_LIMIT: &[u8] = b"UPPER-LIMIT";
+const B_VT: &[u8] = b"VT";
+const B_V: &[u8] = b"V";
+const B_COMPU_NUMERATOR: &[u8] = b"COMPU-NUMERATOR";
+const B_COMPU_DENOMINATOR: &[u8] = b"COMPU-DENOMINATOR";
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum Event {
+    Ecu {
+        id: String,
+    },
     PduStart {
         id: String,
     },
@@ -542,12 +1445,14 @@ pub(crate) enum Event {
     Coding {
         id: String,
         base_data_type: String,
+        compu_method: Option<CompuMethod>,
     },
     Eof,
 }
 pub(crate) struct XmlReaderWithContext<B: BufRead> {
     xml_reader: XmlReader<B>,
-    file_path: PathBuf,
+    /// path of the file that is read, used to report line and column of errors
+    file_path: Option<PathBuf>,
 }
 impl<B: BufRead> XmlReaderWithContext<B> {
     pub fn buffer_position(&self) -> usize {
@@ -571,7 +1476,13 @@ impl<B: BufRead> XmlReaderWithContext<B> {
     }
     // Note: Use this only on fatal errors due performance.
     pub fn line_and_column(&self) -> Result<(usize, usize), Error> {
-        let s = std::fs::read_to_string(&self.file_path)?;
+        let file_path = self.file_path.as_ref().ok_or_else(|| {
+            Error::Parse(format!(
+                "no line information, position {}",
+                self.buffer_position()
+            ))
+        })?;
+        let s = std::fs::read_to_string(file_path)?;
         let mut line = 1;
         let mut column = 0;
         for c in s.chars().take(self.buffer_position()) {
@@ -590,6 +1501,13 @@ impl<B: BufRead> XmlReaderWithContext<B> {
             Error::Parse(format!("can't parse usize at {}:{}: {}", line, column, e))
         })
     }
+    pub fn read_f64(&mut self) -> Result<f64, Error> {
+        let text = self.read_text_buf()?;
+        text.trim().parse::<f64>().map_err(|e| {
+            let (line, column) = self.line_and_column().unwrap_or((0, 0));
+            Error::Parse(format!("can't parse f64 at {}:{}: {}", line, column, e))
+        })
+    }
     pub fn read_text_buf(&mut self) -> Result<String, Error> {
         self.read_text(&mut Vec::new())
     }
@@ -665,14 +1583,57 @@ pub(crate) struct Reader<B: BufRead> {
     message_type: Option<String>,
     message_info: Option<String>,
     base_data_type: Option<String>,
+    compu_scales: Vec<CompuScale>,
+    compu_scale: CompuScaleData,
+    /// inside `COMPU-PHYS-TO-INTERNAL`, whose scales are not used
+    in_phys_to_internal: bool,
+    /// byte offset of the xml event that was read last
+    event_offset: usize,
+}
+
+/// The parts of a `COMPU-SCALE` read so far
+#[derive(Default)]
+struct CompuScaleData {
+    lower_limit: Option<f64>,
+    upper_limit: Option<f64>,
+    text: Option<String>,
+    numerator: Vec<f64>,
+    denominator: Vec<f64>,
+    in_denominator: bool,
+}
+
+impl CompuScaleData {
+    fn into_scale(self) -> Option<CompuScale> {
+        let value = match self.text {
+            Some(text) => CompuValue::Text(text),
+            None if !self.numerator.is_empty() => CompuValue::Rational {
+                numerator: self.numerator,
+                denominator: self.denominator,
+            },
+            None => return None,
+        };
+        Some(CompuScale {
+            lower_limit: self.lower_limit,
+            upper_limit: self.upper_limit,
+            value,
+        })
+    }
 }
 
 impl Reader<BufReader<File>> {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        Ok(Reader {
+        let mut reader = Reader::from_reader(BufReader::new(File::open(path.as_ref())?));
+        reader.xml_reader.file_path = Some(path.as_ref().to_owned());
+        Ok(reader)
+    }
+}
+
+impl<B: BufRead> Reader<B> {
+    pub fn from_reader(reader: B) -> Self {
+        Reader {
             xml_reader: XmlReaderWithContext {
-                file_path: path.as_ref().to_owned(),
-                xml_reader: XmlReader::from_file(path)?,
+                file_path: None,
+                xml_reader: XmlReader::from_reader(reader),
             },
             buf: vec![],
             buf2: vec![],
@@ -688,7 +1649,11 @@ impl Reader<BufReader<File>> {
             message_type: None,
             message_info: None,
             base_data_type: None,
-        })
+            compu_scales: vec![],
+            compu_scale: CompuScaleData::default(),
+            in_phys_to_internal: false,
+            event_offset: 0,
+        }
     }
 }
 
@@ -696,8 +1661,14 @@ impl<B: BufRead> Reader<B> {
     #[allow(clippy::cognitive_complexity)]
     pub fn read_event(&mut self) -> Result<Event, Error> {
         loop {
+            self.event_offset = self.xml_reader.buffer_position();
             match self.xml_reader.read_event(&mut self.buf)? {
                 XmlEvent::Start(ref e) => match e.local_name().as_ref() {
+                    B_ECU => {
+                        if let Some(id) = self.xml_reader.attr_opt(e.attributes(), B_ID)? {
+                            return Ok(Event::Ecu { id });
+                        }
+                    }
                     B_PDU => {
                         self.short_name = None;
                         self.byte_length = None;
@@ -771,6 +1742,26 @@ impl<B: BufRead> Reader<B> {
                     B_CODING => {
                         self.id = Some(self.xml_reader.id_attr(e, B_CODING)?);
                         self.base_data_type = None;
+                        self.compu_scales.clear();
+                    }
+                    B_COMPU_SCALE => self.compu_scale = CompuScaleData::default(),
+                    B_COMPU_PHYS_TO_INTERNAL => self.in_phys_to_internal = true,
+                    B_LOWER_LIMIT => {
+                        self.compu_scale.lower_limit = Some(self.xml_reader.read_f64()?)
+                    }
+                    B_UPPER_LIMIT => {
+                        self.compu_scale.upper_limit = Some(self.xml_reader.read_f64()?)
+                    }
+                    B_VT => self.compu_scale.text = Some(self.xml_reader.read_text_buf()?),
+                    B_COMPU_NUMERATOR => self.compu_scale.in_denominator = false,
+                    B_COMPU_DENOMINATOR => self.compu_scale.in_denominator = true,
+                    B_V => {
+                        let v = self.xml_reader.read_f64()?;
+                        if self.compu_scale.in_denominator {
+                            self.compu_scale.denominator.push(v);
+                        } else {
+                            self.compu_scale.numerator.push(v);
+                        }
                     }
                     B_SIGNAL => {
                         self.id = Some(self.xml_reader.id_attr(e, B_SIGNAL)?);
@@ -908,8 +1899,20 @@ impl<B: BufRead> Reader<B> {
                             })?,
                         });
                     }
+                    B_COMPU_SCALE if !self.in_phys_to_internal => {
+                        if let Some(scale) = mem::take(&mut self.compu_scale).into_scale() {
+                            self.compu_scales.push(scale);
+                        }
+                    }
+                    B_COMPU_PHYS_TO_INTERNAL => self.in_phys_to_internal = false,
                     B_CODING => {
+                        let scales = mem::take(&mut self.compu_scales);
                         return Ok(Event::Coding {
+                            compu_method: if scales.is_empty() {
+                                None
+                            } else {
+                                Some(CompuMethod { scales })
+                            },
                             id: mem::take(&mut self.id).ok_or_else(|| {
                                 missing_attr_err(B_ID, B_CODING, self.xml_reader.line_and_column())
                             })?,
@@ -966,16 +1969,92 @@ pub fn extract_metadata<'a>(
     id: u32,
     extended_header: Option<&ExtendedHeader>,
 ) -> Option<&'a FrameMetadata> {
-    let id_text = format!("ID_{}", id);
     match extended_header {
-        Some(extended_header) => {
-            let frame_identifier = FrameMetadataIdentification {
-                context_id: extended_header.context_id.clone(),
-                app_id: extended_header.application_id.clone(),
-                frame_id: id_text,
-            };
-            fibex_metadata.frame_map_with_key.get(&frame_identifier)
-        }
-        None => fibex_metadata.frame_map.get(&id_text),
+        Some(extended_header) => fibex_metadata.frame_with_key(
+            id,
+            &extended_header.application_id,
+            &extended_header.context_id,
+        ),
+        None => fibex_metadata.frame(id),
+    }
+}
+
+/// lookup `FrameMetadata` in the namespace of an ECU, falling back to the frames
+/// that are used for all ECUs (see `extract_metadata`)
+pub fn extract_metadata_for_ecu<'a>(
+    fibex_metadata: &'a FibexMetadata,
+    ecu_id: Option<&str>,
+    id: u32,
+    extended_header: Option<&ExtendedHeader>,
+) -> Option<&'a FrameMetadata> {
+    ecu_id
+        .and_then(|ecu_id| fibex_metadata.ecu(ecu_id))
+        .and_then(|ecu| extract_metadata(ecu, id, extended_header))
+        .or_else(|| extract_metadata(fibex_metadata, id, extended_header))
+}
+
+/// lookup the `FrameMetadata` of a non-verbose message, using the ECU of the
+/// standard header (or else of the storage header) and the extended header
+pub fn frame_for_message<'a>(
+    fibex_metadata: &'a FibexMetadata,
+    message: &Message,
+) -> Option<&'a FrameMetadata> {
+    let ecu_id = message
+        .header
+        .ecu_id
+        .as_deref()
+        .or_else(|| message.storage_header.as_ref().map(|h| h.ecu_id.as_str()));
+    extract_metadata_for_ecu(
+        fibex_metadata,
+        ecu_id,
+        nonverbose_id(message)?,
+        message.extended_header.as_ref(),
+    )
+}
+
+/// Decode the payload of a non-verbose message into typed arguments, using the
+/// signal types of the frame's PDUs found in the fibex model
+///
+/// Returns `None` if the message is not non-verbose, if no frame is found for
+/// its message id or if the payload does not match the signal types.
+pub fn decode_nonverbose(
+    fibex_metadata: &FibexMetadata,
+    message: &Message,
+) -> Option<Vec<Argument>> {
+    frame_for_message(fibex_metadata, message)?.decode(message)
+}
+
+/// Render a non-verbose message as text the way dlt-viewer shows it
+///
+/// See `FrameMetadata::text`, returns `None` if the payload can't be decoded
+/// (see `decode_nonverbose`).
+pub fn nonverbose_text(fibex_metadata: &FibexMetadata, message: &Message) -> Option<String> {
+    frame_for_message(fibex_metadata, message)?.text(message)
+}
+
+/// Validate the payload length of a non-verbose message against the `BYTE-LENGTH`
+/// of its frame in the fibex model
+///
+/// Returns `None` if the length matches or can't be checked (no non-verbose
+/// message, unknown frame, or signals of variable length).
+pub fn check_payload_length(
+    fibex_metadata: &FibexMetadata,
+    message: &Message,
+) -> Option<LengthMismatch> {
+    frame_for_message(fibex_metadata, message)?.check_payload_length(message)
+}
+
+fn nonverbose_id(message: &Message) -> Option<u32> {
+    match &message.payload {
+        PayloadContent::NonVerbose(id, _) => Some(*id),
+        _ => None,
     }
 }
+
+/// The log level of a non-verbose message as defined in the fibex model
+///
+/// Non-verbose messages often come without an extended header and hence without
+/// a log level, this can be used to filter them by level nevertheless.
+pub fn nonverbose_log_level(fibex_metadata: &FibexMetadata, message: &Message) -> Option<LogLevel> {
+    frame_for_message(fibex_metadata, message)?.log_level()
+}
@@ -14,7 +14,32 @@
 
 //! # filter definitions for filtering dlt messages
 use crate::dlt;
-use std::{collections::HashSet, iter::FromIterator};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    iter::FromIterator,
+};
+use thiserror::Error;
+
+/// Errors detected when validating a filter configuration
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A log level that is not in the range 1 (FATAL) to 6 (VERBOSE)
+    #[error("Invalid log level: {0}")]
+    InvalidLogLevel(u8),
+    /// An id that is empty or longer than 4 bytes
+    #[error("Invalid id: {0:?}")]
+    InvalidId(String),
+    /// A list of allowed values that is empty and would never match
+    #[error("Empty filter list: {0}")]
+    EmptyList(&'static str),
+    /// The time range ends before it starts
+    #[error("Invalid time range: {0}")]
+    InvalidTimeRange(String),
+    /// An argument condition that can't be parsed or never matches
+    #[error("Invalid argument condition: {0}")]
+    InvalidCondition(String),
+}
 
 /// Describes what DLT message to filter out based on log-level and app/ecu/context-id
 ///
@@ -24,11 +49,15 @@ use std::{collections::HashSet, iter::FromIterator};
 ///
 /// only this is possible:
 /// - `app-id is_one_of ["abc","foo"] AND log-level <= DEBUG`
+///
+/// The exception are log levels, which can be defined per app-id and context-id
+/// (like dlt-viewer filters do):
+/// - `app-id == "abc" && log-level <= WARN OR app-id != "abc" && log-level <= DEBUG`
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DltFilterConfig {
     /// only select log entries with level MIN_LEVEL and more severe
     ///
@@ -42,56 +71,898 @@ pub struct DltFilterConfig {
     /// ```
     pub min_log_level: Option<u8>,
     /// what app ids should be allowed.
+    ///
+    /// Entries can contain wildcards, see `IdMatcher`
     pub app_ids: Option<Vec<String>>,
-    /// what ecu ids should be allowed
+    /// what ecu ids should be allowed (supports wildcards)
     pub ecu_ids: Option<Vec<String>>,
-    /// what context ids should be allowed
+    /// what context ids should be allowed (supports wildcards)
     pub context_ids: Option<Vec<String>>,
     /// how many app ids exist in total
     pub app_id_count: i64,
     /// how many context ids exist in total
     pub context_id_count: i64,
+    /// maximum log level for messages of specific app ids (app-id, MAX_LEVEL)
+    ///
+    /// Overrides `min_log_level` for messages of these app ids.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub app_id_log_levels: Option<Vec<(String, u8)>>,
+    /// maximum log level for messages of specific context ids (context-id, MAX_LEVEL)
+    ///
+    /// Overrides `min_log_level` and `app_id_log_levels` for messages of these context ids.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub context_id_log_levels: Option<Vec<(String, u8)>>,
+    /// only select messages with a storage header timestamp at or after this point in time
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub min_timestamp: Option<dlt::DltTimeStamp>,
+    /// only select messages with a storage header timestamp at or before this point in time
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub max_timestamp: Option<dlt::DltTimeStamp>,
+    /// what session ids should be allowed (messages without session id are not affected)
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub session_ids: Option<Vec<u32>>,
+    /// what session ids should be excluded (messages without session id are not affected)
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub excluded_session_ids: Option<Vec<u32>>,
+    /// what message ids of non-verbose messages should be allowed (verbose messages are not affected)
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub message_ids: Option<Vec<u32>>,
+    /// what message ids of non-verbose messages should be excluded (verbose messages are not affected)
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub excluded_message_ids: Option<Vec<u32>>,
+    /// only select messages whose header text contains one of these texts
+    ///
+    /// The header text consists of ecu-id, app-id, context-id and message type,
+    /// e.g. `ECU1 APP CTX log warn`.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub header_texts: Option<Vec<String>>,
+    /// only select verbose messages whose payload contains one of these texts
+    ///
+    /// The payload text consists of all argument values separated by spaces.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub payload_texts: Option<Vec<String>>,
+    /// `Some(true)`: only select control messages, `Some(false)`: no control messages
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub control_messages: Option<bool>,
+    /// only select verbose messages whose arguments satisfy all of these conditions
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub argument_conditions: Option<Vec<ArgumentCondition>>,
+    /// how ids are normalized before they are compared
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub id_normalization: IdNormalization,
+}
+
+impl DltFilterConfig {
+    /// Create a builder for a filter configuration
+    pub fn builder() -> DltFilterConfigBuilder {
+        DltFilterConfigBuilder::default()
+    }
+
+    /// Check the configuration for settings that can never match or are invalid
+    ///
+    /// Such configurations would otherwise silently filter out all (or no) messages.
+    pub fn validate(&self) -> Result<(), Error> {
+        let validate_level = |level: u8| {
+            dlt::u8_to_log_level(level)
+                .map(|_| ())
+                .ok_or(Error::InvalidLogLevel(level))
+        };
+        if let Some(level) = self.min_log_level {
+            validate_level(level)?;
+        }
+        for levels in [&self.app_id_log_levels, &self.context_id_log_levels]
+            .into_iter()
+            .flatten()
+        {
+            for (id, level) in levels {
+                validate_id(&self.id_normalization.normalize(id))?;
+                validate_level(*level)?;
+            }
+        }
+        for (name, ids) in [
+            ("app_ids", &self.app_ids),
+            ("ecu_ids", &self.ecu_ids),
+            ("context_ids", &self.context_ids),
+        ] {
+            if let Some(ids) = ids {
+                if ids.is_empty() {
+                    return Err(Error::EmptyList(name));
+                }
+                ids.iter()
+                    .try_for_each(|id| validate_id(&self.id_normalization.normalize(id)))?;
+            }
+        }
+        for (name, texts) in [
+            ("header_texts", &self.header_texts),
+            ("payload_texts", &self.payload_texts),
+        ] {
+            if matches!(texts, Some(texts) if texts.is_empty()) {
+                return Err(Error::EmptyList(name));
+            }
+        }
+        for (name, ids) in [
+            ("session_ids", &self.session_ids),
+            ("message_ids", &self.message_ids),
+        ] {
+            if matches!(ids, Some(ids) if ids.is_empty()) {
+                return Err(Error::EmptyList(name));
+            }
+        }
+        for condition in self.argument_conditions.iter().flatten() {
+            if condition.name.is_empty() || condition.value.is_nan() {
+                return Err(Error::InvalidCondition(format!("{:?}", condition)));
+            }
+        }
+        if let (Some(min), Some(max)) = (&self.min_timestamp, &self.max_timestamp) {
+            if min > max {
+                return Err(Error::InvalidTimeRange(format!(
+                    "{:?} is after {:?}",
+                    min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An id must not be empty and without wildcards it can have at most 4 bytes
+fn validate_id(id: &str) -> Result<(), Error> {
+    let has_wildcard = id.contains(['*', '?']);
+    if id.is_empty() || (!has_wildcard && id.len() > 4) {
+        return Err(Error::InvalidId(id.to_string()));
+    }
+    Ok(())
+}
+
+/// Builder for a `DltFilterConfig`, created with `DltFilterConfig::builder()`
+///
+/// ```
+/// use dlt_core::{dlt::LogLevel, filtering::DltFilterConfig};
+///
+/// let config = DltFilterConfig::builder()
+///     .min_log_level(LogLevel::Warn)
+///     .app_ids(["APP", "NAV*"])
+///     .app_id_log_level("DBG", LogLevel::Debug)
+///     .build()
+///     .expect("valid filter config");
+/// assert_eq!(config.min_log_level, Some(3));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DltFilterConfigBuilder {
+    config: DltFilterConfig,
+}
+
+fn to_strings(ids: impl IntoIterator<Item = impl Into<String>>) -> Vec<String> {
+    ids.into_iter().map(Into::into).collect()
+}
+
+impl DltFilterConfigBuilder {
+    /// only select log entries with this level and more severe
+    pub fn min_log_level(mut self, level: dlt::LogLevel) -> Self {
+        self.config.min_log_level = Some(level.value());
+        self
+    }
+    pub fn app_ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.app_ids = Some(to_strings(ids));
+        self
+    }
+    pub fn ecu_ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.ecu_ids = Some(to_strings(ids));
+        self
+    }
+    pub fn context_ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.context_ids = Some(to_strings(ids));
+        self
+    }
+    pub fn app_id_count(mut self, count: i64) -> Self {
+        self.config.app_id_count = count;
+        self
+    }
+    pub fn context_id_count(mut self, count: i64) -> Self {
+        self.config.context_id_count = count;
+        self
+    }
+    /// maximum log level for messages of the app id
+    pub fn app_id_log_level(mut self, app_id: impl Into<String>, level: dlt::LogLevel) -> Self {
+        self.config
+            .app_id_log_levels
+            .get_or_insert_with(Vec::new)
+            .push((app_id.into(), level.value()));
+        self
+    }
+    /// maximum log level for messages of the context id
+    pub fn context_id_log_level(
+        mut self,
+        context_id: impl Into<String>,
+        level: dlt::LogLevel,
+    ) -> Self {
+        self.config
+            .context_id_log_levels
+            .get_or_insert_with(Vec::new)
+            .push((context_id.into(), level.value()));
+        self
+    }
+    pub fn min_timestamp(mut self, timestamp: dlt::DltTimeStamp) -> Self {
+        self.config.min_timestamp = Some(timestamp);
+        self
+    }
+    pub fn max_timestamp(mut self, timestamp: dlt::DltTimeStamp) -> Self {
+        self.config.max_timestamp = Some(timestamp);
+        self
+    }
+    pub fn session_ids(mut self, ids: impl IntoIterator<Item = u32>) -> Self {
+        self.config.session_ids = Some(ids.into_iter().collect());
+        self
+    }
+    pub fn excluded_session_ids(mut self, ids: impl IntoIterator<Item = u32>) -> Self {
+        self.config.excluded_session_ids = Some(ids.into_iter().collect());
+        self
+    }
+    pub fn message_ids(mut self, ids: impl IntoIterator<Item = u32>) -> Self {
+        self.config.message_ids = Some(ids.into_iter().collect());
+        self
+    }
+    pub fn excluded_message_ids(mut self, ids: impl IntoIterator<Item = u32>) -> Self {
+        self.config.excluded_message_ids = Some(ids.into_iter().collect());
+        self
+    }
+    pub fn header_texts(mut self, texts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.header_texts = Some(to_strings(texts));
+        self
+    }
+    pub fn payload_texts(mut self, texts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.payload_texts = Some(to_strings(texts));
+        self
+    }
+    /// `true`: only select control messages, `false`: no control messages
+    pub fn control_messages(mut self, only_control_messages: bool) -> Self {
+        self.config.control_messages = Some(only_control_messages);
+        self
+    }
+    /// only select verbose messages whose arguments satisfy the condition
+    pub fn argument_condition(mut self, condition: ArgumentCondition) -> Self {
+        self.config
+            .argument_conditions
+            .get_or_insert_with(Vec::new)
+            .push(condition);
+        self
+    }
+    pub fn id_normalization(mut self, normalization: IdNormalization) -> Self {
+        self.config.id_normalization = normalization;
+        self
+    }
+    /// Validate and return the filter configuration
+    pub fn build(self) -> Result<DltFilterConfig, Error> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Comparison operators for argument conditions
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// A numeric condition on a named argument of verbose messages, e.g. `speed > 120`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentCondition {
+    /// name of the argument (verbose arguments with variable info)
+    pub name: String,
+    pub comparison: Comparison,
+    pub value: f64,
+}
+
+impl ArgumentCondition {
+    /// Check if any argument with the name satisfies the condition
+    ///
+    /// Fixed point values are compared after applying quantization and offset.
+    pub fn matches(&self, args: &[dlt::Argument]) -> bool {
+        args.iter().any(|arg| {
+            arg.name.as_deref() == Some(self.name.as_str())
+                && matches!(arg.numeric_value(), Some(v) if self.comparison.holds(v, self.value))
+        })
+    }
+}
+
+/// Parses conditions like `speed > 120` or `temp<=-5.5`
+impl std::str::FromStr for ArgumentCondition {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        const OPERATORS: [(&str, Comparison); 7] = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ];
+        let invalid = || Error::InvalidCondition(s.to_string());
+        let (pos, operator, comparison) = OPERATORS
+            .iter()
+            .filter_map(|(op, cmp)| s.find(op).map(|pos| (pos, *op, *cmp)))
+            .min_by_key(|(pos, op, _)| (*pos, std::cmp::Reverse(op.len())))
+            .ok_or_else(invalid)?;
+        let name = s[..pos].trim();
+        let value = s[pos + operator.len()..]
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| invalid())?;
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(ArgumentCondition {
+            name: name.to_string(),
+            comparison,
+            value,
+        })
+    }
+}
+
+/// Normalization applied to ids before they are compared
+///
+/// Recordings (and filter definitions) often contain `"APP\0"`, `"APP "` and `"app"`
+/// for the same application, which would not match exactly.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdNormalization {
+    /// remove trailing `\0` and whitespace
+    pub trim: bool,
+    /// compare ids ASCII case-insensitive
+    pub ignore_case: bool,
+}
+
+impl IdNormalization {
+    pub fn normalize<'a>(&self, id: &'a str) -> Cow<'a, str> {
+        let id = self.trim(id);
+        if self.ignore_case && id.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(id.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(id)
+        }
+    }
+
+    fn trim<'a>(&self, id: &'a str) -> &'a str {
+        if self.trim {
+            id.trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
+        } else {
+            id
+        }
+    }
+
+    /// Call `f` with the normalized id, ids of up to 4 bytes are folded on the stack
+    fn with_normalized<R>(&self, id: &str, f: impl FnOnce(&str) -> R) -> R {
+        let id = self.trim(id);
+        if !self.ignore_case || !id.bytes().any(|b| b.is_ascii_uppercase()) {
+            return f(id);
+        }
+        let mut buffer = [0u8; 4];
+        match buffer.get_mut(..id.len()) {
+            Some(folded) => {
+                folded.copy_from_slice(id.as_bytes());
+                folded.make_ascii_lowercase();
+                f(std::str::from_utf8(folded).expect("ASCII folding keeps UTF-8 valid"))
+            }
+            None => f(&id.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Matches DLT ids against a list of ids and wildcard patterns
+///
+/// Patterns can contain `*` (matches any sequence of characters) and `?`
+/// (matches exactly one character), e.g. `"NAV*"` or `"?SOM"`.
+/// Entries without wildcards are packed into `u32` values and matched with a
+/// binary search (ids longer than 4 bytes use a hash lookup), patterns
+/// that only end in a `*` are matched as prefixes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdMatcher {
+    /// sorted packed representations of exact ids with up to 4 bytes
+    packed: Vec<u32>,
+    /// exact ids that can't be packed
+    exact: HashSet<String>,
+    prefixes: Vec<String>,
+    patterns: Vec<String>,
+    normalization: IdNormalization,
+}
+
+impl IdMatcher {
+    pub fn new<I, S>(ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        IdMatcher::with_normalization(ids, IdNormalization::default())
+    }
+
+    /// Create a matcher that normalizes ids and patterns before comparing them
+    pub fn with_normalization<I, S>(ids: I, normalization: IdNormalization) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut matcher = IdMatcher {
+            normalization,
+            ..Default::default()
+        };
+        for id in ids {
+            matcher.insert(id.into());
+        }
+        matcher
+    }
+
+    /// Add an id or wildcard pattern to the matcher
+    pub fn insert(&mut self, id: String) {
+        let id = self.normalization.normalize(&id).into_owned();
+        match id.find(['*', '?']) {
+            None => match pack_id(&id, false) {
+                Some(packed) => {
+                    if let Err(pos) = self.packed.binary_search(&packed) {
+                        self.packed.insert(pos, packed);
+                    }
+                }
+                None => {
+                    self.exact.insert(id);
+                }
+            },
+            Some(pos) if pos == id.len() - 1 && id.ends_with('*') => {
+                self.prefixes.push(id[..pos].to_string());
+            }
+            Some(_) => self.patterns.push(id),
+        }
+    }
+
+    /// Check if the id matches any of the ids or patterns
+    ///
+    /// Ids and patterns are compared without allocating, the case is folded
+    /// while comparing if the matcher ignores case.
+    pub fn matches(&self, id: &str) -> bool {
+        let id = self.normalization.trim(id);
+        let fold = self.normalization.ignore_case;
+        let exact = match pack_id(id, fold) {
+            Some(packed) => self.packed.binary_search(&packed).is_ok(),
+            None => {
+                !self.exact.is_empty()
+                    && self
+                        .normalization
+                        .with_normalized(id, |id| self.exact.contains(id))
+            }
+        };
+        exact
+            || self.prefixes.iter().any(|prefix| {
+                id.len() >= prefix.len()
+                    && bytes_match(prefix.as_bytes(), &id.as_bytes()[..prefix.len()], fold)
+            })
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern.as_bytes(), id.as_bytes(), fold))
+    }
+
+    /// Number of ids and patterns in the matcher
+    pub fn len(&self) -> usize {
+        self.packed.len() + self.exact.len() + self.prefixes.len() + self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for IdMatcher {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        IdMatcher::new(iter)
+    }
+}
+
+/// Pack an id of up to 4 bytes into a `u32` so it can be compared without hashing
+///
+/// Ids containing NUL bytes aren't packed, since they would collide with shorter ids.
+/// With `fold` the bytes are converted to ASCII lowercase while packing.
+fn pack_id(id: &str, fold: bool) -> Option<u32> {
+    let bytes = id.as_bytes();
+    if bytes.len() > 4 || bytes.contains(&0) {
+        return None;
+    }
+    let mut packed = [0u8; 4];
+    for (packed, byte) in packed.iter_mut().zip(bytes) {
+        *packed = if fold {
+            byte.to_ascii_lowercase()
+        } else {
+            *byte
+        };
+    }
+    Some(u32::from_be_bytes(packed))
+}
+
+/// Compare bytes of a (normalized) pattern with bytes of an id
+fn bytes_match(pattern: &[u8], id: &[u8], fold: bool) -> bool {
+    if fold {
+        pattern.eq_ignore_ascii_case(id)
+    } else {
+        pattern == id
+    }
+}
+
+/// Glob style matching of `*` and `?` wildcards
+///
+/// Backtracks only to the last `*`, so runs in `O(pattern * id)` at worst.
+fn wildcard_match(pattern: &[u8], id: &[u8], fold: bool) -> bool {
+    let (mut p, mut i) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while i < id.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                last_star = Some((p, i));
+                p += 1;
+            }
+            Some(c) if *c == b'?' || *c == id[i] || (fold && c.eq_ignore_ascii_case(&id[i])) => {
+                p += 1;
+                i += 1;
+            }
+            _ => match last_star {
+                Some((star_p, star_i)) => {
+                    // let the last `*` consume one more character
+                    p = star_p + 1;
+                    i = star_i + 1;
+                    last_star = Some((star_p, star_i + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
 }
 
 /// A processed version of the filter configuration that can be used to parse dlt.
 ///
 /// When a `DltFilterConfig` is received (e.g. as serialized json), this can easily
 /// be converted into this processed version using `filter_config.into()`
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ProcessedDltFilterConfig {
     pub min_log_level: Option<dlt::LogLevel>,
-    pub app_ids: Option<HashSet<String>>,
-    pub ecu_ids: Option<HashSet<String>>,
-    pub context_ids: Option<HashSet<String>>,
+    pub app_ids: Option<IdMatcher>,
+    pub ecu_ids: Option<IdMatcher>,
+    pub context_ids: Option<IdMatcher>,
     pub app_id_count: i64,
     pub context_id_count: i64,
+    pub app_id_log_levels: Option<HashMap<String, dlt::LogLevel>>,
+    pub context_id_log_levels: Option<HashMap<String, dlt::LogLevel>>,
+    pub min_timestamp: Option<dlt::DltTimeStamp>,
+    pub max_timestamp: Option<dlt::DltTimeStamp>,
+    pub session_ids: Option<HashSet<u32>>,
+    pub excluded_session_ids: Option<HashSet<u32>>,
+    pub message_ids: Option<HashSet<u32>>,
+    pub excluded_message_ids: Option<HashSet<u32>>,
+    pub header_texts: Option<Vec<String>>,
+    pub payload_texts: Option<Vec<String>>,
+    pub control_messages: Option<bool>,
+    pub argument_conditions: Option<Vec<ArgumentCondition>>,
+    pub id_normalization: IdNormalization,
+}
+
+impl ProcessedDltFilterConfig {
+    /// The most verbose log level that is accepted for messages with the given ids
+    ///
+    /// A level defined for the context id takes precedence over a level defined for
+    /// the app id, which takes precedence over the general `min_log_level`.
+    pub fn max_log_level(&self, app_id: &str, context_id: &str) -> Option<dlt::LogLevel> {
+        let lookup = |levels: &Option<HashMap<String, dlt::LogLevel>>, id: &str| {
+            levels.as_ref().and_then(|levels| {
+                self.id_normalization
+                    .with_normalized(id, |id| levels.get(id).copied())
+            })
+        };
+        lookup(&self.context_id_log_levels, context_id)
+            .or_else(|| lookup(&self.app_id_log_levels, app_id))
+            .or(self.min_log_level)
+    }
+
+    /// Check if a storage header timestamp lies within the configured time range
+    pub fn in_time_range(&self, timestamp: &dlt::DltTimeStamp) -> bool {
+        !matches!(&self.min_timestamp, Some(min) if timestamp < min)
+            && !matches!(&self.max_timestamp, Some(max) if timestamp > max)
+    }
+
+    /// Check if a session id passes the session id allow- and deny-lists
+    pub fn session_allowed(&self, session_id: u32) -> bool {
+        !matches!(&self.session_ids, Some(allowed) if !allowed.contains(&session_id))
+            && !matches!(&self.excluded_session_ids, Some(denied) if denied.contains(&session_id))
+    }
+
+    /// Check if the kind of message (control or not) is selected
+    pub fn control_message_allowed(&self, extended_header: Option<&dlt::ExtendedHeader>) -> bool {
+        let is_control = matches!(
+            extended_header,
+            Some(dlt::ExtendedHeader {
+                message_type: dlt::MessageType::Control(_),
+                ..
+            })
+        );
+        !matches!(self.control_messages, Some(only_control) if only_control != is_control)
+    }
+
+    /// Check if the header text of a message contains one of the configured header texts
+    pub fn header_text_matches(
+        &self,
+        header: &dlt::StandardHeader,
+        extended_header: Option<&dlt::ExtendedHeader>,
+    ) -> bool {
+        let texts = match &self.header_texts {
+            Some(texts) => texts,
+            None => return true,
+        };
+        let header_text = header_text(header, extended_header);
+        texts.iter().any(|text| header_text.contains(text.as_str()))
+    }
+
+    /// Check if the payload contains one of the configured payload texts
+    pub fn payload_text_matches(&self, payload: &dlt::PayloadContent) -> bool {
+        match &self.payload_texts {
+            Some(texts) => matches!(
+                payload.verbose_text(),
+                Some(payload_text) if texts.iter().any(|text| payload_text.contains(text.as_str()))
+            ),
+            None => true,
+        }
+    }
+
+    /// Check if the payload satisfies all configured argument conditions
+    pub fn argument_conditions_match(&self, payload: &dlt::PayloadContent) -> bool {
+        match (&self.argument_conditions, payload) {
+            (None, _) => true,
+            (Some(conditions), dlt::PayloadContent::Verbose(args)) => {
+                conditions.iter().all(|condition| condition.matches(args))
+            }
+            (Some(_), _) => false,
+        }
+    }
+
+    /// Check if a non-verbose message id passes the message id allow- and deny-lists
+    pub fn message_id_allowed(&self, message_id: u32) -> bool {
+        !matches!(&self.message_ids, Some(allowed) if !allowed.contains(&message_id))
+            && !matches!(&self.excluded_message_ids, Some(denied) if denied.contains(&message_id))
+    }
+
+    /// Check if an already parsed message passes the filter
+    ///
+    /// Gives the same result as parsing the serialized message with this filter
+    /// using `dlt_message`.
+    pub fn matches(&self, message: &dlt::Message) -> bool {
+        !matches!(&message.storage_header, Some(h) if !self.in_time_range(&h.timestamp))
+            && self.matches_headers(&message.header, message.extended_header.as_ref())
+            && self.matches_payload(&message.payload)
+    }
+
+    /// Check the conditions that only depend on the message headers
+    ///
+    /// The time range is not checked here since it depends on the storage header.
+    pub fn matches_headers(
+        &self,
+        header: &dlt::StandardHeader,
+        extended_header: Option<&dlt::ExtendedHeader>,
+    ) -> bool {
+        if !self.control_message_allowed(extended_header)
+            || !self.header_text_matches(header, extended_header)
+        {
+            return false;
+        }
+        if matches!(header.session_id, Some(id) if !self.session_allowed(id)) {
+            return false;
+        }
+        match extended_header {
+            Some(h) => {
+                if let Some(max_level) = self.max_log_level(&h.application_id, &h.context_id) {
+                    if h.skip_with_level(max_level) {
+                        return false;
+                    }
+                }
+                !matches!(&self.app_ids, Some(ids) if !ids.matches(&h.application_id))
+                    && !matches!(&self.context_ids, Some(ids) if !ids.matches(&h.context_id))
+                    && !matches!(
+                        (&self.ecu_ids, &header.ecu_id),
+                        (Some(ids), Some(ecu_id)) if !ids.matches(ecu_id)
+                    )
+            }
+            // without extended header, drop messages if some app or context id was filtered
+            None => {
+                !matches!(&self.app_ids, Some(ids) if self.app_id_count > ids.len() as i64)
+                    && !matches!(
+                        &self.context_ids,
+                        Some(ids) if self.context_id_count > ids.len() as i64
+                    )
+            }
+        }
+    }
+
+    /// Like `matches`, but non-verbose messages without extended header are also
+    /// checked against the log levels, using the log level and ids of their frame
+    /// in the fibex model
+    pub fn matches_with_fibex(
+        &self,
+        message: &dlt::Message,
+        fibex_metadata: &crate::fibex::FibexMetadata,
+    ) -> bool {
+        if !self.matches(message) {
+            return false;
+        }
+        match (&message.extended_header, &message.payload) {
+            (None, dlt::PayloadContent::NonVerbose(_, _)) => {
+                match crate::fibex::frame_for_message(fibex_metadata, message) {
+                    Some(frame) => match frame.log_level() {
+                        Some(level) => self.log_level_allowed(
+                            level,
+                            frame.application_id.as_deref().unwrap_or_default(),
+                            frame.context_id.as_deref().unwrap_or_default(),
+                        ),
+                        None => true,
+                    },
+                    None => true,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    /// Check if messages with the level pass the log level filters for the ids
+    pub fn log_level_allowed(&self, level: dlt::LogLevel, app_id: &str, context_id: &str) -> bool {
+        !matches!(
+            self.max_log_level(app_id, context_id),
+            Some(max_level) if level.skip_with_level(max_level)
+        )
+    }
+
+    /// Check the conditions that depend on the decoded payload
+    pub fn matches_payload(&self, payload: &dlt::PayloadContent) -> bool {
+        let message_id_allowed = match payload {
+            dlt::PayloadContent::NonVerbose(message_id, _) => self.message_id_allowed(*message_id),
+            _ => true,
+        };
+        message_id_allowed
+            && self.payload_text_matches(payload)
+            && self.argument_conditions_match(payload)
+    }
+
+    /// Check if some conditions depend on the payload of the messages
+    pub fn has_payload_conditions(&self) -> bool {
+        self.message_ids.is_some()
+            || self.excluded_message_ids.is_some()
+            || self.payload_texts.is_some()
+            || self.argument_conditions.is_some()
+    }
+
+    /// The verdict of the filter on a message of which only the headers are known
+    pub fn headers_verdict(
+        &self,
+        storage_header: Option<&dlt::StorageHeader>,
+        header: &dlt::StandardHeader,
+        extended_header: Option<&dlt::ExtendedHeader>,
+    ) -> FilterVerdict {
+        if matches!(storage_header, Some(h) if !self.in_time_range(&h.timestamp))
+            || !self.matches_headers(header, extended_header)
+        {
+            FilterVerdict::FilteredOut
+        } else if self.has_payload_conditions() {
+            FilterVerdict::NeedsPayload
+        } else {
+            FilterVerdict::Matches
+        }
+    }
+}
+
+/// The result of filtering a message by its headers, see
+/// `ProcessedDltFilterConfig::headers_verdict`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// The message passes the filter
+    Matches,
+    /// The message is filtered out by its headers
+    FilteredOut,
+    /// The headers pass, but the payload has to be checked as well
+    NeedsPayload,
+}
+
+/// The text header filters are matched against, e.g. `ECU1 APP CTX log warn`
+pub(crate) fn header_text(
+    header: &dlt::StandardHeader,
+    extended_header: Option<&dlt::ExtendedHeader>,
+) -> String {
+    let ecu_id = header.ecu_id.as_deref().unwrap_or_default();
+    match extended_header {
+        Some(h) => format!(
+            "{} {} {} {}",
+            ecu_id, h.application_id, h.context_id, h.message_type
+        ),
+        None => ecu_id.to_string(),
+    }
+}
+
+fn process_log_levels(
+    levels: &[(String, u8)],
+    normalization: IdNormalization,
+) -> HashMap<String, dlt::LogLevel> {
+    levels
+        .iter()
+        .filter_map(|(id, level)| {
+            dlt::u8_to_log_level(*level)
+                .map(|level| (normalization.normalize(id).into_owned(), level))
+        })
+        .collect()
 }
 
 impl From<DltFilterConfig> for ProcessedDltFilterConfig {
     fn from(cfg: DltFilterConfig) -> Self {
-        ProcessedDltFilterConfig {
-            min_log_level: cfg.min_log_level.and_then(dlt::u8_to_log_level),
-            app_ids: cfg.app_ids.map(HashSet::from_iter),
-            ecu_ids: cfg.ecu_ids.map(HashSet::from_iter),
-            context_ids: cfg.context_ids.map(HashSet::from_iter),
-            app_id_count: cfg.app_id_count,
-            context_id_count: cfg.context_id_count,
-        }
+        ProcessedDltFilterConfig::from(&cfg)
     }
 }
 
 impl From<&DltFilterConfig> for ProcessedDltFilterConfig {
     fn from(cfg: &DltFilterConfig) -> Self {
+        let normalization = cfg.id_normalization;
+        let id_matcher = |ids: &Option<Vec<String>>| {
+            ids.as_ref()
+                .map(|ids| IdMatcher::with_normalization(ids.iter().cloned(), normalization))
+        };
+        let log_levels = |levels: &Option<Vec<(String, u8)>>| {
+            levels
+                .as_deref()
+                .map(|levels| process_log_levels(levels, normalization))
+        };
+        let id_set = |ids: &Option<Vec<u32>>| ids.as_ref().map(|s| HashSet::from_iter(s.clone()));
         ProcessedDltFilterConfig {
             min_log_level: cfg.min_log_level.and_then(dlt::u8_to_log_level),
-            app_ids: cfg.app_ids.as_ref().map(|s| HashSet::from_iter(s.clone())),
-            ecu_ids: cfg.ecu_ids.as_ref().map(|s| HashSet::from_iter(s.clone())),
-            context_ids: cfg
-                .context_ids
-                .as_ref()
-                .map(|s| HashSet::from_iter(s.clone())),
+            app_ids: id_matcher(&cfg.app_ids),
+            ecu_ids: id_matcher(&cfg.ecu_ids),
+            context_ids: id_matcher(&cfg.context_ids),
             app_id_count: cfg.app_id_count,
             context_id_count: cfg.context_id_count,
+            app_id_log_levels: log_levels(&cfg.app_id_log_levels),
+            context_id_log_levels: log_levels(&cfg.context_id_log_levels),
+            min_timestamp: cfg.min_timestamp.clone(),
+            max_timestamp: cfg.max_timestamp.clone(),
+            session_ids: id_set(&cfg.session_ids),
+            excluded_session_ids: id_set(&cfg.excluded_session_ids),
+            message_ids: id_set(&cfg.message_ids),
+            excluded_message_ids: id_set(&cfg.excluded_message_ids),
+            header_texts: cfg.header_texts.clone(),
+            payload_texts: cfg.payload_texts.clone(),
+            control_messages: cfg.control_messages,
+            argument_conditions: cfg.argument_conditions.clone(),
+            id_normalization: normalization,
         }
     }
 }
@@ -0,0 +1,770 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Text formatting of messages
+//!
+//! [`MessageFormatter`] renders messages as lines of text with configurable
+//! columns, column widths, separator and format of the storage time. Levels
+//! can be shown as tags like `[WARN]` and lines colored by their level.
+//! [`HexDump`] renders payloads that cannot be decoded as hex and ASCII.
+//!
+//! ```
+//! # use dlt_core::fmt::{Column, MessageFormatter};
+//! let formatter = MessageFormatter::new()
+//!     .columns([Column::Index, Column::Application, Column::Context, Column::Payload])
+//!     .width(Column::Index, 6)
+//!     .separator(" | ");
+//! ```
+use crate::{
+    control::control_text,
+    dlt::{DltTimeStamp, LogLevel, Message, MessageType, PayloadContent, Value},
+};
+use std::fmt::{self, Write};
+
+/// A column of a formatted message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// Index of the message, as passed to the formatter
+    Index,
+    /// Time of the storage header
+    StorageTime,
+    /// Timestamp of the standard header in seconds
+    Timestamp,
+    Ecu,
+    Application,
+    Context,
+    /// Log level of log messages, message type of other messages
+    Level,
+    Payload,
+}
+
+impl Column {
+    /// Name of the column, e.g. in the header of a CSV export
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Index => "index",
+            Column::StorageTime => "storage_time",
+            Column::Timestamp => "timestamp",
+            Column::Ecu => "ecu",
+            Column::Application => "app",
+            Column::Context => "ctx",
+            Column::Level => "level",
+            Column::Payload => "payload",
+        }
+    }
+}
+
+/// All columns in their default order
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Index,
+    Column::StorageTime,
+    Column::Timestamp,
+    Column::Ecu,
+    Column::Application,
+    Column::Context,
+    Column::Level,
+    Column::Payload,
+];
+
+/// How the time of the storage header is rendered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// seconds since the epoch with microseconds, e.g. `1700000000.000123`
+    #[default]
+    Epoch,
+    /// ISO-8601 date and time at an offset from UTC in minutes, see [`iso_8601`]
+    Iso8601 { offset_minutes: i32 },
+    /// seconds since the start of the trace, see [`relative_time`]
+    ///
+    /// Without a start, the time of the first formatted message is the start.
+    Relative(Option<DltTimeStamp>),
+}
+
+/// Renders messages as lines of text
+///
+/// Columns are padded to their width with spaces, longer values are not cut.
+/// Formatting takes `&mut self`, since the formatter remembers the start of
+/// relative times and counts the messages of `format_message_into`.
+#[derive(Debug, Clone)]
+pub struct MessageFormatter {
+    columns: Vec<(Column, usize)>,
+    separator: String,
+    time_format: TimeFormat,
+    /// start of a relative time in microseconds, once known
+    start: Option<u64>,
+    /// messages written by `format_message_into`
+    count: usize,
+    level_tags: bool,
+    colors: bool,
+}
+
+impl Default for MessageFormatter {
+    fn default() -> Self {
+        MessageFormatter {
+            columns: DEFAULT_COLUMNS.iter().map(|column| (*column, 0)).collect(),
+            separator: " ".to_string(),
+            time_format: TimeFormat::default(),
+            start: None,
+            count: 0,
+            level_tags: false,
+            colors: false,
+        }
+    }
+}
+
+impl MessageFormatter {
+    /// A formatter with all columns, no widths and a space as separator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select the columns and their order, widths of kept columns are retained
+    pub fn columns(mut self, columns: impl IntoIterator<Item = Column>) -> Self {
+        self.columns = columns
+            .into_iter()
+            .map(|column| (column, self.column_width(column)))
+            .collect();
+        self
+    }
+
+    /// Set the minimum width of a column
+    ///
+    /// Only columns that are selected have a width, the width of other
+    /// columns is ignored. `columns` keeps the widths of the columns it selects.
+    pub fn width(mut self, column: Column, width: usize) -> Self {
+        for (c, w) in self.columns.iter_mut() {
+            if *c == column {
+                *w = width;
+            }
+        }
+        self
+    }
+
+    /// Set the text between columns
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Set how the storage time is rendered
+    pub fn time_format(mut self, time_format: TimeFormat) -> Self {
+        self.start = match &time_format {
+            TimeFormat::Relative(start) => start.as_ref().map(DltTimeStamp::as_us),
+            _ => None,
+        };
+        self.time_format = time_format;
+        self
+    }
+
+    /// Render the level of log messages as tag, e.g. `[ERROR]` or `[WARN]`
+    pub fn level_tags(mut self, level_tags: bool) -> Self {
+        self.level_tags = level_tags;
+        self
+    }
+
+    /// Color lines of log messages with ANSI escape codes by their level
+    ///
+    /// Fatal and error messages are red, warnings yellow, debug and verbose
+    /// messages dimmed. Other lines are not colored.
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    fn column_width(&self, column: Column) -> usize {
+        self.columns
+            .iter()
+            .find(|(c, _)| *c == column)
+            .map(|(_, w)| *w)
+            .unwrap_or_default()
+    }
+
+    /// Format the message with the given index as a line without line break
+    pub fn format(&mut self, index: usize, message: &Message) -> String {
+        let mut line = String::new();
+        // writing to a String never fails
+        let _ = self.write_message(&mut line, index, message);
+        line
+    }
+
+    /// Write the message with the given index as a line without line break
+    ///
+    /// Values are written directly to `out`, only the text of control
+    /// messages is rendered into an intermediate `String`.
+    pub fn write_message(
+        &mut self,
+        out: &mut impl Write,
+        index: usize,
+        message: &Message,
+    ) -> fmt::Result {
+        let level = match message.extended_header.as_ref().map(|h| &h.message_type) {
+            Some(MessageType::Log(level)) => Some(level),
+            _ => None,
+        };
+        let color = match level {
+            Some(level) if self.colors => ansi_color(level),
+            _ => None,
+        };
+        if let Some(color) = color {
+            out.write_str(color)?;
+        }
+        for (i, (column, width)) in self.columns.iter().enumerate() {
+            if i > 0 {
+                out.write_str(&self.separator)?;
+            }
+            let mut counting = CharCounter { out, chars: 0 };
+            match (column, &message.storage_header, level) {
+                (Column::StorageTime, Some(storage_header), _) => write_storage_time(
+                    &mut counting,
+                    &storage_header.timestamp,
+                    &self.time_format,
+                    &mut self.start,
+                )?,
+                (Column::Level, _, Some(level)) if self.level_tags => {
+                    write_level_tag(&mut counting, level)?
+                }
+                _ => write_column_value(&mut counting, *column, index, message)?,
+            }
+            for _ in counting.chars..*width {
+                out.write_char(' ')?;
+            }
+        }
+        if color.is_some() {
+            out.write_str(ANSI_RESET)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the storage time, `start` of relative times is set by the first call
+fn write_storage_time(
+    out: &mut impl Write,
+    timestamp: &DltTimeStamp,
+    time_format: &TimeFormat,
+    start: &mut Option<u64>,
+) -> fmt::Result {
+    match time_format {
+        TimeFormat::Epoch => write_epoch_time(out, timestamp),
+        TimeFormat::Iso8601 { offset_minutes } => write_iso_8601(out, timestamp, *offset_minutes),
+        TimeFormat::Relative(_) => {
+            let start = *start.get_or_insert_with(|| timestamp.as_us());
+            write_relative_time(out, timestamp, &DltTimeStamp::from_us(start))
+        }
+    }
+}
+
+/// Write the message as a line without line break, without allocations
+///
+/// The line is rendered like [`MessageFormatter::write_message`] does. The
+/// index column counts the messages that were written with `options` by this
+/// function, starting at 0.
+///
+/// ```
+/// # use dlt_core::{dlt::*, fmt::{format_message_into, Column, MessageFormatter}};
+/// # let message = Message::new(
+/// #     MessageConfig {
+/// #         version: 1,
+/// #         counter: 0,
+/// #         endianness: Endianness::Big,
+/// #         ecu_id: Some("ECU1".to_string()),
+/// #         session_id: None,
+/// #         timestamp: None,
+/// #         payload: PayloadContent::NonVerbose(1, vec![0xab].into()),
+/// #         extended_header_info: None,
+/// #     },
+/// #     None,
+/// # );
+/// let mut options =
+///     MessageFormatter::new().columns([Column::Index, Column::Ecu, Column::Payload]);
+/// let mut out = String::new();
+/// for _ in 0..2 {
+///     format_message_into(&message, &mut out, &mut options).expect("write");
+///     out.push('\n');
+/// }
+/// assert_eq!(out, "0 ECU1 [1] ab\n1 ECU1 [1] ab\n");
+/// ```
+pub fn format_message_into(
+    message: &Message,
+    out: &mut impl Write,
+    options: &mut MessageFormatter,
+) -> fmt::Result {
+    let index = options.count;
+    options.count += 1;
+    options.write_message(out, index, message)
+}
+
+/// Counts the characters written to the inner writer, for padding
+struct CharCounter<'a, W> {
+    out: &'a mut W,
+    chars: usize,
+}
+
+impl<W: Write> Write for CharCounter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.chars += s.chars().count();
+        self.out.write_str(s)
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// The ANSI escape code that starts the color of a level
+fn ansi_color(level: &LogLevel) -> Option<&'static str> {
+    match level {
+        LogLevel::Fatal => Some("\x1b[1;31m"),
+        LogLevel::Error => Some("\x1b[31m"),
+        LogLevel::Warn => Some("\x1b[33m"),
+        LogLevel::Debug | LogLevel::Verbose => Some("\x1b[2m"),
+        LogLevel::Info | LogLevel::Invalid(_) => None,
+    }
+}
+
+/// The level as tag, e.g. `[ERROR]`
+pub fn level_tag(level: &LogLevel) -> String {
+    let mut tag = String::new();
+    // writing to a String never fails
+    let _ = write_level_tag(&mut tag, level);
+    tag
+}
+
+fn write_level_tag(out: &mut impl Write, level: &LogLevel) -> fmt::Result {
+    match level {
+        LogLevel::Fatal => out.write_str("[FATAL]"),
+        LogLevel::Error => out.write_str("[ERROR]"),
+        LogLevel::Warn => out.write_str("[WARN]"),
+        LogLevel::Info => out.write_str("[INFO]"),
+        LogLevel::Debug => out.write_str("[DEBUG]"),
+        LogLevel::Verbose => out.write_str("[VERBOSE]"),
+        LogLevel::Invalid(v) => write!(out, "[{}]", v),
+    }
+}
+
+/// The timestamp as seconds since the epoch with microseconds, e.g. `1700000000.000123`
+pub fn epoch_time(timestamp: &DltTimeStamp) -> String {
+    let mut time = String::new();
+    // writing to a String never fails
+    let _ = write_epoch_time(&mut time, timestamp);
+    time
+}
+
+fn write_epoch_time(out: &mut impl Write, timestamp: &DltTimeStamp) -> fmt::Result {
+    write!(out, "{}.{:06}", timestamp.seconds, timestamp.microseconds)
+}
+
+/// The timestamp as ISO-8601 date and time with microseconds
+///
+/// The time is shown at `offset_minutes` from UTC, a zero offset is written
+/// as `Z`.
+///
+/// ```
+/// # use dlt_core::{dlt::DltTimeStamp, fmt::iso_8601};
+/// let timestamp = DltTimeStamp { seconds: 1_700_000_000, microseconds: 123 };
+/// assert_eq!(iso_8601(&timestamp, 0), "2023-11-14T22:13:20.000123Z");
+/// assert_eq!(iso_8601(&timestamp, 90), "2023-11-14T23:43:20.000123+01:30");
+/// ```
+pub fn iso_8601(timestamp: &DltTimeStamp, offset_minutes: i32) -> String {
+    let mut time = String::new();
+    // writing to a String never fails
+    let _ = write_iso_8601(&mut time, timestamp, offset_minutes);
+    time
+}
+
+fn write_iso_8601(
+    out: &mut impl Write,
+    timestamp: &DltTimeStamp,
+    offset_minutes: i32,
+) -> fmt::Result {
+    let (year, month, day, time) = date_and_time(timestamp, offset_minutes);
+    write!(
+        out,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        timestamp.microseconds
+    )?;
+    match offset_minutes {
+        0 => out.write_char('Z'),
+        _ => write!(
+            out,
+            "{}{:02}:{:02}",
+            if offset_minutes < 0 { '-' } else { '+' },
+            offset_minutes.abs() / 60,
+            offset_minutes.abs() % 60
+        ),
+    }
+}
+
+/// The time since `start` in seconds with microseconds, e.g. `12.000500` or `-0.250000`
+pub fn relative_time(timestamp: &DltTimeStamp, start: &DltTimeStamp) -> String {
+    let mut time = String::new();
+    // writing to a String never fails
+    let _ = write_relative_time(&mut time, timestamp, start);
+    time
+}
+
+fn write_relative_time(
+    out: &mut impl Write,
+    timestamp: &DltTimeStamp,
+    start: &DltTimeStamp,
+) -> fmt::Result {
+    let difference = timestamp.as_us() as i64 - start.as_us() as i64;
+    write!(
+        out,
+        "{}{}.{:06}",
+        if difference < 0 { "-" } else { "" },
+        difference.abs() / 1_000_000,
+        difference.abs() % 1_000_000
+    )
+}
+
+/// Year, month, day and seconds of the day at an offset from UTC in minutes
+fn date_and_time(timestamp: &DltTimeStamp, offset_minutes: i32) -> (i64, u32, u32, i64) {
+    let seconds = timestamp.seconds as i64 + offset_minutes as i64 * 60;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    (year, month, day, seconds.rem_euclid(86_400))
+}
+
+/// Year, month and day of the days since 1970-01-01 in the proleptic
+/// Gregorian calendar, after Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub(crate) fn column_value(column: Column, index: usize, message: &Message) -> String {
+    let mut value = String::new();
+    // writing to a String never fails
+    let _ = write_column_value(&mut value, column, index, message);
+    value
+}
+
+/// Write the value of a column with the default rendering of the formatter
+fn write_column_value(
+    out: &mut impl Write,
+    column: Column,
+    index: usize,
+    message: &Message,
+) -> fmt::Result {
+    let extended_header = message.extended_header.as_ref();
+    match column {
+        Column::Index => write!(out, "{}", index),
+        Column::StorageTime => match &message.storage_header {
+            Some(storage_header) => write_epoch_time(out, &storage_header.timestamp),
+            None => Ok(()),
+        },
+        Column::Timestamp => match message.header.timestamp {
+            Some(t) => write!(out, "{}.{:04}", t / 10_000, t % 10_000),
+            None => Ok(()),
+        },
+        Column::Ecu => match (&message.header.ecu_id, &message.storage_header) {
+            (Some(ecu_id), _) => out.write_str(ecu_id),
+            (None, Some(storage_header)) => out.write_str(&storage_header.ecu_id),
+            (None, None) => Ok(()),
+        },
+        Column::Application => out.write_str(extended_header.map_or("", |h| &h.application_id)),
+        Column::Context => out.write_str(extended_header.map_or("", |h| &h.context_id)),
+        Column::Level => match extended_header.map(|h| &h.message_type) {
+            Some(MessageType::Log(level)) => write!(out, "{}", level),
+            Some(message_type) => write!(out, "{}", message_type),
+            None => Ok(()),
+        },
+        Column::Payload => write_payload_text(out, message),
+    }
+}
+
+/// The message as line of the "export ASCII" format of dlt-viewer
+///
+/// The line has the index, the storage time at `offset_minutes` from UTC as
+/// `yyyy/MM/dd hh:mm:ss.micros`, the timestamp, counter, ECU, application and
+/// context id, type, subtype, mode, number of arguments and the payload, all
+/// separated by spaces. Whitespace of the payload is collapsed to single
+/// spaces like dlt-viewer does.
+///
+/// ```
+/// # use dlt_core::{dlt::*, fmt::dlt_viewer_line};
+/// let message = Message::new(
+///     MessageConfig {
+///         version: 1,
+///         counter: 3,
+///         endianness: Endianness::Big,
+///         ecu_id: Some("ECU1".to_string()),
+///         session_id: None,
+///         timestamp: Some(12_345),
+///         payload: PayloadContent::NonVerbose(42, vec![1, 2].into()),
+///         extended_header_info: Some(ExtendedHeaderConfig {
+///             message_type: MessageType::Log(LogLevel::Warn),
+///             app_id: "APP".to_string(),
+///             context_id: "CTX".to_string(),
+///         }),
+///     },
+///     Some(StorageHeader {
+///         timestamp: DltTimeStamp { seconds: 1_700_000_000, microseconds: 7 },
+///         ecu_id: DltId::new("ECU1"),
+///     }),
+/// );
+/// assert_eq!(
+///     dlt_viewer_line(0, &message, 60),
+///     "0 2023/11/14 23:13:20.000007 1.2345 3 ECU1 APP CTX log warn non-verbose 0 [42] 01 02"
+/// );
+/// ```
+pub fn dlt_viewer_line(index: usize, message: &Message, offset_minutes: i32) -> String {
+    let time = match &message.storage_header {
+        Some(storage_header) => {
+            let timestamp = &storage_header.timestamp;
+            let (year, month, day, time) = date_and_time(timestamp, offset_minutes);
+            format!(
+                "{:04}/{:02}/{:02} {:02}:{:02}:{:02}.{:06}",
+                year,
+                month,
+                day,
+                time / 3600,
+                time % 3600 / 60,
+                time % 60,
+                timestamp.microseconds
+            )
+        }
+        None => String::new(),
+    };
+    let extended_header = message.extended_header.as_ref();
+    let message_type = extended_header
+        .map(|h| h.message_type.to_string())
+        .unwrap_or_default();
+    let (kind, subtype) = message_type
+        .split_once(' ')
+        .unwrap_or((message_type.as_str(), ""));
+    let mode = match extended_header {
+        Some(h) if h.verbose => "verbose",
+        _ => "non-verbose",
+    };
+    let payload = payload_text(message);
+    format!(
+        "{} {} {} {} {} {} {} {} {} {} {} {}",
+        index,
+        time,
+        column_value(Column::Timestamp, index, message),
+        message.header.message_counter,
+        column_value(Column::Ecu, index, message),
+        column_value(Column::Application, index, message),
+        column_value(Column::Context, index, message),
+        kind,
+        subtype,
+        mode,
+        extended_header
+            .map(|h| h.argument_count)
+            .unwrap_or_default(),
+        payload.split_whitespace().collect::<Vec<_>>().join(" ")
+    )
+}
+
+/// Quote a CSV field if it contains a separator, a quote or a line break
+pub(crate) fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// The payload as text: verbose arguments separated by spaces, raw data as hex bytes
+pub fn payload_text(message: &Message) -> String {
+    let mut text = String::new();
+    // writing to a String never fails
+    let _ = write_payload_text(&mut text, message);
+    text
+}
+
+/// Write the payload as text, see [`payload_text`]
+pub fn write_payload_text(out: &mut impl Write, message: &Message) -> fmt::Result {
+    match &message.payload {
+        PayloadContent::Verbose(arguments) => {
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(' ')?;
+                }
+                write!(out, "{}", argument.value)?;
+            }
+            Ok(())
+        }
+        PayloadContent::NonVerbose(id, data) => {
+            write!(out, "[{}] ", id)?;
+            write_hex(out, data)
+        }
+        PayloadContent::ControlMsg(_, _) => {
+            out.write_str(&control_text(message).unwrap_or_default())
+        }
+        PayloadContent::NetworkTrace(slices) => {
+            for (i, slice) in slices.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(' ')?;
+                }
+                write_hex(out, slice)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write bytes as hex separated by spaces like `Value::Raw` is displayed
+fn write_hex(out: &mut impl Write, data: &[u8]) -> fmt::Result {
+    for (i, byte) in data.iter().enumerate() {
+        if i > 0 {
+            out.write_char(' ')?;
+        }
+        write!(out, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+/// Renders bytes as canonical hex dump with offsets, hex bytes and ASCII
+///
+/// ```
+/// # use dlt_core::fmt::HexDump;
+/// let dump = HexDump::new().bytes_per_line(8).dump(b"hello, world");
+/// assert_eq!(
+///     dump,
+///     "00000000  68 65 6c 6c 6f 2c 20 77  |hello, w|\n\
+///      00000008  6f 72 6c 64              |orld|"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexDump {
+    bytes_per_line: usize,
+    offsets: bool,
+    base_offset: usize,
+}
+
+impl Default for HexDump {
+    fn default() -> Self {
+        HexDump {
+            bytes_per_line: 16,
+            offsets: true,
+            base_offset: 0,
+        }
+    }
+}
+
+impl HexDump {
+    /// 16 bytes per line with offsets from 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of bytes per line, at least 1
+    ///
+    /// Bytes are grouped by 8 with an extra space between the groups.
+    pub fn bytes_per_line(mut self, bytes_per_line: usize) -> Self {
+        self.bytes_per_line = bytes_per_line.max(1);
+        self
+    }
+
+    /// Show or hide the offset at the start of each line
+    pub fn offsets(mut self, offsets: bool) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// Set the offset of the first byte, e.g. its position in the message
+    pub fn base_offset(mut self, base_offset: usize) -> Self {
+        self.base_offset = base_offset;
+        self
+    }
+
+    /// The dump of `data`, lines separated by line breaks without a final one
+    pub fn dump(&self, data: &[u8]) -> String {
+        let mut dump = String::new();
+        // writing to a String never fails
+        let _ = self.write_dump(&mut dump, data);
+        dump
+    }
+
+    /// Write the dump of `data`, lines separated by line breaks without a final one
+    pub fn write_dump(&self, out: &mut impl Write, data: &[u8]) -> fmt::Result {
+        for (line, chunk) in data.chunks(self.bytes_per_line).enumerate() {
+            if line > 0 {
+                out.write_char('\n')?;
+            }
+            if self.offsets {
+                write!(
+                    out,
+                    "{:08x}  ",
+                    self.base_offset + line * self.bytes_per_line
+                )?;
+            }
+            for i in 0..self.bytes_per_line {
+                if i > 0 && i.is_multiple_of(8) {
+                    out.write_char(' ')?;
+                }
+                match chunk.get(i) {
+                    Some(byte) => write!(out, "{:02x} ", byte)?,
+                    None => out.write_str("   ")?,
+                }
+            }
+            out.write_str(" |")?;
+            for byte in chunk {
+                let c = *byte as char;
+                out.write_char(if c.is_ascii_graphic() || c == ' ' {
+                    c
+                } else {
+                    '.'
+                })?;
+            }
+            out.write_char('|')?;
+        }
+        Ok(())
+    }
+
+    /// The dump of the raw data of the payload
+    ///
+    /// This covers the data of non-verbose messages, raw arguments of verbose
+    /// messages and the slices of network trace messages. Each is dumped with
+    /// offsets from the base offset, separated by an empty line. `None` if the
+    /// payload has no raw data.
+    pub fn payload(&self, message: &Message) -> Option<String> {
+        let parts: Vec<&[u8]> = match &message.payload {
+            PayloadContent::NonVerbose(_, data) => vec![data],
+            PayloadContent::Verbose(arguments) => arguments
+                .iter()
+                .filter_map(|argument| match &argument.value {
+                    Value::Raw(data) => Some(data.as_slice()),
+                    _ => None,
+                })
+                .collect(),
+            PayloadContent::NetworkTrace(slices) => slices.iter().map(AsRef::as_ref).collect(),
+            PayloadContent::ControlMsg(_, _) => vec![],
+        };
+        if parts.is_empty() {
+            return None;
+        }
+        Some(
+            parts
+                .iter()
+                .map(|part| self.dump(part))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        )
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Offset index for random access to dlt messages
+//!
+//! `index` scans a source once and remembers where each message starts,
+//! together with its timestamp and ids. With such an index, a
+//! `DltIndexedReader` can fetch any range of messages of a huge file
+//! without reading the messages before it.
+use crate::{
+    dlt::{DltId, DltTimeStamp},
+    parse::{
+        dlt_consume_msg, dlt_extended_header, dlt_message, dlt_standard_header, dlt_storage_header,
+        DltParseError, ParsedMessage,
+    },
+    read::DltMessageReader,
+};
+#[cfg(feature = "stream")]
+use crate::{progress::Progress, stream::DltStreamReader};
+#[cfg(feature = "stream")]
+use futures::io::{AsyncRead, AsyncSeek, AsyncSeekExt};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Position and identification of one message in the source
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// byte offset of the message (of its storage header if present)
+    pub offset: u64,
+    /// number of bytes of the message including the storage header
+    pub length: u32,
+    /// timestamp of the storage header
+    pub timestamp: Option<DltTimeStamp>,
+    pub ecu_id: Option<DltId>,
+    pub application_id: Option<DltId>,
+    pub context_id: Option<DltId>,
+}
+
+/// The `IndexEntry`s of all messages of a source, in the order of the source
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DltIndex {
+    with_storage_header: bool,
+    entries: Vec<IndexEntry>,
+}
+
+impl DltIndex {
+    pub fn new(with_storage_header: bool) -> Self {
+        DltIndex {
+            with_storage_header,
+            entries: vec![],
+        }
+    }
+
+    pub fn with_storage_header(&self) -> bool {
+        self.with_storage_header
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// The entry of the `n`th message
+    pub fn get(&self, n: usize) -> Option<&IndexEntry> {
+        self.entries.get(n)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of bytes of the source that are covered by the index
+    pub fn indexed_bytes(&self) -> u64 {
+        self.entries
+            .last()
+            .map(|entry| entry.offset + entry.length as u64)
+            .unwrap_or_default()
+    }
+
+    /// Add a message that starts at `offset` in the source, after the last
+    /// indexed message
+    ///
+    /// The headers of the message are parsed for the timestamp and the ids,
+    /// which are left empty if the headers are invalid.
+    pub(crate) fn push_message(&mut self, offset: u64, message: &[u8]) {
+        let entry = index_entry(offset, message, self.with_storage_header);
+        self.entries.push(entry);
+    }
+}
+
+fn index_entry(offset: u64, message: &[u8], with_storage_header: bool) -> IndexEntry {
+    let mut entry = IndexEntry {
+        offset,
+        length: message.len() as u32,
+        timestamp: None,
+        ecu_id: None,
+        application_id: None,
+        context_id: None,
+    };
+    let after_storage_header = if with_storage_header {
+        match dlt_storage_header(message) {
+            Ok((rest, storage_header)) => {
+                if let Some((header, _)) = storage_header {
+                    entry.timestamp = Some(header.timestamp);
+                    entry.ecu_id = Some(header.ecu_id);
+                }
+                rest
+            }
+            Err(_) => return entry,
+        }
+    } else {
+        message
+    };
+    if let Ok((rest, header)) = dlt_standard_header(after_storage_header) {
+        entry.ecu_id = header.ecu_id.or(entry.ecu_id);
+        if header.has_extended_header {
+            if let Ok((_, extended_header)) = dlt_extended_header(rest) {
+                entry.application_id = Some(extended_header.application_id);
+                entry.context_id = Some(extended_header.context_id);
+            }
+        }
+    }
+    entry
+}
+
+/// Scan all messages of the reader and build their index
+///
+/// The offsets are relative to the position of the reader when the scan
+/// starts. Progress hook and cancellation token of the reader are used.
+pub fn build_index<S: Read>(reader: &mut DltMessageReader<S>) -> Result<DltIndex, DltParseError> {
+    let mut index = DltIndex::new(reader.with_storage_header());
+    loop {
+        // bytes skipped by the reader are not covered by the index
+        let (offset, slice) = reader.next_message_slice_at()?;
+        if slice.is_empty() {
+            return Ok(index);
+        }
+        index.push_message(offset, slice);
+    }
+}
+
+/// Build the index of an async source without blocking the executor
+///
+/// The source is indexed from its start, `progress` is called after each
+/// message with the number of indexed bytes and the size of the source.
+#[cfg(feature = "stream")]
+pub async fn build_index_async<S: AsyncRead + AsyncSeek + Unpin>(
+    mut source: S,
+    with_storage_header: bool,
+    mut progress: impl FnMut(Progress),
+) -> Result<DltIndex, DltParseError> {
+    let total = source.seek(SeekFrom::End(0)).await?;
+    source.seek(SeekFrom::Start(0)).await?;
+    let mut reader = DltStreamReader::new(source, with_storage_header);
+    let mut index = DltIndex::new(with_storage_header);
+    loop {
+        let slice = reader.next_message_slice().await?;
+        if slice.is_empty() {
+            return Ok(index);
+        }
+        index.push_message(index.indexed_bytes(), slice);
+        progress(Progress {
+            processed: index.indexed_bytes(),
+            total: Some(total),
+        });
+    }
+}
+
+/// Build the index of messages with storage headers that are completely in memory
+///
+/// Incomplete data at the end of the input is not indexed.
+pub fn build_index_from_slice(input: &[u8]) -> Result<DltIndex, DltParseError> {
+    let mut index = DltIndex::new(true);
+    let mut rest = input;
+    loop {
+        match dlt_consume_msg(rest) {
+            Ok((after_message, Some(consumed))) => {
+                let offset = (input.len() - rest.len()) as u64;
+                index.push_message(offset, &rest[..consumed as usize]);
+                rest = after_message;
+            }
+            Ok((_, None)) | Err(DltParseError::IncompleteParse { .. }) => return Ok(index),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads arbitrary messages of an indexed source
+pub struct DltIndexedReader<S: Read + Seek> {
+    source: S,
+    index: DltIndex,
+    buffer: Vec<u8>,
+}
+
+impl<S: Read + Seek> DltIndexedReader<S> {
+    /// Create a reader for a source with an index that was built from it
+    pub fn new(source: S, index: DltIndex) -> Self {
+        DltIndexedReader {
+            source,
+            index,
+            buffer: vec![],
+        }
+    }
+
+    pub fn index(&self) -> &DltIndex {
+        &self.index
+    }
+
+    /// Number of messages in the index
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Read and parse the `n`th message, `None` if it is not in the index
+    pub fn message(&mut self, n: usize) -> Result<Option<ParsedMessage>, DltParseError> {
+        Ok(self.messages(n..n + 1)?.pop())
+    }
+
+    /// Read and parse a range of messages
+    ///
+    /// The range is cut to the messages in the index, the bytes of all
+    /// messages of the range are read at once.
+    pub fn messages(&mut self, range: Range<usize>) -> Result<Vec<ParsedMessage>, DltParseError> {
+        let entries = match self
+            .index
+            .entries
+            .get(range.start..range.end.min(self.index.len()))
+        {
+            Some(entries) if !entries.is_empty() => entries,
+            _ => return Ok(vec![]),
+        };
+        let start = entries[0].offset;
+        let end = entries[entries.len() - 1].offset + entries[entries.len() - 1].length as u64;
+        self.buffer.resize((end - start) as usize, 0);
+        self.source.seek(SeekFrom::Start(start))?;
+        self.source.read_exact(&mut self.buffer)?;
+        let mut messages = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let from = (entry.offset - start) as usize;
+            let slice = &self.buffer[from..from + entry.length as usize];
+            let (_, message) = dlt_message(slice, None, self.index.with_storage_header)?;
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
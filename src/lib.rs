@@ -19,15 +19,30 @@
 #[macro_use]
 extern crate log;
 
+pub mod control;
+pub mod dlf;
 pub mod dlt;
+pub mod export;
 pub mod fibex;
 pub mod filtering;
+pub mod fmt;
+pub mod index;
+pub mod net;
+pub mod nw_trace;
 pub mod parse;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+pub mod progress;
+pub mod read;
+#[cfg(feature = "serial")]
+pub mod serial;
 #[cfg(not(tarpaulin_include))]
 pub mod service_id;
 #[cfg(not(tarpaulin_include))]
 #[cfg(feature = "statistics")]
 pub mod statistics;
+#[cfg(feature = "stream")]
+pub mod stream;
 
 #[cfg(test)]
 pub mod proptest_strategies;
@@ -0,0 +1,296 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # TCP client for dlt-daemon
+//!
+//! dlt-daemon sends the messages of all connected applications to each TCP
+//! client (port 3490 by default) and accepts control messages on the same
+//! connection. With the `tls` feature, the connection can be secured with
+//! TLS (via `rustls`).
+use super::Error;
+use crate::{
+    dlt::Message,
+    filtering::ProcessedDltFilterConfig,
+    parse::{DltParseError, ParsedMessage},
+    progress::CancellationToken,
+    read::{read_message, DltMessageReader, FilterHandle},
+};
+#[cfg(feature = "tls")]
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, StreamOwned};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    thread,
+    time::Duration,
+};
+
+/// When and how often a lost connection is established again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// time to wait before each attempt
+    pub delay: Duration,
+    /// number of attempts after the connection was lost, unlimited if `None`
+    pub attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            delay: Duration::from_secs(1),
+            attempts: None,
+        }
+    }
+}
+
+/// A plain or TLS secured connection
+enum Connection {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Server name and client configuration of a TLS connection
+#[cfg(feature = "tls")]
+type TlsSettings = (ServerName<'static>, Arc<ClientConfig>);
+
+/// Reads the message stream of a dlt-daemon TCP endpoint
+pub struct DltTcpClient {
+    addresses: Vec<SocketAddr>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsSettings>,
+    reader: DltMessageReader<Connection>,
+    /// the TCP socket of the connection, to shut it down from other threads
+    socket: TcpStream,
+    filter: FilterHandle,
+    reconnect: Option<ReconnectPolicy>,
+    cancel: CancellationToken,
+}
+
+impl DltTcpClient {
+    /// Connect to a dlt-daemon, e.g. `DltTcpClient::connect("192.168.0.2:3490")`
+    pub fn connect(address: impl ToSocketAddrs) -> Result<Self, Error> {
+        let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+        let socket = TcpStream::connect(addresses.as_slice())?;
+        Ok(DltTcpClient {
+            addresses,
+            #[cfg(feature = "tls")]
+            tls: None,
+            reader: DltMessageReader::new(Connection::Plain(socket.try_clone()?), false),
+            socket,
+            filter: FilterHandle::default(),
+            reconnect: None,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Connect to a dlt-daemon over TLS
+    ///
+    /// The certificate of the server is verified for `server_name` as
+    /// configured in `config`. Reconnects use the same settings.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        address: impl ToSocketAddrs,
+        server_name: ServerName<'static>,
+        config: Arc<ClientConfig>,
+    ) -> Result<Self, Error> {
+        let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+        let socket = TcpStream::connect(addresses.as_slice())?;
+        let tls = (server_name, config);
+        let connection = tls_connection(&socket, &tls)?;
+        Ok(DltTcpClient {
+            addresses,
+            tls: Some(tls),
+            reader: DltMessageReader::new(connection, false),
+            socket,
+            filter: FilterHandle::default(),
+            reconnect: None,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Connect again when the connection is lost, instead of ending the messages
+    pub fn set_reconnect(&mut self, policy: Option<ReconnectPolicy>) {
+        self.reconnect = policy;
+    }
+
+    /// The token that stops waiting for a new connection
+    ///
+    /// A reader that is blocked in reading is not interrupted, but the
+    /// connection can be closed with `DltTcpClient::shutdown`.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// A handle to the filter that is used by `DltTcpClient::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltTcpClient::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// The address of the connected dlt-daemon
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.socket.peer_addr()?)
+    }
+
+    /// Send a message to the dlt-daemon, e.g. a control request
+    ///
+    /// A storage header of the message is not sent.
+    pub fn send_message(&mut self, message: &Message) -> Result<(), Error> {
+        let bytes = message.as_bytes();
+        let offset = if message.storage_header.is_some() {
+            crate::dlt::STORAGE_HEADER_LENGTH as usize
+        } else {
+            0
+        };
+        let connection = self.reader.source_mut();
+        connection.write_all(&bytes[offset..])?;
+        connection.flush()?;
+        Ok(())
+    }
+
+    /// Close the connection, a blocked `next_message` returns
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.socket.shutdown(std::net::Shutdown::Both)?;
+        Ok(())
+    }
+
+    /// Wait for the next message and parse it using the current filter
+    ///
+    /// Returns `None` when the dlt-daemon closes the connection and it isn't
+    /// reconnected. Messages that were partly received when the connection
+    /// was lost are dropped. An invalid message length leaves the connection
+    /// out of sync, it is handled like a lost connection.
+    pub fn next_message(&mut self) -> Result<Option<ParsedMessage>, Error> {
+        loop {
+            let filter = self.filter.get();
+            let result = read_message(&mut self.reader, filter.as_deref());
+            match result {
+                Ok(Some(message)) => return Ok(Some(message)),
+                Ok(None)
+                | Err(DltParseError::Unrecoverable(_))
+                | Err(DltParseError::IncompleteParse { .. })
+                    if self.reconnect.is_some() =>
+                {
+                    if !self.connect_again()? {
+                        return Ok(None);
+                    }
+                }
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Iterate over the received messages, see `DltTcpClient::next_message`
+    pub fn messages(&mut self) -> impl Iterator<Item = Result<ParsedMessage, Error>> + '_ {
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            match self.next_message() {
+                Ok(Some(message)) => Some(Ok(message)),
+                Ok(None) => None,
+                Err(e) => {
+                    done = !matches!(e, Error::Parse(DltParseError::ParsingHickup(_)));
+                    Some(Err(e))
+                }
+            }
+        })
+    }
+
+    /// Establish a new connection according to the reconnect policy,
+    /// `false` if all attempts failed or waiting was cancelled
+    fn connect_again(&mut self) -> Result<bool, Error> {
+        let policy = match self.reconnect {
+            Some(policy) => policy,
+            None => return Ok(false),
+        };
+        let mut attempt = 0;
+        while policy.attempts.is_none_or(|attempts| attempt < attempts) {
+            attempt += 1;
+            if self.cancel.is_cancelled() {
+                return Ok(false);
+            }
+            thread::sleep(policy.delay);
+            if self.cancel.is_cancelled() {
+                return Ok(false);
+            }
+            match TcpStream::connect(self.addresses.as_slice()) {
+                Ok(socket) => {
+                    debug!("reconnected to {:?}", socket.peer_addr());
+                    self.reader = DltMessageReader::new(self.connection(&socket)?, false);
+                    self.socket = socket;
+                    return Ok(true);
+                }
+                Err(e) => debug!("reconnect attempt {} failed: {}", attempt, e),
+            }
+        }
+        Ok(false)
+    }
+
+    fn connection(&self, socket: &TcpStream) -> Result<Connection, Error> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return tls_connection(socket, tls);
+        }
+        Ok(Connection::Plain(socket.try_clone()?))
+    }
+}
+
+#[cfg(feature = "tls")]
+fn tls_connection(
+    socket: &TcpStream,
+    (server_name, config): &TlsSettings,
+) -> Result<Connection, Error> {
+    let client = ClientConnection::new(config.clone(), server_name.clone())?;
+    Ok(Connection::Tls(Box::new(StreamOwned::new(
+        client,
+        socket.try_clone()?,
+    ))))
+}
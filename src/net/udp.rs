@@ -0,0 +1,256 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # UDP receiver for dlt messages
+//!
+//! dlt-daemon and many ECUs send their messages as UDP datagrams, often to
+//! a multicast group that is shared by several ECUs of one network segment.
+use super::Error;
+use crate::{
+    dlt::DltId,
+    filtering::ProcessedDltFilterConfig,
+    parse::{dlt_extended_header, dlt_message, dlt_standard_header, DltParseError, ParsedMessage},
+    progress::CancellationToken,
+    read::FilterHandle,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// A message with the address it was sent from
+#[derive(Debug, PartialEq)]
+pub struct ReceivedMessage {
+    pub source: SocketAddr,
+    pub message: ParsedMessage,
+}
+
+/// The ids that identify a sequence of message counters
+///
+/// An id is `None` if the message doesn't carry it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CounterSource {
+    pub ecu_id: Option<DltId>,
+    pub application_id: Option<DltId>,
+    pub context_id: Option<DltId>,
+}
+
+/// Running estimate of the messages that got lost on the way
+///
+/// Losses are derived from gaps in the message counters, so more than 255
+/// consecutive lost messages of one source can't be detected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LossEstimate {
+    /// Messages that were received, including the ones that were filtered out
+    pub received: u64,
+    /// Messages that are missing according to the message counters
+    pub lost: u64,
+    /// Messages that repeated the counter of their predecessor
+    pub duplicated: u64,
+}
+
+impl LossEstimate {
+    /// The share of sent messages that got lost, between 0 and 1
+    pub fn loss_ratio(&self) -> f64 {
+        let sent = self.received + self.lost;
+        if sent == 0 {
+            0.0
+        } else {
+            self.lost as f64 / sent as f64
+        }
+    }
+
+    fn count(&mut self, last_counter: Option<u8>, counter: u8) {
+        self.received += 1;
+        match last_counter {
+            Some(last) if last == counter => self.duplicated += 1,
+            Some(last) => self.lost += u64::from(counter.wrapping_sub(last).wrapping_sub(1)),
+            None => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct LossTracker {
+    total: LossEstimate,
+    sources: HashMap<CounterSource, (u8, LossEstimate)>,
+}
+
+impl LossTracker {
+    fn track(&mut self, message: &[u8]) {
+        let Ok((rest, header)) = dlt_standard_header(message) else {
+            return;
+        };
+        let mut source = CounterSource {
+            ecu_id: header.ecu_id,
+            application_id: None,
+            context_id: None,
+        };
+        if header.has_extended_header {
+            if let Ok((_, extended_header)) = dlt_extended_header(rest) {
+                source.application_id = Some(extended_header.application_id);
+                source.context_id = Some(extended_header.context_id);
+            }
+        }
+        let counter = header.message_counter;
+        let last_counter = self.sources.get(&source).map(|(last, _)| *last);
+        let (last, estimate) = self.sources.entry(source).or_default();
+        *last = counter;
+        estimate.count(last_counter, counter);
+        self.total.count(last_counter, counter);
+    }
+}
+
+/// Receives the messages of UDP datagrams, each can contain several messages
+///
+/// The message counters of all received messages are tracked per
+/// [`CounterSource`] to estimate how many messages got lost, see
+/// [`DltUdpReceiver::loss_estimate`].
+pub struct DltUdpReceiver {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+    pending: VecDeque<ReceivedMessage>,
+    filter: FilterHandle,
+    cancel: CancellationToken,
+    losses: LossTracker,
+}
+
+impl DltUdpReceiver {
+    /// Receive the datagrams sent to a local address, e.g. `0.0.0.0:3490`
+    ///
+    /// To receive multicast datagrams, bind to the port of the group and join it.
+    pub fn bind(address: impl ToSocketAddrs) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(address)?;
+        // wake up regularly to check for cancellation
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        Ok(DltUdpReceiver {
+            socket,
+            buffer: vec![0; MAX_DATAGRAM_SIZE],
+            pending: VecDeque::new(),
+            filter: FilterHandle::default(),
+            cancel: CancellationToken::new(),
+            losses: LossTracker::default(),
+        })
+    }
+
+    /// Join an IPv4 multicast group on the interface with the given address
+    ///
+    /// `Ipv4Addr::UNSPECIFIED` lets the operating system choose the interface.
+    /// A receiver can join the same group on several interfaces.
+    pub fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> Result<(), Error> {
+        self.socket.join_multicast_v4(&group, &interface)?;
+        Ok(())
+    }
+
+    /// Join an IPv6 multicast group on the interface with the given index (0 for any)
+    pub fn join_multicast_v6(&self, group: Ipv6Addr, interface: u32) -> Result<(), Error> {
+        self.socket.join_multicast_v6(&group, interface)?;
+        Ok(())
+    }
+
+    /// Leave an IPv4 multicast group that was joined before
+    pub fn leave_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> Result<(), Error> {
+        self.socket.leave_multicast_v4(&group, &interface)?;
+        Ok(())
+    }
+
+    /// Leave an IPv6 multicast group that was joined before
+    pub fn leave_multicast_v6(&self, group: Ipv6Addr, interface: u32) -> Result<(), Error> {
+        self.socket.leave_multicast_v6(&group, interface)?;
+        Ok(())
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// The token that stops waiting for datagrams with `DltParseError::Cancelled`
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// A handle to the filter that is used by `DltUdpReceiver::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltUdpReceiver::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// The loss estimate over all messages received so far
+    pub fn loss_estimate(&self) -> LossEstimate {
+        self.losses.total
+    }
+
+    /// The loss estimates of the individual counter sources
+    pub fn loss_estimates(&self) -> HashMap<CounterSource, LossEstimate> {
+        self.losses
+            .sources
+            .iter()
+            .map(|(source, (_, estimate))| (*source, *estimate))
+            .collect()
+    }
+
+    /// Forget all counters and start a new loss estimate
+    pub fn reset_loss_estimate(&mut self) {
+        self.losses = LossTracker::default();
+    }
+
+    /// Wait for the next message and parse it using the current filter
+    ///
+    /// Bytes of a datagram that don't form a message are dropped.
+    pub fn next_message(&mut self) -> Result<ReceivedMessage, Error> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(message);
+            }
+            if self.cancel.is_cancelled() {
+                return Err(DltParseError::Cancelled.into());
+            }
+            let (length, source) = match self.socket.recv_from(&mut self.buffer) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let filter = self.filter.get();
+            let mut rest = &self.buffer[..length];
+            while !rest.is_empty() {
+                match dlt_message(rest, filter.as_deref(), false) {
+                    Ok((after_message, message)) => {
+                        self.losses.track(&rest[..rest.len() - after_message.len()]);
+                        self.pending.push_back(ReceivedMessage { source, message });
+                        rest = after_message;
+                    }
+                    Err(e) => {
+                        debug!(
+                            "dropped {} bytes of datagram from {}: {}",
+                            rest.len(),
+                            source,
+                            e
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
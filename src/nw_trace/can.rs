@@ -0,0 +1,166 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # CAN frames of network traces
+//!
+//! CAN traces carry two slices: the identifier and the data of the frame.
+//! The identifier is a big endian value of 2 or 4 bytes that uses the
+//! SocketCAN encoding, the highest bits flag extended, remote and error
+//! frames. It can be followed by a byte with the SocketCAN CAN-FD flags.
+use super::{network_trace, Error};
+use crate::dlt::{Message, NetworkTraceType};
+use byteorder::{BigEndian, ByteOrder};
+
+const EXTENDED_FRAME_FLAG: u32 = 0x8000_0000;
+const REMOTE_FRAME_FLAG: u32 = 0x4000_0000;
+const ERROR_FRAME_FLAG: u32 = 0x2000_0000;
+const EXTENDED_ID_MASK: u32 = 0x1fff_ffff;
+const STANDARD_ID_MASK: u32 = 0x0000_07ff;
+const BIT_RATE_SWITCH_FLAG: u8 = 0x01;
+const ERROR_STATE_INDICATOR_FLAG: u8 = 0x02;
+const FD_FRAME_FLAG: u8 = 0x04;
+/// Data lengths of CAN-FD frames for the data length codes 9 to 15
+const FD_DATA_LENGTHS: [usize; 7] = [12, 16, 20, 24, 32, 48, 64];
+const MAX_CLASSIC_DATA_LENGTH: usize = 8;
+
+/// Flags of a CAN frame
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CanFlags {
+    /// 29 bit identifier instead of 11 bits
+    pub extended: bool,
+    /// Remote transmission request
+    pub remote: bool,
+    pub error: bool,
+    /// CAN-FD frame with up to 64 bytes of data
+    pub fd: bool,
+    /// CAN-FD data phase with a higher bit rate
+    pub bit_rate_switch: bool,
+    /// CAN-FD sender is error passive
+    pub error_state_indicator: bool,
+}
+
+/// A CAN or CAN-FD frame
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanFrame {
+    /// The identifier without flags
+    pub id: u32,
+    pub flags: CanFlags,
+    /// Data length code as sent on the bus
+    pub dlc: u8,
+    pub data: Vec<u8>,
+}
+
+impl CanFrame {
+    /// Create a frame from the identifier and data slices of a trace
+    pub fn parse(id: &[u8], data: &[u8]) -> Result<CanFrame, Error> {
+        let (raw_id, fd_flags) = match id.len() {
+            2 => (u32::from(BigEndian::read_u16(id)), None),
+            4 => (BigEndian::read_u32(id), None),
+            5 => (BigEndian::read_u32(&id[..4]), Some(id[4])),
+            other => {
+                return Err(Error::Invalid {
+                    what: "CAN identifier",
+                    reason: format!("unsupported length {}", other),
+                })
+            }
+        };
+        let fd_flags = fd_flags.unwrap_or_default();
+        let extended = raw_id & EXTENDED_FRAME_FLAG != 0;
+        let flags = CanFlags {
+            extended,
+            remote: raw_id & REMOTE_FRAME_FLAG != 0,
+            error: raw_id & ERROR_FRAME_FLAG != 0,
+            fd: fd_flags & FD_FRAME_FLAG != 0 || data.len() > MAX_CLASSIC_DATA_LENGTH,
+            bit_rate_switch: fd_flags & BIT_RATE_SWITCH_FLAG != 0,
+            error_state_indicator: fd_flags & ERROR_STATE_INDICATOR_FLAG != 0,
+        };
+        let dlc = data_length_code(data.len(), flags.fd).ok_or_else(|| Error::Invalid {
+            what: "CAN data",
+            reason: format!("{} bytes don't fit a frame", data.len()),
+        })?;
+        Ok(CanFrame {
+            id: raw_id
+                & if extended {
+                    EXTENDED_ID_MASK
+                } else {
+                    STANDARD_ID_MASK
+                },
+            flags,
+            dlc,
+            data: data.to_vec(),
+        })
+    }
+
+    /// The identifier and data slices to trace the frame
+    ///
+    /// The identifier is written with 4 bytes, CAN-FD frames get a flags byte.
+    pub fn to_slices(&self) -> Vec<Vec<u8>> {
+        let mut raw_id = self.id;
+        for (set, flag) in [
+            (self.flags.extended, EXTENDED_FRAME_FLAG),
+            (self.flags.remote, REMOTE_FRAME_FLAG),
+            (self.flags.error, ERROR_FRAME_FLAG),
+        ] {
+            if set {
+                raw_id |= flag;
+            }
+        }
+        let mut id = raw_id.to_be_bytes().to_vec();
+        if self.flags.fd {
+            let mut fd_flags = FD_FRAME_FLAG;
+            if self.flags.bit_rate_switch {
+                fd_flags |= BIT_RATE_SWITCH_FLAG;
+            }
+            if self.flags.error_state_indicator {
+                fd_flags |= ERROR_STATE_INDICATOR_FLAG;
+            }
+            id.push(fd_flags);
+        }
+        vec![id, self.data.clone()]
+    }
+}
+
+/// The data length code of a data length, `None` for lengths frames can't have
+fn data_length_code(length: usize, fd: bool) -> Option<u8> {
+    if length <= MAX_CLASSIC_DATA_LENGTH {
+        Some(length as u8)
+    } else if fd {
+        FD_DATA_LENGTHS
+            .iter()
+            .position(|fd_length| *fd_length == length)
+            .map(|index| (MAX_CLASSIC_DATA_LENGTH + 1 + index) as u8)
+    } else {
+        None
+    }
+}
+
+/// Decode the frame of a CAN network trace, `Ok(None)` if the message is no CAN trace
+pub fn decode(message: &Message) -> Result<Option<CanFrame>, Error> {
+    match network_trace(message) {
+        Some((NetworkTraceType::Can, [id, data])) => CanFrame::parse(id, data).map(Some),
+        Some((NetworkTraceType::Can, slices)) => Err(Error::Invalid {
+            what: "CAN trace",
+            reason: format!("expected 2 slices, got {}", slices.len()),
+        }),
+        _ => Ok(None),
+    }
+}
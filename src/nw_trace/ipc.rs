@@ -0,0 +1,129 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # IPC network traces
+//!
+//! IPC traces of the adaptive platform carry two slices: the endpoint info
+//! and the traced message, usually a SOME/IP message. The info is
+//!
+//! - IPv4 address (4 bytes) and port (2 bytes)
+//! - transport protocol (1 byte) and direction (1 byte)
+//! - instance id (1, 2 or 4 bytes)
+//!
+//! All values are big endian.
+use super::{
+    check_length, network_trace, someip::SomeIpMessage, someip_sd::TransportProtocol, Error,
+};
+use crate::dlt::{Message, NetworkTraceType};
+use byteorder::{BigEndian, ByteOrder};
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+const ENDPOINT_LENGTH: usize = 8;
+
+/// Whether the traced message was received or sent by the endpoint
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+    Other(u8),
+}
+
+impl From<u8> for Direction {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Incoming,
+            0x01 => Self::Outgoing,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// The endpoint info of an IPC trace
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpcEndpoint {
+    pub address: SocketAddrV4,
+    pub protocol: TransportProtocol,
+    pub direction: Direction,
+    pub instance_id: u32,
+}
+
+impl IpcEndpoint {
+    /// Parse the endpoint info slice
+    pub fn parse(info: &[u8]) -> Result<IpcEndpoint, Error> {
+        check_length("IPC endpoint info", info, ENDPOINT_LENGTH + 1)?;
+        let instance = &info[ENDPOINT_LENGTH..];
+        let instance_id = match instance.len() {
+            1 => u32::from(instance[0]),
+            2 => u32::from(BigEndian::read_u16(instance)),
+            4 => BigEndian::read_u32(instance),
+            other => {
+                return Err(Error::Invalid {
+                    what: "IPC instance id",
+                    reason: format!("unsupported length {}", other),
+                })
+            }
+        };
+        Ok(IpcEndpoint {
+            address: SocketAddrV4::new(
+                Ipv4Addr::new(info[0], info[1], info[2], info[3]),
+                BigEndian::read_u16(&info[4..6]),
+            ),
+            protocol: info[6].into(),
+            direction: info[7].into(),
+            instance_id,
+        })
+    }
+}
+
+/// An IPC trace, borrowing the traced message from the trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpcTrace<'a> {
+    pub endpoint: IpcEndpoint,
+    pub message: &'a [u8],
+}
+
+impl<'a> IpcTrace<'a> {
+    /// Create a trace from the endpoint info and message slices
+    pub fn parse(info: &[u8], message: &'a [u8]) -> Result<IpcTrace<'a>, Error> {
+        Ok(IpcTrace {
+            endpoint: IpcEndpoint::parse(info)?,
+            message,
+        })
+    }
+
+    /// Decode the traced message as SOME/IP message
+    pub fn someip(&self) -> Result<SomeIpMessage<'a>, Error> {
+        SomeIpMessage::parse(self.message).map(|(message, _)| message)
+    }
+}
+
+/// Decode an IPC network trace, `Ok(None)` if the message is no IPC trace
+pub fn decode(message: &Message) -> Result<Option<IpcTrace<'_>>, Error> {
+    match network_trace(message) {
+        Some((NetworkTraceType::Ipc, [info, message])) => IpcTrace::parse(info, message).map(Some),
+        Some((NetworkTraceType::Ipc, slices)) => Err(Error::Invalid {
+            what: "IPC trace",
+            reason: format!("expected 2 slices, got {}", slices.len()),
+        }),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Decoders for network trace messages
+//!
+//! Network trace messages carry the traffic of a bus or network in raw
+//! slices (`PayloadContent::NetworkTrace`), usually an info slice describing
+//! the endpoint followed by the traced message itself.
+use crate::dlt::{Message, MessageType, NetworkTraceType, PayloadContent};
+use bytes::Bytes;
+use thiserror::Error;
+
+pub mod can;
+pub mod ipc;
+pub mod registry;
+pub mod segmented;
+pub mod someip;
+pub mod someip_sd;
+
+/// Errors decoding the slices of a network trace
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A slice ends before the structure that is decoded from it
+    #[error("Not enough data for {what}: needed {needed} bytes, got {available}")]
+    Incomplete {
+        what: &'static str,
+        needed: usize,
+        available: usize,
+    },
+    /// A slice contains a value that violates the protocol
+    #[error("Invalid {what}: {reason}")]
+    Invalid { what: &'static str, reason: String },
+}
+
+/// The network trace type and the slices of a message
+///
+/// `None` if the message is no network trace or has no slices.
+pub fn network_trace(message: &Message) -> Option<(NetworkTraceType, &[Bytes])> {
+    let trace_type = match &message.extended_header.as_ref()?.message_type {
+        MessageType::NetworkTrace(trace_type) => trace_type.clone(),
+        _ => return None,
+    };
+    match &message.payload {
+        PayloadContent::NetworkTrace(slices) if !slices.is_empty() => {
+            Some((trace_type, slices.as_slice()))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn check_length(what: &'static str, input: &[u8], needed: usize) -> Result<(), Error> {
+    if input.len() < needed {
+        Err(Error::Incomplete {
+            what,
+            needed,
+            available: input.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Decoders for user defined network traces
+//!
+//! The trace types 7 to 15 are reserved for user defined protocols. A
+//! [`NetworkTraceRegistry`] holds the decoders that applications install for
+//! them and attaches their results to parsed messages.
+//!
+//! ```
+//! # use bytes::Bytes;
+//! # use dlt_core::{dlt::Value, nw_trace::registry::*};
+//! let mut registry = NetworkTraceRegistry::new();
+//! registry
+//!     .register(7, "LIN", |slices: &[Bytes]| {
+//!         Ok(vec![DecodedField::new("id", Value::U8(slices[0][0]))])
+//!     })
+//!     .expect("user defined trace type");
+//! ```
+use super::{network_trace, Error};
+use crate::dlt::{Message, NetworkTraceType, Value};
+use bytes::Bytes;
+use std::{collections::HashMap, ops::RangeInclusive, sync::Arc};
+
+/// The trace types that can have user defined decoders
+pub const USER_DEFINED_TRACE_TYPES: RangeInclusive<u8> = 0x7..=0xf;
+
+/// Decodes the slices of a network trace into named fields
+pub type TraceDecoder = dyn Fn(&[Bytes]) -> Result<Vec<DecodedField>, Error> + Send + Sync;
+
+/// A named value decoded from a network trace
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedField {
+    pub name: String,
+    pub value: Value,
+}
+
+impl DecodedField {
+    pub fn new(name: impl Into<String>, value: Value) -> Self {
+        DecodedField {
+            name: name.into(),
+            value,
+        }
+    }
+}
+
+/// The fields a decoder produced and the protocol it was registered for
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTrace {
+    pub protocol: String,
+    pub fields: Vec<DecodedField>,
+}
+
+/// A message with the result of its user defined network trace decoder
+#[derive(Debug, PartialEq)]
+pub struct DecodedMessage {
+    pub message: Message,
+    /// `None` if the message is no trace or no decoder is installed for its type
+    pub trace: Option<Result<DecodedTrace, Error>>,
+}
+
+/// Decoders of the user defined network trace types
+///
+/// Cloning a registry is cheap, the clones share the decoders.
+#[derive(Clone, Default)]
+pub struct NetworkTraceRegistry {
+    decoders: HashMap<u8, (String, Arc<TraceDecoder>)>,
+}
+
+impl NetworkTraceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Install the decoder of a user defined trace type (7 to 15)
+    ///
+    /// A decoder that was installed before for the type is replaced.
+    pub fn register<F>(
+        &mut self,
+        trace_type: u8,
+        protocol: impl Into<String>,
+        decoder: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&[Bytes]) -> Result<Vec<DecodedField>, Error> + Send + Sync + 'static,
+    {
+        if !USER_DEFINED_TRACE_TYPES.contains(&trace_type) {
+            return Err(Error::Invalid {
+                what: "user defined trace type",
+                reason: format!("{} is not in {:?}", trace_type, USER_DEFINED_TRACE_TYPES),
+            });
+        }
+        self.decoders
+            .insert(trace_type, (protocol.into(), Arc::new(decoder)));
+        Ok(())
+    }
+
+    /// Remove the decoder of a trace type, returns whether one was installed
+    pub fn unregister(&mut self, trace_type: u8) -> bool {
+        self.decoders.remove(&trace_type).is_some()
+    }
+
+    /// The protocol name of the decoder installed for a trace type
+    pub fn protocol(&self, trace_type: u8) -> Option<&str> {
+        self.decoders
+            .get(&trace_type)
+            .map(|(protocol, _)| protocol.as_str())
+    }
+
+    /// Decode a message with the decoder of its trace type
+    ///
+    /// `None` if the message is no user defined trace or no decoder is installed.
+    pub fn decode(&self, message: &Message) -> Option<Result<DecodedTrace, Error>> {
+        let (NetworkTraceType::UserDefined(trace_type), slices) = network_trace(message)? else {
+            return None;
+        };
+        let (protocol, decoder) = self.decoders.get(&trace_type)?;
+        Some(decoder(slices).map(|fields| DecodedTrace {
+            protocol: protocol.clone(),
+            fields,
+        }))
+    }
+
+    /// Attach the decoded trace to a message, the post-processing step after parsing
+    pub fn process(&self, message: Message) -> DecodedMessage {
+        let trace = self.decode(&message);
+        DecodedMessage { message, trace }
+    }
+}
+
+impl std::fmt::Debug for NetworkTraceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.decoders
+                    .iter()
+                    .map(|(trace_type, (protocol, _))| (trace_type, protocol)),
+            )
+            .finish()
+    }
+}
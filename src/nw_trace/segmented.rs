@@ -0,0 +1,242 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Segmented network traces
+//!
+//! Traces that don't fit a single message are sent in segments, each
+//! segment is a verbose network trace message that starts with a string:
+//!
+//! - `NWST`: stream handle, header, payload length, segment count and segment size
+//! - `NWCH`: stream handle, sequence number (starting at 0) and a chunk of the payload
+//! - `NWEN`: stream handle
+//!
+//! The [`SegmentReassembler`] collects the segments of all streams and
+//! returns the complete trace once its end message arrives.
+use super::Error;
+use crate::dlt::{Argument, DltId, Endianness, Message, MessageType, PayloadContent, Value};
+use byteorder::{BigEndian, LittleEndian};
+use std::collections::HashMap;
+
+pub const SEGMENT_START: &str = "NWST";
+pub const SEGMENT_CHUNK: &str = "NWCH";
+pub const SEGMENT_END: &str = "NWEN";
+
+/// Stream handles are unique per application of an ecu
+type StreamKey = (Option<DltId>, DltId, u32);
+
+struct PartialTrace {
+    start: Message,
+    header: Vec<u8>,
+    length: usize,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+/// Collects the segments of segmented network traces
+#[derive(Default)]
+pub struct SegmentReassembler {
+    streams: HashMap<StreamKey, PartialTrace>,
+}
+
+/// A segment of a segmented network trace
+enum Segment<'a> {
+    Start {
+        handle: u32,
+        header: &'a [u8],
+        length: u32,
+        count: u16,
+    },
+    Chunk {
+        handle: u32,
+        sequence: u16,
+        data: &'a [u8],
+    },
+    End {
+        handle: u32,
+    },
+}
+
+impl<'a> Segment<'a> {
+    fn parse(message: &'a Message) -> Result<Option<Segment<'a>>, Error> {
+        let (Some(MessageType::NetworkTrace(_)), PayloadContent::Verbose(arguments)) = (
+            message.extended_header.as_ref().map(|h| &h.message_type),
+            &message.payload,
+        ) else {
+            return Ok(None);
+        };
+        let values: Vec<&Value> = arguments.iter().map(|a: &Argument| &a.value).collect();
+        let segment = match values.as_slice() {
+            [Value::StringVal(tag), rest @ ..] => match (tag.as_str(), rest) {
+                (
+                    SEGMENT_START,
+                    [Value::U32(handle), Value::Raw(header), Value::U32(length), Value::U16(count), ..],
+                ) => Segment::Start {
+                    handle: *handle,
+                    header,
+                    length: *length,
+                    count: *count,
+                },
+                (SEGMENT_CHUNK, [Value::U32(handle), Value::U16(sequence), Value::Raw(data)]) => {
+                    Segment::Chunk {
+                        handle: *handle,
+                        sequence: *sequence,
+                        data,
+                    }
+                }
+                (SEGMENT_END, [Value::U32(handle)]) => Segment::End { handle: *handle },
+                (SEGMENT_START | SEGMENT_CHUNK | SEGMENT_END, _) => {
+                    return Err(Error::Invalid {
+                        what: "network trace segment",
+                        reason: format!("unexpected arguments of {}", tag),
+                    })
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(segment))
+    }
+}
+
+/// Whether the message is a segment of a segmented network trace
+pub fn is_segment(message: &Message) -> bool {
+    matches!(Segment::parse(message), Ok(Some(_)) | Err(_))
+}
+
+impl SegmentReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a message, returns the complete trace when the message ends a stream
+    ///
+    /// The complete trace is the start message with the header and the
+    /// reassembled payload as `PayloadContent::NetworkTrace` slices, so the
+    /// decoders of the other modules can be used on it. Messages that are no
+    /// segments are ignored. A stream that can't be completed is dropped.
+    pub fn push(&mut self, message: &Message) -> Result<Option<Message>, Error> {
+        let Some(segment) = Segment::parse(message)? else {
+            return Ok(None);
+        };
+        let key = |handle: u32| -> StreamKey {
+            (
+                message.header.ecu_id,
+                message
+                    .extended_header
+                    .as_ref()
+                    .map(|h| h.application_id)
+                    .unwrap_or_default(),
+                handle,
+            )
+        };
+        match segment {
+            Segment::Start {
+                handle,
+                header,
+                length,
+                count,
+            } => {
+                let previous = self.streams.insert(
+                    key(handle),
+                    PartialTrace {
+                        start: message.clone(),
+                        header: header.to_vec(),
+                        length: length as usize,
+                        chunks: vec![None; count as usize],
+                    },
+                );
+                if previous.is_some() {
+                    warn!("segmented trace {} restarted before its end", handle);
+                }
+                Ok(None)
+            }
+            Segment::Chunk {
+                handle,
+                sequence,
+                data,
+            } => {
+                let key = key(handle);
+                let trace = self.streams.get_mut(&key).ok_or_else(|| unknown(handle))?;
+                match trace.chunks.get_mut(sequence as usize) {
+                    Some(chunk) => {
+                        *chunk = Some(data.to_vec());
+                        Ok(None)
+                    }
+                    None => {
+                        let count = trace.chunks.len();
+                        self.streams.remove(&key);
+                        Err(Error::Invalid {
+                            what: "network trace segment",
+                            reason: format!(
+                                "sequence number {} of stream {} exceeds {} segments",
+                                sequence, handle, count
+                            ),
+                        })
+                    }
+                }
+            }
+            Segment::End { handle } => {
+                let trace = self
+                    .streams
+                    .remove(&key(handle))
+                    .ok_or_else(|| unknown(handle))?;
+                trace.complete().map(Some)
+            }
+        }
+    }
+
+    /// Number of streams that were started but didn't end yet
+    pub fn pending(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Drop the segments of all pending streams
+    pub fn clear(&mut self) {
+        self.streams.clear();
+    }
+}
+
+fn unknown(handle: u32) -> Error {
+    Error::Invalid {
+        what: "network trace segment",
+        reason: format!("stream {} was not started", handle),
+    }
+}
+
+impl PartialTrace {
+    fn complete(self) -> Result<Message, Error> {
+        let received: usize = self.chunks.iter().flatten().map(|chunk| chunk.len()).sum();
+        if self.chunks.iter().any(Option::is_none) || received < self.length {
+            return Err(Error::Incomplete {
+                what: "segmented trace",
+                needed: self.length,
+                available: received,
+            });
+        }
+        let mut payload: Vec<u8> = self.chunks.into_iter().flatten().flatten().collect();
+        payload.truncate(self.length);
+        let mut message = self.start;
+        message.payload = PayloadContent::NetworkTrace(vec![self.header.into(), payload.into()]);
+        if let Some(extended_header) = message.extended_header.as_mut() {
+            extended_header.argument_count = 2;
+        }
+        let payload_length = if message.header.endianness == Endianness::Big {
+            message.payload.as_bytes::<BigEndian>().len()
+        } else {
+            message.payload.as_bytes::<LittleEndian>().len()
+        };
+        // saturates for payloads that only fit a reassembled trace
+        message.header.payload_length = payload_length.min(u16::MAX as usize) as u16;
+        Ok(message)
+    }
+}
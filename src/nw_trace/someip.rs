@@ -0,0 +1,228 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # SOME/IP messages of network traces
+//!
+//! SOME/IP traces carry an endpoint info slice followed by the SOME/IP
+//! message, which starts with the 16 byte SOME/IP header. All header fields
+//! are big endian.
+use super::{check_length, network_trace, Error};
+use crate::dlt::{Message, NetworkTraceType};
+use byteorder::{BigEndian, ByteOrder};
+
+/// Length of the SOME/IP header
+pub const SOMEIP_HEADER_LENGTH: usize = 16;
+/// Bytes of the header that are included in the length field
+const LENGTH_COVERED_HEADER: u32 = 8;
+
+/// The kind of a SOME/IP message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SomeIpMessageType {
+    Request,
+    RequestNoReturn,
+    Notification,
+    Response,
+    Error,
+    TpRequest,
+    TpRequestNoReturn,
+    TpNotification,
+    TpResponse,
+    TpError,
+    Unknown(u8),
+}
+
+impl From<u8> for SomeIpMessageType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Request,
+            0x01 => Self::RequestNoReturn,
+            0x02 => Self::Notification,
+            0x80 => Self::Response,
+            0x81 => Self::Error,
+            0x20 => Self::TpRequest,
+            0x21 => Self::TpRequestNoReturn,
+            0x22 => Self::TpNotification,
+            0xa0 => Self::TpResponse,
+            0xa1 => Self::TpError,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl From<SomeIpMessageType> for u8 {
+    fn from(message_type: SomeIpMessageType) -> Self {
+        match message_type {
+            SomeIpMessageType::Request => 0x00,
+            SomeIpMessageType::RequestNoReturn => 0x01,
+            SomeIpMessageType::Notification => 0x02,
+            SomeIpMessageType::Response => 0x80,
+            SomeIpMessageType::Error => 0x81,
+            SomeIpMessageType::TpRequest => 0x20,
+            SomeIpMessageType::TpRequestNoReturn => 0x21,
+            SomeIpMessageType::TpNotification => 0x22,
+            SomeIpMessageType::TpResponse => 0xa0,
+            SomeIpMessageType::TpError => 0xa1,
+            SomeIpMessageType::Unknown(other) => other,
+        }
+    }
+}
+
+/// The result of a SOME/IP request, `Ok` for all other message types
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SomeIpReturnCode {
+    Ok,
+    NotOk,
+    UnknownService,
+    UnknownMethod,
+    NotReady,
+    NotReachable,
+    Timeout,
+    WrongProtocolVersion,
+    WrongInterfaceVersion,
+    MalformedMessage,
+    WrongMessageType,
+    /// Reserved or service specific codes
+    Other(u8),
+}
+
+impl From<u8> for SomeIpReturnCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Ok,
+            0x01 => Self::NotOk,
+            0x02 => Self::UnknownService,
+            0x03 => Self::UnknownMethod,
+            0x04 => Self::NotReady,
+            0x05 => Self::NotReachable,
+            0x06 => Self::Timeout,
+            0x07 => Self::WrongProtocolVersion,
+            0x08 => Self::WrongInterfaceVersion,
+            0x09 => Self::MalformedMessage,
+            0x0a => Self::WrongMessageType,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// The header of a SOME/IP message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SomeIpHeader {
+    pub service_id: u16,
+    pub method_id: u16,
+    /// Length of the message after the length field, including 8 header bytes
+    pub length: u32,
+    pub client_id: u16,
+    pub session_id: u16,
+    pub protocol_version: u8,
+    pub interface_version: u8,
+    pub message_type: SomeIpMessageType,
+    pub return_code: SomeIpReturnCode,
+}
+
+impl SomeIpHeader {
+    /// Parse the header at the start of the input
+    pub fn parse(input: &[u8]) -> Result<SomeIpHeader, Error> {
+        check_length("SOME/IP header", input, SOMEIP_HEADER_LENGTH)?;
+        let header = SomeIpHeader {
+            service_id: BigEndian::read_u16(&input[0..2]),
+            method_id: BigEndian::read_u16(&input[2..4]),
+            length: BigEndian::read_u32(&input[4..8]),
+            client_id: BigEndian::read_u16(&input[8..10]),
+            session_id: BigEndian::read_u16(&input[10..12]),
+            protocol_version: input[12],
+            interface_version: input[13],
+            message_type: input[14].into(),
+            return_code: input[15].into(),
+        };
+        if header.length < LENGTH_COVERED_HEADER {
+            return Err(Error::Invalid {
+                what: "SOME/IP length",
+                reason: format!("{} is shorter than the header", header.length),
+            });
+        }
+        Ok(header)
+    }
+
+    /// Length of the payload that follows the header
+    pub fn payload_length(&self) -> usize {
+        (self.length - LENGTH_COVERED_HEADER) as usize
+    }
+
+    /// Whether the message belongs to the SOME/IP service discovery
+    pub fn is_service_discovery(&self) -> bool {
+        self.service_id == super::someip_sd::SD_SERVICE_ID
+            && self.method_id == super::someip_sd::SD_METHOD_ID
+    }
+
+    /// Whether the method id denotes an event (highest bit set)
+    pub fn is_event(&self) -> bool {
+        self.method_id & 0x8000 != 0
+    }
+}
+
+/// A SOME/IP message, borrowing its payload from the traced slice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SomeIpMessage<'a> {
+    pub header: SomeIpHeader,
+    pub payload: &'a [u8],
+}
+
+impl<'a> SomeIpMessage<'a> {
+    /// Parse the message at the start of the input, returning the bytes after it
+    ///
+    /// A slice can carry several SOME/IP messages back to back.
+    pub fn parse(input: &'a [u8]) -> Result<(SomeIpMessage<'a>, &'a [u8]), Error> {
+        let header = SomeIpHeader::parse(input)?;
+        let end = SOMEIP_HEADER_LENGTH + header.payload_length();
+        check_length("SOME/IP payload", input, end)?;
+        Ok((
+            SomeIpMessage {
+                header,
+                payload: &input[SOMEIP_HEADER_LENGTH..end],
+            },
+            &input[end..],
+        ))
+    }
+}
+
+/// Decode the SOME/IP messages of a SOME/IP network trace
+///
+/// The messages are taken from the last slice, the ones before describe the
+/// endpoint. `Ok(None)` if the message is no SOME/IP trace.
+pub fn decode(message: &Message) -> Result<Option<Vec<SomeIpMessage<'_>>>, Error> {
+    let slices = match network_trace(message) {
+        Some((NetworkTraceType::Someip, slices)) => slices,
+        _ => return Ok(None),
+    };
+    let mut rest = &slices[slices.len() - 1][..];
+    let mut messages = Vec::new();
+    while !rest.is_empty() {
+        let (message, after_message) = SomeIpMessage::parse(rest)?;
+        messages.push(message);
+        rest = after_message;
+    }
+    Ok(Some(messages))
+}
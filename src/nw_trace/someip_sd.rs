@@ -0,0 +1,350 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # SOME/IP service discovery
+//!
+//! Service discovery messages are SOME/IP notifications of service `0xFFFF`
+//! and method `0x8100`. Their payload holds an array of entries that offer,
+//! find or subscribe services, and an array of options the entries refer
+//! to, e.g. the endpoints of the offered services.
+use super::{
+    check_length,
+    someip::{self, SomeIpMessage},
+    Error,
+};
+use crate::dlt::Message;
+use byteorder::{BigEndian, ByteOrder};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Service id of service discovery messages
+pub const SD_SERVICE_ID: u16 = 0xffff;
+/// Method id of service discovery messages
+pub const SD_METHOD_ID: u16 = 0x8100;
+
+const SD_HEADER_LENGTH: usize = 8;
+const ENTRY_LENGTH: usize = 16;
+/// Length and type fields that are not included in the option length
+const OPTION_HEADER_LENGTH: usize = 3;
+const REBOOT_FLAG: u8 = 0x80;
+const UNICAST_FLAG: u8 = 0x40;
+
+/// The meaning of an entry, stop and negative variants are sent with a TTL of 0
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SdEntryType {
+    FindService,
+    OfferService,
+    StopOfferService,
+    SubscribeEventgroup,
+    StopSubscribeEventgroup,
+    SubscribeEventgroupAck,
+    SubscribeEventgroupNack,
+    Unknown(u8),
+}
+
+impl SdEntryType {
+    fn new(value: u8, ttl: u32) -> Self {
+        match (value, ttl) {
+            (0x00, _) => Self::FindService,
+            (0x01, 0) => Self::StopOfferService,
+            (0x01, _) => Self::OfferService,
+            (0x06, 0) => Self::StopSubscribeEventgroup,
+            (0x06, _) => Self::SubscribeEventgroup,
+            (0x07, 0) => Self::SubscribeEventgroupNack,
+            (0x07, _) => Self::SubscribeEventgroupAck,
+            (other, _) => Self::Unknown(other),
+        }
+    }
+}
+
+/// The fields that differ between service and eventgroup entries
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdEntryTarget {
+    Service { minor_version: u32 },
+    Eventgroup { counter: u8, eventgroup_id: u16 },
+}
+
+/// An entry of a service discovery message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdEntry {
+    pub entry_type: SdEntryType,
+    pub service_id: u16,
+    pub instance_id: u16,
+    pub major_version: u8,
+    /// Lifetime of the entry in seconds, `0xFFFFFF` means until the next reboot
+    pub ttl: u32,
+    pub target: SdEntryTarget,
+    /// Index of the first option and number of options of both option runs
+    pub option_runs: [(u8, u8); 2],
+}
+
+impl SdEntry {
+    fn parse(input: &[u8]) -> SdEntry {
+        let ttl = BigEndian::read_u24(&input[9..12]);
+        let target = if input[0] < 0x04 {
+            SdEntryTarget::Service {
+                minor_version: BigEndian::read_u32(&input[12..16]),
+            }
+        } else {
+            SdEntryTarget::Eventgroup {
+                counter: input[13] & 0x0f,
+                eventgroup_id: BigEndian::read_u16(&input[14..16]),
+            }
+        };
+        SdEntry {
+            entry_type: SdEntryType::new(input[0], ttl),
+            service_id: BigEndian::read_u16(&input[4..6]),
+            instance_id: BigEndian::read_u16(&input[6..8]),
+            major_version: input[8],
+            ttl,
+            target,
+            option_runs: [(input[1], input[3] >> 4), (input[2], input[3] & 0x0f)],
+        }
+    }
+
+    /// The options of the entry, options that don't exist are skipped
+    pub fn options<'a>(&self, message: &'a SdMessage) -> impl Iterator<Item = &'a SdOption> {
+        self.option_runs
+            .into_iter()
+            .flat_map(|(index, count)| index as usize..index as usize + count as usize)
+            .filter_map(|index| message.options.get(index))
+    }
+}
+
+/// The transport protocol of an endpoint
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportProtocol {
+    Tcp,
+    Udp,
+    Other(u8),
+}
+
+impl From<u8> for TransportProtocol {
+    fn from(value: u8) -> Self {
+        match value {
+            0x06 => Self::Tcp,
+            0x11 => Self::Udp,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// What an endpoint option is used for
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndpointKind {
+    /// Unicast address of a service or subscriber
+    Unicast,
+    /// Multicast address of an eventgroup
+    Multicast,
+    /// Address of the service discovery itself
+    ServiceDiscovery,
+}
+
+/// An option of a service discovery message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SdOption {
+    /// Configuration strings, usually `key=value`
+    Configuration(Vec<String>),
+    LoadBalancing {
+        priority: u16,
+        weight: u16,
+    },
+    Endpoint {
+        kind: EndpointKind,
+        address: SocketAddr,
+        protocol: TransportProtocol,
+    },
+    /// Options of other types with the data after the reserved byte
+    Unknown {
+        option_type: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl SdOption {
+    /// Parse the option at the start of the input, returning the bytes after it
+    fn parse(input: &[u8]) -> Result<(SdOption, &[u8]), Error> {
+        check_length("SOME/IP-SD option", input, OPTION_HEADER_LENGTH + 1)?;
+        let length = BigEndian::read_u16(&input[0..2]) as usize;
+        let option_type = input[2];
+        let end = OPTION_HEADER_LENGTH + length;
+        check_length("SOME/IP-SD option", input, end)?;
+        // skip the reserved byte
+        let data = &input[OPTION_HEADER_LENGTH + 1..end];
+        let option = match option_type {
+            0x01 => SdOption::Configuration(configuration_strings(data)?),
+            0x02 => {
+                check_length("SOME/IP-SD load balancing option", data, 4)?;
+                SdOption::LoadBalancing {
+                    priority: BigEndian::read_u16(&data[0..2]),
+                    weight: BigEndian::read_u16(&data[2..4]),
+                }
+            }
+            0x04 | 0x14 | 0x24 => {
+                check_length("SOME/IP-SD IPv4 endpoint option", data, 8)?;
+                let mut octets = [0u8; 4];
+                octets.copy_from_slice(&data[0..4]);
+                endpoint(option_type, Ipv4Addr::from(octets).into(), &data[4..])
+            }
+            0x06 | 0x16 | 0x26 => {
+                check_length("SOME/IP-SD IPv6 endpoint option", data, 20)?;
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&data[0..16]);
+                endpoint(option_type, Ipv6Addr::from(octets).into(), &data[16..])
+            }
+            _ => SdOption::Unknown {
+                option_type,
+                data: data.to_vec(),
+            },
+        };
+        Ok((option, &input[end..]))
+    }
+}
+
+/// Endpoint option from the address and the reserved, protocol and port bytes
+fn endpoint(option_type: u8, ip: IpAddr, rest: &[u8]) -> SdOption {
+    let kind = match option_type >> 4 {
+        0x0 => EndpointKind::Unicast,
+        0x1 => EndpointKind::Multicast,
+        _ => EndpointKind::ServiceDiscovery,
+    };
+    SdOption::Endpoint {
+        kind,
+        address: SocketAddr::new(ip, BigEndian::read_u16(&rest[2..4])),
+        protocol: rest[1].into(),
+    }
+}
+
+/// The length prefixed strings of a configuration option, terminated by 0
+fn configuration_strings(mut data: &[u8]) -> Result<Vec<String>, Error> {
+    let mut strings = Vec::new();
+    while let Some((&length, rest)) = data.split_first() {
+        if length == 0 {
+            break;
+        }
+        check_length("SOME/IP-SD configuration string", rest, length as usize)?;
+        let (string, rest) = rest.split_at(length as usize);
+        strings.push(String::from_utf8_lossy(string).into_owned());
+        data = rest;
+    }
+    Ok(strings)
+}
+
+/// A service discovery message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdMessage {
+    /// Set until the session id of the sender wraps around after a reboot
+    pub reboot: bool,
+    /// Whether the sender supports receiving unicast messages
+    pub unicast: bool,
+    pub entries: Vec<SdEntry>,
+    pub options: Vec<SdOption>,
+}
+
+impl SdMessage {
+    /// Parse the payload of a service discovery message
+    pub fn parse(payload: &[u8]) -> Result<SdMessage, Error> {
+        check_length("SOME/IP-SD header", payload, SD_HEADER_LENGTH)?;
+        let flags = payload[0];
+        let entries_length = BigEndian::read_u32(&payload[4..8]) as usize;
+        let entries_end = SD_HEADER_LENGTH + entries_length;
+        check_length("SOME/IP-SD entries", payload, entries_end + 4)?;
+        if !entries_length.is_multiple_of(ENTRY_LENGTH) {
+            return Err(Error::Invalid {
+                what: "SOME/IP-SD entries",
+                reason: format!(
+                    "length {} is no multiple of {}",
+                    entries_length, ENTRY_LENGTH
+                ),
+            });
+        }
+        let entries = payload[SD_HEADER_LENGTH..entries_end]
+            .chunks_exact(ENTRY_LENGTH)
+            .map(SdEntry::parse)
+            .collect();
+        let options_length = BigEndian::read_u32(&payload[entries_end..entries_end + 4]) as usize;
+        let options_start = entries_end + 4;
+        check_length(
+            "SOME/IP-SD options",
+            payload,
+            options_start + options_length,
+        )?;
+        let mut rest = &payload[options_start..options_start + options_length];
+        let mut options = Vec::new();
+        while !rest.is_empty() {
+            let (option, after_option) = SdOption::parse(rest)?;
+            options.push(option);
+            rest = after_option;
+        }
+        Ok(SdMessage {
+            reboot: flags & REBOOT_FLAG != 0,
+            unicast: flags & UNICAST_FLAG != 0,
+            entries,
+            options,
+        })
+    }
+
+    /// Parse a SOME/IP message, `Ok(None)` if it is no service discovery message
+    pub fn from_someip(message: &SomeIpMessage) -> Result<Option<SdMessage>, Error> {
+        if message.header.is_service_discovery() {
+            SdMessage::parse(message.payload).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Decode the service discovery messages of a SOME/IP network trace
+///
+/// Other SOME/IP messages of the trace are skipped. `Ok(None)` if the message
+/// is no SOME/IP trace.
+pub fn decode(message: &Message) -> Result<Option<Vec<SdMessage>>, Error> {
+    let Some(messages) = someip::decode(message)? else {
+        return Ok(None);
+    };
+    let mut sd_messages = Vec::new();
+    for message in &messages {
+        if let Some(sd_message) = SdMessage::from_someip(message)? {
+            sd_messages.push(sd_message);
+        }
+    }
+    Ok(Some(sd_messages))
+}
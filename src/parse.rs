@@ -16,15 +16,16 @@
 use crate::{
     dlt::{
         calculate_all_headers_length, float_width_to_type_length, ApplicationTraceType, Argument,
-        ControlType, DltTimeStamp, Endianness, ExtendedHeader, FixedPoint, FixedPointValue,
-        FloatWidth, LogLevel, Message, MessageType, NetworkTraceType, PayloadContent,
-        StandardHeader, StorageHeader, TypeInfo, TypeInfoKind, TypeLength, Value, BIG_ENDIAN_FLAG,
-        STORAGE_HEADER_LENGTH, VERBOSE_FLAG, WITH_ECU_ID_FLAG, WITH_EXTENDED_HEADER_FLAG,
-        WITH_SESSION_ID_FLAG, WITH_TIMESTAMP_FLAG,
+        Arguments, BorrowedArgument, ControlType, DltId, DltTimeStamp, Endianness, ExtendedHeader,
+        FixedPoint, FixedPointValue, FloatWidth, LogLevel, Message, MessageType, NetworkTraceType,
+        PayloadContent, StandardHeader, StorageHeader, TypeInfo, TypeInfoKind, TypeLength, Value,
+        BIG_ENDIAN_FLAG, STORAGE_HEADER_LENGTH, VERBOSE_FLAG, WITH_ECU_ID_FLAG,
+        WITH_EXTENDED_HEADER_FLAG, WITH_SESSION_ID_FLAG, WITH_TIMESTAMP_FLAG,
     },
     filtering,
 };
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use bytes::Bytes;
 use nom::{
     bytes::streaming::{tag, take, take_while_m_n},
     combinator::map,
@@ -38,14 +39,16 @@ use nom::{
     Err::Error,
     IResult,
 };
-use std::convert::TryFrom;
+use std::{borrow::Cow, convert::TryFrom};
 use thiserror::Error;
 
 /// DLT pattern at the start of a storage header
 pub const DLT_PATTERN: &[u8] = &[0x44, 0x4C, 0x54, 0x01];
 
-pub(crate) fn parse_ecu_id(input: &[u8]) -> IResult<&[u8], &str, DltParseError> {
-    dlt_zero_terminated_string_intern(input, 4)
+pub(crate) fn parse_ecu_id(input: &[u8]) -> IResult<&[u8], DltId, DltParseError> {
+    map(take(4usize), |bytes: &[u8]| {
+        DltId::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    })(input)
 }
 
 impl ParseError<&[u8]> for DltParseError {
@@ -73,6 +76,10 @@ pub enum DltParseError {
     IncompleteParse {
         needed: Option<std::num::NonZeroUsize>,
     },
+    #[error("parsing was cancelled")]
+    Cancelled,
+    #[error("message of {length} bytes at offset {offset} exceeds the maximal length")]
+    MessageTooLarge { length: usize, offset: u64 },
 }
 
 impl From<std::io::Error> for DltParseError {
@@ -147,7 +154,7 @@ pub(crate) fn dlt_storage_header(
             let (input, (_, _, seconds, microseconds)) =
                 tuple((tag("DLT"), tag(&[0x01]), le_u32, le_u32))(rest)?;
 
-            let (after_string, ecu_id) = dlt_zero_terminated_string_intern(input, 4)?;
+            let (after_string, ecu_id) = parse_ecu_id(input)?;
             Ok((
                 after_string,
                 Some((
@@ -156,7 +163,7 @@ pub(crate) fn dlt_storage_header(
                             seconds,
                             microseconds,
                         },
-                        ecu_id: ecu_id.to_string(),
+                        ecu_id,
                     },
                     consumed,
                 )),
@@ -169,13 +176,13 @@ pub(crate) fn dlt_storage_header(
     }
 }
 
-fn maybe_parse_ecu_id(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<&str>, DltParseError> {
-    fn parse_ecu_id_to_option(input: &[u8]) -> IResult<&[u8], Option<&str>, DltParseError> {
+fn maybe_parse_ecu_id(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<DltId>, DltParseError> {
+    fn parse_ecu_id_to_option(input: &[u8]) -> IResult<&[u8], Option<DltId>, DltParseError> {
         let (rest, ecu_id) = parse_ecu_id(input)?;
         Ok((rest, Some(ecu_id)))
     }
     #[allow(clippy::unnecessary_wraps)]
-    fn parse_nothing_str(input: &[u8]) -> IResult<&[u8], Option<&str>, DltParseError> {
+    fn parse_nothing_str(input: &[u8]) -> IResult<&[u8], Option<DltId>, DltParseError> {
         Ok((input, None))
     }
     if a {
@@ -225,6 +232,16 @@ fn nom_to_dlt_parse_error(ne: nom::Err<DltParseError>, desc: &str) -> DltParseEr
     }
 }
 
+/// Parse the overall length of a message (without storage header)
+///
+/// Only the first 4 bytes of the standard header are needed, which allows
+/// reading a message of unknown size from a stream.
+pub fn parse_length(input: &[u8]) -> IResult<&[u8], usize, DltParseError> {
+    let (input, (_header_type, _message_counter, overall_length)) =
+        tuple((be_u8, be_u8, be_u16))(input)?;
+    Ok((input, overall_length as usize))
+}
+
 /// The standard header is part of every DLT message
 /// all big endian format [PRS_Dlt_00091]
 pub(crate) fn dlt_standard_header(input: &[u8]) -> IResult<&[u8], StandardHeader, DltParseError> {
@@ -261,7 +278,7 @@ pub(crate) fn dlt_standard_header(input: &[u8]) -> IResult<&[u8], StandardHeader
             message_counter,
             has_extended_header,
             payload_length,
-            ecu_id.map(|r| r.to_string()),
+            ecu_id,
             session_id,
             timestamp,
         ),
@@ -299,8 +316,8 @@ pub(crate) fn dlt_extended_header(input: &[u8]) -> IResult<&[u8], ExtendedHeader
                     verbose,
                     argument_count,
                     message_type,
-                    application_id: app_id.to_string(),
-                    context_id: context_id.to_string(),
+                    application_id: app_id,
+                    context_id,
                 },
             ))
         }
@@ -319,8 +336,12 @@ fn is_not_null(chr: u8) -> bool {
 /// Extracts the string in a byte sequence up to the `\0` termination character
 ///
 /// In various places within the DLT message, there can be strings that are
-/// terminated with a `\0`.
-pub fn dlt_zero_terminated_string(s: &[u8], size: usize) -> Result<(&[u8], &str), DltParseError> {
+/// terminated with a `\0`. The string is borrowed from `s`, invalid UTF-8
+/// content is cut off at the first invalid byte.
+pub fn dlt_zero_terminated_string(
+    s: &[u8],
+    size: usize,
+) -> Result<(&[u8], Cow<'_, str>), DltParseError> {
     dlt_zero_terminated_string_intern(s, size).map_err(DltParseError::from)
 }
 
@@ -328,7 +349,10 @@ pub fn dlt_zero_terminated_string(s: &[u8], size: usize) -> Result<(&[u8], &str)
 ///
 /// In various places within the DLT message, there can be strings that are
 /// terminated with a `\0`.
-fn dlt_zero_terminated_string_intern(s: &[u8], size: usize) -> IResult<&[u8], &str, DltParseError> {
+fn dlt_zero_terminated_string_intern(
+    s: &[u8],
+    size: usize,
+) -> IResult<&[u8], Cow<'_, str>, DltParseError> {
     let (rest_with_null, content_without_null) = take_while_m_n(0, size, is_not_null)(s)?;
     let res_str = match nom::lib::std::str::from_utf8(content_without_null) {
         Ok(content) => content,
@@ -339,13 +363,15 @@ fn dlt_zero_terminated_string_intern(s: &[u8], size: usize) -> IResult<&[u8], &s
     };
     let missing = size - content_without_null.len();
     let (rest, _) = take(missing)(rest_with_null)?;
-    Ok((rest, res_str))
+    Ok((rest, Cow::Borrowed(res_str)))
 }
 
-fn dlt_variable_name<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], String, DltParseError> {
+fn dlt_variable_name<'a, T: NomByteOrder, S: From<Cow<'a, str>>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], S, DltParseError> {
     let (i, size) = T::parse_u16(input)?;
     let (i2, name) = dlt_zero_terminated_string_intern(i, size as usize)?;
-    Ok((i2, name.to_string()))
+    Ok((i2, name.into()))
 }
 
 pub(crate) trait NomByteOrder: Clone + Copy + Eq + Ord + PartialEq + PartialOrd {
@@ -396,11 +422,11 @@ impl NomByteOrder for BigEndian {
 }
 
 #[allow(clippy::type_complexity)]
-fn dlt_variable_name_and_unit<T: NomByteOrder>(
+fn dlt_variable_name_and_unit<'a, T: NomByteOrder, S: From<Cow<'a, str>>>(
     type_info: &TypeInfo,
-) -> fn(&[u8]) -> IResult<&[u8], (Option<String>, Option<String>), DltParseError> {
+) -> fn(&'a [u8]) -> IResult<&'a [u8], (Option<S>, Option<S>), DltParseError> {
     if type_info.has_variable_info {
-        |input: &[u8]| -> IResult<&[u8], (Option<String>, Option<String>), DltParseError> {
+        |input: &'a [u8]| -> IResult<&'a [u8], (Option<S>, Option<S>), DltParseError> {
             let (i2, name_size_unit_size) = tuple((T::parse_u16, T::parse_u16))(input)?;
             dbg_parsed("namesize, unitsize", input, i2, &name_size_unit_size);
             let (i3, name) = dlt_zero_terminated_string_intern(i2, name_size_unit_size.0 as usize)?;
@@ -408,7 +434,7 @@ fn dlt_variable_name_and_unit<T: NomByteOrder>(
             let (rest, unit) =
                 dlt_zero_terminated_string_intern(i3, name_size_unit_size.1 as usize)?;
             dbg_parsed("unit", i3, rest, &unit);
-            Ok((rest, (Some(name.to_string()), Some(unit.to_string()))))
+            Ok((rest, (Some(name.into()), Some(unit.into()))))
         }
     } else {
         |input| Ok((input, (None, None)))
@@ -528,11 +554,17 @@ pub(crate) fn dlt_fixed_point<T: NomByteOrder>(
 pub(crate) fn dlt_argument<T: NomByteOrder>(
     input: &[u8],
 ) -> IResult<&[u8], Argument, DltParseError> {
+    dlt_argument_intern::<T, String>(input)
+}
+
+fn dlt_argument_intern<'a, T: NomByteOrder, S: From<Cow<'a, str>> + std::fmt::Debug>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Argument<S>, DltParseError> {
     let (i, type_info) = dlt_type_info::<T>(input)?;
     dbg_parsed("type info", input, i, &type_info);
     match type_info.kind {
         TypeInfoKind::Signed(width) => {
-            let (before_val, name_unit) = dlt_variable_name_and_unit::<T>(&type_info)(i)?;
+            let (before_val, name_unit) = dlt_variable_name_and_unit::<T, S>(&type_info)(i)?;
             dbg_parsed("name and unit", i, before_val, &name_unit);
             let (rest, value) = dlt_sint::<T>(width)(before_val)?;
             dbg_parsed("sint", before_val, rest, &value);
@@ -548,7 +580,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
             ))
         }
         TypeInfoKind::SignedFixedPoint(width) => {
-            let (before_val, name_unit) = dlt_variable_name_and_unit::<T>(&type_info)(i)?;
+            let (before_val, name_unit) = dlt_variable_name_and_unit::<T, S>(&type_info)(i)?;
             dbg_parsed("name and unit", i, before_val, &name_unit);
             let (r, fp) = dlt_fixed_point::<T>(before_val, width)?;
             let (after_fixed_point, fixed_point) = (r, Some(fp));
@@ -567,7 +599,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
             ))
         }
         TypeInfoKind::Unsigned(width) => {
-            let (before_val, (name, unit)) = dlt_variable_name_and_unit::<T>(&type_info)(i)?;
+            let (before_val, (name, unit)) = dlt_variable_name_and_unit::<T, S>(&type_info)(i)?;
             let (rest, value) = dlt_uint::<T>(width)(before_val)?;
             dbg_parsed("unsigned", before_val, rest, &value);
             Ok((
@@ -582,7 +614,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
             ))
         }
         TypeInfoKind::UnsignedFixedPoint(width) => {
-            let (before_val, (name, unit)) = dlt_variable_name_and_unit::<T>(&type_info)(i)?;
+            let (before_val, (name, unit)) = dlt_variable_name_and_unit::<T, S>(&type_info)(i)?;
             let (after_fixed_point, fixed_point) = {
                 let (r, fp) = dlt_fixed_point::<T>(before_val, width)?;
                 (r, Some(fp))
@@ -602,7 +634,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
         }
         TypeInfoKind::Float(width) => {
             let (rest, ((name, unit), value)) = tuple((
-                dlt_variable_name_and_unit::<T>(&type_info),
+                dlt_variable_name_and_unit::<T, S>(&type_info),
                 dlt_fint::<T>(width),
             ))(i)?;
             Ok((
@@ -619,7 +651,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
         TypeInfoKind::Raw => {
             let (i2, raw_byte_cnt) = T::parse_u16(i)?;
             let (i3, name) = if type_info.has_variable_info {
-                map(dlt_variable_name::<T>, Some)(i2)?
+                map(dlt_variable_name::<T, S>, Some)(i2)?
             } else {
                 (i2, None)
             };
@@ -637,7 +669,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
         }
         TypeInfoKind::Bool => {
             let (after_var_name, name) = if type_info.has_variable_info {
-                map(dlt_variable_name::<T>, Some)(i)?
+                map(dlt_variable_name::<T, S>, Some)(i)?
             } else {
                 (i, None)
             };
@@ -658,7 +690,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
         TypeInfoKind::StringType => {
             let (i2, size) = T::parse_u16(i)?;
             let (i3, name) = if type_info.has_variable_info {
-                map(dlt_variable_name::<T>, Some)(i2)?
+                map(dlt_variable_name::<T, S>, Some)(i2)?
             } else {
                 (i2, None)
             };
@@ -670,7 +702,7 @@ pub(crate) fn dlt_argument<T: NomByteOrder>(
                     name,
                     unit: None,
                     fixed_point: None,
-                    value: Value::StringVal(value.to_string()),
+                    value: Value::StringVal(value.into_owned()),
                     type_info,
                 },
             ))
@@ -683,24 +715,70 @@ struct DltArgumentParser {
     current_index: Option<usize>,
 }
 
-fn dlt_payload<T: NomByteOrder>(
-    input: &[u8],
+/// Raw data of a payload, shared with `source` if the data is part of it
+fn payload_bytes(source: Option<&Bytes>, data: &[u8]) -> Bytes {
+    match source {
+        Some(source) => source.slice_ref(data),
+        None => Bytes::copy_from_slice(data),
+    }
+}
+
+/// Parse `arg_cnt` arguments, into the allocation of `scratch` if given
+///
+/// `scratch` keeps its allocation if the arguments can't be parsed.
+fn dlt_arguments<'a, T: NomByteOrder>(
+    mut input: &'a [u8],
+    arg_cnt: u8,
+    scratch: Option<&mut Arguments>,
+) -> IResult<&'a [u8], Arguments, DltParseError> {
+    let mut owned;
+    let arguments = match scratch {
+        Some(scratch) => {
+            scratch.clear();
+            scratch.reserve(arg_cnt as usize);
+            scratch
+        }
+        None => {
+            owned = Arguments::with_capacity(arg_cnt as usize);
+            &mut owned
+        }
+    };
+    for _ in 0..arg_cnt {
+        let (rest, argument) = dlt_argument::<T>(input)?;
+        arguments.push(argument);
+        input = rest;
+    }
+    Ok((input, std::mem::take(arguments)))
+}
+
+fn dlt_payload<'a, T: NomByteOrder>(
+    input: &'a [u8],
+    source: Option<&Bytes>,
+    mut scratch: Option<&mut Arguments>,
     verbose: bool,
     payload_length: u16,
     arg_cnt: u8,
     msg_type: Option<MessageType>,
-) -> IResult<&[u8], PayloadContent, DltParseError> {
+) -> IResult<&'a [u8], PayloadContent, DltParseError> {
     if verbose {
-        match count(dlt_argument::<T>, arg_cnt as usize)(input) {
-            Ok((rest, arguments)) => {
-                if let Some(MessageType::NetworkTrace(_)) = msg_type {
+        match dlt_arguments::<T>(input, arg_cnt, scratch.as_deref_mut()) {
+            Ok((rest, mut arguments)) => {
+                // segmented network traces (NWST/NWCH/NWEN) also carry
+                // strings and numbers, they are kept as verbose arguments
+                let all_raw = arguments
+                    .iter()
+                    .all(|argument| matches!(argument.value, Value::Raw(_)));
+                if matches!(msg_type, Some(MessageType::NetworkTrace(_))) && all_raw {
                     let slices = arguments
-                        .iter()
-                        .filter_map(|i| match &i.value {
-                            Value::Raw(bytes) => Some(bytes.clone()),
+                        .drain(..)
+                        .filter_map(|argument| match argument.value {
+                            Value::Raw(bytes) => Some(Bytes::from(bytes)),
                             _ => None,
                         })
                         .collect();
+                    if let Some(scratch) = scratch {
+                        *scratch = arguments;
+                    }
                     Ok((rest, PayloadContent::NetworkTrace(slices)))
                 } else {
                     Ok((rest, PayloadContent::Verbose(arguments)))
@@ -723,7 +801,7 @@ fn dlt_payload<T: NomByteOrder>(
                 rest,
                 PayloadContent::ControlMsg(
                     ControlType::from_value(control_msg_id),
-                    payload.to_vec(),
+                    payload_bytes(source, payload),
                 ),
             )),
             Err(e) => Err(e),
@@ -738,13 +816,70 @@ fn dlt_payload<T: NomByteOrder>(
         match tuple((T::parse_u32, take(payload_length - 4)))(input) {
             Ok((rest, (message_id, payload))) => Ok((
                 rest,
-                PayloadContent::NonVerbose(message_id, payload.to_vec()),
+                PayloadContent::NonVerbose(message_id, payload_bytes(source, payload)),
             )),
             Err(e) => Err(e),
         }
     }
 }
 
+/// Parse the payload of a message whose headers are already parsed
+pub fn dlt_payload_content(
+    header: &StandardHeader,
+    extended_header: Option<&ExtendedHeader>,
+    payload: &[u8],
+) -> Result<PayloadContent, DltParseError> {
+    let (verbose, arg_count, msg_type) = match extended_header {
+        Some(h) => (h.verbose, h.argument_count, Some(h.message_type.clone())),
+        None => (false, 0, None),
+    };
+    let payload_length = payload.len() as u16;
+    let (_, content) = if header.endianness == Endianness::Big {
+        dlt_payload::<BigEndian>(
+            payload,
+            None,
+            None,
+            verbose,
+            payload_length,
+            arg_count,
+            msg_type,
+        )?
+    } else {
+        dlt_payload::<LittleEndian>(
+            payload,
+            None,
+            None,
+            verbose,
+            payload_length,
+            arg_count,
+            msg_type,
+        )?
+    };
+    Ok(content)
+}
+
+/// Parse the arguments of a payload whose headers are already parsed
+///
+/// Unlike `dlt_payload_content` and `dlt_message`, which copy names and units
+/// into the owned arguments of the message, the names and units are borrowed
+/// from `payload`. Non-verbose payloads have no arguments.
+pub fn dlt_borrowed_arguments<'a>(
+    header: &StandardHeader,
+    extended_header: Option<&ExtendedHeader>,
+    payload: &'a [u8],
+) -> Result<Vec<BorrowedArgument<'a>>, DltParseError> {
+    let arg_count = match extended_header {
+        Some(h) if h.verbose => h.argument_count as usize,
+        _ => return Ok(Vec::new()),
+    };
+    let (_, arguments) = if header.endianness == Endianness::Big {
+        count(dlt_argument_intern::<BigEndian, Cow<str>>, arg_count)(payload)?
+    } else {
+        count(dlt_argument_intern::<LittleEndian, Cow<str>>, arg_count)(payload)?
+    };
+    Ok(arguments)
+}
+
 #[inline]
 fn dbg_parsed<T: std::fmt::Debug>(_name: &str, _before: &[u8], _after: &[u8], _value: &T) {
     // #[cfg(feature = "debug_parser")]
@@ -768,7 +903,12 @@ fn dbg_parsed<T: std::fmt::Debug>(_name: &str, _before: &[u8], _after: &[u8], _v
 
 /// Used when producing messages in a stream, indicates if messages
 /// where filtered or could not be parsed
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
 pub enum ParsedMessage {
     /// Regular message, could be parsed
     Item(Message),
@@ -824,11 +964,219 @@ pub fn dlt_message<'a>(
     filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
     with_storage_header: bool,
 ) -> Result<(&'a [u8], ParsedMessage), DltParseError> {
-    dlt_message_intern(input, filter_config_opt, with_storage_header).map_err(DltParseError::from)
+    dlt_message_intern(input, None, None, filter_config_opt, with_storage_header)
+        .map_err(DltParseError::from)
+}
+
+/// Parse a DLT-message from a shared buffer, like `dlt_message`
+///
+/// The raw data of non-verbose and control payloads refers to `input`
+/// instead of being copied, so it stays valid as long as the message is kept
+/// without copying the buffer. Returns the rest of the input after the
+/// message, which shares the buffer as well.
+///
+/// ```
+/// # use bytes::Bytes;
+/// # use dlt_core::{dlt::*, parse::{dlt_message_bytes, ParsedMessage}};
+/// let message = Message::new(
+///     MessageConfig {
+///         version: 1,
+///         counter: 0,
+///         endianness: Endianness::Big,
+///         ecu_id: None,
+///         session_id: None,
+///         timestamp: None,
+///         payload: PayloadContent::NonVerbose(1, Bytes::from_static(&[1, 2, 3])),
+///         extended_header_info: None,
+///     },
+///     None,
+/// );
+/// let input = Bytes::from(message.as_bytes());
+/// let (rest, parsed) = dlt_message_bytes(&input, None, false).expect("valid message");
+/// assert!(rest.is_empty());
+/// match parsed {
+///     ParsedMessage::Item(Message { payload: PayloadContent::NonVerbose(_, data), .. }) => {
+///         assert_eq!(data.as_ptr(), input[input.len() - 3..].as_ptr())
+///     }
+///     _ => panic!("expected a non-verbose message"),
+/// }
+/// ```
+pub fn dlt_message_bytes(
+    input: &Bytes,
+    filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
+    with_storage_header: bool,
+) -> Result<(Bytes, ParsedMessage), DltParseError> {
+    let (rest, message) = dlt_message_intern(
+        input,
+        Some(input),
+        None,
+        filter_config_opt,
+        with_storage_header,
+    )?;
+    Ok((input.slice_ref(rest), message))
+}
+
+/// The outcome of `dlt_message_into`, like `ParsedMessage` without the message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsedInto {
+    /// The message was parsed into the given message
+    Item,
+    /// message was filtered out due to filter conditions (Log-Level etc.)
+    FilteredOut(usize),
+    /// Parsed message was invalid, no parse error but content was not DLT-conform
+    Invalid,
+}
+
+/// Parse a DLT-message into an existing message, like `dlt_message`
+///
+/// The allocation of the arguments of `message` is reused for the parsed
+/// arguments, so pipelines that handle one message at a time can parse all
+/// messages into the same scratch message. The content of `message` is only
+/// valid if `ParsedInto::Item` is returned. Filtered, invalid and unparsable
+/// messages leave the allocation in `message`, only a parsed message with
+/// another than a verbose payload releases it.
+///
+/// ```
+/// # use dlt_core::{dlt::*, parse::{dlt_message_into, ParsedInto}};
+/// # let input = Message::new(
+/// #     MessageConfig {
+/// #         version: 1,
+/// #         counter: 0,
+/// #         endianness: Endianness::Big,
+/// #         ecu_id: None,
+/// #         session_id: None,
+/// #         timestamp: None,
+/// #         payload: PayloadContent::NonVerbose(1, vec![1, 2, 3].into()),
+/// #         extended_header_info: None,
+/// #     },
+/// #     None,
+/// # )
+/// # .as_bytes()
+/// # .repeat(3);
+/// let mut message = Message::default();
+/// let mut rest = &input[..];
+/// while !rest.is_empty() {
+///     let (after_message, parsed) = dlt_message_into(rest, None, false, &mut message)?;
+///     if parsed == ParsedInto::Item {
+///         assert!(!message.header.has_extended_header);
+///     }
+///     rest = after_message;
+/// }
+/// # Ok::<(), dlt_core::parse::DltParseError>(())
+/// ```
+pub fn dlt_message_into<'a>(
+    input: &'a [u8],
+    filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
+    with_storage_header: bool,
+    message: &mut Message,
+) -> Result<(&'a [u8], ParsedInto), DltParseError> {
+    let mut scratch = match &mut message.payload {
+        PayloadContent::Verbose(arguments) => std::mem::take(arguments),
+        _ => Arguments::new(),
+    };
+    let result = dlt_message_intern(
+        input,
+        None,
+        Some(&mut scratch),
+        filter_config_opt,
+        with_storage_header,
+    );
+    let (rest, parsed) = match result {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            message.payload = PayloadContent::Verbose(scratch);
+            return Err(e.into());
+        }
+    };
+    let parsed = match parsed {
+        ParsedMessage::Item(parsed) => {
+            message.storage_header = parsed.storage_header;
+            message.header = parsed.header;
+            message.extended_header = parsed.extended_header;
+            // verbose arguments were parsed into the allocation of `scratch`
+            message.payload = parsed.payload;
+            ParsedInto::Item
+        }
+        ParsedMessage::FilteredOut(length) => {
+            message.payload = PayloadContent::Verbose(scratch);
+            ParsedInto::FilteredOut(length)
+        }
+        ParsedMessage::Invalid => {
+            message.payload = PayloadContent::Verbose(scratch);
+            ParsedInto::Invalid
+        }
+    };
+    Ok((rest, parsed))
+}
+
+/// The headers of a DLT-message and the verdict of a filter on them
+///
+/// Returned by `dlt_message_headers`, the payload is not parsed.
+#[derive(Debug, PartialEq)]
+pub struct MessageHeaders<'a> {
+    pub storage_header: Option<StorageHeader>,
+    pub header: StandardHeader,
+    pub extended_header: Option<ExtendedHeader>,
+    pub payload: &'a [u8],
+    pub verdict: filtering::FilterVerdict,
+}
+
+/// Parse the headers of a DLT-message and check them against the filter
+///
+/// The payload is skipped without being parsed, which is a lot faster when only
+/// the headers are needed, e.g. to count messages per log level. Without filter
+/// the verdict is always `FilterVerdict::Matches`. For filters with payload
+/// conditions, messages whose headers pass get `FilterVerdict::NeedsPayload`,
+/// their payload can be parsed with `dlt_payload_content` and checked with
+/// `ProcessedDltFilterConfig::matches_payload`.
+pub fn dlt_message_headers<'a>(
+    input: &'a [u8],
+    filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
+    with_storage_header: bool,
+) -> Result<(&'a [u8], MessageHeaders<'a>), DltParseError> {
+    let (after_storage_header, storage_header) = if with_storage_header {
+        let (rest, storage_header) = dlt_storage_header(input)?;
+        (rest, storage_header.map(|(header, _)| header))
+    } else {
+        (input, None)
+    };
+    let (after_standard_header, header) = dlt_standard_header(after_storage_header)?;
+    let payload_length = validated_payload_length(&header, after_storage_header.len())?;
+    let (after_headers, extended_header) = if header.has_extended_header {
+        let (rest, extended_header) = dlt_extended_header(after_standard_header)?;
+        (rest, Some(extended_header))
+    } else {
+        (after_standard_header, None)
+    };
+    let (rest, payload) = take::<u16, &[u8], DltParseError>(payload_length)(after_headers)?;
+    let verdict = match filter_config_opt {
+        Some(filter_config) => filter_config.headers_verdict(
+            storage_header.as_ref(),
+            &header,
+            extended_header.as_ref(),
+        ),
+        None => filtering::FilterVerdict::Matches,
+    };
+    Ok((
+        rest,
+        MessageHeaders {
+            storage_header,
+            header,
+            extended_header,
+            payload,
+            verdict,
+        },
+    ))
 }
 
 fn dlt_message_intern<'a>(
     input: &'a [u8],
+    source: Option<&Bytes>,
+    mut scratch: Option<&mut Arguments>,
     filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
     with_storage_header: bool,
 ) -> IResult<&'a [u8], ParsedMessage, DltParseError> {
@@ -887,9 +1235,10 @@ fn dlt_message_intern<'a>(
         }
     };
     if filtered_out(
+        storage_header_shifted.as_ref().map(|(h, _)| h),
+        &header,
         extended_header.as_ref(),
         filter_config_opt,
-        header.ecu_id.as_ref(),
     ) {
         let (after_message, _) = take(payload_length)(after_headers)?;
         return Ok((
@@ -898,11 +1247,35 @@ fn dlt_message_intern<'a>(
         ));
     }
     let (i, payload) = if header.endianness == Endianness::Big {
-        dlt_payload::<BigEndian>(after_headers, verbose, payload_length, arg_count, msg_type)?
+        dlt_payload::<BigEndian>(
+            after_headers,
+            source,
+            scratch.as_deref_mut(),
+            verbose,
+            payload_length,
+            arg_count,
+            msg_type,
+        )?
     } else {
-        dlt_payload::<LittleEndian>(after_headers, verbose, payload_length, arg_count, msg_type)?
+        dlt_payload::<LittleEndian>(
+            after_headers,
+            source,
+            scratch.as_deref_mut(),
+            verbose,
+            payload_length,
+            arg_count,
+            msg_type,
+        )?
     };
     dbg_parsed("payload", after_headers, i, &payload);
+    if let Some(filter_config) = filter_config_opt {
+        if !filter_config.matches_payload(&payload) {
+            if let (Some(scratch), PayloadContent::Verbose(arguments)) = (scratch, payload) {
+                *scratch = arguments;
+            }
+            return Ok((i, ParsedMessage::FilteredOut(payload_length as usize)));
+        }
+    }
     Ok((
         i,
         ParsedMessage::Item(Message {
@@ -915,55 +1288,17 @@ fn dlt_message_intern<'a>(
 }
 
 fn filtered_out(
+    storage_header: Option<&StorageHeader>,
+    header: &StandardHeader,
     extended_header: Option<&ExtendedHeader>,
     filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
-    ecu_id: Option<&String>,
 ) -> bool {
-    if let Some(filter_config) = filter_config_opt {
-        if let Some(h) = &extended_header {
-            if let Some(min_filter_level) = filter_config.min_log_level {
-                if h.skip_with_level(min_filter_level) {
-                    // trace!("no need to parse further, skip payload (skipped level)");
-                    return true;
-                }
-            }
-            if let Some(only_these_components) = &filter_config.app_ids {
-                if !only_these_components.contains(&h.application_id) {
-                    // trace!("no need to parse further, skip payload (skipped app id)");
-                    return true;
-                }
-            }
-            if let Some(only_these_context_ids) = &filter_config.context_ids {
-                if !only_these_context_ids.contains(&h.context_id) {
-                    // trace!("no need to parse further, skip payload (skipped context id)");
-                    return true;
-                }
-            }
-            if let Some(only_these_ecu_ids) = &filter_config.ecu_ids {
-                if let Some(ecu_id) = ecu_id {
-                    if !only_these_ecu_ids.contains(ecu_id) {
-                        // trace!("no need to parse further, skip payload (skipped ecu id)");
-                        return true;
-                    }
-                }
-            }
-        } else {
-            // filter out some messages when we do not have an extended header
-            if let Some(app_id_set) = &filter_config.app_ids {
-                if filter_config.app_id_count > app_id_set.len() as i64 {
-                    // some app id was filtered, ignore this entry
-                    return true;
-                }
-            }
-            if let Some(context_id_set) = &filter_config.context_ids {
-                if filter_config.context_id_count > context_id_set.len() as i64 {
-                    // some context id was filtered, ignore this entry
-                    return true;
-                }
-            }
-        }
-    }
-    false
+    matches!(
+        filter_config_opt.map(|filter_config| {
+            filter_config.headers_verdict(storage_header, header, extended_header)
+        }),
+        Some(filtering::FilterVerdict::FilteredOut)
+    )
 }
 
 pub(crate) fn validated_payload_length(
@@ -1004,6 +1339,7 @@ pub(crate) fn skip_till_after_next_storage_header(
 
 /// Remove the storage header from the input if present
 pub fn skip_storage_header(input: &[u8]) -> Result<(&[u8], u64), DltParseError> {
+    #[allow(clippy::useless_conversion)]
     let (i, (_, _, _)): (&[u8], _) = tuple((tag("DLT"), tag(&[0x01]), take(12usize)))(input)
         .map_err(nom::Err::<DltParseError>::from)?;
     if input.len() - i.len() == STORAGE_HEADER_LENGTH as usize {
@@ -1024,6 +1360,7 @@ pub fn dlt_consume_msg(input: &[u8]) -> Result<(&[u8], Option<u64>), DltParseErr
     let (after_storage_header, skipped_bytes) = skip_storage_header(input)?;
     let (_, header) = dlt_standard_header(after_storage_header)?;
     let overall_length_without_storage_header = header.overall_length();
+    #[allow(clippy::useless_conversion)]
     let (after_message, _) = take(overall_length_without_storage_header)(after_storage_header)
         .map_err(nom::Err::<DltParseError>::from)?;
     let consumed = skipped_bytes + overall_length_without_storage_header as u64;
@@ -0,0 +1,375 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Extracting dlt messages from network captures
+//!
+//! `pcap` reads pcap and pcapng files (e.g. recorded with wireshark or
+//! tcpdump) and parses the dlt messages that were sent over UDP or TCP.
+//! As messages on the network have no storage header, the capture time
+//! of the packet is used for it.
+use crate::{
+    dlt::{DltTimeStamp, StorageHeader},
+    filtering::ProcessedDltFilterConfig,
+    parse::{dlt_message, DltParseError, ParsedMessage},
+    read::DltPushParser,
+};
+use pcap_parser::{
+    create_reader, pcapng::Block, traits::PcapReaderIterator, Linktype, PcapBlockOwned, PcapError,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Read,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+use thiserror::Error;
+
+/// Default port of dlt-daemon
+pub const DLT_PORT: u16 = 3490;
+
+const BUFFER_CAPACITY: usize = 65536;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Pcap error: {0}")]
+    Pcap(String),
+    #[error("Parse error: {0}")]
+    Parse(#[from] DltParseError),
+}
+
+impl<I: std::fmt::Debug> From<PcapError<I>> for Error {
+    fn from(e: PcapError<I>) -> Self {
+        Error::Pcap(format!("{:?}", e))
+    }
+}
+
+/// Reads the dlt messages of a pcap or pcapng capture
+///
+/// Messages are taken from UDP datagrams and TCP segments from or to one of
+/// the dlt ports (`DLT_PORT` by default). Each parsed message gets a storage
+/// header with the capture time and the ECU id of the message.
+pub struct PcapMessageReader<'a> {
+    reader: Box<dyn PcapReaderIterator + 'a>,
+    capture: Capture,
+    done: bool,
+}
+
+/// State of the capture that is read
+#[derive(Default)]
+struct Capture {
+    ports: Vec<u16>,
+    filter: Option<ProcessedDltFilterConfig>,
+    /// link type and timestamp resolution of the legacy pcap file
+    legacy: Interface,
+    /// interfaces of the current pcapng section
+    interfaces: Vec<Interface>,
+    /// bytes of TCP connections that don't form a complete message yet
+    streams: HashMap<Flow, DltPushParser>,
+    pending: VecDeque<ParsedMessage>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    linktype: Linktype,
+    /// units per second
+    resolution: u64,
+    offset: i64,
+}
+
+impl Default for Interface {
+    fn default() -> Self {
+        Interface {
+            linktype: Linktype::ETHERNET,
+            resolution: 1_000_000,
+            offset: 0,
+        }
+    }
+}
+
+/// Source and destination of a TCP connection
+type Flow = (IpAddr, u16, IpAddr, u16);
+
+enum Transport<'p> {
+    Udp(&'p [u8]),
+    Tcp(Flow, &'p [u8]),
+}
+
+impl<'a> PcapMessageReader<'a> {
+    /// Create a reader for a pcap or pcapng source, the format is detected
+    pub fn new<R: Read + 'a>(source: R) -> Result<Self, Error> {
+        Ok(PcapMessageReader {
+            reader: create_reader(BUFFER_CAPACITY, source)?,
+            capture: Capture {
+                ports: vec![DLT_PORT],
+                ..Capture::default()
+            },
+            done: false,
+        })
+    }
+
+    /// The UDP and TCP ports of the dlt traffic
+    pub fn set_ports(&mut self, ports: impl IntoIterator<Item = u16>) {
+        self.capture.ports = ports.into_iter().collect();
+    }
+
+    /// Parse messages with a filter, see `dlt_message`
+    pub fn set_filter(&mut self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.capture.filter = filter_config_opt;
+    }
+
+    /// The next message of the capture, `None` at its end
+    pub fn next_message(&mut self) -> Result<Option<ParsedMessage>, Error> {
+        loop {
+            if let Some(message) = self.capture.pending.pop_front() {
+                return Ok(Some(message));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.reader.next() {
+                Ok((offset, block)) => {
+                    self.capture.handle_block(block);
+                    self.reader.consume(offset);
+                }
+                Err(PcapError::Eof) => self.done = true,
+                Err(PcapError::Incomplete(_)) => self.reader.refill()?,
+                Err(PcapError::BufferTooSmall) => {
+                    let capacity = 2 * self.reader.data().len().max(BUFFER_CAPACITY);
+                    if !self.reader.grow(capacity) {
+                        return Err(PcapError::<&[u8]>::BufferTooSmall.into());
+                    }
+                    self.reader.refill()?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Capture {
+    fn handle_block(&mut self, block: PcapBlockOwned) {
+        match block {
+            PcapBlockOwned::LegacyHeader(header) => {
+                self.legacy = Interface {
+                    linktype: header.network,
+                    resolution: if header.is_nanosecond_precision() {
+                        1_000_000_000
+                    } else {
+                        1_000_000
+                    },
+                    offset: 0,
+                };
+            }
+            PcapBlockOwned::Legacy(packet) => {
+                let microseconds = packet.ts_usec as u64 * 1_000_000 / self.legacy.resolution;
+                let timestamp = DltTimeStamp {
+                    seconds: packet.ts_sec,
+                    microseconds: microseconds as u32,
+                };
+                self.handle_packet(self.legacy.linktype, timestamp, packet.data);
+            }
+            PcapBlockOwned::NG(Block::SectionHeader(_)) => self.interfaces.clear(),
+            PcapBlockOwned::NG(Block::InterfaceDescription(interface)) => {
+                self.interfaces.push(Interface {
+                    linktype: interface.linktype,
+                    resolution: interface.ts_resolution().unwrap_or(1_000_000),
+                    offset: interface.ts_offset(),
+                });
+            }
+            PcapBlockOwned::NG(Block::EnhancedPacket(packet)) => {
+                let interface = self
+                    .interfaces
+                    .get(packet.if_id as usize)
+                    .copied()
+                    .unwrap_or_default();
+                let (seconds, fraction) =
+                    packet.decode_ts(interface.offset as u64, interface.resolution);
+                let microseconds = fraction as u64 * 1_000_000 / interface.resolution;
+                let timestamp = DltTimeStamp {
+                    seconds,
+                    microseconds: microseconds as u32,
+                };
+                let data = &packet.data[..(packet.caplen as usize).min(packet.data.len())];
+                self.handle_packet(interface.linktype, timestamp, data);
+            }
+            PcapBlockOwned::NG(Block::SimplePacket(packet)) => {
+                let linktype = self
+                    .interfaces
+                    .first()
+                    .map(|interface| interface.linktype)
+                    .unwrap_or(Linktype::ETHERNET);
+                self.handle_packet(linktype, DltTimeStamp::from_ms(0), packet.data);
+            }
+            PcapBlockOwned::NG(_) => (),
+        }
+    }
+
+    fn handle_packet(&mut self, linktype: Linktype, timestamp: DltTimeStamp, data: &[u8]) {
+        let transport = match transport_payload(linktype, data, &self.ports) {
+            Some(transport) => transport,
+            None => return,
+        };
+        match transport {
+            Transport::Udp(payload) => {
+                let mut rest = payload;
+                while !rest.is_empty() {
+                    match dlt_message(rest, self.filter.as_ref(), false) {
+                        Ok((after_message, message)) => {
+                            self.push_message(message, &timestamp);
+                            rest = after_message;
+                        }
+                        Err(e) => {
+                            debug!("no dlt message in UDP payload: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Transport::Tcp(flow, payload) => {
+                let mut stream = self.streams.remove(&flow).unwrap_or_default();
+                stream.push(payload);
+                loop {
+                    match stream.next_message_slice() {
+                        Ok(Some(slice)) => match dlt_message(slice, self.filter.as_ref(), false) {
+                            Ok((_, message)) => self.push_message(message, &timestamp),
+                            Err(_) => self.pending.push_back(ParsedMessage::Invalid),
+                        },
+                        Ok(None) => break,
+                        Err(e) => {
+                            // not in sync with the message boundaries, the data was dropped
+                            debug!("dropped data of TCP connection {:?}: {}", flow, e);
+                            break;
+                        }
+                    }
+                }
+                if stream.buffered() > 0 {
+                    self.streams.insert(flow, stream);
+                }
+            }
+        }
+    }
+
+    fn push_message(&mut self, message: ParsedMessage, timestamp: &DltTimeStamp) {
+        let message = match message {
+            ParsedMessage::Item(mut message) => {
+                message.storage_header = Some(StorageHeader {
+                    timestamp: timestamp.clone(),
+                    ecu_id: message.header.ecu_id.unwrap_or_default(),
+                });
+                ParsedMessage::Item(message)
+            }
+            other => other,
+        };
+        self.pending.push_back(message);
+    }
+}
+
+impl Iterator for PcapMessageReader<'_> {
+    type Item = Result<ParsedMessage, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_message() {
+            Ok(Some(message)) => Some(Ok(message)),
+            Ok(None) => None,
+            Err(e) => {
+                self.done = true;
+                self.capture.pending.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The UDP or TCP payload of a captured frame if it is sent from or to one of the ports
+fn transport_payload<'p>(
+    linktype: Linktype,
+    frame: &'p [u8],
+    ports: &[u16],
+) -> Option<Transport<'p>> {
+    let (ethertype, packet) = match linktype {
+        Linktype::ETHERNET => {
+            let mut ethertype = u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]);
+            let mut offset = 14;
+            // VLAN tags
+            while ethertype == 0x8100 || ethertype == 0x88a8 {
+                ethertype = u16::from_be_bytes([*frame.get(offset + 2)?, *frame.get(offset + 3)?]);
+                offset += 4;
+            }
+            (Some(ethertype), frame.get(offset..)?)
+        }
+        Linktype::LINUX_SLL => (
+            Some(u16::from_be_bytes([*frame.get(14)?, *frame.get(15)?])),
+            frame.get(16..)?,
+        ),
+        Linktype::NULL => (None, frame.get(4..)?),
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => (None, frame),
+        _ => return None,
+    };
+    let version = packet.first()? >> 4;
+    let (protocol, source, destination, segment) = match (ethertype, version) {
+        (Some(0x0800), _) | (None, 4) => {
+            let header_length = (packet.first()? & 0x0f) as usize * 4;
+            let total_length = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*packet.get(6)?, *packet.get(7)?]);
+            if fragment & 0x3fff != 0 {
+                // fragmented datagrams are not reassembled
+                return None;
+            }
+            let address = |at: usize| -> Option<IpAddr> {
+                let bytes: [u8; 4] = packet.get(at..at + 4)?.try_into().ok()?;
+                Some(IpAddr::V4(Ipv4Addr::from(bytes)))
+            };
+            let end = total_length.min(packet.len());
+            (
+                *packet.get(9)?,
+                address(12)?,
+                address(16)?,
+                packet.get(header_length..end)?,
+            )
+        }
+        (Some(0x86dd), _) | (None, 6) => {
+            let payload_length = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]) as usize;
+            let address = |at: usize| -> Option<IpAddr> {
+                let bytes: [u8; 16] = packet.get(at..at + 16)?.try_into().ok()?;
+                Some(IpAddr::V6(Ipv6Addr::from(bytes)))
+            };
+            let end = (40 + payload_length).min(packet.len());
+            (
+                *packet.get(6)?,
+                address(8)?,
+                address(24)?,
+                packet.get(40..end)?,
+            )
+        }
+        _ => return None,
+    };
+    let source_port = u16::from_be_bytes([*segment.first()?, *segment.get(1)?]);
+    let destination_port = u16::from_be_bytes([*segment.get(2)?, *segment.get(3)?]);
+    if !ports.contains(&source_port) && !ports.contains(&destination_port) {
+        return None;
+    }
+    match protocol {
+        17 => {
+            let length = u16::from_be_bytes([*segment.get(4)?, *segment.get(5)?]) as usize;
+            Some(Transport::Udp(segment.get(8..length.min(segment.len()))?))
+        }
+        6 => {
+            let data_offset = (segment.get(12)? >> 4) as usize * 4;
+            Some(Transport::Tcp(
+                (source, source_port, destination, destination_port),
+                segment.get(data_offset..)?,
+            ))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Progress reporting and cancellation of long running operations
+//!
+//! Reading big traces or many FIBEX files can take a while. A frontend can
+//! observe the progress of such an operation and abort it with a
+//! `CancellationToken` from another thread.
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Shared flag to abort an operation, checked between messages or files
+///
+/// All clones of a token refer to the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the cancellation of all operations that use this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far an operation has come
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// bytes processed so far
+    pub processed: u64,
+    /// total number of bytes if known (e.g. the size of a file)
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// The processed part between 0.0 and 1.0 if the total is known
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.processed as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+}
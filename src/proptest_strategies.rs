@@ -19,8 +19,8 @@ use proptest::{collection::hash_set, prelude::*};
 use std::collections::HashSet;
 
 prop_compose! {
-    fn ecu_id_strategy()(id in "[a-zA-Z]{2,5}") /*"*/-> Option<String> {
-        if id.len() == 5 { None } else { Some(id) }
+    fn ecu_id_strategy()(id in "[a-zA-Z]{2,5}") /*"*/-> Option<DltId> {
+        if id.len() == 5 { None } else { Some(DltId::new(&id)) }
     }
 }
 fn unit_name_strategy() -> impl Strategy<Value = String> {
@@ -235,10 +235,10 @@ pub fn argument_strategy() -> impl Strategy<Value = Argument> {
 
 fn payload_strategy(count: usize) -> impl Strategy<Value = PayloadContent> {
     if count == 0 {
-        Just(PayloadContent::Verbose(vec![])).boxed()
+        Just(PayloadContent::Verbose(Arguments::new())).boxed()
     } else {
         prop::collection::vec(argument_strategy(), 0..count)
-            .prop_flat_map(|args| Just(PayloadContent::Verbose(args)))
+            .prop_flat_map(|args| Just(PayloadContent::Verbose(args.into_iter().collect())))
             .boxed()
     }
 }
@@ -246,12 +246,12 @@ fn payload_strategy(count: usize) -> impl Strategy<Value = PayloadContent> {
 fn non_verbose_payload_strategy() -> impl Strategy<Value = PayloadContent> {
     prop_oneof![
         (0..10u32, prop::collection::vec(any::<u8>(), 0..5))
-            .prop_map(|(a, b)| PayloadContent::NonVerbose(a, b)),
+            .prop_map(|(a, b)| PayloadContent::NonVerbose(a, b.into())),
         (
             any::<ControlType>(),
             prop::collection::vec(any::<u8>(), 0..6)
         )
-            .prop_map(|(a, b)| PayloadContent::ControlMsg(a, b))
+            .prop_map(|(a, b)| PayloadContent::ControlMsg(a, b.into()))
     ]
 }
 
@@ -272,8 +272,8 @@ pub fn unsigned_strategy() -> impl Strategy<Value = TypeInfoKind> {
     ]
 }
 
-pub fn argument_vector_strategy() -> impl Strategy<Value = Vec<Argument>> {
-    prop::collection::vec(argument_strategy(), 0..2)
+pub fn argument_vector_strategy() -> impl Strategy<Value = Arguments> {
+    prop::collection::vec(argument_strategy(), 0..2).prop_map(|args| args.into_iter().collect())
 }
 
 fn extheader_payload_endian_strategy(
@@ -290,13 +290,13 @@ fn extheader_payload_endian_strategy(
     })
 }
 
-pub fn vec_of_vec() -> impl Strategy<Value = Vec<Vec<u8>>> {
+pub fn vec_of_vec() -> impl Strategy<Value = Vec<bytes::Bytes>> {
     const N: u8 = 10;
 
     let length = 0..N;
     length
         .prop_flat_map(vec_from_length)
-        .prop_map(|i| i.into_iter().map(Vec::from_iter).collect())
+        .prop_map(|i| i.into_iter().map(bytes::Bytes::from_iter).collect())
 }
 
 fn vec_from_length(length: u8) -> impl Strategy<Value = Vec<HashSet<u8>>> {
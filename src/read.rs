@@ -0,0 +1,1023 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Reading dlt messages from a byte source
+//!
+//! `read` contains a reader that reads single dlt messages from any
+//! `std::io::Read` source (e.g. a file or a socket), so not the whole
+//! input has to be in memory for parsing.
+use crate::{
+    dlt::{Message, HEADER_MIN_LENGTH, STORAGE_HEADER_LENGTH},
+    filtering::ProcessedDltFilterConfig,
+    parse::{
+        dlt_message, dlt_message_into, forward_to_next_storage_header, parse_length, DltParseError,
+        ParsedInto, ParsedMessage, DLT_PATTERN,
+    },
+    progress::{CancellationToken, Progress},
+};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{sync_channel, Receiver},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Read the next message from the reader and parse it
+///
+/// Returns `None` when the end of the source is reached.
+pub fn read_message<S: Read>(
+    reader: &mut DltMessageReader<S>,
+    filter_config_opt: Option<&ProcessedDltFilterConfig>,
+) -> Result<Option<ParsedMessage>, DltParseError> {
+    Ok(read_message_envelope(reader, filter_config_opt)?.map(|envelope| envelope.message))
+}
+
+/// Parse the next message of a buffer, returning the number of consumed bytes
+/// instead of the rest of the buffer
+///
+/// The result doesn't borrow from the input, so a caller that manages its
+/// own buffer (e.g. a `BufRead`) can consume the bytes right away and keep
+/// the message. Returns `None` for an empty input.
+pub fn read_owned_message(
+    input: &[u8],
+    filter_config_opt: Option<&ProcessedDltFilterConfig>,
+    with_storage_header: bool,
+) -> Result<Option<(ParsedMessage, usize)>, DltParseError> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let (rest, message) = dlt_message(input, filter_config_opt, with_storage_header)?;
+    Ok(Some((message, input.len() - rest.len())))
+}
+
+/// Counters about the quality of the data that was read
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReaderStats {
+    /// bytes that were skipped, in recovery mode or as part of too large messages
+    pub bytes_skipped: u64,
+    /// messages that could not be parsed or were too large
+    pub invalid_messages: usize,
+    /// messages that were filtered out
+    pub filtered_messages: usize,
+}
+
+impl ReaderStats {
+    pub(crate) fn count_parsed(&mut self, result: &Result<ParsedMessage, DltParseError>) {
+        match result {
+            Ok(ParsedMessage::FilteredOut(_)) => self.filtered_messages += 1,
+            Ok(ParsedMessage::Invalid) | Err(_) => self.invalid_messages += 1,
+            Ok(ParsedMessage::Item(_)) => (),
+        }
+    }
+}
+
+/// A parsed message with the position of its bytes in the source
+#[derive(Debug, PartialEq)]
+pub struct MessageEnvelope {
+    /// byte offset of the message (of its storage header if present)
+    pub offset: u64,
+    /// number of bytes of the message including the storage header
+    pub length: usize,
+    pub message: ParsedMessage,
+}
+
+/// Like `read_message`, but the message is returned with its position in the source
+///
+/// The offset is counted from the start of the reader, see `DltMessageReader::bytes_processed`.
+pub fn read_message_envelope<S: Read>(
+    reader: &mut DltMessageReader<S>,
+    filter_config_opt: Option<&ProcessedDltFilterConfig>,
+) -> Result<Option<MessageEnvelope>, DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    let slice = reader.next_message_slice()?;
+    if slice.is_empty() {
+        return Ok(None);
+    }
+    let length = slice.len();
+    let result = dlt_message(slice, filter_config_opt, with_storage_header).map(|(_, m)| m);
+    reader.stats.count_parsed(&result);
+    let message = result?;
+    Ok(Some(MessageEnvelope {
+        offset: reader.bytes_processed() - length as u64,
+        length,
+        message,
+    }))
+}
+
+/// Shared and swappable filter of a `DltMessageReader`
+///
+/// All clones of a handle refer to the same filter, so the filter can be
+/// changed (e.g. by a UI) while messages are read, without recreating the
+/// reader and losing buffered bytes.
+#[derive(Clone, Debug, Default)]
+pub struct FilterHandle(Arc<RwLock<Option<Arc<ProcessedDltFilterConfig>>>>);
+
+impl FilterHandle {
+    pub fn new(filter_config_opt: Option<ProcessedDltFilterConfig>) -> Self {
+        FilterHandle(Arc::new(RwLock::new(filter_config_opt.map(Arc::new))))
+    }
+
+    /// Replace the filter, takes effect for the next message that is read
+    pub fn set(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        let mut filter = self.0.write().unwrap_or_else(|e| e.into_inner());
+        *filter = filter_config_opt.map(Arc::new);
+    }
+
+    /// The current filter
+    pub fn get(&self) -> Option<Arc<ProcessedDltFilterConfig>> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Wrap a source in a decoder if it is compressed with gzip or zstd
+///
+/// The compression is detected by the magic bytes at the start of the
+/// source, other sources are read as they are.
+#[cfg(feature = "compression")]
+pub fn decompress<'a, S: Read + Send + 'a>(
+    source: S,
+) -> Result<Box<dyn Read + Send + 'a>, DltParseError> {
+    const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+    const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+    let mut source = BufReader::new(source);
+    let start = source.fill_buf()?;
+    Ok(if start.starts_with(GZIP_MAGIC) {
+        Box::new(flate2::bufread::MultiGzDecoder::new(source))
+    } else if start.starts_with(ZSTD_MAGIC) {
+        Box::new(zstd::stream::read::Decoder::with_buffer(source)?)
+    } else {
+        Box::new(source)
+    })
+}
+
+#[cfg(feature = "compression")]
+impl<'a> DltMessageReader<Box<dyn Read + Send + 'a>> {
+    /// Create a reader for a source that may be compressed, see `decompress`
+    pub fn decompressing<S: Read + Send + 'a>(
+        source: S,
+        with_storage_header: bool,
+    ) -> Result<Self, DltParseError> {
+        Ok(DltMessageReader::new(
+            decompress(source)?,
+            with_storage_header,
+        ))
+    }
+}
+
+/// Reads the bytes of single dlt messages from a source
+pub struct DltMessageReader<S: Read> {
+    source: BufReader<S>,
+    with_storage_header: bool,
+    buffer: Vec<u8>,
+    filter: FilterHandle,
+    processed: u64,
+    progress: Option<ProgressHook>,
+    cancel: Option<CancellationToken>,
+    recover: bool,
+    skipped: Vec<Range<u64>>,
+    max_message_length: Option<usize>,
+    /// bytes at the start of the buffer that belong to the next message
+    buffered: usize,
+    stats: ReaderStats,
+}
+
+struct ProgressHook {
+    total: Option<u64>,
+    hook: Box<dyn FnMut(Progress) + Send>,
+}
+
+impl<S: Read> DltMessageReader<S> {
+    /// Create a reader for a source that contains messages with or without storage headers
+    pub fn new(source: S, with_storage_header: bool) -> Self {
+        DltMessageReader {
+            source: BufReader::new(source),
+            with_storage_header,
+            buffer: Vec::with_capacity(STORAGE_HEADER_LENGTH as usize + u16::MAX as usize),
+            filter: FilterHandle::default(),
+            processed: 0,
+            progress: None,
+            cancel: None,
+            recover: false,
+            skipped: vec![],
+            max_message_length: None,
+            buffered: 0,
+            stats: ReaderStats::default(),
+        }
+    }
+
+    /// Create a reader that expects storage headers if the source starts with one
+    ///
+    /// The first bytes are checked for the storage header pattern `DLT\x01`
+    /// without consuming them.
+    pub fn auto_detect(source: S) -> Result<Self, DltParseError> {
+        let mut reader = Self::new(source, false);
+        reader.with_storage_header = reader.source.fill_buf()?.starts_with(DLT_PATTERN);
+        Ok(reader)
+    }
+
+    pub fn with_storage_header(&self) -> bool {
+        self.with_storage_header
+    }
+
+    /// The source, e.g. to write to a socket that is read
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        self.source.get_mut()
+    }
+
+    /// A handle to the filter that is used by `DltMessageReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltMessageReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Call `hook` after each message with the number of bytes read so far
+    ///
+    /// `total` is the size of the source if known, e.g. the length of a file.
+    pub fn set_progress_hook(
+        &mut self,
+        total: Option<u64>,
+        hook: impl FnMut(Progress) + Send + 'static,
+    ) {
+        self.progress = Some(ProgressHook {
+            total,
+            hook: Box::new(hook),
+        });
+    }
+
+    /// Stop reading with `DltParseError::Cancelled` once the token is cancelled
+    ///
+    /// The token is checked before each message.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancel = Some(token);
+    }
+
+    /// Number of bytes that were read from the source so far, including
+    /// bytes that were skipped in recovery mode
+    pub fn bytes_processed(&self) -> u64 {
+        self.processed
+    }
+
+    /// Skip corrupted data instead of failing, only for sources with storage headers
+    ///
+    /// If a message doesn't start with a storage header or its length is
+    /// implausible (it is not followed by another storage header, but contains
+    /// one), the reader searches forward for the next storage header and
+    /// continues there. The skipped bytes are reported by `take_skipped_ranges`.
+    pub fn set_recovery(&mut self, recover: bool) {
+        self.recover = recover;
+    }
+
+    /// Treat messages longer than `max` bytes (including the storage header) as corrupted
+    ///
+    /// By default (`None`) there is no limit. Such messages are reported as
+    /// `DltParseError::MessageTooLarge`, and the reader continues at the next
+    /// storage header, since the length of the message can't be trusted.
+    /// Without storage headers, the `length` bytes of the message are skipped.
+    pub fn set_max_message_length(&mut self, max: Option<usize>) {
+        self.max_message_length = max;
+    }
+
+    /// Skipped bytes, invalid and filtered messages so far
+    ///
+    /// Messages are only counted as invalid or filtered if they are parsed by
+    /// the reader, e.g. with `next_message` or `read_message`.
+    pub fn stats(&self) -> &ReaderStats {
+        &self.stats
+    }
+
+    /// The byte ranges of the source that were skipped since the last call
+    pub fn take_skipped_ranges(&mut self) -> Vec<Range<u64>> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    /// Read and parse the next message using the current filter
+    ///
+    /// Returns `None` when the end of the source is reached. The message
+    /// owns its data, so it can be kept or sent to other threads while the
+    /// reader continues.
+    pub fn next_message(&mut self) -> Result<Option<ParsedMessage>, DltParseError> {
+        let filter = self.filter.get();
+        read_message(self, filter.as_deref())
+    }
+
+    /// Like `next_message`, but the message is returned with its position in the source
+    pub fn next_message_envelope(&mut self) -> Result<Option<MessageEnvelope>, DltParseError> {
+        let filter = self.filter.get();
+        read_message_envelope(self, filter.as_deref())
+    }
+
+    /// Like `next_message`, but the message is parsed into `message`, reusing
+    /// its allocations, see `parse::dlt_message_into`
+    pub fn next_message_into(
+        &mut self,
+        message: &mut Message,
+    ) -> Result<Option<ParsedInto>, DltParseError> {
+        let filter = self.filter.get();
+        let with_storage_header = self.with_storage_header;
+        let slice = self.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(None);
+        }
+        let result = dlt_message_into(slice, filter.as_deref(), with_storage_header, message)
+            .map(|(_, parsed)| parsed);
+        match &result {
+            Ok(ParsedInto::FilteredOut(_)) => self.stats.filtered_messages += 1,
+            Ok(ParsedInto::Invalid) | Err(_) => self.stats.invalid_messages += 1,
+            Ok(ParsedInto::Item) => (),
+        }
+        Ok(Some(result?))
+    }
+
+    /// Iterate over the remaining messages, parsed with the current filter
+    ///
+    /// The iteration ends at the end of the source, or after an error other
+    /// than `DltParseError::ParsingHickup` or `DltParseError::MessageTooLarge`.
+    pub fn messages(&mut self) -> DltMessages<'_, S> {
+        DltMessages {
+            reader: self,
+            done: false,
+        }
+    }
+
+    /// Like `next_message_slice`, but with the offset of the message in the source
+    pub(crate) fn next_message_slice_at(&mut self) -> Result<(u64, &[u8]), DltParseError> {
+        self.next_message_slice()?;
+        Ok((self.processed - self.buffer.len() as u64, &self.buffer))
+    }
+
+    /// Read the bytes of the next message (including the storage header if present)
+    ///
+    /// Returns an empty slice when the end of the source is reached.
+    pub fn next_message_slice(&mut self) -> Result<&[u8], DltParseError> {
+        if matches!(&self.cancel, Some(token) if token.is_cancelled()) {
+            return Err(DltParseError::Cancelled);
+        }
+        if self.recover && self.with_storage_header {
+            self.read_recovering()?;
+        } else {
+            let prefix_length = prefix_length(self.with_storage_header);
+            if !self.read_prefix(prefix_length)? {
+                return Ok(&self.buffer);
+            }
+            let length = self.buffered_message_length(prefix_length)?;
+            if matches!(self.max_message_length, Some(max) if length > max) {
+                let offset = self.processed;
+                let skipped = if self.with_storage_header {
+                    self.skip_to_next_storage_header()?
+                } else {
+                    let rest = (length - prefix_length) as u64;
+                    prefix_length as u64
+                        + std::io::copy(&mut (&mut self.source).take(rest), &mut std::io::sink())?
+                };
+                self.processed += skipped;
+                self.stats.bytes_skipped += skipped;
+                self.stats.invalid_messages += 1;
+                return Err(DltParseError::MessageTooLarge { length, offset });
+            }
+            self.buffer.resize(length, 0);
+            if !read_or_eof(&mut self.source, &mut self.buffer[prefix_length..])? {
+                return Err(DltParseError::IncompleteParse {
+                    needed: std::num::NonZeroUsize::new(length - prefix_length),
+                });
+            }
+            self.processed += length as u64;
+        }
+        if self.buffer.is_empty() {
+            return Ok(&self.buffer);
+        }
+        if let Some(progress) = self.progress.as_mut() {
+            (progress.hook)(Progress {
+                processed: self.processed,
+                total: progress.total,
+            });
+        }
+        Ok(&self.buffer)
+    }
+}
+
+impl<S: Read> DltMessageReader<S> {
+    /// Read the first `prefix_length` bytes of the next message into the buffer,
+    /// `false` at the end of the source
+    fn read_prefix(&mut self, prefix_length: usize) -> Result<bool, DltParseError> {
+        let buffered = std::mem::take(&mut self.buffered);
+        self.buffer.truncate(buffered);
+        self.buffer.resize(prefix_length, 0);
+        if read_or_eof(&mut self.source, &mut self.buffer[buffered..])? {
+            return Ok(true);
+        }
+        self.buffer.clear();
+        if buffered > 0 {
+            // a storage header pattern at the end of the source
+            return Err(DltParseError::IncompleteParse {
+                needed: std::num::NonZeroUsize::new(prefix_length - buffered),
+            });
+        }
+        Ok(false)
+    }
+
+    /// The length of the message of which the prefix is in the buffer
+    ///
+    /// An invalid length leaves the reader out of sync with the messages of
+    /// the source: the prefix is counted as invalid message and the error is
+    /// `DltParseError::Unrecoverable`.
+    fn buffered_message_length(&mut self, prefix_length: usize) -> Result<usize, DltParseError> {
+        message_length(&self.buffer, self.with_storage_header).map_err(|e| {
+            self.processed += prefix_length as u64;
+            self.stats.invalid_messages += 1;
+            out_of_sync(e)
+        })
+    }
+
+    /// Drop the message in the buffer and the bytes up to the next storage
+    /// header pattern, which is kept in the buffer for the next message
+    ///
+    /// Returns the number of dropped bytes.
+    fn skip_to_next_storage_header(&mut self) -> Result<u64, DltParseError> {
+        let mut matched = 0;
+        // the storage header of the message itself isn't trusted
+        if let Some(end) = find_storage_header_pattern(&mut matched, &self.buffer[1..]) {
+            let start = 1 + end - DLT_PATTERN.len();
+            self.buffer.drain(..start);
+            self.buffered = self.buffer.len();
+            return Ok(start as u64);
+        }
+        let mut dropped = self.buffer.len() as u64;
+        loop {
+            let available = self.source.fill_buf()?;
+            if available.is_empty() {
+                matched = 0;
+                break;
+            }
+            let (consumed, found) = match find_storage_header_pattern(&mut matched, available) {
+                Some(end) => (end, true),
+                None => (available.len(), false),
+            };
+            self.source.consume(consumed);
+            dropped += consumed as u64;
+            if found {
+                break;
+            }
+        }
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&DLT_PATTERN[..matched]);
+        self.buffered = matched;
+        Ok(dropped - matched as u64)
+    }
+
+    /// Read the next plausible message into the buffer, skipping corrupted
+    /// bytes before it
+    ///
+    /// The buffer is left empty at the end of the source.
+    fn read_recovering(&mut self) -> Result<(), DltParseError> {
+        let prefix_length = prefix_length(true);
+        let mut skipped = 0;
+        self.buffer.truncate(std::mem::take(&mut self.buffered));
+        loop {
+            if !fill_to(&mut self.source, &mut self.buffer, prefix_length)? {
+                // trailing bytes that can't be a message
+                skipped += self.buffer.len();
+                self.buffer.clear();
+                self.record_skipped(skipped);
+                return Ok(());
+            }
+            if self.buffer.starts_with(DLT_PATTERN) {
+                let length = message_length(&self.buffer, true).ok().filter(
+                    |length| !matches!(self.max_message_length, Some(max) if *length > max),
+                );
+                if let Some(length) = length {
+                    if fill_to(&mut self.source, &mut self.buffer, length)? {
+                        let next = self.source.fill_buf()?;
+                        // a message that isn't followed by a storage header is only
+                        // accepted if there is no other storage header within it
+                        if next.starts_with(&DLT_PATTERN[..next.len().min(DLT_PATTERN.len())])
+                            || forward_to_next_storage_header(&self.buffer[1..]).is_none()
+                        {
+                            self.record_skipped(skipped);
+                            self.processed += length as u64;
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            // drop the bytes up to the next storage header pattern
+            let drop = match forward_to_next_storage_header(&self.buffer[1..]) {
+                Some((to_next, _)) => to_next as usize + 1,
+                None => self
+                    .buffer
+                    .len()
+                    .saturating_sub(DLT_PATTERN.len() - 1)
+                    .max(1),
+            };
+            self.buffer.drain(..drop);
+            skipped += drop;
+        }
+    }
+
+    fn record_skipped(&mut self, skipped: usize) {
+        if skipped > 0 {
+            let start = self.processed;
+            self.processed += skipped as u64;
+            self.stats.bytes_skipped += skipped as u64;
+            debug!("skipped {} corrupted bytes at offset {}", skipped, start);
+            self.skipped.push(start..self.processed);
+        }
+    }
+}
+
+/// Reads the messages of several files as one source, e.g. of a recording
+/// that is split into `trace_001.dlt`, `trace_002.dlt`, ...
+///
+/// Each file has to contain complete messages, a message that is cut off at
+/// the end of a file is reported as `DltParseError::IncompleteParse` and
+/// reading continues with the next file. Likewise, a file that can't be
+/// opened is reported as error and skipped.
+pub struct DltMultiFileReader {
+    files: VecDeque<PathBuf>,
+    current: Option<(PathBuf, DltMessageReader<File>)>,
+    with_storage_header: bool,
+    filter: FilterHandle,
+}
+
+impl DltMultiFileReader {
+    /// Create a reader for files in the order in which they should be read
+    pub fn new(
+        files: impl IntoIterator<Item = impl Into<PathBuf>>,
+        with_storage_header: bool,
+    ) -> Self {
+        DltMultiFileReader {
+            files: files.into_iter().map(Into::into).collect(),
+            current: None,
+            with_storage_header,
+            filter: FilterHandle::default(),
+        }
+    }
+
+    pub fn with_storage_header(&self) -> bool {
+        self.with_storage_header
+    }
+
+    /// The file that is currently read
+    pub fn current_file(&self) -> Option<&Path> {
+        self.current.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// A handle to the filter that is used by `DltMultiFileReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltMultiFileReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Read and parse the next message using the current filter
+    ///
+    /// Returns `None` when the end of the last file is reached.
+    pub fn next_message(&mut self) -> Result<Option<ParsedMessage>, DltParseError> {
+        let with_storage_header = self.with_storage_header;
+        let filter = self.filter.get();
+        let slice = self.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(None);
+        }
+        let (_, message) = dlt_message(slice, filter.as_deref(), with_storage_header)?;
+        Ok(Some(message))
+    }
+
+    /// Read the bytes of the next message, continuing with the next file at
+    /// the end of a file
+    ///
+    /// Returns an empty slice when the end of the last file is reached.
+    pub fn next_message_slice(&mut self) -> Result<&[u8], DltParseError> {
+        loop {
+            if let Some((_, reader)) = self.current.as_mut() {
+                if !reader.next_message_slice()?.is_empty() {
+                    break;
+                }
+            }
+            match self.files.pop_front() {
+                Some(path) => {
+                    let file = File::open(&path)?;
+                    self.current =
+                        Some((path, DltMessageReader::new(file, self.with_storage_header)));
+                }
+                None => {
+                    self.current = None;
+                    return Ok(&[]);
+                }
+            }
+        }
+        match &self.current {
+            Some((_, reader)) => Ok(&reader.buffer),
+            None => Ok(&[]),
+        }
+    }
+}
+
+/// Parses data that arrives in arbitrary pieces, e.g. the segments of a TCP stream
+///
+/// The received bytes are pushed into the parser and complete messages are
+/// taken out. A message that is split over several pieces is returned once
+/// its last byte was pushed, several messages of one piece are returned one
+/// after the other.
+#[derive(Debug, Default)]
+pub struct DltPushParser {
+    with_storage_header: bool,
+    buffer: Vec<u8>,
+    consumed: usize,
+}
+
+impl DltPushParser {
+    pub fn new(with_storage_header: bool) -> Self {
+        DltPushParser {
+            with_storage_header,
+            buffer: vec![],
+            consumed: 0,
+        }
+    }
+
+    /// Add received bytes
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Number of pushed bytes that are not returned as message yet
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.consumed
+    }
+
+    /// Drop the buffered bytes, e.g. after a gap in the received data
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.consumed = 0;
+    }
+
+    /// The bytes of the next complete message, `None` if more bytes are needed
+    ///
+    /// If the length of a message can't be parsed, the data is not in sync
+    /// with the message boundaries. All buffered bytes are dropped then and
+    /// the error is returned.
+    pub fn next_message_slice(&mut self) -> Result<Option<&[u8]>, DltParseError> {
+        let available = &self.buffer[self.consumed..];
+        if available.len() < prefix_length(self.with_storage_header) {
+            return Ok(None);
+        }
+        let length = match message_length(available, self.with_storage_header) {
+            Ok(length) => length,
+            Err(e) => {
+                self.clear();
+                return Err(e);
+            }
+        };
+        if available.len() < length {
+            return Ok(None);
+        }
+        let start = self.consumed;
+        self.consumed += length;
+        Ok(Some(&self.buffer[start..self.consumed]))
+    }
+
+    /// Parse the next complete message, `None` if more bytes are needed
+    pub fn next_message(
+        &mut self,
+        filter_config_opt: Option<&ProcessedDltFilterConfig>,
+    ) -> Result<Option<ParsedMessage>, DltParseError> {
+        let with_storage_header = self.with_storage_header;
+        match self.next_message_slice()? {
+            Some(slice) => {
+                let (_, message) = dlt_message(slice, filter_config_opt, with_storage_header)?;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads the messages of a source that is still written, e.g. a file of the
+/// offline logging of dlt-daemon
+///
+/// At the end of the source, the reader waits for more data instead of
+/// stopping. Reading can be stopped with the token of `cancellation_token`
+/// from another thread, the waiting reader then returns `DltParseError::Cancelled`.
+pub struct DltTailReader<S: Read> {
+    source: S,
+    with_storage_header: bool,
+    poll_interval: Duration,
+    cancel: CancellationToken,
+    filter: FilterHandle,
+    pending: Vec<u8>,
+    consumed: usize,
+}
+
+impl DltTailReader<File> {
+    /// Tail a file from its start
+    pub fn open(path: &Path, with_storage_header: bool) -> Result<Self, DltParseError> {
+        Ok(DltTailReader::new(File::open(path)?, with_storage_header))
+    }
+}
+
+impl<S: Read> DltTailReader<S> {
+    pub fn new(source: S, with_storage_header: bool) -> Self {
+        DltTailReader {
+            source,
+            with_storage_header,
+            poll_interval: Duration::from_millis(100),
+            cancel: CancellationToken::new(),
+            filter: FilterHandle::default(),
+            pending: vec![],
+            consumed: 0,
+        }
+    }
+
+    /// How long to wait before checking the source for new data (100 ms by default)
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// The token that stops the reader
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// A handle to the filter that is used by `DltTailReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltTailReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Wait for the next message and parse it using the current filter
+    pub fn next_message(&mut self) -> Result<ParsedMessage, DltParseError> {
+        let with_storage_header = self.with_storage_header;
+        let filter = self.filter.get();
+        let slice = self.next_message_slice()?;
+        let (_, message) = dlt_message(slice, filter.as_deref(), with_storage_header)?;
+        Ok(message)
+    }
+
+    /// Wait for the bytes of the next message (including the storage header if present)
+    ///
+    /// A message that is only partly written is completed once the rest is
+    /// available. If the length of a message can't be parsed, all data that
+    /// was read is dropped and the error is returned.
+    pub fn next_message_slice(&mut self) -> Result<&[u8], DltParseError> {
+        let prefix_length = prefix_length(self.with_storage_header);
+        loop {
+            if self.cancel.is_cancelled() {
+                return Err(DltParseError::Cancelled);
+            }
+            let available = &self.pending[self.consumed..];
+            if available.len() >= prefix_length {
+                match message_length(available, self.with_storage_header) {
+                    Ok(length) if length <= available.len() => {
+                        let start = self.consumed;
+                        self.consumed += length;
+                        return Ok(&self.pending[start..self.consumed]);
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        self.pending.clear();
+                        self.consumed = 0;
+                        return Err(e);
+                    }
+                }
+            }
+            // only the start of a message is left, drop the returned messages
+            // before reading more
+            self.pending.drain(..self.consumed);
+            self.consumed = 0;
+            if read_chunk(&mut self.source, &mut self.pending)? == 0 {
+                thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
+
+/// Iterator over the messages of a `DltMessageReader`, see `DltMessageReader::messages`
+pub struct DltMessages<'a, S: Read> {
+    reader: &'a mut DltMessageReader<S>,
+    done: bool,
+}
+
+impl<S: Read> Iterator for DltMessages<'_, S> {
+    type Item = Result<ParsedMessage, DltParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.next_message() {
+            Ok(Some(message)) => Some(Ok(message)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = !matches!(
+                    e,
+                    DltParseError::ParsingHickup(_) | DltParseError::MessageTooLarge { .. }
+                );
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<S: Read + Send + 'static> DltMessageReader<S> {
+    /// Read the messages in a new thread and send them into a bounded channel
+    ///
+    /// The thread blocks while `capacity` messages wait in the channel and
+    /// stops at the end of the messages or when the receiver is dropped.
+    /// Joining the thread gives back the reader, e.g. for its `stats`.
+    pub fn spawn_channel(
+        mut self,
+        capacity: usize,
+    ) -> (
+        Receiver<Result<ParsedMessage, DltParseError>>,
+        JoinHandle<Self>,
+    ) {
+        let (sender, receiver) = sync_channel(capacity);
+        let handle = thread::spawn(move || {
+            for result in self.messages() {
+                if sender.send(result).is_err() {
+                    break;
+                }
+            }
+            self
+        });
+        (receiver, handle)
+    }
+}
+
+impl<'a, S: Read> IntoIterator for &'a mut DltMessageReader<S> {
+    type Item = Result<ParsedMessage, DltParseError>;
+    type IntoIter = DltMessages<'a, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages()
+    }
+}
+
+impl<S: Read + Seek> DltMessageReader<S> {
+    /// Skip the next `n` messages, only their lengths are parsed
+    ///
+    /// Returns the number of skipped messages, which is less than `n` if the
+    /// end of the source is reached. A message that is cut off by the end of
+    /// the source is counted as skipped.
+    pub fn skip_messages(&mut self, n: usize) -> Result<usize, DltParseError> {
+        let prefix_length = prefix_length(self.with_storage_header);
+        for skipped in 0..n {
+            if matches!(&self.cancel, Some(token) if token.is_cancelled()) {
+                return Err(DltParseError::Cancelled);
+            }
+            if !self.read_prefix(prefix_length)? {
+                return Ok(skipped);
+            }
+            let length = self.buffered_message_length(prefix_length)?;
+            self.source.seek_relative((length - prefix_length) as i64)?;
+            self.processed += length as u64;
+        }
+        Ok(n)
+    }
+
+    /// Continue reading at an offset of the source, which has to be the start of a message
+    ///
+    /// E.g. an offset of an `index::IndexEntry`. `bytes_processed` is set to the offset.
+    pub fn seek_to_offset(&mut self, offset: u64) -> Result<(), DltParseError> {
+        self.source.seek(SeekFrom::Start(offset))?;
+        self.processed = offset;
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+/// Bytes that tail readers read from their source at once
+pub(crate) const TAIL_CHUNK_LENGTH: u64 = 64 * 1024;
+
+/// Append up to `TAIL_CHUNK_LENGTH` bytes of the source to the buffer
+fn read_chunk<S: Read>(source: &mut S, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+    source.take(TAIL_CHUNK_LENGTH).read_to_end(buffer)
+}
+
+/// Search the storage header pattern in bytes that follow `matched` bytes of it
+///
+/// Returns the position after the pattern, `matched` is updated so the search
+/// can continue with the following bytes.
+fn find_storage_header_pattern(matched: &mut usize, bytes: &[u8]) -> Option<usize> {
+    for (i, byte) in bytes.iter().enumerate() {
+        // no prefix of the pattern appears again within it
+        *matched = if *byte == DLT_PATTERN[*matched] {
+            *matched + 1
+        } else {
+            usize::from(*byte == DLT_PATTERN[0])
+        };
+        if *matched == DLT_PATTERN.len() {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Number of bytes that are read first to know the length of a message
+pub(crate) fn prefix_length(with_storage_header: bool) -> usize {
+    storage_header_length(with_storage_header) + HEADER_MIN_LENGTH as usize
+}
+
+fn storage_header_length(with_storage_header: bool) -> usize {
+    if with_storage_header {
+        STORAGE_HEADER_LENGTH as usize
+    } else {
+        0
+    }
+}
+
+/// The length of a message including the storage header, parsed from its prefix
+pub(crate) fn message_length(
+    prefix: &[u8],
+    with_storage_header: bool,
+) -> Result<usize, DltParseError> {
+    let storage_header_length = storage_header_length(with_storage_header);
+    let (_, message_length) = parse_length(&prefix[storage_header_length..])?;
+    if message_length < HEADER_MIN_LENGTH as usize {
+        return Err(DltParseError::ParsingHickup(format!(
+            "Message length {} is less than the minimal header length",
+            message_length
+        )));
+    }
+    Ok(storage_header_length + message_length)
+}
+
+/// The error of a reader that can't find the start of the next message
+/// after an invalid message length
+pub(crate) fn out_of_sync(error: DltParseError) -> DltParseError {
+    DltParseError::Unrecoverable(format!("out of sync with the source: {}", error))
+}
+
+/// Extend the buffer to `length` bytes, `false` if the source ends before
+///
+/// The buffer keeps the bytes that could be read.
+fn fill_to<S: Read>(
+    source: &mut S,
+    buffer: &mut Vec<u8>,
+    length: usize,
+) -> Result<bool, DltParseError> {
+    let filled = buffer.len();
+    if filled >= length {
+        return Ok(true);
+    }
+    source.take((length - filled) as u64).read_to_end(buffer)?;
+    Ok(buffer.len() == length)
+}
+
+/// Fill the buffer completely, `false` if the source has no more data
+///
+/// A source that ends in the middle of the buffer is reported as incomplete.
+fn read_or_eof<S: Read>(source: &mut S, buffer: &mut [u8]) -> Result<bool, DltParseError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match source.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(DltParseError::IncompleteParse {
+                    needed: std::num::NonZeroUsize::new(buffer.len() - filled),
+                })
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
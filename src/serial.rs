@@ -0,0 +1,186 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Reading dlt messages from a serial line
+//!
+//! ECUs that log over a UART prefix each message with the serial header
+//! `DLS\x01`, so a receiver can find the start of the next message after
+//! lost or corrupted bytes. `DltSerialReader` strips these headers and
+//! returns the messages without them.
+use crate::{
+    dlt::HEADER_MIN_LENGTH,
+    filtering::ProcessedDltFilterConfig,
+    parse::{dlt_message, DltParseError, ParsedMessage},
+    progress::CancellationToken,
+    read::{message_length, FilterHandle},
+};
+use memchr::memmem;
+use serialport::SerialPort;
+use std::{
+    io::{ErrorKind, Read},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Serial header in front of each message
+pub const DLS_PATTERN: &[u8] = b"DLS\x01";
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Serial port error: {0}")]
+    Serial(#[from] serialport::Error),
+    #[error("Parse error: {0}")]
+    Parse(#[from] DltParseError),
+}
+
+/// Reads messages with serial headers from a serial port or any other source
+///
+/// Bytes that are not part of a message with serial header are skipped.
+/// Read timeouts (as reported by serial ports without data) are waited out
+/// until the source ends or the token of `cancellation_token` is cancelled.
+pub struct DltSerialReader<S: Read> {
+    source: S,
+    pending: Vec<u8>,
+    consumed: usize,
+    filter: FilterHandle,
+    cancel: CancellationToken,
+    bytes_skipped: u64,
+}
+
+impl DltSerialReader<Box<dyn SerialPort>> {
+    /// Open a serial port, e.g. `/dev/ttyUSB0` or `COM3`, with 8N1 framing
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self, Error> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()?;
+        Ok(DltSerialReader::new(port))
+    }
+}
+
+impl<S: Read> DltSerialReader<S> {
+    pub fn new(source: S) -> Self {
+        DltSerialReader {
+            source,
+            pending: vec![],
+            consumed: 0,
+            filter: FilterHandle::default(),
+            cancel: CancellationToken::new(),
+            bytes_skipped: 0,
+        }
+    }
+
+    /// The token that stops waiting for data with `DltParseError::Cancelled`
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// A handle to the filter that is used by `DltSerialReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltSerialReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Number of bytes that were skipped because they didn't belong to a message
+    pub fn bytes_skipped(&self) -> u64 {
+        self.bytes_skipped
+    }
+
+    /// Wait for the next message and parse it using the current filter
+    ///
+    /// Returns `None` when the source ends.
+    pub fn next_message(&mut self) -> Result<Option<ParsedMessage>, Error> {
+        let filter = self.filter.get();
+        let slice = self.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(None);
+        }
+        let (_, message) = dlt_message(slice, filter.as_deref(), false)?;
+        Ok(Some(message))
+    }
+
+    /// Wait for the bytes of the next message, without the serial header
+    ///
+    /// Returns an empty slice when the source ends, an incomplete message at
+    /// the end is skipped.
+    pub fn next_message_slice(&mut self) -> Result<&[u8], Error> {
+        self.pending.drain(..self.consumed);
+        self.consumed = 0;
+        let finder = memmem::Finder::new(DLS_PATTERN);
+        let header_end = DLS_PATTERN.len() + HEADER_MIN_LENGTH as usize;
+        loop {
+            match finder.find(&self.pending) {
+                Some(position) => {
+                    self.skip(position);
+                    if self.pending.len() >= header_end {
+                        match message_length(&self.pending[DLS_PATTERN.len()..], false) {
+                            Ok(length) if self.pending.len() >= DLS_PATTERN.len() + length => {
+                                self.consumed = DLS_PATTERN.len() + length;
+                                return Ok(&self.pending[DLS_PATTERN.len()..self.consumed]);
+                            }
+                            Ok(_) => (),
+                            Err(_) => {
+                                // the pattern was part of other data
+                                self.skip(1);
+                                continue;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    // keep what could be the start of the pattern
+                    let keep = self.pending.len().min(DLS_PATTERN.len() - 1);
+                    self.skip(self.pending.len() - keep);
+                }
+            }
+            if !self.read_more()? {
+                self.skip(self.pending.len());
+                return Ok(&self.pending);
+            }
+        }
+    }
+
+    fn skip(&mut self, length: usize) {
+        self.pending.drain(..length);
+        self.bytes_skipped += length as u64;
+    }
+
+    /// Append the next bytes of the source, `false` at its end
+    fn read_more(&mut self) -> Result<bool, Error> {
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        loop {
+            if self.cancel.is_cancelled() {
+                return Err(DltParseError::Cancelled.into());
+            }
+            match self.source.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    self.pending.extend_from_slice(&chunk[..n]);
+                    return Ok(true);
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                    ) => {}
+                Err(e) => return Err(DltParseError::from(e).into()),
+            }
+        }
+    }
+}
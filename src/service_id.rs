@@ -13,11 +13,130 @@
 // limitations under the License.
 
 //! # official supported DLT service ids
+//!
+//! Service ids below `0xF00` are reserved for the DLT standard, the ones
+//! from `0xF00` on are user defined. Names of user defined services can be
+//! provided by registering vendor tables, e.g. [`DLT_DAEMON_SERVICES`].
+use lazy_static::lazy_static;
+use std::{fmt, sync::RwLock};
 
-/// Contains all the official service ids with it's u8 representation
-/// Maps from the u8 representation to a tuple (service-id-string, explanation)
+/// The first service id that is not reserved for the DLT standard
+pub const MIN_USER_DEFINED_SERVICE_ID: u32 = 0xF00;
+
+/// A table of user defined services: (service id, service-id-string, explanation)
+pub type VendorServices = &'static [(u32, &'static str, &'static str)];
+
+/// The user defined services of the GENIVI dlt-daemon
+#[rustfmt::skip]
+pub const DLT_DAEMON_SERVICES: VendorServices = &[
+    (0xF01, "unregister_context", "Context was unregistered"),
+    (0xF02, "connection_info", "Client connection state changed"),
+    (0xF03, "timezone", "Timezone of the ECU"),
+    (0xF04, "marker", "Marker set by the client"),
+    (0xF05, "offline_logstorage", "Offline logstorage device state changed"),
+    (0xF06, "passive_node_connect", "Connect or disconnect a passive node"),
+    (0xF07, "passive_node_connection_status", "Connection status of passive nodes"),
+    (0xF08, "set_all_log_level", "Set the Log Level of all contexts"),
+    (0xF09, "set_all_trace_status", "Set the TraceStatus of all contexts"),
+];
+
+lazy_static! {
+    static ref VENDOR_SERVICES: RwLock<Vec<VendorServices>> = RwLock::new(Vec::new());
+}
+
+/// Add a table of user defined services to `service_id_lookup`
+///
+/// Tables registered later take precedence over earlier ones.
+pub fn register_vendor_services(services: VendorServices) {
+    VENDOR_SERVICES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(services);
+}
+
+/// Remove all registered tables of user defined services
+pub fn clear_vendor_services() {
+    VENDOR_SERVICES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+/// The service id of a control message
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ServiceId {
+    /// Ids below `MIN_USER_DEFINED_SERVICE_ID`, including unassigned ones
+    Standard(u32),
+    UserDefined(u32),
+}
+
+impl From<u32> for ServiceId {
+    fn from(service_id: u32) -> Self {
+        if service_id < MIN_USER_DEFINED_SERVICE_ID {
+            ServiceId::Standard(service_id)
+        } else {
+            ServiceId::UserDefined(service_id)
+        }
+    }
+}
+
+impl From<ServiceId> for u32 {
+    fn from(service_id: ServiceId) -> Self {
+        service_id.value()
+    }
+}
+
+impl ServiceId {
+    pub fn value(&self) -> u32 {
+        match *self {
+            ServiceId::Standard(id) | ServiceId::UserDefined(id) => id,
+        }
+    }
+
+    /// The name of the service (e.g. `set_log_level`) if it is known
+    pub fn name(&self) -> Option<&'static str> {
+        service_id_lookup(self.value()).map(|(name, _)| name)
+    }
+}
+
+/// Displays the name of known services, the hex value otherwise (e.g. `0x1234`)
+impl fmt::Display for ServiceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "0x{:x}", self.value()),
+        }
+    }
+}
+
+/// Maps a service id to a tuple (service-id-string, explanation)
+///
+/// Standard services are always known, user defined ones only if they are
+/// in a registered vendor table.
+pub fn service_id_lookup(service_id: u32) -> Option<(&'static str, &'static str)> {
+    if service_id < MIN_USER_DEFINED_SERVICE_ID {
+        return standard_service_lookup(service_id);
+    }
+    VENDOR_SERVICES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .rev()
+        .find_map(|services| {
+            services
+                .iter()
+                .find(|(id, _, _)| *id == service_id)
+                .map(|(_, name, description)| (*name, *description))
+        })
+}
+
+/// Contains all the official service ids
 #[rustfmt::skip]
-pub fn service_id_lookup(service_id: u8) -> Option<(&'static str, &'static str)> {
+fn standard_service_lookup(service_id: u32) -> Option<(&'static str, &'static str)> {
     match service_id {
         0x01 => Some(("set_log_level", "Set the Log Level")),
         0x02 => Some(("set_trace_status", "Enable/Disable Trace Messages")),
@@ -27,13 +146,14 @@ pub fn service_id_lookup(service_id: u8) -> Option<(&'static str, &'static str)>
         0x06 => Some(("restore_to_factory_default", "Sets the configuration back to default")),
         0x07 => Some(("set_com_interface_status", "SetComInterfaceStatus -- deprecated")),
         0x08 => Some(("set_com_interface_max_bandwidth", "SetComInterfaceMaxBandwidth -- deprecated")),
-        0x09 => Some(("set_verbose_mode", "SetVerboseMode -- deprecated")),0x10 => Some(( "set_use_extended_header", "SetUseExtendedHeader -- deprecated")),
+        0x09 => Some(("set_verbose_mode", "SetVerboseMode -- deprecated")),
         0x0A => Some(("set_message_filtering", "Enable/Disable message filtering")),
         0x0B => Some(("set_timing_packets", "SetTimingPackets -- deprecated")),
         0x0C => Some(("get_local_time", "GetLocalTime -- deprecated")),
         0x0D => Some(("set_use_ecuid", "SetUseECUID -- deprecated")),
         0x0E => Some(("set_use_session_id", "SetUseSessionID -- deprecated")),
         0x0F => Some(("set_use_timestamp", "SetUseTimestamp -- deprecated")),
+        0x10 => Some(("set_use_extended_header", "SetUseExtendedHeader -- deprecated")),
         0x11 => Some(("set_default_log_level", "Sets the LogLevel for wildcards")),
         0x12 => Some(("set_default_trace_status", "Enable/Disable TraceMessages for wildcards")),
         0x13 => Some(("get_software_version", "Get the ECU software version")),
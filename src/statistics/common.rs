@@ -0,0 +1,634 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # built-in statistic collectors
+use crate::{
+    control::{ControlPayload, GET_SOFTWARE_VERSION},
+    dlt::{ControlType, DltId, DltTimeStamp, Endianness, ExtendedHeader, MessageType},
+    fmt::csv_field,
+    parse::DltParseError,
+    statistics::{
+        add_for_level, IdMap, LevelDistribution, Statistic, StatisticCollector, StatisticInfo,
+    },
+};
+use rustc_hash::FxHashMap;
+use std::{collections::BTreeMap, ops::Range, time::Duration};
+
+/// Collects the `StatisticInfo` of a trace, like `statistics::collect_dlt_stats`
+#[derive(Debug, Default)]
+pub struct StatisticInfoCollector {
+    app_ids: IdMap,
+    context_ids: IdMap,
+    ecu_ids: IdMap,
+    contained_non_verbose: bool,
+}
+
+impl StatisticInfoCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn statistic_info(self) -> StatisticInfo {
+        let into_vec = |ids: IdMap| {
+            ids.into_iter()
+                .collect::<Vec<(String, LevelDistribution)>>()
+        };
+        StatisticInfo {
+            app_ids: into_vec(self.app_ids),
+            context_ids: into_vec(self.context_ids),
+            ecu_ids: into_vec(self.ecu_ids),
+            contained_non_verbose: self.contained_non_verbose,
+        }
+    }
+}
+
+impl StatisticCollector for StatisticInfoCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        let level = statistic.log_level();
+        self.contained_non_verbose = self.contained_non_verbose || !statistic.is_verbose();
+        let (app_id, context_id) = match &statistic.extended_header {
+            Some(header) => (header.application_id.as_str(), header.context_id.as_str()),
+            None => ("NONE", "NONE"),
+        };
+        add_for_level(level, &mut self.app_ids, app_id);
+        add_for_level(level, &mut self.context_ids, context_id);
+        let ecu_id = statistic
+            .standard_header
+            .ecu_id
+            .as_deref()
+            .unwrap_or("NONE");
+        add_for_level(level, &mut self.ecu_ids, ecu_id);
+        Ok(())
+    }
+}
+
+/// The time span of a trace
+///
+/// Header timestamps are the ticks (0.1 ms) since the start of the ECU.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TimestampRange {
+    pub earliest_storage_timestamp: Option<DltTimeStamp>,
+    pub latest_storage_timestamp: Option<DltTimeStamp>,
+    pub earliest_header_timestamp: Option<u32>,
+    pub latest_header_timestamp: Option<u32>,
+    pub messages: usize,
+    pub without_storage_timestamp: usize,
+    pub without_header_timestamp: usize,
+}
+
+impl TimestampRange {
+    /// The time between the earliest and latest storage timestamp
+    pub fn storage_duration(&self) -> Option<Duration> {
+        match (
+            &self.earliest_storage_timestamp,
+            &self.latest_storage_timestamp,
+        ) {
+            (Some(earliest), Some(latest)) => {
+                Some(Duration::from_micros(latest.as_us() - earliest.as_us()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The time between the earliest and latest header timestamp
+    pub fn header_duration(&self) -> Option<Duration> {
+        match (self.earliest_header_timestamp, self.latest_header_timestamp) {
+            (Some(earliest), Some(latest)) => {
+                Some(Duration::from_micros((latest - earliest) as u64 * 100))
+            }
+            _ => None,
+        }
+    }
+
+    /// The total duration of the trace, using the storage timestamps if present
+    pub fn duration(&self) -> Option<Duration> {
+        self.storage_duration().or_else(|| self.header_duration())
+    }
+
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Collects the `TimestampRange` of a trace
+#[derive(Debug, Default)]
+pub struct TimestampRangeCollector {
+    range: TimestampRange,
+}
+
+impl TimestampRangeCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn range(&self) -> &TimestampRange {
+        &self.range
+    }
+}
+
+impl StatisticCollector for TimestampRangeCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        let range = &mut self.range;
+        range.messages += 1;
+        match &statistic.storage_header {
+            Some(storage_header) => {
+                let timestamp = &storage_header.timestamp;
+                if !matches!(&range.earliest_storage_timestamp, Some(t) if t <= timestamp) {
+                    range.earliest_storage_timestamp = Some(timestamp.clone());
+                }
+                if !matches!(&range.latest_storage_timestamp, Some(t) if t >= timestamp) {
+                    range.latest_storage_timestamp = Some(timestamp.clone());
+                }
+            }
+            None => range.without_storage_timestamp += 1,
+        }
+        match statistic.standard_header.timestamp {
+            Some(timestamp) => {
+                range.earliest_header_timestamp = Some(
+                    range
+                        .earliest_header_timestamp
+                        .map_or(timestamp, |t| t.min(timestamp)),
+                );
+                range.latest_header_timestamp = Some(
+                    range
+                        .latest_header_timestamp
+                        .map_or(timestamp, |t| t.max(timestamp)),
+                );
+            }
+            None => range.without_header_timestamp += 1,
+        }
+        Ok(())
+    }
+}
+
+/// Distribution of sizes in bytes
+///
+/// The buckets are powers of two: bucket 0 counts empty sizes, bucket `i`
+/// counts the sizes in `2^(i-1)..2^i`, see `SizeHistogram::bucket_range`.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SizeHistogram {
+    pub count: usize,
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+    pub total: u64,
+    pub buckets: Vec<usize>,
+}
+
+impl SizeHistogram {
+    pub fn add(&mut self, size: usize) {
+        self.count += 1;
+        self.total += size as u64;
+        self.min = Some(self.min.map_or(size, |min| min.min(size)));
+        self.max = Some(self.max.map_or(size, |max| max.max(size)));
+        let bucket = (usize::BITS - size.leading_zeros()) as usize;
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    /// The average size
+    pub fn average(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total as f64 / self.count as f64)
+    }
+
+    /// The sizes that are counted in a bucket
+    pub fn bucket_range(index: usize) -> Range<usize> {
+        match index {
+            0 => 0..1,
+            i => 1 << (i - 1)..1 << i,
+        }
+    }
+
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// The buckets as CSV, with the sizes of a bucket in `from..to`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("from,to,count\n");
+        for (index, count) in self.buckets.iter().enumerate() {
+            let range = Self::bucket_range(index);
+            csv.push_str(&format!("{},{},{}\n", range.start, range.end, count));
+        }
+        csv
+    }
+}
+
+/// Collects the `SizeHistogram`s of the messages (without storage header)
+/// and payloads of a trace
+#[derive(Debug, Default)]
+pub struct SizeHistogramCollector {
+    message_sizes: SizeHistogram,
+    payload_sizes: SizeHistogram,
+}
+
+impl SizeHistogramCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn message_sizes(&self) -> &SizeHistogram {
+        &self.message_sizes
+    }
+
+    pub fn payload_sizes(&self) -> &SizeHistogram {
+        &self.payload_sizes
+    }
+}
+
+impl StatisticCollector for SizeHistogramCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        self.message_sizes
+            .add(statistic.standard_header.overall_length() as usize);
+        self.payload_sizes.add(statistic.payload.len());
+        Ok(())
+    }
+}
+
+/// Messages and bytes (without storage header) within a time bucket
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeBucket {
+    pub start: DltTimeStamp,
+    pub messages: usize,
+    pub bytes: u64,
+}
+
+/// Counts the messages per time bucket (e.g. per second or minute) based on
+/// the storage timestamps, to locate load spikes and silent gaps
+#[derive(Debug)]
+pub struct TimeBucketCollector {
+    bucket_us: u64,
+    buckets: BTreeMap<u64, (usize, u64)>,
+    without_timestamp: usize,
+}
+
+impl TimeBucketCollector {
+    /// Create a collector for buckets of the given width (at least 1 µs)
+    pub fn new(width: Duration) -> Self {
+        TimeBucketCollector {
+            bucket_us: (width.as_micros() as u64).max(1),
+            buckets: BTreeMap::new(),
+            without_timestamp: 0,
+        }
+    }
+
+    /// The buckets that contain messages, sorted by time
+    pub fn buckets(&self) -> Vec<TimeBucket> {
+        self.buckets
+            .iter()
+            .map(|(index, (messages, bytes))| TimeBucket {
+                start: DltTimeStamp::from_us(index * self.bucket_us),
+                messages: *messages,
+                bytes: *bytes,
+            })
+            .collect()
+    }
+
+    /// Time ranges of one or more buckets without messages, between the first
+    /// and the last message
+    pub fn silent_gaps(&self) -> Vec<Range<DltTimeStamp>> {
+        self.buckets
+            .keys()
+            .zip(self.buckets.keys().skip(1))
+            .filter(|(previous, next)| *next - *previous > 1)
+            .map(|(previous, next)| {
+                DltTimeStamp::from_us((previous + 1) * self.bucket_us)
+                    ..DltTimeStamp::from_us(next * self.bucket_us)
+            })
+            .collect()
+    }
+
+    /// Number of messages without storage header
+    pub fn without_timestamp(&self) -> usize {
+        self.without_timestamp
+    }
+
+    /// The buckets as JSON array
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.buckets())
+    }
+
+    /// The buckets as CSV, with the start of a bucket in µs
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("start_us,messages,bytes\n");
+        for bucket in self.buckets() {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                bucket.start.as_us(),
+                bucket.messages,
+                bucket.bytes
+            ));
+        }
+        csv
+    }
+}
+
+impl StatisticCollector for TimeBucketCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        match &statistic.storage_header {
+            Some(storage_header) => {
+                let index = storage_header.timestamp.as_us() / self.bucket_us;
+                let (messages, bytes) = self.buckets.entry(index).or_default();
+                *messages += 1;
+                *bytes += statistic.standard_header.overall_length() as u64;
+            }
+            None => self.without_timestamp += 1,
+        }
+        Ok(())
+    }
+}
+
+/// Counts the occurrences of each non-verbose message id
+#[derive(Debug, Default)]
+pub struct NonVerboseIdCollector {
+    counts: FxHashMap<u32, usize>,
+}
+
+impl NonVerboseIdCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The message ids with their number of messages, the most frequent first
+    pub fn counts(&self) -> Vec<(u32, usize)> {
+        let mut counts: Vec<(u32, usize)> = self.counts.iter().map(|(k, v)| (*k, *v)).collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Number of messages of one message id
+    pub fn count(&self, message_id: u32) -> usize {
+        self.counts.get(&message_id).copied().unwrap_or_default()
+    }
+
+    /// The counts as JSON array of `[message_id, count]` pairs, see `counts`
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.counts())
+    }
+
+    /// The counts as CSV, the most frequent first
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("message_id,count\n");
+        for (message_id, count) in self.counts() {
+            csv.push_str(&format!("{},{}\n", message_id, count));
+        }
+        csv
+    }
+}
+
+impl StatisticCollector for NonVerboseIdCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        if let Some(id) = statistic.nonverbose_id() {
+            *self.counts.entry(id).or_default() += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the software versions that are reported by ECUs in positive
+/// responses to `get_software_version` control requests
+///
+/// The ECU is taken from the standard header, or from the storage header if
+/// the standard header has no ECU id.
+#[derive(Debug, Default)]
+pub struct SoftwareVersionCollector {
+    versions: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl SoftwareVersionCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The reported versions per ECU with the number of responses
+    pub fn versions(&self) -> &BTreeMap<String, BTreeMap<String, usize>> {
+        &self.versions
+    }
+
+    /// The versions that were reported by one ECU
+    pub fn versions_of(&self, ecu_id: &str) -> Vec<&str> {
+        self.versions
+            .get(ecu_id)
+            .map(|versions| versions.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.versions)
+    }
+
+    /// The versions as CSV, one line per ECU and version
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("ecu,version,count\n");
+        for (ecu_id, versions) in &self.versions {
+            for (version, count) in versions {
+                csv.push_str(&format!(
+                    "{},{},{}\n",
+                    csv_field(ecu_id),
+                    csv_field(version),
+                    count
+                ));
+            }
+        }
+        csv
+    }
+}
+
+impl StatisticCollector for SoftwareVersionCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        if let Some(version) = software_version(statistic) {
+            let ecu_id = statistic
+                .standard_header
+                .ecu_id
+                .or_else(|| statistic.storage_header.as_ref().map(|h| h.ecu_id))
+                .map_or_else(|| "NONE".to_string(), |id| id.to_string());
+            *self
+                .versions
+                .entry(ecu_id)
+                .or_default()
+                .entry(version)
+                .or_default() += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The version of a positive `get_software_version` response
+fn software_version(statistic: &Statistic) -> Option<String> {
+    if !matches!(
+        &statistic.extended_header,
+        Some(ExtendedHeader {
+            message_type: MessageType::Control(ControlType::Response),
+            ..
+        })
+    ) {
+        return None;
+    }
+    let endianness = statistic.standard_header.endianness;
+    // only responses of this service are parsed
+    let service_id = match endianness {
+        Endianness::Big => GET_SOFTWARE_VERSION.to_be_bytes(),
+        Endianness::Little => GET_SOFTWARE_VERSION.to_le_bytes(),
+    };
+    if !statistic.payload.starts_with(&service_id) {
+        return None;
+    }
+    ControlPayload::parse(&ControlType::Response, statistic.payload, endianness)
+        .ok()?
+        .software_version()
+        .map(str::to_string)
+}
+
+/// What identifies a message for `TopMessagesCollector`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageKey {
+    /// the rendered arguments of a verbose message
+    Text(String),
+    /// the message id of a non-verbose message
+    NonVerbose(u32),
+}
+
+/// How often a message was seen, see `TopMessagesCollector`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCount {
+    pub application_id: Option<DltId>,
+    pub context_id: Option<DltId>,
+    pub message: MessageKey,
+    pub count: usize,
+}
+
+type MessageSource = (Option<DltId>, Option<DltId>, MessageKey);
+
+/// Finds the most frequent messages of a trace, e.g. to spot log spam
+///
+/// Verbose messages are counted by their rendered arguments together with
+/// their application and context id, non-verbose messages by their message
+/// id. Control messages are not counted. Every distinct text is kept in
+/// memory until the collector is dropped.
+#[derive(Debug)]
+pub struct TopMessagesCollector {
+    n: usize,
+    counts: FxHashMap<MessageSource, usize>,
+}
+
+impl TopMessagesCollector {
+    /// Create a collector that reports the `n` most frequent messages
+    pub fn new(n: usize) -> Self {
+        TopMessagesCollector {
+            n,
+            counts: FxHashMap::default(),
+        }
+    }
+
+    /// The `n` most frequent messages, the most frequent first
+    pub fn top(&self) -> Vec<MessageCount> {
+        let mut counts: Vec<(&MessageSource, usize)> =
+            self.counts.iter().map(|(k, v)| (k, *v)).collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts
+            .into_iter()
+            .take(self.n)
+            .map(
+                |((application_id, context_id, message), count)| MessageCount {
+                    application_id: *application_id,
+                    context_id: *context_id,
+                    message: message.clone(),
+                    count,
+                },
+            )
+            .collect()
+    }
+
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.top())
+    }
+
+    /// The most frequent messages as CSV, either the message id or the text is set
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("app,context,message_id,text,count\n");
+        for entry in self.top() {
+            let (message_id, text) = match &entry.message {
+                MessageKey::Text(text) => (String::new(), csv_field(text)),
+                MessageKey::NonVerbose(id) => (id.to_string(), "".into()),
+            };
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(entry.application_id.as_deref().unwrap_or_default()),
+                csv_field(entry.context_id.as_deref().unwrap_or_default()),
+                message_id,
+                text,
+                entry.count
+            ));
+        }
+        csv
+    }
+}
+
+impl StatisticCollector for TopMessagesCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        let message = if statistic.is_verbose() {
+            match statistic.payload_content() {
+                Ok(payload) => match payload.verbose_text() {
+                    Some(text) => MessageKey::Text(text),
+                    None => return Ok(()),
+                },
+                Err(e) => {
+                    debug!("top messages...skip payload that can't be parsed: {}", e);
+                    return Ok(());
+                }
+            }
+        } else {
+            match statistic.nonverbose_id() {
+                Some(id) => MessageKey::NonVerbose(id),
+                None => return Ok(()),
+            }
+        };
+        let (application_id, context_id) = match &statistic.extended_header {
+            Some(h) => (Some(h.application_id), Some(h.context_id)),
+            None => (None, None),
+        };
+        *self
+            .counts
+            .entry((application_id, context_id, message))
+            .or_default() += 1;
+        Ok(())
+    }
+}
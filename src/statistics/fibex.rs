@@ -0,0 +1,357 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # fibex related statistics of the non-verbose messages of a trace
+//!
+//! The coverage of an existing fibex model, and a skeleton fibex model that is
+//! inferred from the messages when the original model is not available.
+use crate::{
+    dlt::{LogLevel, MessageType},
+    fibex::{extract_metadata_for_ecu, FibexMetadata},
+    parse::DltParseError,
+    statistics::{Statistic, StatisticCollector},
+};
+use quick_xml::escape::escape;
+use rustc_hash::FxHashMap;
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Which frames of a fibex model were used by the non-verbose messages of a trace
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FibexCoverage {
+    /// message ids with a matching frame and how often they were seen
+    pub seen: Vec<(u32, usize)>,
+    /// message ids without a matching frame and how often they were seen
+    pub unmatched: Vec<(u32, usize)>,
+    /// message ids of frames that were never seen
+    pub unused: Vec<u32>,
+}
+
+impl FibexCoverage {
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// The coverage as CSV, one line per message id with its status
+    /// (`seen`, `unmatched` or `unused`)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("message_id,status,count\n");
+        let lines = self
+            .seen
+            .iter()
+            .map(|(id, count)| (*id, "seen", *count))
+            .chain(
+                self.unmatched
+                    .iter()
+                    .map(|(id, count)| (*id, "unmatched", *count)),
+            )
+            .chain(self.unused.iter().map(|id| (*id, "unused", 0)));
+        for (id, status, count) in lines {
+            let _ = writeln!(csv, "{},{},{}", id, status, count);
+        }
+        csv
+    }
+}
+
+/// Collects the `FibexCoverage` of a trace, see `statistics::collect_statistics`
+///
+/// A frame counts as matching if it is found with `fibex::extract_metadata_for_ecu`,
+/// so frames with application and context ids only match messages with the
+/// same ids in their extended header.
+pub struct FibexCoverageCollector<'a> {
+    fibex: &'a FibexMetadata,
+    seen: FxHashMap<u32, usize>,
+    unmatched: FxHashMap<u32, usize>,
+}
+
+impl<'a> FibexCoverageCollector<'a> {
+    pub fn new(fibex: &'a FibexMetadata) -> Self {
+        FibexCoverageCollector {
+            fibex,
+            seen: FxHashMap::default(),
+            unmatched: FxHashMap::default(),
+        }
+    }
+
+    /// The coverage of all messages collected so far, all lists are sorted by message id
+    pub fn coverage(&self) -> FibexCoverage {
+        let sorted = |counts: &FxHashMap<u32, usize>| {
+            let mut counts: Vec<(u32, usize)> = counts.iter().map(|(k, v)| (*k, *v)).collect();
+            counts.sort_unstable();
+            counts
+        };
+        FibexCoverage {
+            seen: sorted(&self.seen),
+            unmatched: sorted(&self.unmatched),
+            unused: self
+                .fibex
+                .frame_ids()
+                .into_iter()
+                .filter(|id| !self.seen.contains_key(id))
+                .collect(),
+        }
+    }
+}
+
+impl StatisticCollector for FibexCoverageCollector<'_> {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        if let Some(id) = statistic.nonverbose_id() {
+            let counts = match extract_metadata_for_ecu(
+                self.fibex,
+                statistic.standard_header.ecu_id.as_deref(),
+                id,
+                statistic.extended_header.as_ref(),
+            ) {
+                Some(_) => &mut self.seen,
+                None => &mut self.unmatched,
+            };
+            *counts.entry(id).or_default() += 1;
+        }
+        Ok(())
+    }
+}
+
+/// What was seen of a non-verbose message id in a trace
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedFrame {
+    pub message_id: u32,
+    /// ids and type of the first message with an extended header
+    pub application_id: Option<String>,
+    pub context_id: Option<String>,
+    pub message_type: Option<MessageType>,
+    /// payload length without the message id
+    pub min_length: usize,
+    pub max_length: usize,
+    pub count: usize,
+}
+
+impl ObservedFrame {
+    /// The signal types of the frame, inferred from the payload length
+    ///
+    /// The payload is split into 32 bit values, the remaining bytes into 16
+    /// and 8 bit values. Messages of variable length have no signals.
+    pub fn signal_refs(&self) -> Vec<&'static str> {
+        if self.min_length != self.max_length {
+            return vec![];
+        }
+        let mut refs = vec!["S_UINT32"; self.min_length / 4];
+        if self.min_length % 4 >= 2 {
+            refs.push("S_UINT16");
+        }
+        if self.min_length % 2 == 1 {
+            refs.push("S_UINT8");
+        }
+        refs
+    }
+}
+
+/// Collects the non-verbose messages of a trace to generate a skeleton fibex model,
+/// see `statistics::collect_statistics`
+///
+/// The skeleton is meant as a starting point to write a description when the
+/// original fibex model is not available.
+#[derive(Debug, Default)]
+pub struct FibexSkeletonCollector {
+    frames: BTreeMap<u32, ObservedFrame>,
+}
+
+impl FibexSkeletonCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The observed frames, sorted by message id
+    pub fn frames(&self) -> impl Iterator<Item = &ObservedFrame> {
+        self.frames.values()
+    }
+
+    /// A fibex model with a frame for each observed message id, that can be
+    /// read with `fibex::read_fibex_from_bytes`
+    pub fn fibex_xml(&self) -> String {
+        let mut pdus = String::new();
+        let mut frames = String::new();
+        for frame in self.frames() {
+            write_frame(&mut pdus, &mut frames, frame).expect("writing to a String does not fail");
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<fx:FIBEX xmlns:ho="http://www.asam.net/xml" xmlns:fx="http://www.asam.net/xml/fbx">
+    <fx:PROJECT ID="Project">
+        <ho:SHORT-NAME>Skeleton</ho:SHORT-NAME>
+    </fx:PROJECT>
+    <fx:ELEMENTS>
+        <fx:PDUS>
+{}        </fx:PDUS>
+        <fx:FRAMES>
+{}        </fx:FRAMES>
+    </fx:ELEMENTS>
+</fx:FIBEX>
+"#,
+            pdus, frames
+        )
+    }
+}
+
+fn write_frame(pdus: &mut String, frames: &mut String, frame: &ObservedFrame) -> std::fmt::Result {
+    let id = frame.message_id;
+    let signal_refs = frame.signal_refs();
+    let mut pdu_instances = String::new();
+    let mut sequence_number = 0;
+    let mut pdu = |pdus: &mut String, name: String, content: String| {
+        write!(
+            pdus,
+            r#"            <fx:PDU ID="{name}">
+                <ho:SHORT-NAME>{name}</ho:SHORT-NAME>
+{content}                <fx:PDU-TYPE>OTHER</fx:PDU-TYPE>
+            </fx:PDU>
+"#
+        )?;
+        write!(
+            pdu_instances,
+            r#"                    <fx:PDU-INSTANCE ID="{name}_I">
+                        <fx:PDU-REF ID-REF="{name}"/>
+                        <fx:SEQUENCE-NUMBER>{sequence_number}</fx:SEQUENCE-NUMBER>
+                    </fx:PDU-INSTANCE>
+"#
+        )?;
+        sequence_number += 1;
+        Ok(())
+    };
+    let description = if signal_refs.is_empty() {
+        format!(
+            "message {}, {} to {} bytes:",
+            id, frame.min_length, frame.max_length
+        )
+    } else {
+        format!("message {}:", id)
+    };
+    pdu(
+        pdus,
+        format!("ID_{}_DESC", id),
+        format!(
+            "                <ho:DESC>{}</ho:DESC>\n                <fx:BYTE-LENGTH>0</fx:BYTE-LENGTH>\n",
+            description
+        ),
+    )?;
+    for (i, signal_ref) in signal_refs.iter().enumerate() {
+        let byte_length = match *signal_ref {
+            "S_UINT32" => 4,
+            "S_UINT16" => 2,
+            _ => 1,
+        };
+        pdu(
+            pdus,
+            format!("ID_{}_{}", id, i),
+            format!(
+                r#"                <fx:BYTE-LENGTH>{byte_length}</fx:BYTE-LENGTH>
+                <fx:SIGNAL-INSTANCES>
+                    <fx:SIGNAL-INSTANCE ID="ID_{id}_{i}_S">
+                        <fx:SEQUENCE-NUMBER>0</fx:SEQUENCE-NUMBER>
+                        <fx:SIGNAL-REF ID-REF="{signal_ref}"/>
+                    </fx:SIGNAL-INSTANCE>
+                </fx:SIGNAL-INSTANCES>
+"#
+            ),
+        )?;
+    }
+    let mut extension = String::new();
+    if let Some(message_type) = &frame.message_type {
+        let (message_type, message_info) = match message_type {
+            MessageType::Log(level) => ("DLT_TYPE_LOG", log_level_info(*level)),
+            MessageType::ApplicationTrace(_) => ("DLT_TYPE_APP_TRACE", None),
+            MessageType::NetworkTrace(_) => ("DLT_TYPE_NW_TRACE", None),
+            MessageType::Control(_) | MessageType::Unknown(_) => ("DLT_TYPE_LOG", None),
+        };
+        writeln!(
+            extension,
+            "                    <MESSAGE_TYPE>{}</MESSAGE_TYPE>",
+            message_type
+        )?;
+        if let Some(message_info) = message_info {
+            writeln!(
+                extension,
+                "                    <MESSAGE_INFO>{}</MESSAGE_INFO>",
+                message_info
+            )?;
+        }
+    }
+    if let (Some(application_id), Some(context_id)) = (&frame.application_id, &frame.context_id) {
+        writeln!(
+            extension,
+            "                    <APPLICATION_ID>{}</APPLICATION_ID>\n                    <CONTEXT_ID>{}</CONTEXT_ID>",
+            escape(application_id),
+            escape(context_id)
+        )?;
+    }
+    write!(
+        frames,
+        r#"            <fx:FRAME ID="ID_{id}">
+                <ho:SHORT-NAME>{description}</ho:SHORT-NAME>
+                <fx:BYTE-LENGTH>{}</fx:BYTE-LENGTH>
+                <fx:FRAME-TYPE>OTHER</fx:FRAME-TYPE>
+                <fx:PDU-INSTANCES>
+{pdu_instances}                </fx:PDU-INSTANCES>
+                <fx:MANUFACTURER-EXTENSION>
+{extension}                </fx:MANUFACTURER-EXTENSION>
+            </fx:FRAME>
+"#,
+        frame.max_length
+    )
+}
+
+fn log_level_info(level: LogLevel) -> Option<String> {
+    match level {
+        LogLevel::Invalid(_) => None,
+        level => Some(format!("DLT_LOG_{}", level.to_string().to_uppercase())),
+    }
+}
+
+impl StatisticCollector for FibexSkeletonCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        let message_id = match statistic.nonverbose_id() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let length = statistic.payload.len() - 4;
+        let frame = self
+            .frames
+            .entry(message_id)
+            .or_insert_with(|| ObservedFrame {
+                message_id,
+                application_id: None,
+                context_id: None,
+                message_type: None,
+                min_length: length,
+                max_length: length,
+                count: 0,
+            });
+        frame.count += 1;
+        frame.min_length = frame.min_length.min(length);
+        frame.max_length = frame.max_length.max(length);
+        if let (None, Some(header)) = (&frame.message_type, &statistic.extended_header) {
+            frame.application_id = Some(header.application_id.to_string());
+            frame.context_id = Some(header.context_id.to_string());
+            frame.message_type = Some(header.message_type.clone());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,866 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # rapidly gather statistics info of a dlt source
+//!
+//! Besides the built-in `StatisticInfo`, custom statistics can be gathered by
+//! implementing a `StatisticCollector` and passing it to `collect_statistics`.
+//! The payload of the messages is not parsed, collectors only get the headers
+//! and the raw payload bytes.
+pub mod common;
+pub mod fibex;
+
+use crate::{
+    dlt::{
+        DltId, Endianness, ExtendedHeader, LogLevel, MessageType, PayloadContent, StandardHeader,
+        StorageHeader,
+    },
+    filtering::{FilterVerdict, ProcessedDltFilterConfig},
+    fmt::csv_field,
+    parse::{
+        dlt_consume_msg, dlt_extended_header, dlt_message_headers, dlt_payload_content,
+        dlt_standard_header, skip_till_after_next_storage_header, validated_payload_length,
+        DltParseError,
+    },
+    read::DltMessageReader,
+};
+use buf_redux::{policy::MinBuffered, BufReader as ReduxReader};
+use nom::bytes::streaming::take;
+use rustc_hash::FxHashMap;
+use std::{
+    convert::TryInto,
+    fs,
+    io::{BufRead, Read},
+    path::Path,
+};
+
+pub(crate) const BIN_READER_CAPACITY: usize = 10 * 1024 * 1024;
+pub(crate) const BIN_MIN_BUFFER_SPACE: usize = 10 * 1024;
+
+/// The headers and the raw payload of a single dlt message
+#[derive(Debug)]
+pub struct Statistic<'a> {
+    pub storage_header: Option<StorageHeader>,
+    pub standard_header: StandardHeader,
+    pub extended_header: Option<ExtendedHeader>,
+    pub payload: &'a [u8],
+}
+
+impl Statistic<'_> {
+    /// The log level of log messages
+    pub fn log_level(&self) -> Option<LogLevel> {
+        match self.extended_header.as_ref()?.message_type {
+            MessageType::Log(level) => Some(level),
+            _ => None,
+        }
+    }
+
+    /// Messages without an extended header are always non-verbose
+    pub fn is_verbose(&self) -> bool {
+        matches!(&self.extended_header, Some(h) if h.verbose)
+    }
+
+    /// The message id of non-verbose messages (the first 4 bytes of the payload)
+    pub fn nonverbose_id(&self) -> Option<u32> {
+        if self.is_verbose() {
+            return None;
+        }
+        if let Some(ExtendedHeader {
+            message_type: MessageType::Control(_),
+            ..
+        }) = self.extended_header
+        {
+            return None;
+        }
+        let bytes: [u8; 4] = self.payload.get(..4)?.try_into().ok()?;
+        Some(match self.standard_header.endianness {
+            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Parse the payload, which is skipped by `dlt_statistic`
+    pub fn payload_content(&self) -> Result<PayloadContent, DltParseError> {
+        dlt_payload_content(
+            &self.standard_header,
+            self.extended_header.as_ref(),
+            self.payload,
+        )
+    }
+}
+
+/// Gathers custom statistics from the messages of a dlt source
+pub trait StatisticCollector {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError>;
+}
+
+/// Passes each statistic to several collectors, so that they can all be
+/// filled in a single pass over the source
+///
+/// The collectors are borrowed, their results can be used once the
+/// `MultiCollector` is dropped.
+#[derive(Default)]
+pub struct MultiCollector<'a> {
+    collectors: Vec<&'a mut dyn StatisticCollector>,
+}
+
+impl<'a> MultiCollector<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register another collector
+    pub fn add(&mut self, collector: &'a mut dyn StatisticCollector) -> &mut Self {
+        self.collectors.push(collector);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.collectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collectors.is_empty()
+    }
+}
+
+impl StatisticCollector for MultiCollector<'_> {
+    fn collect_statistic(&mut self, statistic: &Statistic) -> Result<(), DltParseError> {
+        for collector in self.collectors.iter_mut() {
+            collector.collect_statistic(statistic)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the headers of the next DLT message in a byte array, the payload is not parsed
+pub fn dlt_statistic(
+    input: &[u8],
+    with_storage_header: bool,
+) -> Result<(&[u8], Statistic<'_>), DltParseError> {
+    let (rest, headers) = dlt_message_headers(input, None, with_storage_header)?;
+    Ok((
+        rest,
+        Statistic {
+            storage_header: headers.storage_header,
+            standard_header: headers.header,
+            extended_header: headers.extended_header,
+            payload: headers.payload,
+        },
+    ))
+}
+
+/// Read all messages from the reader and pass their statistic to the collector
+///
+/// Messages that can't be parsed are skipped. The progress hook and the
+/// cancellation token of the reader are used, see `DltMessageReader::set_progress_hook`.
+pub fn collect_statistics<S: Read>(
+    reader: &mut DltMessageReader<S>,
+    collector: &mut impl StatisticCollector,
+) -> Result<(), DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    loop {
+        let slice = reader.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(());
+        }
+        collect_message(slice, with_storage_header, collector)?;
+    }
+}
+
+/// Like `collect_statistics`, but for an async source
+#[cfg(feature = "stream")]
+pub async fn collect_statistics_async<S: futures::io::AsyncRead + Unpin>(
+    reader: &mut crate::stream::DltStreamReader<S>,
+    collector: &mut impl StatisticCollector,
+) -> Result<(), DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    loop {
+        let slice = reader.next_message_slice().await?;
+        if slice.is_empty() {
+            return Ok(());
+        }
+        collect_message(slice, with_storage_header, collector)?;
+    }
+}
+
+/// Like `collect_statistics`, but only messages that pass the filter are
+/// passed to the collector
+///
+/// The payload is only parsed for messages whose headers pass a filter with
+/// payload conditions, see `parse::dlt_message_headers`.
+pub fn collect_statistics_filtered<S: Read>(
+    reader: &mut DltMessageReader<S>,
+    filter_config: &ProcessedDltFilterConfig,
+    collector: &mut impl StatisticCollector,
+) -> Result<(), DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    loop {
+        let slice = reader.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(());
+        }
+        let headers = match dlt_message_headers(slice, Some(filter_config), with_storage_header) {
+            Ok((_, headers)) => headers,
+            Err(e) => {
+                debug!("stats...skip message that can't be parsed: {}", e);
+                continue;
+            }
+        };
+        let statistic = Statistic {
+            storage_header: headers.storage_header,
+            standard_header: headers.header,
+            extended_header: headers.extended_header,
+            payload: headers.payload,
+        };
+        let matches = match headers.verdict {
+            FilterVerdict::Matches => true,
+            FilterVerdict::FilteredOut => false,
+            FilterVerdict::NeedsPayload => match statistic.payload_content() {
+                Ok(payload) => filter_config.matches_payload(&payload),
+                Err(e) => {
+                    debug!("stats...skip message that can't be parsed: {}", e);
+                    false
+                }
+            },
+        };
+        if matches {
+            collector.collect_statistic(&statistic)?;
+        }
+    }
+}
+
+/// Like `collect_statistics`, but only every `n`th message is passed to the collector
+///
+/// The skipped messages are read but not parsed, so collectors only see
+/// about `1 / n` of the messages. An `n` of 0 or 1 collects all messages.
+pub fn collect_statistics_every_nth<S: Read>(
+    reader: &mut DltMessageReader<S>,
+    collector: &mut impl StatisticCollector,
+    n: usize,
+) -> Result<(), DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    let mut to_skip = 0;
+    loop {
+        let slice = reader.next_message_slice()?;
+        if slice.is_empty() {
+            return Ok(());
+        }
+        if to_skip == 0 {
+            collect_message(slice, with_storage_header, collector)?;
+            to_skip = n.max(1);
+        }
+        to_skip -= 1;
+    }
+}
+
+/// Collect the statistics of samples of a file with storage headers
+///
+/// The file is split into `samples` parts, from the start of each part
+/// messages of about `sample_length` bytes are collected. Each sample starts
+/// at a storage header, so big files can be roughly inspected without reading
+/// them completely.
+pub fn collect_statistics_sampled(
+    in_file: &Path,
+    collector: &mut impl StatisticCollector,
+    samples: usize,
+    sample_length: u64,
+) -> Result<(), DltParseError> {
+    use std::io::{Seek, SeekFrom};
+
+    let file_size = fs::metadata(in_file)?.len();
+    let starts = chunk_starts(in_file, file_size, samples.max(1))?;
+    let ends: Vec<u64> = starts.iter().skip(1).copied().chain([file_size]).collect();
+    for (start, end) in starts.into_iter().zip(ends) {
+        let mut f = fs::File::open(in_file)?;
+        f.seek(SeekFrom::Start(start))?;
+        let mut reader = DltMessageReader::new(f.take(end - start), true);
+        while reader.bytes_processed() < sample_length {
+            let slice = reader.next_message_slice()?;
+            if slice.is_empty() {
+                break;
+            }
+            collect_message(slice, true, collector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Collect the `StatisticInfo` of a file with storage headers using all cores
+///
+/// The file is split into chunks at storage header boundaries, the chunks are
+/// collected in parallel and their results are merged.
+#[cfg(feature = "rayon")]
+pub fn collect_statistics_parallel(in_file: &Path) -> Result<StatisticInfo, DltParseError> {
+    let chunks = collect_statistics_per_chunk(in_file, common::StatisticInfoCollector::new)?;
+    let mut info = StatisticInfo::new();
+    for collector in chunks {
+        info.merge(collector.statistic_info());
+    }
+    Ok(info)
+}
+
+/// Collect the statistics of a file with storage headers using all cores
+///
+/// The file is split into chunks at storage header boundaries, each chunk is
+/// collected by its own collector. The collectors are returned in the order
+/// of the chunks.
+#[cfg(feature = "rayon")]
+pub fn collect_statistics_per_chunk<C, F>(
+    in_file: &Path,
+    new_collector: F,
+) -> Result<Vec<C>, DltParseError>
+where
+    C: StatisticCollector + Send,
+    F: Fn() -> C + Sync,
+{
+    use rayon::prelude::*;
+    use std::io::{Seek, SeekFrom};
+
+    let file_size = fs::metadata(in_file)?.len();
+    let starts = chunk_starts(in_file, file_size, rayon::current_num_threads())?;
+    let ends: Vec<u64> = starts.iter().skip(1).copied().chain([file_size]).collect();
+    starts
+        .into_par_iter()
+        .zip(ends)
+        .map(|(start, end)| {
+            let mut f = fs::File::open(in_file)?;
+            f.seek(SeekFrom::Start(start))?;
+            let mut reader = DltMessageReader::new(f.take(end - start), true);
+            let mut collector = new_collector();
+            collect_statistics(&mut reader, &mut collector)?;
+            Ok(collector)
+        })
+        .collect()
+}
+
+/// Offsets of storage headers that split the file into about `count` chunks
+pub(crate) fn chunk_starts(
+    in_file: &Path,
+    file_size: u64,
+    count: usize,
+) -> Result<Vec<u64>, DltParseError> {
+    use crate::parse::forward_to_next_storage_header;
+    use std::io::{Seek, SeekFrom};
+
+    // large enough to contain at least one complete message
+    const WINDOW: u64 = 4 * u16::MAX as u64;
+    let mut f = fs::File::open(in_file)?;
+    let mut starts = vec![0];
+    let mut window = vec![];
+    for i in 1..count as u64 {
+        let offset = file_size * i / count as u64;
+        if offset <= *starts.last().unwrap_or(&0) {
+            continue;
+        }
+        f.seek(SeekFrom::Start(offset))?;
+        window.clear();
+        (&mut f).take(WINDOW).read_to_end(&mut window)?;
+        let at_eof = offset + window.len() as u64 == file_size;
+        let mut position = 0;
+        while let Some((skipped, _)) = forward_to_next_storage_header(&window[position..]) {
+            let candidate = position + skipped as usize;
+            if is_message_chain(&window, candidate, at_eof) {
+                starts.push(offset + candidate as u64);
+                break;
+            }
+            position = candidate + 1;
+        }
+    }
+    Ok(starts)
+}
+
+/// Check that the storage header pattern at `start` is followed by a chain of
+/// messages up to the end of the window, to not split at a pattern in a payload
+fn is_message_chain(window: &[u8], mut start: usize, at_eof: bool) -> bool {
+    use crate::{
+        dlt::{HEADER_MIN_LENGTH, STORAGE_HEADER_LENGTH},
+        parse::{parse_length, DLT_PATTERN},
+    };
+    loop {
+        if start == window.len() {
+            return at_eof;
+        }
+        if start > window.len() {
+            return !at_eof;
+        }
+        if !window[start..].starts_with(DLT_PATTERN) {
+            return false;
+        }
+        let header_start = start + STORAGE_HEADER_LENGTH as usize;
+        match window.get(header_start..).map(parse_length) {
+            Some(Ok((_, length))) if length >= HEADER_MIN_LENGTH as usize => {
+                start = header_start + length
+            }
+            // a message that is cut by the end of the window
+            Some(Err(nom::Err::Incomplete(_))) | None => return !at_eof,
+            _ => return false,
+        }
+    }
+}
+
+fn collect_message(
+    slice: &[u8],
+    with_storage_header: bool,
+    collector: &mut impl StatisticCollector,
+) -> Result<(), DltParseError> {
+    match dlt_statistic(slice, with_storage_header) {
+        Ok((_, statistic)) => collector.collect_statistic(&statistic),
+        Err(e) => {
+            debug!("stats...skip message that can't be parsed: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Parse out the `StatisticRowInfo` for the next DLT message in a byte array
+pub fn dlt_statistic_row_info(
+    input: &[u8],
+    with_storage_header: bool,
+) -> Result<(&[u8], StatisticRowInfo), DltParseError> {
+    let (after_storage_header, _) = if with_storage_header {
+        skip_till_after_next_storage_header(input)?
+    } else {
+        (input, 0)
+    };
+    let (after_storage_and_normal_header, header) = dlt_standard_header(after_storage_header)?;
+
+    let payload_length = match validated_payload_length(&header, input.len()) {
+        Ok(length) => length,
+        Err(_e) => {
+            return Ok((
+                after_storage_and_normal_header,
+                StatisticRowInfo {
+                    app_id_context_id: None,
+                    ecu_id: header.ecu_id,
+                    level: None,
+                    verbose: false,
+                },
+            ));
+        }
+    };
+    if !header.has_extended_header {
+        // no app id, skip rest
+        let (after_message, _) =
+            take::<u16, &[u8], DltParseError>(payload_length)(after_storage_and_normal_header)?;
+        return Ok((
+            after_message,
+            StatisticRowInfo {
+                app_id_context_id: None,
+                ecu_id: header.ecu_id,
+                level: None,
+                verbose: false,
+            },
+        ));
+    }
+
+    let (after_headers, extended_header) = dlt_extended_header(after_storage_and_normal_header)?;
+    // skip payload
+    let (after_message, _) = take::<u16, &[u8], DltParseError>(payload_length)(after_headers)?;
+    let level = match extended_header.message_type {
+        MessageType::Log(level) => Some(level),
+        _ => None,
+    };
+    Ok((
+        after_message,
+        StatisticRowInfo {
+            app_id_context_id: Some((extended_header.application_id, extended_header.context_id)),
+            ecu_id: header.ecu_id,
+            level,
+            verbose: extended_header.verbose,
+        },
+    ))
+}
+
+/// Shows how many messages per log level where found
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Default, Clone)]
+pub struct LevelDistribution {
+    pub non_log: usize,
+    pub log_fatal: usize,
+    pub log_error: usize,
+    pub log_warning: usize,
+    pub log_info: usize,
+    pub log_debug: usize,
+    pub log_verbose: usize,
+    pub log_invalid: usize,
+}
+
+impl LevelDistribution {
+    pub fn new(level: Option<LogLevel>) -> LevelDistribution {
+        let all_zero = Default::default();
+        match level {
+            None => LevelDistribution {
+                non_log: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Fatal) => LevelDistribution {
+                log_fatal: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Error) => LevelDistribution {
+                log_error: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Warn) => LevelDistribution {
+                log_warning: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Info) => LevelDistribution {
+                log_info: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Debug) => LevelDistribution {
+                log_debug: 1,
+                ..all_zero
+            },
+            Some(LogLevel::Verbose) => LevelDistribution {
+                log_verbose: 1,
+                ..all_zero
+            },
+            _ => LevelDistribution {
+                log_invalid: 1,
+                ..all_zero
+            },
+        }
+    }
+
+    pub fn merge(&mut self, outside: &LevelDistribution) {
+        self.non_log += outside.non_log;
+        self.log_fatal += outside.log_fatal;
+        self.log_error += outside.log_error;
+        self.log_warning += outside.log_warning;
+        self.log_info += outside.log_info;
+        self.log_debug += outside.log_debug;
+        self.log_verbose += outside.log_verbose;
+        self.log_invalid += outside.log_invalid;
+    }
+
+    /// The counts as comma separated values, in the order of `LEVEL_CSV_HEADER`
+    fn csv_values(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.non_log,
+            self.log_fatal,
+            self.log_error,
+            self.log_warning,
+            self.log_info,
+            self.log_debug,
+            self.log_verbose,
+            self.log_invalid
+        )
+    }
+}
+
+const LEVEL_CSV_HEADER: &str = "non_log,fatal,error,warning,info,debug,verbose,invalid";
+
+type IdMap = FxHashMap<String, LevelDistribution>;
+
+/// Includes the `LevelDistribution` for all `app-ids`, `context-ids` and
+/// `ecu_ids`
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug)]
+pub struct StatisticInfo {
+    pub app_ids: Vec<(String, LevelDistribution)>,
+    pub context_ids: Vec<(String, LevelDistribution)>,
+    pub ecu_ids: Vec<(String, LevelDistribution)>,
+    pub contained_non_verbose: bool,
+}
+
+impl StatisticInfo {
+    pub fn new() -> Self {
+        Self {
+            app_ids: vec![],
+            context_ids: vec![],
+            ecu_ids: vec![],
+            contained_non_verbose: false,
+        }
+    }
+
+    pub fn merge(&mut self, stat: StatisticInfo) {
+        StatisticInfo::merge_levels(&mut self.app_ids, stat.app_ids);
+        StatisticInfo::merge_levels(&mut self.context_ids, stat.context_ids);
+        StatisticInfo::merge_levels(&mut self.ecu_ids, stat.ecu_ids);
+        self.contained_non_verbose = self.contained_non_verbose || stat.contained_non_verbose;
+    }
+
+    /// The statistic as JSON
+    #[cfg(feature = "serde-support")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// The level distributions as CSV, one line per id
+    ///
+    /// The first column tells the kind of the id (`app`, `context` or `ecu`).
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("kind,id,{}\n", LEVEL_CSV_HEADER);
+        for (kind, ids) in [
+            ("app", &self.app_ids),
+            ("context", &self.context_ids),
+            ("ecu", &self.ecu_ids),
+        ] {
+            for (id, levels) in ids {
+                csv.push_str(&format!(
+                    "{},{},{}\n",
+                    kind,
+                    csv_field(id),
+                    levels.csv_values()
+                ));
+            }
+        }
+        csv
+    }
+
+    fn merge_levels(
+        owner: &mut Vec<(String, LevelDistribution)>,
+        incomes: Vec<(String, LevelDistribution)>,
+    ) {
+        incomes.iter().for_each(|(income_id, income)| {
+            if let Some((_, existed)) = owner.iter_mut().find(|(owner_id, _)| owner_id == income_id)
+            {
+                existed.merge(income);
+            } else {
+                owner.push((income_id.to_owned(), income.clone()));
+            }
+        });
+    }
+}
+
+impl Default for StatisticInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stats about a row in a DLT file
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug)]
+pub struct StatisticRowInfo {
+    pub app_id_context_id: Option<(DltId, DltId)>,
+    pub ecu_id: Option<DltId>,
+    pub level: Option<LogLevel>,
+    pub verbose: bool,
+}
+
+/// Read in a DLT file and collect some statistics about it
+pub fn collect_dlt_stats(in_file: &Path) -> Result<StatisticInfo, DltParseError> {
+    let f = fs::File::open(in_file)?;
+
+    let mut reader = ReduxReader::with_capacity(BIN_READER_CAPACITY, f)
+        .set_policy(MinBuffered(BIN_MIN_BUFFER_SPACE));
+
+    let mut app_ids: IdMap = FxHashMap::default();
+    let mut context_ids: IdMap = FxHashMap::default();
+    let mut ecu_ids: IdMap = FxHashMap::default();
+    let mut contained_non_verbose = false;
+    loop {
+        match read_one_dlt_message_info(&mut reader, true) {
+            Ok(Some((
+                consumed,
+                StatisticRowInfo {
+                    app_id_context_id: Some((app_id, context_id)),
+                    ecu_id: ecu,
+                    level,
+                    verbose,
+                },
+            ))) => {
+                contained_non_verbose = contained_non_verbose || !verbose;
+                reader.consume(consumed as usize);
+                add_for_level(level, &mut app_ids, &app_id);
+                add_for_level(level, &mut context_ids, &context_id);
+                match ecu {
+                    Some(id) => add_for_level(level, &mut ecu_ids, &id),
+                    None => add_for_level(level, &mut ecu_ids, "NONE"),
+                };
+            }
+            Ok(Some((
+                consumed,
+                StatisticRowInfo {
+                    app_id_context_id: None,
+                    ecu_id: ecu,
+                    level,
+                    verbose,
+                },
+            ))) => {
+                contained_non_verbose = contained_non_verbose || !verbose;
+                reader.consume(consumed as usize);
+                add_for_level(level, &mut app_ids, "NONE");
+                add_for_level(level, &mut context_ids, "NONE");
+                match ecu {
+                    Some(id) => add_for_level(level, &mut ecu_ids, &id),
+                    None => add_for_level(level, &mut ecu_ids, "NONE"),
+                };
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                // we couldn't parse the message. try to skip it and find the next.
+                debug!("stats...try to skip and continue parsing: {}", e);
+                match e {
+                    DltParseError::ParsingHickup(reason) => {
+                        // we couldn't parse the message. try to skip it and find the next.
+                        reader.consume(4); // at least skip the magic DLT pattern
+                        debug!(
+                            "error parsing 1 dlt message, try to continue parsing: {}",
+                            reason
+                        );
+                    }
+                    _ => return Err(e),
+                }
+            }
+        }
+    }
+    let res = StatisticInfo {
+        app_ids: app_ids
+            .into_iter()
+            .collect::<Vec<(String, LevelDistribution)>>(),
+        context_ids: context_ids
+            .into_iter()
+            .collect::<Vec<(String, LevelDistribution)>>(),
+        ecu_ids: ecu_ids
+            .into_iter()
+            .collect::<Vec<(String, LevelDistribution)>>(),
+        contained_non_verbose,
+    };
+    Ok(res)
+}
+
+fn read_one_dlt_message_info<T: Read>(
+    reader: &mut ReduxReader<T, MinBuffered>,
+    with_storage_header: bool,
+) -> Result<Option<(u64, StatisticRowInfo)>, DltParseError> {
+    match reader.fill_buf() {
+        Ok(content) => {
+            if content.is_empty() {
+                return Ok(None);
+            }
+            let available = content.len();
+            let r = dlt_statistic_row_info(content, with_storage_header)?;
+            let consumed = available - r.0.len();
+            Ok(Some((consumed as u64, r.1)))
+        }
+        Err(e) => Err(DltParseError::ParsingHickup(format!(
+            "error while parsing dlt messages: {}",
+            e
+        ))),
+    }
+}
+
+fn add_for_level(level: Option<LogLevel>, ids: &mut IdMap, id: &str) {
+    if let Some(n) = ids.get_mut(id) {
+        match level {
+            Some(LogLevel::Fatal) => {
+                *n = LevelDistribution {
+                    log_fatal: n.log_fatal + 1,
+                    ..*n
+                }
+            }
+            Some(LogLevel::Error) => {
+                *n = LevelDistribution {
+                    log_error: n.log_error + 1,
+                    ..*n
+                }
+            }
+            Some(LogLevel::Warn) => {
+                *n = LevelDistribution {
+                    log_warning: n.log_warning + 1,
+                    ..*n
+                }
+            }
+            Some(LogLevel::Info) => {
+                *n = LevelDistribution {
+                    log_info: n.log_info + 1,
+                    ..*n
+                }
+            }
+            Some(LogLevel::Debug) => {
+                *n = LevelDistribution {
+                    log_debug: n.log_debug + 1,
+                    ..*n
+                };
+            }
+            Some(LogLevel::Verbose) => {
+                *n = LevelDistribution {
+                    log_verbose: n.log_verbose + 1,
+                    ..*n
+                };
+            }
+            Some(LogLevel::Invalid(_)) => {
+                *n = LevelDistribution {
+                    log_invalid: n.log_invalid + 1,
+                    ..*n
+                };
+            }
+            None => {
+                *n = LevelDistribution {
+                    non_log: n.non_log + 1,
+                    ..*n
+                };
+            }
+        }
+    } else {
+        ids.insert(id.to_string(), LevelDistribution::new(level));
+    }
+}
+
+/// Count the dlt messages in a file. This assumes that messages are stored with using a `StorageHeader`
+pub fn count_dlt_messages(input: &Path) -> Result<u64, DltParseError> {
+    if input.exists() {
+        let f = fs::File::open(input)?;
+
+        let mut reader = ReduxReader::with_capacity(BIN_READER_CAPACITY, f)
+            .set_policy(MinBuffered(BIN_MIN_BUFFER_SPACE));
+
+        let mut msg_cnt: u64 = 0;
+        loop {
+            match reader.fill_buf() {
+                Ok(content) => {
+                    if content.is_empty() {
+                        break;
+                    }
+                    if let Ok((_rest, Some(consumed))) = dlt_consume_msg(content) {
+                        reader.consume(consumed as usize);
+                        msg_cnt += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    trace!("no more content");
+                    return Err(DltParseError::Unrecoverable(format!(
+                        "error for filling buffer with dlt messages: {:?}",
+                        e
+                    )));
+                }
+            }
+        }
+        Ok(msg_cnt)
+    } else {
+        Err(DltParseError::Unrecoverable(format!(
+            "Couldn't find dlt file: {:?}",
+            input
+        )))
+    }
+}
@@ -0,0 +1,325 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Reading dlt messages from an async byte source
+//!
+//! `stream` is the async counterpart of `read`: it reads single dlt messages
+//! from any `futures::io::AsyncRead` source (e.g. a socket or an async file).
+//! Tokio sources can be adapted with `tokio_util::compat`.
+use crate::{
+    filtering::ProcessedDltFilterConfig,
+    parse::{dlt_message, DltParseError, ParsedMessage},
+    read::{
+        message_length, out_of_sync, prefix_length, FilterHandle, ReaderStats, TAIL_CHUNK_LENGTH,
+    },
+};
+use futures::{
+    channel::mpsc,
+    future::Future,
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, BufReader},
+    stream::{self, BoxStream, Stream, StreamExt},
+    SinkExt,
+};
+use std::{
+    io::{ErrorKind, SeekFrom},
+    pin::Pin,
+};
+
+/// Read the next message from the reader and parse it
+///
+/// Returns `None` when the end of the source is reached.
+pub async fn read_message<S: AsyncRead + Unpin>(
+    reader: &mut DltStreamReader<S>,
+    filter_config_opt: Option<&ProcessedDltFilterConfig>,
+) -> Result<Option<ParsedMessage>, DltParseError> {
+    let with_storage_header = reader.with_storage_header();
+    let slice = reader.next_message_slice().await?;
+    if slice.is_empty() {
+        return Ok(None);
+    }
+    let result = dlt_message(slice, filter_config_opt, with_storage_header).map(|(_, m)| m);
+    reader.stats.count_parsed(&result);
+    Ok(Some(result?))
+}
+
+/// Reads the bytes of single dlt messages from an async source
+pub struct DltStreamReader<S: AsyncRead + Unpin> {
+    source: BufReader<S>,
+    with_storage_header: bool,
+    buffer: Vec<u8>,
+    filter: FilterHandle,
+    stats: ReaderStats,
+}
+
+impl<S: AsyncRead + Unpin> DltStreamReader<S> {
+    /// Create a reader for a source that contains messages with or without storage headers
+    pub fn new(source: S, with_storage_header: bool) -> Self {
+        DltStreamReader {
+            source: BufReader::new(source),
+            with_storage_header,
+            buffer: Vec::with_capacity(prefix_length(true) + u16::MAX as usize),
+            filter: FilterHandle::default(),
+            stats: ReaderStats::default(),
+        }
+    }
+
+    /// A handle to the filter that is used by `DltStreamReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltStreamReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Read and parse the next message using the current filter
+    ///
+    /// Returns `None` when the end of the source is reached.
+    pub async fn next_message(&mut self) -> Result<Option<ParsedMessage>, DltParseError> {
+        let filter = self.filter.get();
+        read_message(self, filter.as_deref()).await
+    }
+
+    /// Invalid and filtered messages so far, see `DltMessageReader::stats`
+    pub fn stats(&self) -> &ReaderStats {
+        &self.stats
+    }
+
+    pub fn with_storage_header(&self) -> bool {
+        self.with_storage_header
+    }
+
+    /// Feed the messages, parsed with the current filter, into a bounded channel
+    ///
+    /// The returned future does the reading and has to be spawned on the
+    /// executor of the caller, it is `Send` if the source is. It waits while
+    /// `capacity` messages are queued,
+    /// ends with the messages or when the receiver is dropped, and gives back
+    /// the reader.
+    pub fn into_channel(
+        mut self,
+        capacity: usize,
+    ) -> (
+        mpsc::Receiver<Result<ParsedMessage, DltParseError>>,
+        impl Future<Output = Self>,
+    ) {
+        let (mut sender, receiver) = mpsc::channel(capacity);
+        let producer = async move {
+            loop {
+                let result = self.next_message().await;
+                let resume = matches!(result, Ok(Some(_)) | Err(DltParseError::ParsingHickup(_)));
+                let result = match result {
+                    Ok(Some(message)) => Ok(message),
+                    Ok(None) => break,
+                    Err(e) => Err(e),
+                };
+                if sender.send(result).await.is_err() || !resume {
+                    break;
+                }
+            }
+            self
+        };
+        (receiver, producer)
+    }
+
+    /// Read the bytes of the next message (including the storage header if present)
+    ///
+    /// Returns an empty slice when the end of the source is reached.
+    pub async fn next_message_slice(&mut self) -> Result<&[u8], DltParseError> {
+        let prefix_length = prefix_length(self.with_storage_header);
+        self.buffer.resize(prefix_length, 0);
+        if !read_or_eof(&mut self.source, &mut self.buffer).await? {
+            self.buffer.clear();
+            return Ok(&self.buffer);
+        }
+        let length = self.buffered_message_length()?;
+        self.buffer.resize(length, 0);
+        if !read_or_eof(&mut self.source, &mut self.buffer[prefix_length..]).await? {
+            return Err(DltParseError::IncompleteParse {
+                needed: std::num::NonZeroUsize::new(length - prefix_length),
+            });
+        }
+        Ok(&self.buffer)
+    }
+
+    /// Like `DltMessageReader::buffered_message_length`
+    fn buffered_message_length(&mut self) -> Result<usize, DltParseError> {
+        message_length(&self.buffer, self.with_storage_header).map_err(|e| {
+            self.stats.invalid_messages += 1;
+            out_of_sync(e)
+        })
+    }
+}
+
+impl<S: AsyncRead + Unpin + Send> DltStreamReader<S> {
+    /// Stream of the messages parsed with the current filter, the async
+    /// counterpart of `DltMessageReader::messages`
+    ///
+    /// The stream continues after messages that could not be parsed and ends
+    /// after the first error that leaves the source in an unknown state.
+    /// The stream is `Send`, so it can be moved to other tasks.
+    pub fn messages(&mut self) -> BoxStream<'_, Result<ParsedMessage, DltParseError>> {
+        stream::unfold(Some(self), |reader| async move {
+            let reader = reader?;
+            match reader.next_message().await {
+                Ok(Some(message)) => Some((Ok(message), Some(reader))),
+                Ok(None) => None,
+                Err(e) => {
+                    let resume = matches!(e, DltParseError::ParsingHickup(_));
+                    Some((Err(e), resume.then_some(reader)))
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> DltStreamReader<S> {
+    /// Like `DltMessageReader::skip_messages`
+    pub async fn skip_messages(&mut self, n: usize) -> Result<usize, DltParseError> {
+        let prefix_length = prefix_length(self.with_storage_header);
+        for skipped in 0..n {
+            self.buffer.resize(prefix_length, 0);
+            if !read_or_eof(&mut self.source, &mut self.buffer).await? {
+                self.buffer.clear();
+                return Ok(skipped);
+            }
+            let length = self.buffered_message_length()?;
+            Pin::new(&mut self.source)
+                .seek_relative((length - prefix_length) as i64)
+                .await?;
+        }
+        Ok(n)
+    }
+
+    /// Like `DltMessageReader::seek_to_offset`
+    pub async fn seek_to_offset(&mut self, offset: u64) -> Result<(), DltParseError> {
+        self.source.seek(SeekFrom::Start(offset)).await?;
+        Ok(())
+    }
+}
+
+/// Async counterpart of `DltTailReader` for sources that are still written
+///
+/// At the end of the source, the reader awaits the next item of
+/// `notifications` before it reads again, so any growth signal can be used,
+/// e.g. events of a file system watcher or the ticks of a runtime's interval
+/// timer. Reading ends when the notifications end; dropping the future
+/// of `next_message` cancels the waiting.
+pub struct DltStreamTailReader<S: AsyncRead + Unpin, N: Stream + Unpin> {
+    source: S,
+    with_storage_header: bool,
+    notifications: N,
+    filter: FilterHandle,
+    pending: Vec<u8>,
+    consumed: usize,
+}
+
+impl<S: AsyncRead + Unpin, N: Stream + Unpin> DltStreamTailReader<S, N> {
+    pub fn new(source: S, with_storage_header: bool, notifications: N) -> Self {
+        DltStreamTailReader {
+            source,
+            with_storage_header,
+            notifications,
+            filter: FilterHandle::default(),
+            pending: vec![],
+            consumed: 0,
+        }
+    }
+
+    /// A handle to the filter that is used by `DltStreamTailReader::next_message`
+    pub fn filter_handle(&self) -> FilterHandle {
+        self.filter.clone()
+    }
+
+    /// Replace the filter that is used by `DltStreamTailReader::next_message`
+    pub fn set_filter(&self, filter_config_opt: Option<ProcessedDltFilterConfig>) {
+        self.filter.set(filter_config_opt);
+    }
+
+    /// Wait for the next message and parse it using the current filter
+    ///
+    /// Returns `None` when the notifications end before a complete message
+    /// is available.
+    pub async fn next_message(&mut self) -> Result<Option<ParsedMessage>, DltParseError> {
+        let with_storage_header = self.with_storage_header;
+        let filter = self.filter.get();
+        let slice = self.next_message_slice().await?;
+        if slice.is_empty() {
+            return Ok(None);
+        }
+        let (_, message) = dlt_message(slice, filter.as_deref(), with_storage_header)?;
+        Ok(Some(message))
+    }
+
+    /// Wait for the bytes of the next message (including the storage header if present)
+    ///
+    /// Partly written messages are handled like in `DltTailReader::next_message_slice`.
+    /// Returns an empty slice when the notifications end.
+    pub async fn next_message_slice(&mut self) -> Result<&[u8], DltParseError> {
+        let prefix_length = prefix_length(self.with_storage_header);
+        loop {
+            let available = &self.pending[self.consumed..];
+            if available.len() >= prefix_length {
+                match message_length(available, self.with_storage_header) {
+                    Ok(length) if length <= available.len() => {
+                        let start = self.consumed;
+                        self.consumed += length;
+                        return Ok(&self.pending[start..self.consumed]);
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        self.pending.clear();
+                        self.consumed = 0;
+                        return Err(e);
+                    }
+                }
+            }
+            self.pending.drain(..self.consumed);
+            self.consumed = 0;
+            let read = (&mut self.source)
+                .take(TAIL_CHUNK_LENGTH)
+                .read_to_end(&mut self.pending)
+                .await?;
+            if read == 0 && self.notifications.next().await.is_none() {
+                return Ok(&[]);
+            }
+        }
+    }
+}
+
+/// Fill the buffer completely, `false` if the source has no more data
+///
+/// A source that ends in the middle of the buffer is reported as incomplete.
+async fn read_or_eof<S: AsyncRead + Unpin>(
+    source: &mut S,
+    buffer: &mut [u8],
+) -> Result<bool, DltParseError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match source.read(&mut buffer[filled..]).await {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(DltParseError::IncompleteParse {
+                    needed: std::num::NonZeroUsize::new(buffer.len() - filled),
+                })
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,582 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        control::*,
+        dlt::*,
+        parse::{dlt_message, ParsedMessage},
+        service_id::*,
+    };
+
+    fn control_message(
+        control_type: ControlType,
+        endianness: Endianness,
+        payload: &[u8],
+    ) -> Message {
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(5),
+                payload: PayloadContent::ControlMsg(
+                    ControlType::from_value(payload[0]),
+                    payload[1..].to_vec().into(),
+                ),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Control(control_type),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        match dlt_message(&message.as_bytes(), None, false) {
+            Ok((_, ParsedMessage::Item(parsed))) => parsed,
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_requests() {
+        #[rustfmt::skip]
+        let set_log_level = control_message(ControlType::Request, Endianness::Little, &[
+            0x01, 0x00, 0x00, 0x00,
+            b'A', b'P', b'P', 0x00, b'C', b'T', b'X', 0x00,
+            0x04,
+            b'r', b'e', b'm', b'o',
+        ]);
+        assert_eq!(
+            ControlPayload::decode(&set_log_level),
+            Ok(Some(ControlPayload::Request(ControlRequest::SetLogLevel {
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+                log_level: ControlLogLevel::Level(LogLevel::Info),
+                com_interface: "remo".to_string(),
+            })))
+        );
+
+        #[rustfmt::skip]
+        let set_default_trace_status = control_message(ControlType::Request, Endianness::Big, &[
+            0x00, 0x00, 0x00, 0x12, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(
+            ControlPayload::decode(&set_default_trace_status),
+            Ok(Some(ControlPayload::Request(
+                ControlRequest::SetDefaultTraceStatus {
+                    trace_status: TraceStatus::Default,
+                    com_interface: String::new(),
+                }
+            )))
+        );
+
+        let vendor = control_message(
+            ControlType::Request,
+            Endianness::Big,
+            &[0x00, 0x00, 0x0f, 0x04, 0x2a],
+        );
+        assert_eq!(
+            ControlPayload::decode(&vendor),
+            Ok(Some(ControlPayload::Request(ControlRequest::Other {
+                service_id: ServiceId::UserDefined(0xf04),
+                parameters: vec![0x2a],
+            })))
+        );
+    }
+
+    #[test]
+    fn test_decode_responses() {
+        let log_level = control_message(
+            ControlType::Response,
+            Endianness::Big,
+            &[0x00, 0x00, 0x00, 0x04, 0x00, 0x00],
+        );
+        assert_eq!(
+            ControlPayload::decode(&log_level),
+            Ok(Some(ControlPayload::Response(ControlResponse {
+                service_id: GET_DEFAULT_LOG_LEVEL.into(),
+                status: ResponseStatus::Ok,
+                content: ResponseContent::LogLevel(ControlLogLevel::Off),
+            })))
+        );
+
+        #[rustfmt::skip]
+        let channels = control_message(ControlType::Response, Endianness::Little, &[
+            0x17, 0x00, 0x00, 0x00, 0x00,
+            0x02, b'D', b'L', b'T', b'1', b'D', b'L', b'T', b'2',
+        ]);
+        let payload = ControlPayload::decode(&channels)
+            .expect("decode")
+            .expect("control");
+        assert_eq!(payload.service_id(), GET_LOG_CHANNEL_NAMES.into());
+        assert_eq!(
+            payload,
+            ControlPayload::Response(ControlResponse {
+                service_id: GET_LOG_CHANNEL_NAMES.into(),
+                status: ResponseStatus::Ok,
+                content: ResponseContent::LogChannelNames(vec![
+                    "DLT1".to_string(),
+                    "DLT2".to_string()
+                ]),
+            })
+        );
+
+        // parameters of failed responses are not decoded
+        let not_supported = control_message(
+            ControlType::Response,
+            Endianness::Little,
+            &[0x04, 0x00, 0x00, 0x00, 0x01],
+        );
+        assert!(matches!(
+            ControlPayload::decode(&not_supported),
+            Ok(Some(ControlPayload::Response(ControlResponse {
+                status: ResponseStatus::NotSupported,
+                content: ResponseContent::None,
+                ..
+            })))
+        ));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let truncated = control_message(
+            ControlType::Request,
+            Endianness::Little,
+            &[0x01, 0x00, 0x00, 0x00, b'A', b'P'],
+        );
+        assert_eq!(
+            ControlPayload::decode(&truncated),
+            Err(crate::control::Error::Incomplete {
+                what: "control parameters",
+                needed: 4,
+                available: 2,
+            })
+        );
+        assert_eq!(
+            ControlPayload::parse(&ControlType::Unknown(3), &[0; 4], Endianness::Big),
+            Err(crate::control::Error::UnknownControlType(3))
+        );
+        let mut log = truncated.clone();
+        log.extended_header.as_mut().unwrap().message_type = MessageType::Log(LogLevel::Info);
+        assert_eq!(ControlPayload::decode(&log), Ok(None));
+    }
+
+    #[test]
+    fn test_decode_get_log_info() {
+        #[rustfmt::skip]
+        let with_descriptions = control_message(ControlType::Response, Endianness::Little, &[
+            0x03, 0x00, 0x00, 0x00, 0x07,
+            /* applications */ 0x02, 0x00,
+            b'A', b'P', b'P', b'1', /* contexts */ 0x02, 0x00,
+            b'C', b'T', b'X', b'1', 0x04, 0x01, 0x03, 0x00, b'c', b't', b'x',
+            b'C', b'T', b'X', b'2', 0xff, 0xff, 0x00, 0x00,
+            /* description */ 0x04, 0x00, b'a', b'p', b'p', b'1',
+            b'A', b'P', b'P', b'2', /* contexts */ 0x00, 0x00,
+            /* description */ 0x00, 0x00,
+            b'r', b'e', b'm', b'o',
+        ]);
+        assert_eq!(
+            ControlPayload::decode(&with_descriptions),
+            Ok(Some(ControlPayload::Response(ControlResponse {
+                service_id: GET_LOG_INFO.into(),
+                status: ResponseStatus::LogInfo(7),
+                content: ResponseContent::LogInfo(LogInfo {
+                    applications: vec![
+                        ApplicationLogInfo {
+                            application_id: "APP1".to_string(),
+                            contexts: vec![
+                                ContextLogInfo {
+                                    context_id: "CTX1".to_string(),
+                                    log_level: Some(ControlLogLevel::Level(LogLevel::Info)),
+                                    trace_status: Some(TraceStatus::On),
+                                    description: Some("ctx".to_string()),
+                                },
+                                ContextLogInfo {
+                                    context_id: "CTX2".to_string(),
+                                    log_level: Some(ControlLogLevel::Default),
+                                    trace_status: Some(TraceStatus::Default),
+                                    description: Some(String::new()),
+                                },
+                            ],
+                            description: Some("app1".to_string()),
+                        },
+                        ApplicationLogInfo {
+                            application_id: "APP2".to_string(),
+                            contexts: vec![],
+                            description: Some(String::new()),
+                        },
+                    ],
+                    com_interface: "remo".to_string(),
+                }),
+            })))
+        );
+
+        #[rustfmt::skip]
+        let log_levels = control_message(ControlType::Response, Endianness::Big, &[
+            0x00, 0x00, 0x00, 0x03, 0x04,
+            0x00, 0x01, b'A', b'P', b'P', b'1',
+            0x00, 0x01, b'C', b'T', b'X', b'1', 0x06,
+            b'r', b'e', b'm', b'o',
+        ]);
+        let Ok(Some(ControlPayload::Response(ControlResponse {
+            content: ResponseContent::LogInfo(log_info),
+            ..
+        }))) = ControlPayload::decode(&log_levels)
+        else {
+            panic!("no log info");
+        };
+        assert_eq!(
+            log_info.applications[0].contexts[0],
+            ContextLogInfo {
+                context_id: "CTX1".to_string(),
+                log_level: Some(ControlLogLevel::Level(LogLevel::Verbose)),
+                trace_status: None,
+                description: None,
+            }
+        );
+        assert_eq!(log_info.applications[0].description, None);
+
+        let no_matching_context = control_message(
+            ControlType::Response,
+            Endianness::Little,
+            &[0x03, 0x00, 0x00, 0x00, 0x08],
+        );
+        assert!(matches!(
+            ControlPayload::decode(&no_matching_context),
+            Ok(Some(ControlPayload::Response(ControlResponse {
+                status: ResponseStatus::NoMatchingContexts,
+                content: ResponseContent::None,
+                ..
+            })))
+        ));
+    }
+
+    #[test]
+    fn test_decode_get_software_version() {
+        let version = b"DLT Package Version: 2.18.8 STABLE\0";
+        let payload = [
+            &[0x13, 0x00, 0x00, 0x00, 0x00][..],
+            &(version.len() as u32).to_le_bytes(),
+            version,
+        ]
+        .concat();
+        let response = control_message(ControlType::Response, Endianness::Little, &payload);
+        let decoded = ControlPayload::decode(&response)
+            .expect("decode")
+            .expect("control");
+        assert_eq!(
+            decoded.software_version(),
+            Some("DLT Package Version: 2.18.8 STABLE")
+        );
+
+        let truncated = control_message(
+            ControlType::Response,
+            Endianness::Little,
+            &payload[..payload.len() - 5],
+        );
+        assert!(ControlPayload::decode(&truncated).is_err());
+        let request = control_message(
+            ControlType::Request,
+            Endianness::Little,
+            &[0x13, 0x00, 0x00, 0x00],
+        );
+        let decoded = ControlPayload::decode(&request)
+            .expect("decode")
+            .expect("control");
+        assert_eq!(
+            decoded,
+            ControlPayload::Request(ControlRequest::GetSoftwareVersion)
+        );
+        assert_eq!(decoded.software_version(), None);
+    }
+
+    #[test]
+    fn test_service_ids() {
+        assert_eq!(ServiceId::from(0x13), ServiceId::Standard(0x13));
+        assert_eq!(ServiceId::from(0xeff), ServiceId::Standard(0xeff));
+        assert_eq!(ServiceId::from(0xf04), ServiceId::UserDefined(0xf04));
+        assert_eq!(ServiceId::from(0x1000), ServiceId::UserDefined(0x1000));
+        assert_eq!(
+            ServiceId::Standard(0x10).name(),
+            Some("set_use_extended_header")
+        );
+        assert_eq!(ServiceId::Standard(0x0eff).to_string(), "0xeff");
+        assert_eq!(service_id_lookup(0xF04), None);
+
+        const VENDOR: VendorServices = &[(0x1000, "vendor_reset", "Reset the vendor module")];
+        register_vendor_services(DLT_DAEMON_SERVICES);
+        register_vendor_services(VENDOR);
+        assert_eq!(
+            service_id_lookup(0xF04),
+            Some(("marker", "Marker set by the client"))
+        );
+        assert_eq!(
+            service_id_lookup(0x01).map(|(name, _)| name),
+            Some("set_log_level")
+        );
+        assert_eq!(ServiceId::from(0x1000).to_string(), "vendor_reset");
+        assert_eq!(
+            service_id_lookup(0x1000),
+            Some(("vendor_reset", "Reset the vendor module"))
+        );
+        assert_eq!(ServiceId::from(0x1001).name(), None);
+        clear_vendor_services();
+        assert_eq!(ServiceId::from(0x1000).name(), None);
+    }
+
+    #[test]
+    fn test_encode_requests() {
+        let set_log_level = ControlRequest::SetLogLevel {
+            application_id: "APP".to_string(),
+            context_id: "CTX".to_string(),
+            log_level: ControlLogLevel::Level(LogLevel::Debug),
+            com_interface: "remote".to_string(),
+        };
+        #[rustfmt::skip]
+        assert_eq!(
+            set_log_level.as_bytes(Endianness::Little),
+            vec![
+                0x01, 0x00, 0x00, 0x00,
+                b'A', b'P', b'P', 0x00, b'C', b'T', b'X', 0x00,
+                0x05,
+                b'r', b'e', b'm', b'o',
+            ]
+        );
+
+        let requests = vec![
+            ControlRequest::SetLogLevel {
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+                log_level: ControlLogLevel::Default,
+                com_interface: "remo".to_string(),
+            },
+            ControlRequest::SetTraceStatus {
+                application_id: "APP1".to_string(),
+                context_id: "CTX1".to_string(),
+                trace_status: TraceStatus::Off,
+                com_interface: String::new(),
+            },
+            ControlRequest::GetLogInfo {
+                options: 7,
+                application_id: String::new(),
+                context_id: String::new(),
+                com_interface: "remo".to_string(),
+            },
+            ControlRequest::SetDefaultLogLevel {
+                log_level: ControlLogLevel::Off,
+                com_interface: String::new(),
+            },
+            ControlRequest::SetDefaultTraceStatus {
+                trace_status: TraceStatus::On,
+                com_interface: String::new(),
+            },
+            ControlRequest::StoreConfiguration,
+            ControlRequest::RestoreToFactoryDefault,
+            ControlRequest::SetMessageFiltering { enabled: true },
+            ControlRequest::GetTraceStatus {
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            },
+            ControlRequest::SetLogChannelAssignment {
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+                log_channel: "DLT1".to_string(),
+                add: true,
+            },
+            ControlRequest::SetLogChannelThreshold {
+                log_channel: "DLT1".to_string(),
+                log_level: ControlLogLevel::Level(LogLevel::Warn),
+                trace_status: TraceStatus::Default,
+            },
+            ControlRequest::GetLogChannelThreshold {
+                log_channel: "DLT1".to_string(),
+            },
+            ControlRequest::Other {
+                service_id: ServiceId::UserDefined(0xf04),
+                parameters: vec![1, 2, 3],
+            },
+        ];
+        for endianness in [Endianness::Big, Endianness::Little] {
+            for request in &requests {
+                let message = Message::new(
+                    MessageConfig {
+                        version: 1,
+                        counter: 0,
+                        endianness,
+                        ecu_id: None,
+                        session_id: None,
+                        timestamp: None,
+                        payload: request.payload(endianness),
+                        extended_header_info: Some(ExtendedHeaderConfig {
+                            message_type: MessageType::Control(ControlType::Request),
+                            app_id: "APP".to_string(),
+                            context_id: "CTX".to_string(),
+                        }),
+                    },
+                    None,
+                );
+                let parsed = match dlt_message(&message.as_bytes(), None, false) {
+                    Ok((_, ParsedMessage::Item(parsed))) => parsed,
+                    other => panic!("unexpected parse result: {:?}", other),
+                };
+                assert_eq!(
+                    ControlPayload::decode(&parsed),
+                    Ok(Some(ControlPayload::Request(request.clone())))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_response_status() {
+        let statuses = [
+            (SET_LOG_LEVEL, 0, ResponseStatus::Ok, "ok"),
+            (
+                SET_LOG_LEVEL,
+                1,
+                ResponseStatus::NotSupported,
+                "not_supported",
+            ),
+            (SET_LOG_LEVEL, 2, ResponseStatus::Error, "error"),
+            (
+                SET_LOG_LEVEL,
+                3,
+                ResponseStatus::PermissionDenied,
+                "perm_denied",
+            ),
+            (SET_LOG_LEVEL, 4, ResponseStatus::Warning, "warning"),
+            (SET_LOG_LEVEL, 7, ResponseStatus::Other(7), "07"),
+            (GET_LOG_INFO, 2, ResponseStatus::Error, "error"),
+            (GET_LOG_INFO, 3, ResponseStatus::LogInfo(3), "03"),
+            (
+                GET_LOG_INFO,
+                8,
+                ResponseStatus::NoMatchingContexts,
+                "no_matching_contexts",
+            ),
+            (GET_LOG_INFO, 9, ResponseStatus::Overflow, "overflow"),
+        ];
+        for (service_id, value, status, text) in statuses {
+            assert_eq!(ResponseStatus::new(service_id, value), status);
+            assert_eq!(status.value(), value);
+            assert_eq!(status.to_string(), text);
+        }
+        assert!(ResponseStatus::LogInfo(7).is_ok());
+        assert!(!ResponseStatus::Warning.is_ok());
+    }
+
+    #[test]
+    fn test_control_text() {
+        let texts: Vec<(ControlType, &[u8], &str)> = vec![
+            (
+                ControlType::Response,
+                &[0x01, 0x00, 0x00, 0x00, 0x00],
+                "[set_log_level ok]",
+            ),
+            (
+                ControlType::Response,
+                &[0x04, 0x00, 0x00, 0x00, 0x01],
+                "[get_default_log_level not_supported]",
+            ),
+            (
+                ControlType::Request,
+                &[0x12, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00],
+                "[set_default_trace_status] 01 00 00 00 00",
+            ),
+            (ControlType::Request, &[0x04, 0x0f, 0x00, 0x00], "[0xf04]"),
+            (
+                ControlType::Response,
+                &[
+                    0x13, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, b'1', b'.', b'0',
+                ],
+                "[get_software_version ok] 1.0",
+            ),
+            (
+                ControlType::Response,
+                &[0x03, 0x00, 0x00, 0x00, 0x07, 0x01],
+                "[get_log_info 07] 01",
+            ),
+        ];
+        for (control_type, payload, text) in texts {
+            let message = control_message(control_type, Endianness::Little, payload);
+            assert_eq!(control_text(&message).as_deref(), Some(text));
+        }
+        let mut log = control_message(ControlType::Request, Endianness::Little, &[0x05]);
+        assert_eq!(control_text(&log).as_deref(), Some("[] 05"));
+        log.extended_header.as_mut().unwrap().message_type = MessageType::Log(LogLevel::Info);
+        assert_eq!(control_text(&log), None);
+    }
+
+    fn assert_endianness_round_trip(message: Message) {
+        let decoded = ControlPayload::decode(&message).expect("decoded");
+        let converted = message.clone().with_endianness(Endianness::Big);
+        let reparsed = match dlt_message(&converted.as_bytes(), None, false) {
+            Ok((_, ParsedMessage::Item(parsed))) => parsed,
+            other => panic!("unexpected parse result: {:?}", other),
+        };
+        assert_eq!(reparsed.header.endianness, Endianness::Big);
+        assert_eq!(ControlPayload::decode(&reparsed), Ok(decoded));
+        assert_eq!(reparsed.with_endianness(Endianness::Little), message);
+    }
+
+    #[test]
+    fn test_request_with_endianness() {
+        #[rustfmt::skip]
+        let set_log_level = control_message(ControlType::Request, Endianness::Little, &[
+            0x01, 0x00, 0x00, 0x00,
+            b'A', b'P', b'P', 0x00, b'C', b'T', b'X', 0x00,
+            0x04,
+            b'r', b'e', b'm', b'o',
+        ]);
+        let converted = set_log_level.clone().with_endianness(Endianness::Big);
+        assert_eq!(
+            converted.payload,
+            PayloadContent::ControlMsg(
+                ControlType::from_value(0x00),
+                b"\x00\x00\x01APP\x00CTX\x00\x04remo".to_vec().into()
+            )
+        );
+        assert_endianness_round_trip(set_log_level);
+    }
+
+    #[test]
+    fn test_response_with_endianness() {
+        #[rustfmt::skip]
+        let log_info = control_message(ControlType::Response, Endianness::Little, &[
+            0x03, 0x00, 0x00, 0x00, 0x07,
+            /* applications */ 0x01, 0x00,
+            b'A', b'P', b'P', b'1', /* contexts */ 0x01, 0x00,
+            b'C', b'T', b'X', b'1', 0x04, 0x01, 0x03, 0x00, b'c', b't', b'x',
+            /* description */ 0x04, 0x00, b'a', b'p', b'p', b'1',
+            b'r', b'e', b'm', b'o',
+        ]);
+        let converted = log_info.clone().with_endianness(Endianness::Big);
+        #[rustfmt::skip]
+        assert_eq!(
+            converted.payload,
+            PayloadContent::ControlMsg(ControlType::from_value(0x00), vec![
+                0x00, 0x00, 0x03, 0x07,
+                0x00, 0x01,
+                b'A', b'P', b'P', b'1', 0x00, 0x01,
+                b'C', b'T', b'X', b'1', 0x04, 0x01, 0x00, 0x03, b'c', b't', b'x',
+                0x00, 0x04, b'a', b'p', b'p', b'1',
+                b'r', b'e', b'm', b'o',
+            ].into())
+        );
+        assert_endianness_round_trip(log_info);
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+mod tests {
+    use crate::{
+        dlf::{Error, *},
+        dlt::*,
+        filtering::DltFilterConfig,
+        tests::TestMessage,
+    };
+    use pretty_assertions::assert_eq;
+
+    const DLT_VIEWER_FILTERS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<dltfilter>
+    <filter>
+        <type>0</type>
+        <name>app</name>
+        <ecuid></ecuid>
+        <applicationid>APP</applicationid>
+        <contextid></contextid>
+        <enablefilter>1</enablefilter>
+        <enableecuid>0</enableecuid>
+        <enableapplicationid>1</enableapplicationid>
+        <enablecontextid>0</enablecontextid>
+        <enableLogLevelMax>1</enableLogLevelMax>
+        <enableLogLevelMin>0</enableLogLevelMin>
+        <filterColour>#000000</filterColour>
+        <logLevelMax>4</logLevelMax>
+        <logLevelMin>0</logLevelMin>
+    </filter>
+    <filter>
+        <type>0</type>
+        <name>context</name>
+        <applicationid>NAV</applicationid>
+        <contextid>CTX</contextid>
+        <enableapplicationid>1</enableapplicationid>
+        <enablecontextid>1</enablecontextid>
+        <enableLogLevelMax>1</enableLogLevelMax>
+        <logLevelMax>2</logLevelMax>
+    </filter>
+    <filter>
+        <type>0</type>
+        <name>disabled</name>
+        <applicationid>OFF</applicationid>
+        <enablefilter>0</enablefilter>
+        <enableapplicationid>1</enableapplicationid>
+    </filter>
+</dltfilter>
+"#;
+
+    #[test]
+    fn test_parse_dlt_viewer_filters() {
+        let filters = DlfParser::parse_filters(DLT_VIEWER_FILTERS.as_bytes()).expect("parse");
+        assert_eq!(filters.len(), 3);
+        assert_eq!(
+            filters[0],
+            DlfFilter {
+                name: "app".to_string(),
+                app_id: Some("APP".to_string()),
+                log_level_max: Some(4),
+                ..Default::default()
+            }
+        );
+        assert!(!filters[2].enabled);
+
+        let config = DlfFilters::from(filters.clone()).to_filter_config();
+        assert_eq!(
+            config.app_ids,
+            Some(vec!["APP".to_string(), "NAV".to_string()])
+        );
+        assert_eq!(config.context_ids, None);
+        assert_eq!(config.min_log_level, None);
+        assert_eq!(config.app_id_log_levels, Some(vec![("APP".to_string(), 4)]));
+        assert_eq!(
+            config.context_id_log_levels,
+            Some(vec![("CTX".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_filter_config() {
+        let config = DltFilterConfig::builder()
+            .min_log_level(LogLevel::Warn)
+            .app_ids(["APP1", "APP2"])
+            .ecu_ids(["ECU"])
+            .build()
+            .expect("valid config");
+        let mut writer = DlfWriter::new(Vec::new());
+        writer.write_config(&config).expect("write");
+        let written = writer.into_inner();
+
+        let filters = DlfParser::parse_filters(&written[..]).expect("parse");
+        assert_eq!(filters.len(), 2);
+        let read = DlfFilters::from(filters.clone()).to_filter_config();
+        assert_eq!(read.min_log_level, config.min_log_level);
+        assert_eq!(read.app_ids, config.app_ids);
+        assert_eq!(read.ecu_ids, config.ecu_ids);
+        assert_eq!(read.context_ids, None);
+    }
+
+    #[test]
+    fn test_write_per_id_log_levels() {
+        let config = DltFilterConfig::builder()
+            .min_log_level(LogLevel::Info)
+            .app_id_log_level("DBG", LogLevel::Debug)
+            .app_id_log_level("QUIE", LogLevel::Error)
+            .build()
+            .expect("valid config");
+        let filters = DlfWriter::<Vec<u8>>::to_filters(&config).expect("convert");
+        assert_eq!(
+            filters,
+            vec![
+                DlfFilter {
+                    log_level_max: Some(4),
+                    ..Default::default()
+                },
+                DlfFilter {
+                    app_id: Some("DBG".to_string()),
+                    log_level_max: Some(5),
+                    ..Default::default()
+                },
+                DlfFilter {
+                    app_id: Some("QUIE".to_string()),
+                    log_level_max: Some(2),
+                    ..Default::default()
+                },
+                DlfFilter {
+                    filter_type: DlfFilterType::Negative,
+                    app_id: Some("QUIE".to_string()),
+                    log_level_min: Some(3),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        let with_time_range = DltFilterConfig {
+            min_timestamp: Some(DltTimeStamp::from_ms(0)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            DlfWriter::<Vec<u8>>::to_filters(&with_time_range),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_text_filters() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+<dltfilter>
+    <filter>
+        <type>0</type>
+        <headertext>ECU1</headertext>
+        <payloadtext>connection lost</payloadtext>
+        <enableheadertext>1</enableheadertext>
+        <enablepayloadtext>1</enablepayloadtext>
+    </filter>
+    <filter>
+        <type>0</type>
+        <headertext>unused</headertext>
+        <payloadtext>timeout</payloadtext>
+        <enableheadertext>0</enableheadertext>
+        <enablepayloadtext>1</enablepayloadtext>
+    </filter>
+</dltfilter>
+"#;
+        let filters = DlfParser::parse_filters(input.as_bytes()).expect("parse");
+        assert_eq!(filters[0].header_text, Some("ECU1".to_string()));
+        assert_eq!(filters[0].payload_text, Some("connection lost".to_string()));
+        assert_eq!(filters[1].header_text, None);
+
+        let config = DlfFilters::from(filters.clone()).to_filter_config();
+        assert_eq!(config.header_texts, None);
+        assert_eq!(
+            config.payload_texts,
+            Some(vec!["connection lost".to_string(), "timeout".to_string()])
+        );
+
+        let mut writer = DlfWriter::new(Vec::new());
+        writer.write_config(&config).expect("write");
+        let written = writer.into_inner();
+        assert_eq!(
+            DlfParser::parse(&written[..])
+                .expect("parse")
+                .to_filter_config()
+                .payload_texts,
+            config.payload_texts
+        );
+    }
+
+    fn log_message(app_id: &str, level: LogLevel) -> Message {
+        TestMessage::new(app_id)
+            .level(level)
+            .timestamp(None)
+            .verbose()
+            .build()
+    }
+
+    #[test]
+    fn test_filter_types() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+<dltfilter>
+    <filter>
+        <type>1</type>
+        <applicationid>NOIS</applicationid>
+        <enableapplicationid>1</enableapplicationid>
+    </filter>
+    <filter>
+        <type>2</type>
+        <enableLogLevelMax>1</enableLogLevelMax>
+        <logLevelMax>2</logLevelMax>
+    </filter>
+    <filter>
+        <type>0</type>
+        <enableLogLevelMax>1</enableLogLevelMax>
+        <logLevelMax>4</logLevelMax>
+    </filter>
+</dltfilter>
+"#;
+        let filters = DlfParser::parse(input.as_bytes()).expect("parse");
+        assert_eq!(filters.positive.len(), 1);
+        assert_eq!(filters.negative.len(), 1);
+        assert_eq!(filters.markers.len(), 1);
+
+        // negative filters are not used as allow-lists
+        let config = filters.to_filter_config();
+        assert_eq!(config.app_ids, None);
+        assert_eq!(config.min_log_level, Some(4));
+
+        assert!(filters.selects(&log_message("APP", LogLevel::Info)));
+        assert!(!filters.selects(&log_message("APP", LogLevel::Debug)));
+        assert!(!filters.selects(&log_message("NOIS", LogLevel::Info)));
+        assert!(filters.is_marked(&log_message("APP", LogLevel::Error)));
+        assert!(!filters.is_marked(&log_message("APP", LogLevel::Warn)));
+
+        assert!(matches!(
+            DlfParser::parse("<dltfilter><filter><type>5</type></filter></dltfilter>".as_bytes()),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_control_message_filters() {
+        let hide_control = r#"<?xml version="1.0" encoding="UTF-8"?>
+<dltfilter>
+    <filter>
+        <type>1</type>
+        <enablectrlmsgs>1</enablectrlmsgs>
+    </filter>
+</dltfilter>
+"#;
+        let filters = DlfParser::parse(hide_control.as_bytes()).expect("parse");
+        assert!(filters.negative[0].control_messages);
+        let config = filters.to_filter_config();
+        assert_eq!(config.control_messages, Some(false));
+
+        let control = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: None,
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::ControlMsg(ControlType::Response, vec![0x0].into()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Control(ControlType::Response),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        assert!(!filters.selects(&control));
+        assert!(filters.selects(&log_message("APP", LogLevel::Info)));
+
+        // the config is written back as negative control message filter
+        let mut writer = DlfWriter::new(Vec::new());
+        writer.write_config(&config).expect("write");
+        let written = writer.into_inner();
+        assert_eq!(
+            DlfParser::parse(&written[..])
+                .expect("parse")
+                .to_filter_config()
+                .control_messages,
+            Some(false)
+        );
+    }
+}
@@ -16,17 +16,17 @@ mod tests {
     use crate::{
         dlt::*,
         parse::{
-            dlt_argument, dlt_consume_msg, dlt_extended_header, dlt_message, dlt_standard_header,
-            dlt_storage_header, dlt_type_info, dlt_zero_terminated_string,
-            forward_to_next_storage_header, parse_ecu_id, DltParseError, ParsedMessage,
-            DLT_PATTERN,
+            dlt_argument, dlt_borrowed_arguments, dlt_consume_msg, dlt_extended_header,
+            dlt_message, dlt_standard_header, dlt_storage_header, dlt_type_info,
+            dlt_zero_terminated_string, forward_to_next_storage_header, parse_ecu_id,
+            DltParseError, ParsedMessage, DLT_PATTERN,
         },
         proptest_strategies::*,
     };
     use core::num::NonZeroUsize;
     use nom::IResult;
     use proptest::prelude::*;
-    use std::io::Write;
+    use std::{borrow::Cow, io::Write};
 
     use byteorder::{BigEndian, LittleEndian};
     use bytes::BytesMut;
@@ -202,6 +202,20 @@ mod tests {
                 }
                 let msg_bytes = msg.as_bytes();
                 assert_eq!(raw1, msg_bytes);
+
+                let big_endian_msg = msg.with_endianness(Endianness::Big);
+                let big_endian_bytes = big_endian_msg.as_bytes();
+                assert_eq!(0x3f, big_endian_bytes[16]); // MSBF set in header-type
+                assert_eq!(
+                    &[0x00, 0x00, 0x04, 0x00, 0x00, 0x0a],
+                    &big_endian_bytes[42..48] // type rawd + len(16bit) of arg 1
+                );
+                match dlt_message(&big_endian_bytes[..], None, true) {
+                    Ok((_rest, ParsedMessage::Item(parsed))) => {
+                        assert_eq!(big_endian_msg, parsed);
+                    }
+                    _ => panic!("could not parse big endian message"),
+                }
             }
             _ => panic!("could not parse message"),
         }
@@ -325,7 +339,7 @@ mod tests {
 
     #[test]
     fn test_ecu_id_parser() {
-        let expected: IResult<&[u8], &str, DltParseError> = Ok((&[], "ecu1"));
+        let expected: IResult<&[u8], DltId, DltParseError> = Ok((&[], DltId::from_bytes(*b"ecu1")));
         assert_eq!(expected, parse_ecu_id(b"ecu1"));
         assert_eq!(
             Err(nom::Err::Incomplete(nom::Needed::Size(
@@ -447,6 +461,17 @@ mod tests {
                 Ok((b"----", ParsedMessage::Item(msg)));
             assert_eq!(expected, dlt_message(&msg_bytes, None, false));
         }
+        #[test]
+        fn test_message_with_endianness_to_bytes_to_message(msg in message_strat(), endianness in any::<Endianness>()) {
+            init_logging();
+            let converted = msg.with_endianness(endianness);
+            assert_eq!(endianness, converted.header.endianness);
+            let mut msg_bytes = converted.as_bytes();
+            msg_bytes.extend(b"----");
+            let expected: Result<(&[u8], ParsedMessage), DltParseError>  =
+                Ok((b"----", ParsedMessage::Item(converted)));
+            assert_eq!(expected, dlt_message(&msg_bytes, None, false));
+        }
     }
 
     fn dump_to_file(msg_bytes: &[u8]) -> std::io::Result<()> {
@@ -459,18 +484,22 @@ mod tests {
     #[test]
     fn test_parse_msg() {
         init_logging();
-        let payload = PayloadContent::Verbose(vec![Argument {
-            type_info: TypeInfo {
-                kind: TypeInfoKind::Unsigned(TypeLength::BitLength32),
-                coding: StringCoding::UTF8,
-                has_variable_info: true,
-                has_trace_info: false,
-            },
-            name: Some("UcbfX".to_string()),
-            unit: Some("seconds".to_string()),
-            fixed_point: None,
-            value: Value::U32(2_063_359_909),
-        }]);
+        let payload = PayloadContent::Verbose(
+            [Argument {
+                type_info: TypeInfo {
+                    kind: TypeInfoKind::Unsigned(TypeLength::BitLength32),
+                    coding: StringCoding::UTF8,
+                    has_variable_info: true,
+                    has_trace_info: false,
+                },
+                name: Some("UcbfX".to_string()),
+                unit: Some("seconds".to_string()),
+                fixed_point: None,
+                value: Value::U32(2_063_359_909),
+            }]
+            .into_iter()
+            .collect(),
+        );
         let msg_conf = MessageConfig {
             version: 0,
             endianness: Endianness::Big,
@@ -497,6 +526,50 @@ mod tests {
         assert_eq!(expected, res);
     }
 
+    #[test]
+    fn test_borrowed_arguments() {
+        let argument = Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::Unsigned(TypeLength::BitLength16),
+                coding: StringCoding::UTF8,
+                has_variable_info: true,
+                has_trace_info: false,
+            },
+            name: Some("speed".to_string()),
+            unit: Some("km/h".to_string()),
+            fixed_point: None,
+            value: Value::U16(42),
+        };
+        let msg = Message::new(
+            MessageConfig {
+                version: 1,
+                endianness: Endianness::Little,
+                counter: 0,
+                ecu_id: None,
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::Verbose([argument.clone()].into_iter().collect()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Info),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        let msg_bytes = msg.as_bytes();
+        let payload = &msg_bytes[msg_bytes.len() - msg.header.payload_length as usize..];
+        let arguments =
+            dlt_borrowed_arguments(&msg.header, msg.extended_header.as_ref(), payload).unwrap();
+        assert_eq!(1, arguments.len());
+        assert!(matches!(arguments[0].name, Some(Cow::Borrowed("speed"))));
+        assert!(matches!(arguments[0].unit, Some(Cow::Borrowed("km/h"))));
+        assert_eq!(argument, arguments[0].clone().into_owned());
+
+        let no_arguments = dlt_borrowed_arguments(&msg.header, None, payload).unwrap();
+        assert!(no_arguments.is_empty());
+    }
+
     #[test]
     fn test_parse_offending_argument() {
         let type_info = TypeInfo {
@@ -856,7 +929,7 @@ mod tests {
         let mut buf = BytesMut::with_capacity(4);
         buf.extend_from_slice(b"id42");
         let res = dlt_zero_terminated_string(&buf, 4);
-        let expected: Result<(&[u8], &str), DltParseError> = Ok((&[], "id42"));
+        let expected: Result<(&[u8], Cow<str>), DltParseError> = Ok((&[], Cow::Borrowed("id42")));
         assert_eq!(expected, res);
     }
     #[test]
@@ -864,7 +937,7 @@ mod tests {
         let mut buf = BytesMut::with_capacity(6);
         buf.extend_from_slice(b"id42++");
         let res = dlt_zero_terminated_string(&buf, 4);
-        let expected: Result<(&[u8], &str), DltParseError> = Ok((b"++", "id42"));
+        let expected: Result<(&[u8], Cow<str>), DltParseError> = Ok((b"++", Cow::Borrowed("id42")));
         assert_eq!(expected, res);
     }
     #[test]
@@ -877,7 +950,7 @@ mod tests {
         ));
         buf.clear();
         buf.extend_from_slice(b"id\0\0");
-        let expected: Result<(&[u8], &str), DltParseError> = Ok((b"", "id"));
+        let expected: Result<(&[u8], Cow<str>), DltParseError> = Ok((b"", Cow::Borrowed("id")));
         assert_eq!(expected, dlt_zero_terminated_string(&buf, 4));
     }
     #[test]
@@ -886,7 +959,8 @@ mod tests {
         buf.extend_from_slice(b"id4\0somethingelse");
         let res = dlt_zero_terminated_string(&buf, 4);
         trace!("res : {:?}", res);
-        let expected: Result<(&[u8], &str), DltParseError> = Ok((b"somethingelse", "id4"));
+        let expected: Result<(&[u8], Cow<str>), DltParseError> =
+            Ok((b"somethingelse", Cow::Borrowed("id4")));
         assert_eq!(expected, res);
     }
     #[test]
@@ -895,7 +969,9 @@ mod tests {
         let broken = vec![0x41, 0, 146, 150];
         buf.extend_from_slice(&broken);
         let res = dlt_zero_terminated_string(&buf, 4);
-        let expected: Result<(&[u8], &str), DltParseError> = Ok((b"", "A"));
+        let expected: Result<(&[u8], Cow<str>), DltParseError> = Ok((b"", Cow::Borrowed("A")));
         assert_eq!(expected, res);
+        let (_, cut) = dlt_zero_terminated_string(&[0x41, 146, 0, 0], 4).unwrap();
+        assert_eq!(Cow::Borrowed("A"), cut);
     }
 }
@@ -62,7 +62,7 @@ mod tests {
             has_extended_header: true,
             message_counter: 0x33,
             payload_length: 0x1,
-            ecu_id: Some("abc".to_string()),
+            ecu_id: Some(DltId::new("abc")),
             session_id: None,
             timestamp: Some(5),
         };
@@ -84,8 +84,8 @@ mod tests {
             argument_count: 1,
             verbose: true,
             message_type: MessageType::Log(LogLevel::Invalid(1)),
-            application_id: "abc".to_string(),
-            context_id: "CON".to_string(),
+            application_id: DltId::new("abc"),
+            context_id: DltId::new("CON"),
         };
         assert!(!extended_header.skip_with_level(LogLevel::Verbose));
         assert!(!extended_header.skip_with_level(LogLevel::Invalid(0)));
@@ -99,8 +99,8 @@ mod tests {
             argument_count: 1,
             verbose: true,
             message_type: MessageType::Log(LogLevel::Debug),
-            application_id: "abc".to_string(),
-            context_id: "CON".to_string(),
+            application_id: DltId::new("abc"),
+            context_id: DltId::new("CON"),
         };
         assert!(!extended_header.skip_with_level(LogLevel::Verbose));
         assert!(!extended_header.skip_with_level(LogLevel::Debug));
@@ -112,8 +112,8 @@ mod tests {
             argument_count: 1,
             verbose: true,
             message_type: MessageType::Control(ControlType::Request),
-            application_id: "abc".to_string(),
-            context_id: "CON".to_string(),
+            application_id: DltId::new("abc"),
+            context_id: DltId::new("CON"),
         };
         // other message types should not be fitered
         assert!(!extended_header.skip_with_level(LogLevel::Fatal));
@@ -125,8 +125,8 @@ mod tests {
             argument_count: 2,
             verbose: true,
             message_type: MessageType::Log(LogLevel::Warn),
-            application_id: "abc".to_string(),
-            context_id: "CON".to_string(),
+            application_id: DltId::new("abc"),
+            context_id: DltId::new("CON"),
         };
         assert_eq!(
             vec![
@@ -153,7 +153,7 @@ mod tests {
         };
         let storage_header = StorageHeader {
             timestamp,
-            ecu_id: "abc".to_string(),
+            ecu_id: DltId::new("abc"),
         };
         assert_eq!(
             vec![
@@ -563,4 +563,211 @@ mod tests {
         expected.extend(vec![0xD, 0xE, 0xA, 0xD]);
         assert_eq!(expected, argument.as_bytes::<BigEndian>());
     }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_message_json_roundtrip() {
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 5,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: Some(0x17f),
+                timestamp: Some(1000),
+                payload: PayloadContent::Verbose(
+                    [
+                        Argument {
+                            type_info: TypeInfo {
+                                kind: TypeInfoKind::StringType,
+                                coding: StringCoding::UTF8,
+                                has_variable_info: false,
+                                has_trace_info: false,
+                            },
+                            name: None,
+                            unit: None,
+                            fixed_point: None,
+                            value: Value::StringVal("speed".to_string()),
+                        },
+                        Argument {
+                            type_info: TypeInfo {
+                                kind: TypeInfoKind::SignedFixedPoint(FloatWidth::Width32),
+                                coding: StringCoding::ASCII,
+                                has_variable_info: true,
+                                has_trace_info: false,
+                            },
+                            name: Some("v".to_string()),
+                            unit: Some("km/h".to_string()),
+                            fixed_point: Some(FixedPoint {
+                                quantization: 0.5,
+                                offset: FixedPointValue::I32(-10),
+                            }),
+                            value: Value::I32(240),
+                        },
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Warn),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            Some(StorageHeader {
+                timestamp: DltTimeStamp::from_ms(1_600_000_000_000),
+                ecu_id: DltId::new("ECU1"),
+            }),
+        );
+        let json = serde_json::to_string(&message).expect("serialize message");
+        let deserialized: Message = serde_json::from_str(&json).expect("deserialize message");
+        assert_eq!(message, deserialized);
+    }
+
+    #[test]
+    fn test_dlt_id() {
+        use std::convert::TryFrom;
+        let id = DltId::try_from("ECU").expect("valid id");
+        assert_eq!(id.as_bytes(), b"ECU\0");
+        assert_eq!(id.len(), 3);
+        assert_eq!(id, "ECU");
+        assert_eq!(id, "ECU".to_string());
+        assert_eq!(id.to_string(), "ECU");
+        assert_eq!("ECU1".parse::<DltId>().expect("valid id"), "ECU1");
+        assert!(DltId::try_from("TOOLONG").is_err());
+        assert!(DltId::default().is_empty());
+        assert_eq!(
+            DltId::from_bytes(*b"AB\0\0"),
+            DltId::try_from("AB").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_payload_text_and_arguments() {
+        let argument = |value: Value| Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::StringType,
+                coding: StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            fixed_point: None,
+            value,
+        };
+        let verbose = Message {
+            payload: PayloadContent::Verbose(
+                [
+                    argument(Value::StringVal("speed".to_string())),
+                    argument(Value::U8(42)),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Message::default()
+        };
+        assert_eq!(verbose.payload_text(), "speed 42");
+        let values: Vec<_> = verbose.arguments().map(|arg| &arg.value).collect();
+        assert_eq!(
+            values,
+            [&Value::StringVal("speed".to_string()), &Value::U8(42)]
+        );
+
+        let non_verbose = Message {
+            payload: PayloadContent::NonVerbose(7, vec![0xab, 0x01].into()),
+            ..Message::default()
+        };
+        assert_eq!(non_verbose.payload_text(), "[7] ab 01");
+        assert_eq!(non_verbose.arguments().count(), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn log_level_display_from_str_roundtrip(level: LogLevel) {
+            assert_eq!(level, level.to_string().parse::<LogLevel>().unwrap());
+            assert_eq!(level, LogLevel::from_value(level.value()));
+        }
+        #[test]
+        fn message_type_display_from_str_roundtrip(message_type: MessageType) {
+            assert_eq!(message_type, message_type.to_string().parse::<MessageType>().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_log_level_from_str() {
+        assert_eq!("warn".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+        assert_eq!("WARNING".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+        assert_eq!(" Info ".parse::<LogLevel>().unwrap(), LogLevel::Info);
+        assert_eq!("1".parse::<LogLevel>().unwrap(), LogLevel::Fatal);
+        assert_eq!("9".parse::<LogLevel>().unwrap(), LogLevel::Invalid(9));
+        assert!("loud".parse::<LogLevel>().is_err());
+        assert!("16".parse::<LogLevel>().is_err());
+        assert!(LogLevel::Fatal < LogLevel::Warn);
+        assert!(LogLevel::Verbose < LogLevel::Invalid(7));
+        assert_eq!(
+            "log error".parse::<MessageType>().unwrap(),
+            MessageType::Log(LogLevel::Error)
+        );
+        assert_eq!(
+            "control response".parse::<MessageType>().unwrap(),
+            MessageType::Control(ControlType::Response)
+        );
+        assert!("log".parse::<MessageType>().is_err());
+    }
+
+    #[test]
+    fn test_timestamp_ordering() {
+        let ts = DltTimeStamp::from_ms(1_500);
+        assert!(DltTimeStamp::from_ms(999) < ts);
+        assert!(DltTimeStamp::from_ms(2_000) > ts);
+    }
+
+    #[test]
+    fn test_timestamp_from_us() {
+        // the microseconds were multiplied by 10^6 (and overflowed)
+        let ts = DltTimeStamp::from_us(1_000_250);
+        assert_eq!(ts.seconds, 1);
+        assert_eq!(ts.microseconds, 250);
+        assert_eq!(
+            DltTimeStamp::from_us(1_500_000),
+            DltTimeStamp::from_ms(1_500)
+        );
+        assert_eq!(DltTimeStamp::from_us(999_999).as_us(), 999_999);
+    }
+
+    #[test]
+    fn test_network_trace_round_trip() {
+        use crate::parse::{dlt_message, ParsedMessage};
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::NetworkTrace(vec![vec![1, 2].into(), vec![3].into()]),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::NetworkTrace(NetworkTraceType::Ipc),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        // the slices are written as raw verbose arguments
+        let extended_header = message.extended_header.as_ref().expect("extended header");
+        assert!(extended_header.verbose);
+        assert_eq!(extended_header.argument_count, 2);
+        let bytes = message.as_bytes();
+        match dlt_message(&bytes, None, false) {
+            Ok((rest, ParsedMessage::Item(parsed))) => {
+                assert!(rest.is_empty());
+                assert_eq!(parsed, message);
+                assert_eq!(parsed.as_bytes(), bytes);
+            }
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,529 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dlt::*, export::*, filtering::DltFilterConfig, fmt::Column, progress::Progress,
+        tests::TestMessage,
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn log_message(ecu_id: &str, app_id: &str, storage_ms: u64, counter: u8) -> Message {
+        TestMessage::new(app_id)
+            .ecu_id(ecu_id)
+            .counter(counter)
+            .stored(DltTimeStamp::from_ms(storage_ms), ecu_id)
+            .build()
+    }
+
+    fn to_bytes(messages: &[Message]) -> Vec<u8> {
+        messages.iter().flat_map(|m| m.as_bytes()).collect()
+    }
+
+    #[test]
+    fn test_filter_copy() {
+        let messages: Vec<Message> = ["APP1", "APP2", "APP1", "APP3"]
+            .iter()
+            .enumerate()
+            .map(|(i, app_id)| log_message("ECU1", app_id, i as u64, i as u8))
+            .collect();
+        let input = [
+            to_bytes(&messages[..1]),
+            b"junk".to_vec(),
+            to_bytes(&messages[1..]),
+        ]
+        .concat();
+        let filter = DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .build()
+            .expect("valid config");
+        let reports = Arc::new(Mutex::new(Vec::<Progress>::new()));
+        let reports_hook = reports.clone();
+        let options = ExportOptions::new()
+            .recover(true)
+            .progress(Some(input.len() as u64), move |progress| {
+                reports_hook.lock().expect("lock").push(progress)
+            });
+
+        let mut output = vec![];
+        let stats = filter_copy(input.as_slice(), &mut output, &filter, options).expect("export");
+        assert_eq!(
+            output,
+            to_bytes(&[messages[0].clone(), messages[2].clone()])
+        );
+        assert_eq!(
+            stats,
+            ExportStats {
+                read_messages: 4,
+                written_messages: 2,
+                written_bytes: output.len() as u64,
+                filtered_messages: 2,
+                invalid_messages: 0,
+                duplicate_messages: 0,
+                bytes_skipped: 4,
+            }
+        );
+        let reports = reports.lock().expect("lock");
+        assert_eq!(reports.len(), 4);
+        assert_eq!(reports[3].fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn test_filter_copy_invalid_filter() {
+        let filter = DltFilterConfig {
+            app_ids: Some(vec![]),
+            ..DltFilterConfig::default()
+        };
+        assert!(matches!(
+            filter_copy(&[][..], vec![], &filter, ExportOptions::new()),
+            Err(crate::export::Error::Filter(_))
+        ));
+    }
+
+    #[test]
+    fn test_split_file() {
+        let messages = vec![
+            log_message("ECU1", "APP", 0, 0),
+            log_message("ECU2", "APP", 400, 1),
+            log_message("ECU1", "APP", 1100, 2),
+            log_message("ECU1", "APP", 1200, 3),
+        ];
+        let dir = std::env::temp_dir().join(format!("dlt-core-split-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let input = dir.join("trace.dlt");
+        std::fs::write(&input, to_bytes(&messages)).expect("write input");
+        let split = |split_by| {
+            split_file(&input, &dir, split_by, ExportOptions::new())
+                .expect("split")
+                .iter()
+                .map(|path| {
+                    let name = path
+                        .file_name()
+                        .expect("name")
+                        .to_string_lossy()
+                        .into_owned();
+                    (name, std::fs::read(path).expect("read part"))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut by_ecu = split(SplitBy::Ecu);
+        by_ecu.sort();
+        assert_eq!(
+            by_ecu,
+            vec![
+                (
+                    "trace_ECU1.dlt".to_string(),
+                    to_bytes(&[
+                        messages[0].clone(),
+                        messages[2].clone(),
+                        messages[3].clone()
+                    ])
+                ),
+                ("trace_ECU2.dlt".to_string(), to_bytes(&messages[1..2])),
+            ]
+        );
+        assert_eq!(
+            split(SplitBy::Duration(std::time::Duration::from_secs(1))),
+            vec![
+                ("trace_0.dlt".to_string(), to_bytes(&messages[..2])),
+                ("trace_1.dlt".to_string(), to_bytes(&messages[2..])),
+            ]
+        );
+        let length = messages[0].as_bytes().len() as u64;
+        assert_eq!(
+            split(SplitBy::Size(3 * length - 1)),
+            vec![
+                ("trace_0.dlt".to_string(), to_bytes(&messages[..2])),
+                ("trace_1.dlt".to_string(), to_bytes(&messages[2..])),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+    }
+
+    #[test]
+    fn test_split_file_with_unsafe_ecu_ids() {
+        let messages = vec![
+            log_message("../", "APP", 0, 0),
+            log_message("", "APP", 0, 1),
+            log_message("a_-Z", "APP", 0, 2),
+        ];
+        let dir = std::env::temp_dir().join(format!("dlt-core-split-ids-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let input = dir.join("trace.dlt");
+        std::fs::write(&input, to_bytes(&messages)).expect("write input");
+        let mut names: Vec<String> = split_file(&input, &dir, SplitBy::Ecu, ExportOptions::new())
+            .expect("split")
+            .iter()
+            .map(|path| {
+                assert_eq!(path.parent(), Some(dir.as_path()));
+                path.file_name()
+                    .expect("name")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["trace_%00.dlt", "trace_%2E%2E%2F.dlt", "trace_a_-Z.dlt"]
+        );
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+    }
+
+    #[test]
+    fn test_split_without_storage_headers() {
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::NonVerbose(1, vec![].into()),
+                extended_header_info: None,
+            },
+            None,
+        );
+        let input = message.as_bytes();
+        assert!(matches!(
+            split(
+                input.as_slice(),
+                SplitBy::Ecu,
+                |_| Ok(vec![]),
+                ExportOptions::new()
+            ),
+            Err(crate::export::Error::MissingStorageHeaders)
+        ));
+    }
+
+    #[test]
+    fn test_rewrite_timestamps() {
+        let messages: Vec<Message> = (0..2)
+            .map(|i| log_message("ECU1", "APP", i * 1000, i as u8))
+            .collect();
+        let input = to_bytes(&messages);
+        let rewritten = |rewrite| {
+            let mut output = vec![];
+            let options = ExportOptions::new().rewrite_timestamps(rewrite);
+            filter_copy(
+                input.as_slice(),
+                &mut output,
+                &DltFilterConfig::default(),
+                options,
+            )
+            .expect("export");
+            output
+        };
+        let with_storage_timestamps = |timestamps: [DltTimeStamp; 2]| {
+            let messages: Vec<Message> = messages
+                .iter()
+                .zip(timestamps)
+                .map(|(message, timestamp)| {
+                    let mut message = message.clone();
+                    message
+                        .storage_header
+                        .as_mut()
+                        .expect("storage header")
+                        .timestamp = timestamp;
+                    message
+                })
+                .collect();
+            to_bytes(&messages)
+        };
+
+        // the header timestamp of both messages is 5 * 0.1 ms
+        let base = DltTimeStamp::from_ms(1_000_000);
+        assert_eq!(
+            rewritten(TimestampRewrite::FromHeaderTimestamp(base)),
+            with_storage_timestamps([
+                DltTimeStamp::from_us(1_000_000_500),
+                DltTimeStamp::from_us(1_000_000_500)
+            ])
+        );
+        assert_eq!(
+            rewritten(TimestampRewrite::Map(Box::new(|timestamp, _| {
+                DltTimeStamp::from_us(timestamp.as_us() + 2_000_000)
+            }))),
+            with_storage_timestamps([DltTimeStamp::from_ms(2000), DltTimeStamp::from_ms(3000)])
+        );
+    }
+
+    #[test]
+    fn test_renumber_counters() {
+        let messages = vec![
+            log_message("ECU1", "APP1", 0, 7),
+            log_message("ECU1", "APP2", 0, 8),
+            log_message("ECU2", "APP1", 0, 3),
+            log_message("ECU1", "APP1", 0, 9),
+            log_message("ECU2", "APP1", 0, 4),
+        ];
+        let filter = DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .build()
+            .expect("valid config");
+        let mut output = vec![];
+        let options = ExportOptions::new().renumber_counters(true);
+        filter_copy(
+            to_bytes(&messages).as_slice(),
+            &mut output,
+            &filter,
+            options,
+        )
+        .expect("export");
+        let expected = vec![
+            log_message("ECU1", "APP1", 0, 0),
+            log_message("ECU2", "APP1", 0, 0),
+            log_message("ECU1", "APP1", 0, 1),
+            log_message("ECU2", "APP1", 0, 1),
+        ];
+        assert_eq!(output, to_bytes(&expected));
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let recorded = |message: &Message, logger: &str, storage_ms: u64| {
+            let mut message = message.clone();
+            message.storage_header = Some(StorageHeader {
+                timestamp: DltTimeStamp::from_ms(storage_ms),
+                ecu_id: DltId::new(logger),
+            });
+            message
+        };
+        let first = log_message("ECU1", "APP1", 0, 1);
+        let second = log_message("ECU1", "APP1", 0, 2);
+        let messages = vec![
+            recorded(&first, "LOG1", 0),
+            recorded(&first, "LOG2", 10),
+            recorded(&second, "LOG1", 20),
+            recorded(&second, "LOG2", 15),
+            recorded(&first, "LOG1", 5000),
+        ];
+        let mut output = vec![];
+        let options = ExportOptions::new().deduplicate(std::time::Duration::from_secs(1));
+        let stats = filter_copy(
+            to_bytes(&messages).as_slice(),
+            &mut output,
+            &DltFilterConfig::default(),
+            options,
+        )
+        .expect("export");
+        assert_eq!(
+            output,
+            to_bytes(&[
+                messages[0].clone(),
+                messages[2].clone(),
+                messages[4].clone()
+            ])
+        );
+        assert_eq!(stats.duplicate_messages, 2);
+        assert_eq!(stats.written_messages, 3);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_to_json_lines() {
+        let arguments = vec![
+            Argument {
+                type_info: TypeInfo {
+                    kind: TypeInfoKind::Unsigned(TypeLength::BitLength32),
+                    coding: StringCoding::UTF8,
+                    has_variable_info: true,
+                    has_trace_info: false,
+                },
+                name: Some("speed".to_string()),
+                unit: Some("km/h".to_string()),
+                fixed_point: None,
+                value: Value::U32(42),
+            },
+            Argument {
+                type_info: TypeInfo {
+                    kind: TypeInfoKind::StringType,
+                    coding: StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                fixed_point: None,
+                value: Value::StringVal("say \"hi\"".to_string()),
+            },
+        ];
+        let verbose = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 1,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(5),
+                payload: PayloadContent::Verbose(arguments.into_iter().collect()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Warn),
+                    app_id: "APP2".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            Some(StorageHeader {
+                timestamp: DltTimeStamp::from_ms(1500),
+                ecu_id: DltId::new("ECU1"),
+            }),
+        );
+        let messages = vec![log_message("ECU1", "APP1", 1000, 0), verbose];
+        let mut output = vec![];
+        let stats = to_json_lines(
+            to_bytes(&messages).as_slice(),
+            &mut output,
+            None,
+            ExportOptions::new(),
+        )
+        .expect("export");
+        assert_eq!(stats.written_messages, 2);
+        assert_eq!(stats.written_bytes, output.len() as u64);
+        let output = String::from_utf8(output).expect("utf8");
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json"))
+            .collect();
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "storage_seconds": 1, "storage_microseconds": 0, "storage_ecu": "ECU1",
+                "ecu": "ECU1", "counter": 0, "timestamp": 5, "app": "APP1", "ctx": "CTX",
+                "type": "log", "subtype": "info", "verbose": false,
+                "message_id": 1, "payload": "01 02 03"
+            })
+        );
+        assert_eq!(lines[1]["subtype"], "warn");
+        assert_eq!(lines[1]["verbose"], true);
+        assert_eq!(
+            lines[1]["arguments"],
+            serde_json::json!([
+                {"name": "speed", "unit": "km/h", "value": 42},
+                {"name": null, "unit": null, "value": "say \"hi\""}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let messages = vec![
+            log_message("ECU1", "APP1", 1000, 0),
+            log_message("ECU2", "APP1", 1500, 1),
+            log_message("ECU1", "APP2", 2000, 2),
+        ];
+        let filter = DltFilterConfig::builder()
+            .ecu_ids(["ECU1"])
+            .build()
+            .expect("valid config");
+        let columns = [
+            Column::Index,
+            Column::StorageTime,
+            Column::Application,
+            Column::Level,
+            Column::Payload,
+        ];
+        let mut output = vec![];
+        let stats = to_csv(
+            to_bytes(&messages).as_slice(),
+            &mut output,
+            &columns,
+            Some(&filter),
+            ExportOptions::new(),
+        )
+        .expect("export");
+        assert_eq!(
+            String::from_utf8(output.clone()).expect("utf8"),
+            "index,storage_time,app,level,payload\n\
+             0,1.000000,APP1,info,[1] 01 02 03\n\
+             1,2.000000,APP2,info,[1] 01 02 03\n"
+        );
+        assert_eq!(stats.written_messages, 2);
+        assert_eq!(stats.filtered_messages, 1);
+        assert_eq!(stats.written_bytes, output.len() as u64);
+    }
+
+    #[test]
+    fn test_to_csv_quoting() {
+        let argument = Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::StringType,
+                coding: StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            fixed_point: None,
+            value: Value::StringVal("a, \"b\"".to_string()),
+        };
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::Verbose([argument].into_iter().collect()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Info),
+                    app_id: "APP1".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        let mut output = vec![];
+        to_csv(
+            message.as_bytes().as_slice(),
+            &mut output,
+            &[Column::Context, Column::Payload],
+            None,
+            ExportOptions::new(),
+        )
+        .expect("export");
+        assert_eq!(output, b"ctx,payload\nCTX,\"a, \"\"b\"\"\"\n");
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let messages = vec![
+            log_message("ECU1", "APP1", 1000, 0),
+            log_message("ECU1", "APP2", 1500, 1),
+            log_message("ECU1", "APP1", 2000, 2),
+        ];
+        let filter = DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .build()
+            .expect("valid config");
+        let mut output = vec![];
+        let stats = to_ascii(
+            to_bytes(&messages).as_slice(),
+            &mut output,
+            Some(&filter),
+            0,
+            ExportOptions::new(),
+        )
+        .expect("export");
+        // the index is the position in the input, not in the output
+        assert_eq!(
+            String::from_utf8(output.clone()).expect("utf8"),
+            "0 1970/01/01 00:00:01.000000 0.0005 0 ECU1 APP1 CTX log info non-verbose 0 [1] 01 02 03\n\
+             2 1970/01/01 00:00:02.000000 0.0005 2 ECU1 APP1 CTX log info non-verbose 0 [1] 01 02 03\n"
+        );
+        assert_eq!(stats.written_bytes, output.len() as u64);
+    }
+}
@@ -14,8 +14,9 @@
 #[cfg(test)]
 mod tests {
     use crate::{
-        dlt::{StringCoding::*, TypeInfo, TypeInfoKind::*, TypeLength::*},
+        dlt::{StringCoding::*, TypeInfo, TypeInfoKind::*, TypeLength::*, *},
         fibex::*,
+        filtering::{DltFilterConfig, ProcessedDltFilterConfig},
     };
     use std::{collections::HashMap, path::PathBuf};
 
@@ -28,8 +29,8 @@ mod tests {
 
         assert_eq!(
             fibex,
-            FibexMetadata {
-                frame_map_with_key: HashMap::from([
+            FibexMetadata::new(
+                HashMap::from([
                     (
                         FrameMetadataIdentification {
                             context_id: "CTX1".to_string(),
@@ -41,11 +42,15 @@ mod tests {
                             pdus: [
                                 PduMetadata {
                                     description: Some("timeing: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: Some("type: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -55,11 +60,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("contextId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -69,11 +78,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("eventId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -83,11 +96,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("ts: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -97,11 +114,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 },
                                 PduMetadata {
                                     description: Some("threadId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -111,14 +132,17 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 }
                             ]
                             .to_vec(),
                             application_id: Some("DR".to_string()),
                             context_id: Some("CTX1".to_string()),
                             message_type: Some("DLT_TYPE_LOG".to_string()),
-                            message_info: Some("DLT_LOG_WARN".to_string())
+                            message_info: Some("DLT_LOG_WARN".to_string()),
+                            byte_length: 24
                         }
                     ),
                     (
@@ -132,11 +156,15 @@ mod tests {
                             pdus: [
                                 PduMetadata {
                                     description: Some("direction".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: Some("speed: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -146,11 +174,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 },
                                 PduMetadata {
                                     description: Some("heading: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -160,18 +192,21 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 }
                             ]
                             .to_vec(),
                             application_id: Some("DR".to_string()),
                             context_id: Some("CTX1".to_string()),
                             message_type: Some("DLT_TYPE_LOG".to_string()),
-                            message_info: Some("DLT_LOG_WARN".to_string())
+                            message_info: Some("DLT_LOG_WARN".to_string()),
+                            byte_length: 16
                         }
                     )
                 ]),
-                frame_map: HashMap::from([
+                HashMap::from([
                     (
                         "ID_64".to_string(),
                         FrameMetadata {
@@ -179,11 +214,15 @@ mod tests {
                             pdus: [
                                 PduMetadata {
                                     description: Some("direction".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: Some("speed: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -193,11 +232,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 },
                                 PduMetadata {
                                     description: Some("heading: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -207,14 +250,17 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 }
                             ]
                             .to_vec(),
                             application_id: Some("DR".to_string()),
                             context_id: Some("CTX1".to_string()),
                             message_type: Some("DLT_TYPE_LOG".to_string()),
-                            message_info: Some("DLT_LOG_WARN".to_string())
+                            message_info: Some("DLT_LOG_WARN".to_string()),
+                            byte_length: 16
                         }
                     ),
                     (
@@ -224,11 +270,15 @@ mod tests {
                             pdus: [
                                 PduMetadata {
                                     description: Some("timeing: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: Some("type: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -238,11 +288,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("contextId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -252,11 +306,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("eventId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -266,11 +324,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 },
                                 PduMetadata {
                                     description: Some("ts: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -280,11 +342,15 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 8
                                 },
                                 PduMetadata {
                                     description: Some("threadId: ".to_string()),
-                                    signal_types: [].to_vec()
+                                    signal_types: [].to_vec(),
+                                    compu_methods: [].to_vec(),
+                                    byte_length: 0
                                 },
                                 PduMetadata {
                                     description: None,
@@ -294,19 +360,66 @@ mod tests {
                                         has_variable_info: false,
                                         has_trace_info: false
                                     }]
-                                    .to_vec()
+                                    .to_vec(),
+                                    compu_methods: [None].to_vec(),
+                                    byte_length: 4
                                 }
                             ]
                             .to_vec(),
                             application_id: Some("DR".to_string()),
                             context_id: Some("CTX1".to_string()),
                             message_type: Some("DLT_TYPE_LOG".to_string()),
-                            message_info: Some("DLT_LOG_WARN".to_string())
+                            message_info: Some("DLT_LOG_WARN".to_string()),
+                            byte_length: 24
                         }
                     )
                 ])
-            }
+            )
+        );
+    }
+
+    #[test]
+    fn test_fibex_from_bytes() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml");
+        let from_file = read_fibexes(vec![path.clone()]).expect("can't parse fibex");
+        let bytes = std::fs::read(path).expect("can't read fibex");
+        assert_eq!(
+            read_fibex_from_bytes(&bytes).expect("can't parse fibex"),
+            from_file
+        );
+        assert_eq!(
+            read_fibex_from_reader(std::io::Cursor::new(bytes)).expect("can't parse fibex"),
+            from_file
         );
+        assert!(read_fibex_from_bytes(b"<FIBEX><PDU></PDU></FIBEX>").is_err());
+    }
+
+    #[test]
+    fn test_fibex_merge_report() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml");
+        let copy = std::env::temp_dir().join(format!("dlt-messages-{}.xml", std::process::id()));
+        std::fs::copy(&path, &copy).expect("can't copy fibex");
+        let (fibex, report) =
+            read_fibexes_with_report(vec![path.clone(), copy.clone()]).expect("can't parse fibex");
+        std::fs::remove_file(&copy).expect("can't remove fibex copy");
+
+        assert_eq!(
+            fibex,
+            read_fibexes(vec![path.clone()]).expect("can't parse fibex")
+        );
+        assert!(report.conflicts.contains(&FibexConflict {
+            kind: FibexItemKind::Frame,
+            id: "ID_65".to_string(),
+            used_from: Some(path.clone()),
+            ignored_from: Some(copy.clone()),
+        }));
+        assert!(report
+            .conflicts
+            .iter()
+            .any(|c| c.kind == FibexItemKind::Pdu));
+
+        let (_, report) = read_fibexes_with_report(vec![path]).expect("can't parse fibex");
+        assert!(report.is_empty());
     }
 
     #[test]
@@ -318,4 +431,407 @@ mod tests {
 
         println!("{:?}", fibex);
     }
+
+    fn non_verbose_message(app_id: &str, message_id: u32, data: Vec<u8>) -> Message {
+        Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::NonVerbose(message_id, data.into()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Warn),
+                    app_id: app_id.to_string(),
+                    context_id: "CTX1".to_string(),
+                }),
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_decode_nonverbose() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml")
+        ])
+        .expect("can't parse fibex");
+
+        let mut data = vec![];
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend_from_slice(&4u64.to_be_bytes());
+        data.extend_from_slice(&(-5i32).to_be_bytes());
+        let arguments = decode_nonverbose(&fibex, &non_verbose_message("DR", 65, data.clone()))
+            .expect("decoded arguments");
+        let values: Vec<Value> = arguments.into_iter().map(|a| a.value).collect();
+        assert_eq!(
+            values,
+            vec![
+                Value::U32(1),
+                Value::U32(2),
+                Value::U32(3),
+                Value::U64(4),
+                Value::I32(-5)
+            ]
+        );
+
+        // unknown frame or truncated payload
+        assert!(decode_nonverbose(&fibex, &non_verbose_message("XX", 65, data.clone())).is_none());
+        assert!(decode_nonverbose(&fibex, &non_verbose_message("DR", 66, data.clone())).is_none());
+        data.truncate(10);
+        assert!(decode_nonverbose(&fibex, &non_verbose_message("DR", 65, data)).is_none());
+    }
+
+    #[test]
+    fn test_nonverbose_text() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml")
+        ])
+        .expect("can't parse fibex");
+
+        let mut data = vec![];
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend_from_slice(&4u64.to_be_bytes());
+        data.extend_from_slice(&(-5i32).to_be_bytes());
+        assert_eq!(
+            nonverbose_text(&fibex, &non_verbose_message("DR", 65, data)),
+            Some("timeing:  type:  1 contextId:  2 eventId:  3 ts:  4 threadId:  -5".to_string())
+        );
+        assert_eq!(
+            nonverbose_text(&fibex, &non_verbose_message("DR", 65, vec![1])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compu_methods() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compu-methods.xml")
+        ])
+        .expect("can't parse fibex");
+        let frame = fibex.frame_map.get("ID_10").expect("frame");
+        let compu_methods: Vec<Option<CompuMethod>> = frame
+            .pdus
+            .iter()
+            .flat_map(|pdu| pdu.compu_methods.clone())
+            .collect();
+        assert_eq!(
+            compu_methods,
+            vec![
+                Some(CompuMethod {
+                    scales: vec![
+                        CompuScale {
+                            lower_limit: Some(0.0),
+                            upper_limit: Some(0.0),
+                            value: CompuValue::Text("OFF".to_string()),
+                        },
+                        CompuScale {
+                            lower_limit: Some(1.0),
+                            upper_limit: Some(1.0),
+                            value: CompuValue::Text("ON".to_string()),
+                        },
+                    ]
+                }),
+                Some(CompuMethod {
+                    scales: vec![CompuScale {
+                        lower_limit: None,
+                        upper_limit: None,
+                        value: CompuValue::Rational {
+                            numerator: vec![-10.0, 0.5],
+                            denominator: vec![1.0],
+                        },
+                    }]
+                }),
+            ]
+        );
+        let state = compu_methods[0].as_ref().expect("compu method");
+        assert_eq!(
+            state.apply(1.0),
+            Some(PhysicalValue::Text("ON".to_string()))
+        );
+        assert_eq!(state.apply(2.0), None);
+
+        let mut data = vec![1u8];
+        data.extend_from_slice(&100u16.to_be_bytes());
+        let message = non_verbose_message("CAR", 10, data);
+        assert_eq!(
+            nonverbose_text(&fibex, &message).as_deref(),
+            Some("state: ON speed: 40")
+        );
+        // arguments keep the raw values
+        let arguments = decode_nonverbose(&fibex, &message).expect("decoded arguments");
+        assert_eq!(arguments[0].value, Value::U8(1));
+    }
+
+    #[test]
+    fn test_nonverbose_log_level() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compu-methods.xml")
+        ])
+        .expect("can't parse fibex");
+        let frame = fibex.frame_map.get("ID_10").expect("frame");
+        assert_eq!(frame.log_level(), Some(LogLevel::Info));
+
+        // non-verbose message without extended header
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::NonVerbose(10, vec![1, 0, 100].into()),
+                extended_header_info: None,
+            },
+            None,
+        );
+        assert_eq!(nonverbose_log_level(&fibex, &message), Some(LogLevel::Info));
+
+        let config = |level: LogLevel| -> ProcessedDltFilterConfig {
+            DltFilterConfig::builder()
+                .min_log_level(level)
+                .build()
+                .expect("valid config")
+                .into()
+        };
+        assert!(config(LogLevel::Warn).matches(&message));
+        assert!(!config(LogLevel::Warn).matches_with_fibex(&message, &fibex));
+        assert!(config(LogLevel::Info).matches_with_fibex(&message, &fibex));
+        let per_app: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .min_log_level(LogLevel::Warn)
+            .app_id_log_level("CAR", LogLevel::Verbose)
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(per_app.matches_with_fibex(&message, &fibex));
+    }
+
+    #[test]
+    fn test_lazy_fibex() {
+        let files = vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml"),
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compu-methods.xml"),
+        ];
+        let fibex = read_fibexes(files.clone()).expect("can't parse fibex");
+        let lazy = LazyFibex::index(files).expect("can't index fibex");
+        assert_eq!(lazy.len(), fibex.frame_map.len());
+
+        for (id, frame) in &fibex.frame_map {
+            let id: u32 = id.trim_start_matches("ID_").parse().expect("numeric id");
+            assert_eq!(lazy.frame(id, None).as_deref(), Some(frame));
+        }
+        for (key, frame) in &fibex.frame_map_with_key {
+            let id: u32 = key
+                .frame_id
+                .trim_start_matches("ID_")
+                .parse()
+                .expect("numeric id");
+            let header = ExtendedHeader {
+                verbose: false,
+                argument_count: 0,
+                message_type: MessageType::Log(LogLevel::Info),
+                application_id: DltId::new(&key.app_id),
+                context_id: DltId::new(&key.context_id),
+            };
+            assert_eq!(lazy.frame(id, Some(&header)).as_deref(), Some(frame));
+        }
+        assert!(lazy.frame(4711, None).is_none());
+
+        let mut data = vec![0u8];
+        data.extend_from_slice(&20u16.to_be_bytes());
+        let message = non_verbose_message("CAR", 10, data);
+        assert_eq!(
+            lazy.nonverbose_text(&message),
+            nonverbose_text(&fibex, &message)
+        );
+        assert!(lazy
+            .decode_nonverbose(&non_verbose_message("XX", 10, vec![]))
+            .is_none());
+    }
+
+    #[test]
+    fn test_numeric_frame_lookup() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml")
+        ])
+        .expect("can't parse fibex");
+        assert_eq!(fibex.frame(65), fibex.frame_map.get("ID_65"));
+        assert_eq!(
+            fibex.frame_with_key(65, "DR", "CTX1"),
+            fibex.frame_map_with_key.get(&FrameMetadataIdentification {
+                context_id: "CTX1".to_string(),
+                app_id: "DR".to_string(),
+                frame_id: "ID_65".to_string(),
+            })
+        );
+        assert!(fibex.frame_with_key(65, "DR", "CTX2").is_none());
+        assert!(fibex.frame(66).is_none());
+
+        let rebuilt = FibexMetadata::new(fibex.frame_map_with_key.clone(), fibex.frame_map.clone());
+        assert_eq!(rebuilt, fibex);
+    }
+
+    #[test]
+    fn test_check_payload_length() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compu-methods.xml")
+        ])
+        .expect("can't parse fibex");
+        let frame = fibex.frame_map.get("ID_10").expect("frame");
+        assert_eq!(frame.byte_length, 3);
+        assert_eq!(
+            frame
+                .pdus
+                .iter()
+                .map(|pdu| pdu.byte_length)
+                .collect::<Vec<usize>>(),
+            vec![0, 1, 0, 2]
+        );
+
+        let matching = non_verbose_message("CAR", 10, vec![1, 0, 100]);
+        assert_eq!(check_payload_length(&fibex, &matching), None);
+
+        let truncated = non_verbose_message("CAR", 10, vec![1, 0]);
+        let mismatch = check_payload_length(&fibex, &truncated).expect("mismatch");
+        assert_eq!(
+            mismatch,
+            LengthMismatch {
+                message_id: 10,
+                declared: 3,
+                actual: 2,
+            }
+        );
+        assert_eq!(
+            mismatch.to_string(),
+            "non-verbose message 10: payload has 2 bytes, fibex declares 3"
+        );
+
+        // unknown frames can't be checked
+        let unknown = non_verbose_message("CAR", 11, vec![1]);
+        assert_eq!(check_payload_length(&fibex, &unknown), None);
+    }
+
+    #[test]
+    fn test_namespaced_fibexes() {
+        let path = |name: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join(name)
+        };
+        // both files define frame ID_65 and PDU ID_4000
+        let (fibex, report) = read_fibexes_namespaced(vec![
+            (path("dlt-messages.xml"), FibexNamespace::FromFile),
+            (path("dlt-messages-ecu2.xml"), FibexNamespace::FromFile),
+        ])
+        .expect("can't parse fibex");
+        assert!(report.is_empty());
+        let mut ecu_ids: Vec<&String> = fibex.ecu_ids().collect();
+        ecu_ids.sort();
+        assert_eq!(ecu_ids, vec!["ECU1", "ECU2"]);
+        assert!(fibex.frame(65).is_none());
+        assert_eq!(fibex.frame_ids(), vec![64, 65]);
+
+        let mut message = non_verbose_message("DR", 65, vec![7]);
+        message.header.ecu_id = Some(DltId::new("ECU2"));
+        assert_eq!(
+            nonverbose_text(&fibex, &message),
+            Some("counter: 7".to_string())
+        );
+        assert_eq!(nonverbose_log_level(&fibex, &message), Some(LogLevel::Info));
+
+        let message = non_verbose_message("DR", 65, vec![0; 24]);
+        assert_eq!(
+            frame_for_message(&fibex, &message).map(|frame| frame.byte_length),
+            Some(24)
+        );
+        let mut message = non_verbose_message("DR", 65, vec![0; 24]);
+        message.header.ecu_id = Some(DltId::new("ECU3"));
+        assert!(frame_for_message(&fibex, &message).is_none());
+
+        // without namespaces the first frame wins
+        let (fibex, report) = read_fibexes_namespaced(vec![
+            (path("dlt-messages.xml"), FibexNamespace::Global),
+            (path("dlt-messages-ecu2.xml"), FibexNamespace::Global),
+        ])
+        .expect("can't parse fibex");
+        assert!(!report.is_empty());
+        assert_eq!(fibex.frame(65).map(|frame| frame.byte_length), Some(24));
+
+        // files without ECU element can be put into a namespace explicitly
+        let (fibex, _) = read_fibexes_namespaced(vec![(
+            path("compu-methods.xml"),
+            FibexNamespace::Ecu("ECU9".to_string()),
+        )])
+        .expect("can't parse fibex");
+        assert!(fibex.ecu("ECU9").and_then(|ecu| ecu.frame(10)).is_some());
+    }
+
+    #[test]
+    fn test_read_fibexes_recovering() {
+        let path = |name: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join(name)
+        };
+        let mut progress = vec![];
+        let result = read_fibexes_recovering(
+            vec![
+                (path("dlt-messages.xml"), FibexNamespace::Global),
+                (path("missing.xml"), FibexNamespace::Global),
+                (path("compu-methods.xml"), FibexNamespace::Global),
+            ],
+            |p| progress.push((p.done, p.total, p.error.is_some())),
+        );
+        assert_eq!(progress, vec![(1, 3, false), (2, 3, true), (3, 3, false)]);
+        assert_eq!(result.failed_files, 1);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].0, path("missing.xml"));
+        assert_eq!(result.fibex.frame_ids(), vec![10, 64, 65]);
+
+        let fibex = gather_fibex_data(FibexConfig {
+            fibex_file_paths: vec![
+                path("missing.xml").to_string_lossy().to_string(),
+                path("compu-methods.xml").to_string_lossy().to_string(),
+            ],
+            namespace_by_ecu: false,
+        })
+        .expect("partial model");
+        assert!(fibex.frame(10).is_some());
+        assert!(gather_fibex_data(FibexConfig {
+            fibex_file_paths: vec![path("missing.xml").to_string_lossy().to_string()],
+            namespace_by_ecu: false,
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn test_read_fibexes_cancellable() {
+        let path = |name: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join(name)
+        };
+        let token = crate::progress::CancellationToken::new();
+        let mut done = vec![];
+        let result = read_fibexes_cancellable(
+            vec![
+                (path("dlt-messages.xml"), FibexNamespace::Global),
+                (path("compu-methods.xml"), FibexNamespace::Global),
+            ],
+            |p| {
+                done.push(p.done);
+                token.cancel();
+            },
+            &token,
+        );
+        assert!(matches!(result, Err(crate::fibex::Error::Cancelled)));
+        assert_eq!(done, vec![1]);
+    }
 }
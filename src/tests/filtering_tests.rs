@@ -0,0 +1,638 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+mod tests {
+    use crate::{
+        dlt::*,
+        filtering::{Error, *},
+        parse::{dlt_message, ParsedMessage},
+    };
+
+    fn log_message(app_id: &str, context_id: &str, level: LogLevel) -> Vec<u8> {
+        log_message_at(app_id, context_id, level, DltTimeStamp::from_ms(0))
+    }
+
+    fn log_message_at(
+        app_id: &str,
+        context_id: &str,
+        level: LogLevel,
+        timestamp: DltTimeStamp,
+    ) -> Vec<u8> {
+        log_message_with(app_id, context_id, level, timestamp, None)
+    }
+
+    fn log_message_with(
+        app_id: &str,
+        context_id: &str,
+        level: LogLevel,
+        timestamp: DltTimeStamp,
+        session_id: Option<u32>,
+    ) -> Vec<u8> {
+        log_message_with_args(app_id, context_id, level, timestamp, session_id, vec![])
+    }
+
+    fn log_message_with_args(
+        app_id: &str,
+        context_id: &str,
+        level: LogLevel,
+        timestamp: DltTimeStamp,
+        session_id: Option<u32>,
+        args: Vec<Argument>,
+    ) -> Vec<u8> {
+        Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id,
+                timestamp: None,
+                payload: PayloadContent::Verbose(args.into_iter().collect()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(level),
+                    app_id: app_id.to_string(),
+                    context_id: context_id.to_string(),
+                }),
+            },
+            Some(StorageHeader {
+                timestamp,
+                ecu_id: DltId::new("ECU1"),
+            }),
+        )
+        .as_bytes()
+    }
+
+    fn non_verbose_message(message_id: u32) -> Vec<u8> {
+        Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Little,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::NonVerbose(message_id, vec![0x1, 0x2].into()),
+                extended_header_info: None,
+            },
+            Some(StorageHeader {
+                timestamp: DltTimeStamp::from_ms(0),
+                ecu_id: DltId::new("ECU1"),
+            }),
+        )
+        .as_bytes()
+    }
+
+    fn is_filtered_out(bytes: &[u8], config: &ProcessedDltFilterConfig) -> bool {
+        match dlt_message(bytes, Some(config), true).expect("parse message") {
+            (_, ParsedMessage::Item(_)) => false,
+            (_, ParsedMessage::FilteredOut(_)) => true,
+            (_, ParsedMessage::Invalid) => panic!("invalid message"),
+        }
+    }
+
+    #[test]
+    fn test_per_id_log_levels() {
+        let config: ProcessedDltFilterConfig = DltFilterConfig {
+            min_log_level: Some(3),
+            app_id_log_levels: Some(vec![("APP1".to_string(), 5)]),
+            context_id_log_levels: Some(vec![("CTX1".to_string(), 1)]),
+            ..Default::default()
+        }
+        .into();
+
+        // general level applies
+        assert!(!is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Warn),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Info),
+            &config
+        ));
+        // app level overrides general level
+        assert!(!is_filtered_out(
+            &log_message("APP1", "CTX", LogLevel::Debug),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("APP1", "CTX", LogLevel::Verbose),
+            &config
+        ));
+        // context level overrides app level
+        assert!(!is_filtered_out(
+            &log_message("APP1", "CTX1", LogLevel::Fatal),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("APP1", "CTX1", LogLevel::Error),
+            &config
+        ));
+    }
+
+    #[test]
+    fn test_time_range() {
+        let config: ProcessedDltFilterConfig = DltFilterConfig {
+            min_timestamp: Some(DltTimeStamp::from_ms(1_000)),
+            max_timestamp: Some(DltTimeStamp::from_ms(2_000)),
+            ..Default::default()
+        }
+        .into();
+        let at = |ms| log_message_at("APP", "CTX", LogLevel::Info, DltTimeStamp::from_ms(ms));
+
+        assert!(is_filtered_out(&at(999), &config));
+        assert!(!is_filtered_out(&at(1_000), &config));
+        assert!(!is_filtered_out(&at(1_500), &config));
+        assert!(!is_filtered_out(&at(2_000), &config));
+        assert!(is_filtered_out(&at(2_001), &config));
+
+        // without storage header the time range can't be applied
+        let bytes = at(0);
+        match dlt_message(
+            &bytes[STORAGE_HEADER_LENGTH as usize..],
+            Some(&config),
+            false,
+        ) {
+            Ok((_, ParsedMessage::Item(_))) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_ids() {
+        let in_session = |session_id| {
+            log_message_with(
+                "APP",
+                "CTX",
+                LogLevel::Info,
+                DltTimeStamp::from_ms(0),
+                session_id,
+            )
+        };
+        let allow: ProcessedDltFilterConfig = DltFilterConfig {
+            session_ids: Some(vec![1, 2]),
+            ..Default::default()
+        }
+        .into();
+        assert!(!is_filtered_out(&in_session(Some(1)), &allow));
+        assert!(is_filtered_out(&in_session(Some(3)), &allow));
+        assert!(!is_filtered_out(&in_session(None), &allow));
+
+        let deny: ProcessedDltFilterConfig = DltFilterConfig {
+            excluded_session_ids: Some(vec![2]),
+            ..Default::default()
+        }
+        .into();
+        assert!(!is_filtered_out(&in_session(Some(1)), &deny));
+        assert!(is_filtered_out(&in_session(Some(2)), &deny));
+    }
+
+    #[test]
+    fn test_id_matcher() {
+        let matcher = IdMatcher::new(vec!["APP", "NAV*", "?SOM", "A*Z"]);
+        assert_eq!(matcher.len(), 4);
+        assert!(matcher.matches("APP"));
+        assert!(!matcher.matches("APP1"));
+        assert!(matcher.matches("NAV"));
+        assert!(matcher.matches("NAV1"));
+        assert!(!matcher.matches("NA"));
+        assert!(matcher.matches("XSOM"));
+        assert!(!matcher.matches("SOM"));
+        assert!(!matcher.matches("XXSOM"));
+        assert!(matcher.matches("AZ"));
+        assert!(matcher.matches("ABZZ"));
+        assert!(!matcher.matches("ABZX"));
+        assert!(IdMatcher::new(vec!["*"]).matches(""));
+        assert!(!IdMatcher::default().matches("APP"));
+    }
+
+    #[test]
+    fn test_wildcard_id_filter() {
+        let config: ProcessedDltFilterConfig = DltFilterConfig {
+            app_ids: Some(vec!["NAV*".to_string()]),
+            context_ids: Some(vec!["C?X".to_string()]),
+            ..Default::default()
+        }
+        .into();
+        assert!(!is_filtered_out(
+            &log_message("NAV2", "CTX", LogLevel::Info),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Info),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("NAV2", "CTXX", LogLevel::Info),
+            &config
+        ));
+    }
+
+    #[test]
+    fn test_non_verbose_message_ids() {
+        let allow: ProcessedDltFilterConfig = DltFilterConfig {
+            message_ids: Some(vec![42]),
+            ..Default::default()
+        }
+        .into();
+        assert!(!is_filtered_out(&non_verbose_message(42), &allow));
+        assert!(is_filtered_out(&non_verbose_message(43), &allow));
+        // verbose messages are not affected
+        assert!(!is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Info),
+            &allow
+        ));
+
+        let deny: ProcessedDltFilterConfig = DltFilterConfig {
+            excluded_message_ids: Some(vec![42]),
+            ..Default::default()
+        }
+        .into();
+        let mut bytes = non_verbose_message(42);
+        bytes.extend(non_verbose_message(43));
+        let (rest, filtered) = dlt_message(&bytes, Some(&deny), true).expect("parse message");
+        assert!(matches!(filtered, ParsedMessage::FilteredOut(_)));
+        assert!(!is_filtered_out(rest, &deny));
+    }
+
+    #[test]
+    fn test_builder_and_validate() {
+        let config = DltFilterConfig::builder()
+            .min_log_level(LogLevel::Info)
+            .app_ids(["APP", "NAV*"])
+            .context_id_log_level("CTX", LogLevel::Error)
+            .session_ids([1])
+            .build()
+            .expect("valid config");
+        assert_eq!(config.min_log_level, Some(4));
+        assert_eq!(
+            config.context_id_log_levels,
+            Some(vec![("CTX".to_string(), 2)])
+        );
+
+        let invalid_level = DltFilterConfig {
+            min_log_level: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(invalid_level.validate(), Err(Error::InvalidLogLevel(7)));
+        assert_eq!(
+            DltFilterConfig::builder()
+                .ecu_ids(["ECU", ""])
+                .build()
+                .err(),
+            Some(Error::InvalidId(String::new()))
+        );
+        assert_eq!(
+            DltFilterConfig::builder()
+                .app_ids(["TOOLONG"])
+                .build()
+                .err(),
+            Some(Error::InvalidId("TOOLONG".to_string()))
+        );
+        assert_eq!(
+            DltFilterConfig::builder()
+                .context_ids(Vec::<String>::new())
+                .build()
+                .err(),
+            Some(Error::EmptyList("context_ids"))
+        );
+        assert!(DltFilterConfig::builder()
+            .min_timestamp(DltTimeStamp::from_ms(2))
+            .max_timestamp(DltTimeStamp::from_ms(1))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_id_normalization() {
+        let normalization = IdNormalization {
+            trim: true,
+            ignore_case: true,
+        };
+        assert_eq!(normalization.normalize("APP\0"), "app");
+        assert_eq!(normalization.normalize("App "), "app");
+        assert_eq!(IdNormalization::default().normalize("APP "), "APP ");
+
+        let config = DltFilterConfig::builder()
+            .app_ids(["app ", "nav*"])
+            .context_id_log_level("ctx\0\0", LogLevel::Error)
+            .id_normalization(normalization)
+            .build()
+            .expect("valid config");
+        let config: ProcessedDltFilterConfig = config.into();
+        assert!(!is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Error),
+            &config
+        ));
+        assert!(!is_filtered_out(
+            &log_message("NAV1", "XY", LogLevel::Info),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Warn),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &log_message("OTHR", "XY", LogLevel::Info),
+            &config
+        ));
+
+        // without normalization ids have to match exactly
+        let exact: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .app_ids(["app"])
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(is_filtered_out(
+            &log_message("APP", "CTX", LogLevel::Error),
+            &exact
+        ));
+
+        let matcher = IdMatcher::with_normalization(["n?v*", "Longer", "ab"], normalization);
+        assert!(matcher.matches("NAV1"));
+        assert!(matcher.matches("LONGER"));
+        assert!(matcher.matches("Ab\0"));
+        assert!(!matcher.matches("ABC"));
+    }
+
+    fn string_arg(text: &str) -> Argument {
+        Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::StringType,
+                coding: StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            fixed_point: None,
+            value: Value::StringVal(text.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_header_and_payload_texts() {
+        let message = |app_id: &str, args: Vec<Argument>| {
+            log_message_with_args(
+                app_id,
+                "CTX",
+                LogLevel::Warn,
+                DltTimeStamp::from_ms(0),
+                None,
+                args,
+            )
+        };
+        let u32_arg = Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::Unsigned(TypeLength::BitLength32),
+                coding: StringCoding::ASCII,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            fixed_point: None,
+            value: Value::U32(42),
+        };
+
+        let header: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .header_texts(["APP CTX log warn"])
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(!is_filtered_out(&message("APP", vec![]), &header));
+        assert!(is_filtered_out(&message("APP1", vec![]), &header));
+
+        let payload: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .payload_texts(["lost 42"])
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(!is_filtered_out(
+            &message("APP", vec![string_arg("connection lost"), u32_arg.clone()]),
+            &payload
+        ));
+        assert!(is_filtered_out(
+            &message("APP", vec![string_arg("connection ok"), u32_arg]),
+            &payload
+        ));
+        assert!(is_filtered_out(&non_verbose_message(1), &payload));
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let control = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+                payload: PayloadContent::ControlMsg(ControlType::Request, vec![0x1].into()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Control(ControlType::Request),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            Some(StorageHeader {
+                timestamp: DltTimeStamp::from_ms(0),
+                ecu_id: DltId::new("ECU1"),
+            }),
+        )
+        .as_bytes();
+        let log = log_message("APP", "CTX", LogLevel::Info);
+
+        let only_control: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .control_messages(true)
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(!is_filtered_out(&control, &only_control));
+        assert!(is_filtered_out(&log, &only_control));
+
+        let no_control: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .control_messages(false)
+            .build()
+            .expect("valid config")
+            .into();
+        assert!(is_filtered_out(&control, &no_control));
+        assert!(!is_filtered_out(&log, &no_control));
+    }
+
+    fn named_arg(name: &str, value: Value, fixed_point: Option<FixedPoint>) -> Argument {
+        Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::Signed(TypeLength::BitLength32),
+                coding: StringCoding::UTF8,
+                has_variable_info: true,
+                has_trace_info: false,
+            },
+            name: Some(name.to_string()),
+            unit: None,
+            fixed_point,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_argument_conditions() {
+        let message = |args: Vec<Argument>| {
+            log_message_with_args(
+                "APP",
+                "CTX",
+                LogLevel::Info,
+                DltTimeStamp::from_ms(0),
+                None,
+                args,
+            )
+        };
+        let config: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .argument_condition("speed > 120".parse().expect("condition"))
+            .build()
+            .expect("valid config")
+            .into();
+
+        assert!(!is_filtered_out(
+            &message(vec![named_arg("speed", Value::I32(130), None)]),
+            &config
+        ));
+        assert!(is_filtered_out(
+            &message(vec![named_arg("speed", Value::I32(120), None)]),
+            &config
+        ));
+        // other arguments don't count
+        assert!(is_filtered_out(
+            &message(vec![named_arg("rpm", Value::I32(5000), None)]),
+            &config
+        ));
+        // fixed point values are compared after scaling
+        assert!(!is_filtered_out(
+            &message(vec![named_arg(
+                "speed",
+                Value::I32(100),
+                Some(FixedPoint {
+                    quantization: 1.5,
+                    offset: FixedPointValue::I32(0),
+                })
+            )]),
+            &config
+        ));
+        assert!(is_filtered_out(&non_verbose_message(1), &config));
+    }
+
+    #[test]
+    fn test_parse_argument_condition() {
+        assert_eq!(
+            "temp<=-5.5".parse::<ArgumentCondition>(),
+            Ok(ArgumentCondition {
+                name: "temp".to_string(),
+                comparison: Comparison::LessOrEqual,
+                value: -5.5,
+            })
+        );
+        assert_eq!(
+            "speed != 0"
+                .parse::<ArgumentCondition>()
+                .map(|c| c.comparison),
+            Ok(Comparison::NotEqual)
+        );
+        assert!(matches!(
+            "speed >".parse::<ArgumentCondition>(),
+            Err(Error::InvalidCondition(_))
+        ));
+        assert!(matches!(
+            "> 5".parse::<ArgumentCondition>(),
+            Err(Error::InvalidCondition(_))
+        ));
+    }
+
+    #[test]
+    fn test_id_matcher_exact_ids() {
+        let matcher = IdMatcher::new(vec!["A", "AB", "ABC", "ABCD", "ABCDE", "A\0", "ABC"]);
+        assert_eq!(matcher.len(), 6);
+        for id in ["A", "AB", "ABC", "ABCD", "ABCDE", "A\0"] {
+            assert!(matcher.matches(id), "{:?} should match", id);
+        }
+        for id in ["", "B", "ABD", "ABCDF", "A\0\0", "AB\0"] {
+            assert!(!matcher.matches(id), "{:?} should not match", id);
+        }
+        let normalized = IdMatcher::with_normalization(
+            vec!["app"],
+            IdNormalization {
+                trim: true,
+                ignore_case: true,
+            },
+        );
+        assert!(normalized.matches("APP\0"));
+    }
+
+    #[test]
+    fn test_matches_parsed_message() {
+        let parse = |bytes: &[u8]| match dlt_message(bytes, None, true).expect("parse message") {
+            (_, ParsedMessage::Item(message)) => message,
+            _ => panic!("no message"),
+        };
+        let messages = vec![
+            log_message("APP", "CTX", LogLevel::Warn),
+            log_message("APP", "CTX", LogLevel::Verbose),
+            log_message("NAV", "CTX", LogLevel::Error),
+            log_message_at("APP", "CTX", LogLevel::Warn, DltTimeStamp::from_ms(5000)),
+            log_message_with_args(
+                "APP",
+                "CTX",
+                LogLevel::Info,
+                DltTimeStamp::from_ms(0),
+                Some(1),
+                vec![string_arg("hello world")],
+            ),
+            non_verbose_message(7),
+        ];
+        let configs: Vec<ProcessedDltFilterConfig> = vec![
+            DltFilterConfig::builder()
+                .min_log_level(LogLevel::Info)
+                .app_ids(["APP"])
+                .build()
+                .expect("valid config")
+                .into(),
+            DltFilterConfig::builder()
+                .max_timestamp(DltTimeStamp::from_ms(1000))
+                .payload_texts(["world"])
+                .build()
+                .expect("valid config")
+                .into(),
+            DltFilterConfig::builder()
+                .excluded_message_ids([7])
+                .session_ids([1])
+                .build()
+                .expect("valid config")
+                .into(),
+        ];
+        for config in &configs {
+            for bytes in &messages {
+                assert_eq!(
+                    config.matches(&parse(bytes)),
+                    !is_filtered_out(bytes, config),
+                    "config: {:?}",
+                    config
+                );
+            }
+        }
+
+        // headers pass, but the payload text does not
+        let message = parse(&log_message("NAV", "CTX", LogLevel::Error));
+        assert!(configs[1].matches_headers(&message.header, message.extended_header.as_ref()));
+        assert!(!configs[1].matches_payload(&message.payload));
+        assert!(!configs[1].matches(&message));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{dlt::*, fmt::*, tests::TestMessage};
+
+    fn string_argument(value: &str) -> Argument {
+        Argument {
+            type_info: TypeInfo {
+                kind: TypeInfoKind::StringType,
+                coding: StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            fixed_point: None,
+            value: Value::StringVal(value.to_string()),
+        }
+    }
+
+    fn log_message(level: LogLevel, payload: PayloadContent) -> Message {
+        let stored = DltTimeStamp {
+            seconds: 1_700_000_000,
+            microseconds: 42,
+        };
+        TestMessage::new("APP")
+            .level(level)
+            .timestamp(Some(12_345_678))
+            .payload(payload)
+            .stored(stored, "ECU1")
+            .build()
+    }
+
+    fn verbose(values: &[&str]) -> PayloadContent {
+        PayloadContent::Verbose(values.iter().map(|v| string_argument(v)).collect())
+    }
+
+    #[test]
+    fn test_default_columns() {
+        let message = log_message(LogLevel::Warn, verbose(&["hello", "world"]));
+        assert_eq!(
+            MessageFormatter::new().format(7, &message),
+            "7 1700000000.000042 1234.5678 ECU1 APP CTX warn hello world"
+        );
+    }
+
+    #[test]
+    fn test_columns_widths_and_separator() {
+        let message = log_message(LogLevel::Info, verbose(&["text"]));
+        let mut formatter = MessageFormatter::new()
+            .width(Column::Index, 4)
+            .columns([Column::Index, Column::Ecu, Column::Level, Column::Payload])
+            .width(Column::Ecu, 6)
+            .width(Column::Timestamp, 20)
+            .separator("|");
+        assert_eq!(formatter.format(1, &message), "1   |ECU1  |info|text");
+
+        let mut out = String::new();
+        formatter
+            .write_message(&mut out, 12345, &message)
+            .expect("write");
+        assert_eq!(out, "12345|ECU1  |info|text");
+    }
+
+    #[test]
+    fn test_payload_text() {
+        let non_verbose = log_message(
+            LogLevel::Info,
+            PayloadContent::NonVerbose(42, vec![1, 0xab].into()),
+        );
+        assert_eq!(payload_text(&non_verbose), "[42] 01 ab");
+        let mut trace = log_message(
+            LogLevel::Info,
+            PayloadContent::NetworkTrace(vec![vec![1, 2].into(), vec![0xff].into()]),
+        );
+        assert_eq!(payload_text(&trace), "01 02 ff");
+        trace.extended_header.as_mut().unwrap().message_type =
+            MessageType::NetworkTrace(NetworkTraceType::Can);
+        trace.storage_header = None;
+        assert_eq!(
+            MessageFormatter::new().format(0, &trace),
+            "0  1234.5678 ECU1 APP CTX nw_trace can 01 02 ff"
+        );
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        let timestamp = DltTimeStamp {
+            seconds: 0,
+            microseconds: 5,
+        };
+        assert_eq!(iso_8601(&timestamp, 0), "1970-01-01T00:00:00.000005Z");
+        assert_eq!(
+            iso_8601(&timestamp, -150),
+            "1969-12-31T21:30:00.000005-02:30"
+        );
+        // leap day and the last second of a year
+        let leap_day = DltTimeStamp {
+            seconds: 951_825_600,
+            microseconds: 0,
+        };
+        assert_eq!(iso_8601(&leap_day, 0), "2000-02-29T12:00:00.000000Z");
+        let new_year = DltTimeStamp {
+            seconds: 4_102_444_799,
+            microseconds: 999_999,
+        };
+        assert_eq!(iso_8601(&new_year, 0), "2099-12-31T23:59:59.999999Z");
+        assert_eq!(iso_8601(&new_year, 60), "2100-01-01T00:59:59.999999+01:00");
+    }
+
+    #[test]
+    fn test_time_formats() {
+        let mut later = log_message(LogLevel::Info, verbose(&["b"]));
+        later.storage_header.as_mut().unwrap().timestamp = DltTimeStamp {
+            seconds: 1_700_000_012,
+            microseconds: 500,
+        };
+        let message = log_message(LogLevel::Info, verbose(&["a"]));
+        let formatter = MessageFormatter::new().columns([Column::StorageTime, Column::Payload]);
+
+        let mut iso = formatter
+            .clone()
+            .time_format(TimeFormat::Iso8601 { offset_minutes: 0 });
+        assert_eq!(iso.format(0, &message), "2023-11-14T22:13:20.000042Z a");
+
+        let mut relative = formatter.clone().time_format(TimeFormat::Relative(None));
+        assert_eq!(relative.format(0, &message), "0.000000 a");
+        assert_eq!(relative.format(1, &later), "12.000458 b");
+
+        let start = DltTimeStamp {
+            seconds: 1_700_000_001,
+            microseconds: 0,
+        };
+        let mut relative = formatter.time_format(TimeFormat::Relative(Some(start)));
+        assert_eq!(relative.format(0, &message), "-0.999958 a");
+        assert_eq!(relative.format(1, &later), "11.000500 b");
+    }
+
+    #[test]
+    fn test_level_tags_and_colors() {
+        let mut formatter = MessageFormatter::new()
+            .columns([Column::Level, Column::Payload])
+            .level_tags(true);
+        let error = log_message(LogLevel::Error, verbose(&["failed"]));
+        let warn = log_message(LogLevel::Warn, verbose(&["careful"]));
+        let info = log_message(LogLevel::Info, verbose(&["fine"]));
+        assert_eq!(formatter.format(0, &error), "[ERROR] failed");
+        assert_eq!(formatter.format(0, &warn), "[WARN] careful");
+
+        let mut formatter = formatter.width(Column::Level, 7).colors(true);
+        assert_eq!(formatter.format(0, &error), "\x1b[31m[ERROR] failed\x1b[0m");
+        assert_eq!(formatter.format(0, &warn), "\x1b[33m[WARN]  careful\x1b[0m");
+        assert_eq!(formatter.format(0, &info), "[INFO]  fine");
+
+        let mut trace = info;
+        trace.extended_header.as_mut().unwrap().message_type =
+            MessageType::ApplicationTrace(ApplicationTraceType::Variable);
+        assert_eq!(formatter.format(0, &trace), "app_trace variable fine");
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let data: Vec<u8> = (0x3c..0x50).collect();
+        assert_eq!(
+            HexDump::new().dump(&data),
+            "00000000  3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|\n\
+             00000010  4c 4d 4e 4f                                       |LMNO|"
+        );
+        assert_eq!(
+            HexDump::new()
+                .bytes_per_line(4)
+                .base_offset(0x20)
+                .dump(&[0, b'a', 0x7f, b' ', 0xff]),
+            "00000020  00 61 7f 20  |.a. |\n00000024  ff           |.|"
+        );
+        assert_eq!(
+            HexDump::new().offsets(false).bytes_per_line(2).dump(&[1]),
+            "01     |.|"
+        );
+        assert_eq!(HexDump::new().dump(&[]), "");
+    }
+
+    #[test]
+    fn test_hex_dump_payload() {
+        let dump = HexDump::new().bytes_per_line(4);
+        let non_verbose = log_message(
+            LogLevel::Info,
+            PayloadContent::NonVerbose(1, vec![b'A'].into()),
+        );
+        assert_eq!(
+            dump.payload(&non_verbose).as_deref(),
+            Some("00000000  41           |A|")
+        );
+        let trace = log_message(
+            LogLevel::Info,
+            PayloadContent::NetworkTrace(vec![vec![1, 2].into(), vec![b'x'].into()]),
+        );
+        assert_eq!(
+            dump.payload(&trace).as_deref(),
+            Some("00000000  01 02        |..|\n\n00000000  78           |x|")
+        );
+        let mut raw = string_argument("");
+        raw.value = Value::Raw(vec![b'o', b'k']);
+        let mixed = log_message(
+            LogLevel::Info,
+            PayloadContent::Verbose([string_argument("text"), raw].into_iter().collect()),
+        );
+        assert_eq!(
+            dump.payload(&mixed).as_deref(),
+            Some("00000000  6f 6b        |ok|")
+        );
+        let text = log_message(LogLevel::Info, verbose(&["text"]));
+        assert_eq!(dump.payload(&text), None);
+    }
+
+    #[test]
+    fn test_format_message_into() {
+        let mut options = MessageFormatter::new()
+            .width(Column::Index, 3)
+            .width(Column::Payload, 8)
+            .level_tags(true)
+            .time_format(TimeFormat::Iso8601 { offset_minutes: 0 });
+        let message = log_message(LogLevel::Debug, verbose(&["größe", "1"]));
+        let mut out = String::new();
+        format_message_into(&message, &mut out, &mut options).expect("write");
+        out.push('|');
+        format_message_into(&message, &mut out, &mut options).expect("write");
+        assert_eq!(
+            out,
+            "0   2023-11-14T22:13:20.000042Z 1234.5678 ECU1 APP CTX [DEBUG] größe 1 |\
+             1   2023-11-14T22:13:20.000042Z 1234.5678 ECU1 APP CTX [DEBUG] größe 1 "
+        );
+        assert_eq!(options.format(0, &message), out.split('|').next().unwrap());
+        fn assert_sync<T: Send + Sync>(_: &T) {}
+        assert_sync(&options);
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dlt::*, index::*, parse::ParsedMessage, read::DltMessageReader, tests::TestMessage,
+    };
+    use std::{convert::TryFrom, io::Cursor};
+
+    fn log_message(app_id: &str, storage_ms: u64, text_length: usize) -> Message {
+        TestMessage::new(app_id)
+            .payload(PayloadContent::NonVerbose(1, vec![0; text_length].into()))
+            .stored(DltTimeStamp::from_ms(storage_ms), "STOR")
+            .build()
+    }
+
+    #[test]
+    fn test_index_and_random_access() {
+        let messages: Vec<Message> = (0..5)
+            .map(|i| log_message(&format!("APP{}", i), i as u64 * 10, i))
+            .collect();
+        let bytes: Vec<u8> = messages.iter().flat_map(|m| m.as_bytes()).collect();
+
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        let index = build_index(&mut reader).expect("index");
+        assert_eq!(index.len(), 5);
+        assert_eq!(index.indexed_bytes(), bytes.len() as u64);
+        assert_eq!(build_index_from_slice(&bytes).expect("index"), index);
+        // incomplete messages at the end are not indexed
+        assert_eq!(
+            build_index_from_slice(&bytes[..bytes.len() - 1])
+                .expect("index")
+                .len(),
+            4
+        );
+
+        let third = index.get(2).expect("entry");
+        let length = |n: usize| messages[n].as_bytes().len();
+        assert_eq!(third.offset, (length(0) + length(1)) as u64);
+        assert_eq!(third.length as usize, length(2));
+        assert_eq!(third.timestamp, Some(DltTimeStamp::from_ms(20)));
+        assert_eq!(third.ecu_id, DltId::try_from("ECU1").ok());
+        assert_eq!(third.application_id, DltId::try_from("APP2").ok());
+        assert_eq!(third.context_id, DltId::try_from("CTX").ok());
+
+        let mut indexed = DltIndexedReader::new(Cursor::new(bytes), index);
+        assert_eq!(
+            indexed.messages(3..10).expect("messages"),
+            vec![
+                ParsedMessage::Item(messages[3].clone()),
+                ParsedMessage::Item(messages[4].clone())
+            ]
+        );
+        assert_eq!(
+            indexed.message(1).expect("message"),
+            Some(ParsedMessage::Item(messages[1].clone()))
+        );
+        assert_eq!(indexed.message(5).expect("message"), None);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_build_index_async() {
+        let bytes: Vec<u8> = (0..3)
+            .flat_map(|i| log_message("APP", i, i as usize).as_bytes())
+            .collect();
+        let expected =
+            build_index(&mut DltMessageReader::new(bytes.as_slice(), true)).expect("index");
+
+        let mut reports = vec![];
+        let mut source = futures::io::Cursor::new(bytes.clone());
+        source.set_position(7);
+        let index = futures::executor::block_on(build_index_async(source, true, |progress| {
+            reports.push(progress)
+        }))
+        .expect("index");
+        assert_eq!(index, expected);
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[2].processed, bytes.len() as u64);
+        assert_eq!(reports[2].fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn test_index_with_corrupted_data() {
+        let messages: Vec<Message> = (0..3)
+            .map(|i| log_message(&format!("APP{}", i), i as u64 * 10, 4))
+            .collect();
+        let garbage = b"\x01\x02corrupted\x00";
+        let mut bytes = messages[0].as_bytes();
+        bytes.extend_from_slice(garbage);
+        bytes.extend(messages[1].as_bytes());
+        bytes.extend(messages[2].as_bytes());
+
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_recovery(true);
+        let index = build_index(&mut reader).expect("index");
+        assert_eq!(index.len(), 3);
+        let second = index.get(1).expect("entry");
+        assert_eq!(
+            second.offset,
+            (messages[0].as_bytes().len() + garbage.len()) as u64
+        );
+        assert_eq!(index.indexed_bytes(), bytes.len() as u64);
+
+        let mut indexed = DltIndexedReader::new(Cursor::new(bytes), index);
+        assert_eq!(
+            indexed.messages(1..3).expect("messages"),
+            vec![
+                ParsedMessage::Item(messages[1].clone()),
+                ParsedMessage::Item(messages[2].clone())
+            ]
+        );
+    }
+}
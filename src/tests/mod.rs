@@ -13,7 +13,120 @@
 // limitations under the License.
 #[macro_use]
 mod dlt_tests;
+mod control_tests;
+mod dlf_tests;
 mod dlt_parse_tests;
+mod export_tests;
 mod fibex_tests;
+mod filtering_tests;
+mod fmt_tests;
+mod index_tests;
+mod net_tests;
+mod nw_trace_tests;
+#[cfg(feature = "pcap")]
+mod pcap_tests;
+mod read_tests;
+#[cfg(feature = "serial")]
+mod serial_tests;
 #[cfg(feature = "statistics")]
 mod statistics_tests;
+#[cfg(feature = "stream")]
+mod stream_tests;
+
+use crate::dlt::{
+    Arguments, DltId, DltTimeStamp, Endianness, ExtendedHeaderConfig, LogLevel, Message,
+    MessageConfig, MessageType, PayloadContent, StorageHeader,
+};
+
+/// Builds the messages of the tests
+///
+/// Starts with a big endian info message of `ECU1` and context `CTX` with a
+/// non-verbose payload and without storage header.
+pub(crate) struct TestMessage {
+    config: MessageConfig,
+    storage_header: Option<StorageHeader>,
+}
+
+impl TestMessage {
+    pub(crate) fn new(app_id: &str) -> Self {
+        TestMessage {
+            config: MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(5),
+                payload: PayloadContent::NonVerbose(1, vec![1, 2, 3].into()),
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::Log(LogLevel::Info),
+                    app_id: app_id.to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            storage_header: None,
+        }
+    }
+
+    /// Only used by the statistics tests
+    #[allow(dead_code)]
+    pub(crate) fn context_id(mut self, context_id: &str) -> Self {
+        if let Some(info) = self.config.extended_header_info.as_mut() {
+            info.context_id = context_id.to_string();
+        }
+        self
+    }
+
+    pub(crate) fn ecu_id(mut self, ecu_id: &str) -> Self {
+        self.config.ecu_id = Some(ecu_id.to_string());
+        self
+    }
+
+    pub(crate) fn endianness(mut self, endianness: Endianness) -> Self {
+        self.config.endianness = endianness;
+        self
+    }
+
+    pub(crate) fn counter(mut self, counter: u8) -> Self {
+        self.config.counter = counter;
+        self
+    }
+
+    pub(crate) fn timestamp(mut self, timestamp: Option<u32>) -> Self {
+        self.config.timestamp = timestamp;
+        self
+    }
+
+    pub(crate) fn level(self, level: LogLevel) -> Self {
+        self.message_type(MessageType::Log(level))
+    }
+
+    pub(crate) fn message_type(mut self, message_type: MessageType) -> Self {
+        if let Some(info) = self.config.extended_header_info.as_mut() {
+            info.message_type = message_type;
+        }
+        self
+    }
+
+    pub(crate) fn payload(mut self, payload: PayloadContent) -> Self {
+        self.config.payload = payload;
+        self
+    }
+
+    /// Empty verbose payload
+    pub(crate) fn verbose(self) -> Self {
+        self.payload(PayloadContent::Verbose(Arguments::new()))
+    }
+
+    pub(crate) fn stored(mut self, timestamp: DltTimeStamp, ecu_id: &str) -> Self {
+        self.storage_header = Some(StorageHeader {
+            timestamp,
+            ecu_id: DltId::new(ecu_id),
+        });
+        self
+    }
+
+    pub(crate) fn build(self) -> Message {
+        Message::new(self.config, self.storage_header)
+    }
+}
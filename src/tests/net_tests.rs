@@ -0,0 +1,258 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dlt::*,
+        net::{tcp::*, udp::*},
+        parse::ParsedMessage,
+        tests::TestMessage,
+    };
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, UdpSocket},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_tcp_client_reconnects() {
+        let messages = [
+            TestMessage::new("APP1").build(),
+            TestMessage::new("APP2").build(),
+        ];
+        let request = TestMessage::new("CTRL").build();
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().expect("address");
+        let (first, second, expected_request) = (
+            messages[0].as_bytes(),
+            messages[1].as_bytes(),
+            request.as_bytes(),
+        );
+        let daemon = std::thread::spawn(move || {
+            let (mut connection, _) = listener.accept().expect("accept");
+            connection.write_all(&first).expect("write");
+            connection.write_all(&second[..5]).expect("write");
+            let mut received = vec![0; expected_request.len()];
+            connection.read_exact(&mut received).expect("read request");
+            assert_eq!(received, expected_request);
+            drop(connection);
+            let (mut connection, _) = listener.accept().expect("accept again");
+            connection.write_all(&second).expect("write");
+        });
+
+        let mut client = DltTcpClient::connect(address).expect("connect");
+        client.set_reconnect(Some(ReconnectPolicy {
+            delay: Duration::from_millis(10),
+            attempts: Some(2),
+        }));
+        assert_eq!(
+            client.next_message().expect("first"),
+            Some(ParsedMessage::Item(messages[0].clone()))
+        );
+        client.send_message(&request).expect("send");
+        // the partly sent message is dropped with the first connection
+        assert_eq!(
+            client.next_message().expect("second"),
+            Some(ParsedMessage::Item(messages[1].clone()))
+        );
+        daemon.join().expect("daemon");
+        assert_eq!(client.next_message().expect("end"), None);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_client() {
+        use rustls::{
+            crypto::ring::default_provider,
+            pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+            ClientConfig, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+        };
+        use std::{path::PathBuf, sync::Arc};
+
+        let read = |name: &str| {
+            std::fs::read(
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/tls")
+                    .join(name),
+            )
+            .expect("test certificate")
+        };
+        let server_config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("protocol versions")
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(read("server.der"))],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(read("server.key.der"))),
+            )
+            .expect("server config");
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(read("ca.der")))
+            .expect("root certificate");
+        let client_config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let messages = [
+            TestMessage::new("APP1").build(),
+            TestMessage::new("APP2").build(),
+        ];
+        let request = TestMessage::new("CTRL").build();
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().expect("address");
+        let (first, second, expected_request) = (
+            messages[0].as_bytes(),
+            messages[1].as_bytes(),
+            request.as_bytes(),
+        );
+        let daemon = std::thread::spawn(move || {
+            let (socket, _) = listener.accept().expect("accept");
+            let connection = ServerConnection::new(Arc::new(server_config)).expect("server");
+            let mut stream = StreamOwned::new(connection, socket);
+            stream.write_all(&first).expect("write");
+            let mut received = vec![0; expected_request.len()];
+            stream.read_exact(&mut received).expect("read request");
+            assert_eq!(received, expected_request);
+            stream.write_all(&second).expect("write");
+            stream.conn.send_close_notify();
+            stream.flush().expect("flush");
+        });
+
+        let server_name = ServerName::try_from("localhost").expect("server name");
+        let mut client = DltTcpClient::connect_tls(address, server_name, Arc::new(client_config))
+            .expect("connect");
+        assert_eq!(
+            client.next_message().expect("first"),
+            Some(ParsedMessage::Item(messages[0].clone()))
+        );
+        client.send_message(&request).expect("send");
+        assert_eq!(
+            client.next_message().expect("second"),
+            Some(ParsedMessage::Item(messages[1].clone()))
+        );
+        assert_eq!(client.next_message().expect("end"), None);
+        daemon.join().expect("daemon");
+    }
+
+    #[test]
+    fn test_udp_receiver() {
+        let messages = [
+            TestMessage::new("APP1").build(),
+            TestMessage::new("APP2").build(),
+            TestMessage::new("APP3").build(),
+        ];
+        let mut receiver = DltUdpReceiver::bind("127.0.0.1:0").expect("bind");
+        let address = receiver.local_addr().expect("address");
+        let sender = UdpSocket::bind("127.0.0.1:0").expect("bind sender");
+        let datagram = [messages[0].as_bytes(), messages[1].as_bytes()].concat();
+        sender.send_to(&datagram, address).expect("send");
+        sender
+            .send_to(&[messages[2].as_bytes(), vec![1, 2]].concat(), address)
+            .expect("send");
+        for message in &messages {
+            assert_eq!(
+                receiver.next_message().expect("message"),
+                ReceivedMessage {
+                    source: sender.local_addr().expect("sender address"),
+                    message: ParsedMessage::Item(message.clone()),
+                }
+            );
+        }
+        receiver.cancellation_token().cancel();
+        assert!(receiver.next_message().is_err());
+    }
+
+    #[test]
+    fn test_udp_receiver_loss_estimate() {
+        let counted = |app_id: &str, counter: u8| {
+            let mut message = TestMessage::new(app_id).build();
+            message.header.message_counter = counter;
+            message
+        };
+        // APP1 loses 2 and wraps around, APP2 repeats a counter
+        let messages = [
+            counted("APP1", 254),
+            counted("APP2", 7),
+            counted("APP1", 1),
+            counted("APP2", 7),
+            counted("APP2", 8),
+        ];
+        let mut receiver = DltUdpReceiver::bind("127.0.0.1:0").expect("bind");
+        let address = receiver.local_addr().expect("address");
+        let sender = UdpSocket::bind("127.0.0.1:0").expect("bind sender");
+        for message in &messages {
+            sender.send_to(&message.as_bytes(), address).expect("send");
+            receiver.next_message().expect("message");
+        }
+
+        let total = receiver.loss_estimate();
+        assert_eq!(
+            total,
+            LossEstimate {
+                received: 5,
+                lost: 2,
+                duplicated: 1,
+            }
+        );
+        assert!((total.loss_ratio() - 2.0 / 7.0).abs() < f64::EPSILON);
+        let source = |app_id: &str| CounterSource {
+            ecu_id: DltId::try_from("ECU1").ok(),
+            application_id: DltId::try_from(app_id).ok(),
+            context_id: DltId::try_from("CTX").ok(),
+        };
+        let estimates = receiver.loss_estimates();
+        assert_eq!(estimates.len(), 2);
+        assert_eq!(estimates[&source("APP1")].lost, 2);
+        assert_eq!(estimates[&source("APP2")].lost, 0);
+        assert_eq!(estimates[&source("APP2")].duplicated, 1);
+
+        receiver.reset_loss_estimate();
+        assert_eq!(receiver.loss_estimate(), LossEstimate::default());
+    }
+
+    #[test]
+    fn test_tcp_client_segmented_stream() {
+        let messages = [
+            TestMessage::new("APP1").build(),
+            TestMessage::new("APP2").build(),
+            TestMessage::new("APP3").build(),
+        ];
+        let bytes: Vec<u8> = messages.iter().flat_map(|m| m.as_bytes()).collect();
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().expect("address");
+        let daemon = std::thread::spawn(move || {
+            let (mut connection, _) = listener.accept().expect("accept");
+            connection.set_nodelay(true).expect("nodelay");
+            // single bytes, then the rest coalesced
+            for byte in &bytes[..30] {
+                connection.write_all(&[*byte]).expect("write");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            connection.write_all(&bytes[30..]).expect("write");
+        });
+
+        let mut client = DltTcpClient::connect(address).expect("connect");
+        for message in &messages {
+            assert_eq!(
+                client.next_message().expect("message"),
+                Some(ParsedMessage::Item(message.clone()))
+            );
+        }
+        daemon.join().expect("daemon");
+        assert_eq!(client.next_message().expect("end"), None);
+    }
+}
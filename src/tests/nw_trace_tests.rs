@@ -0,0 +1,510 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dlt::*,
+        nw_trace::{
+            can::{self, *},
+            ipc::{self, *},
+            registry::*,
+            segmented::*,
+            someip::{self, *},
+            someip_sd::{self, *},
+            Error,
+        },
+        parse::{dlt_message, ParsedMessage},
+    };
+    use bytes::Bytes;
+    use std::net::{SocketAddr, SocketAddrV4};
+
+    fn trace(trace_type: NetworkTraceType, slices: Vec<Vec<u8>>) -> Message {
+        trace_message(
+            trace_type,
+            PayloadContent::NetworkTrace(slices.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    fn trace_message(trace_type: NetworkTraceType, payload: PayloadContent) -> Message {
+        let message = Message::new(
+            MessageConfig {
+                version: 1,
+                counter: 0,
+                endianness: Endianness::Big,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(5),
+                payload,
+                extended_header_info: Some(ExtendedHeaderConfig {
+                    message_type: MessageType::NetworkTrace(trace_type),
+                    app_id: "APP".to_string(),
+                    context_id: "CTX".to_string(),
+                }),
+            },
+            None,
+        );
+        match dlt_message(&message.as_bytes(), None, false) {
+            Ok((_, ParsedMessage::Item(parsed))) => parsed,
+            other => panic!("unexpected parse result: {:?}", other),
+        }
+    }
+
+    #[rustfmt::skip]
+    const SOMEIP_NOTIFICATION: &[u8] = &[
+        /* service, method */ 0xf9, 0x61, 0x85, 0x1d,
+        /* length */ 0x00, 0x00, 0x00, 0x10,
+        /* client, session */ 0x00, 0x00, 0x28, 0x15,
+        /* protocol, interface, type, return code */ 0x01, 0x01, 0x02, 0x00,
+        /* payload */ 0x01, 0xfe, 0xfc, 0xff, 0xff, 0xff, 0xff, 0xff,
+    ];
+
+    #[test]
+    fn test_someip_decode() {
+        let info = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x01, 0x00, 0x01];
+        let message = trace(
+            NetworkTraceType::Someip,
+            vec![info, SOMEIP_NOTIFICATION.to_vec()],
+        );
+        let messages = someip::decode(&message)
+            .expect("decode")
+            .expect("someip trace");
+        assert_eq!(
+            messages,
+            vec![SomeIpMessage {
+                header: SomeIpHeader {
+                    service_id: 0xf961,
+                    method_id: 0x851d,
+                    length: 16,
+                    client_id: 0,
+                    session_id: 0x2815,
+                    protocol_version: 1,
+                    interface_version: 1,
+                    message_type: SomeIpMessageType::Notification,
+                    return_code: SomeIpReturnCode::Ok,
+                },
+                payload: &SOMEIP_NOTIFICATION[16..],
+            }]
+        );
+        assert!(messages[0].header.is_event());
+    }
+
+    #[test]
+    fn test_someip_decode_back_to_back() {
+        let mut response = SOMEIP_NOTIFICATION[..16].to_vec();
+        response[7] = 8; // no payload
+        response[14] = 0x81;
+        response[15] = 0x03;
+        let slice = [SOMEIP_NOTIFICATION, &response].concat();
+        let message = trace(NetworkTraceType::Someip, vec![slice]);
+        let messages = someip::decode(&message)
+            .expect("decode")
+            .expect("someip trace");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].header.message_type, SomeIpMessageType::Error);
+        assert_eq!(
+            messages[1].header.return_code,
+            SomeIpReturnCode::UnknownMethod
+        );
+        assert!(messages[1].payload.is_empty());
+    }
+
+    #[test]
+    fn test_someip_decode_errors() {
+        let message = trace(NetworkTraceType::Can, vec![SOMEIP_NOTIFICATION.to_vec()]);
+        assert_eq!(someip::decode(&message), Ok(None));
+
+        let truncated = trace(
+            NetworkTraceType::Someip,
+            vec![SOMEIP_NOTIFICATION[..20].to_vec()],
+        );
+        assert_eq!(
+            someip::decode(&truncated),
+            Err(Error::Incomplete {
+                what: "SOME/IP payload",
+                needed: 24,
+                available: 20,
+            })
+        );
+
+        let mut invalid = SOMEIP_NOTIFICATION.to_vec();
+        invalid[7] = 4;
+        assert!(matches!(
+            SomeIpHeader::parse(&invalid),
+            Err(Error::Invalid { .. })
+        ));
+    }
+
+    #[rustfmt::skip]
+    const SD_PAYLOAD: &[u8] = &[
+        /* flags, reserved */ 0xc0, 0x00, 0x00, 0x00,
+        /* entries length */ 0x00, 0x00, 0x00, 0x20,
+        /* offer: type, options 0 and 2, 1 + 1 options */ 0x01, 0x00, 0x02, 0x11,
+        /* service, instance */ 0x12, 0x34, 0x00, 0x01,
+        /* major, ttl */ 0x01, 0x00, 0x00, 0x03,
+        /* minor */ 0x00, 0x00, 0x00, 0x05,
+        /* subscribe: type, option 1 */ 0x06, 0x01, 0x00, 0x10,
+        /* service, instance */ 0x12, 0x34, 0x00, 0x01,
+        /* major, ttl 0 */ 0x01, 0x00, 0x00, 0x00,
+        /* reserved, counter, eventgroup */ 0x00, 0x02, 0x00, 0x07,
+        /* options length */ 0x00, 0x00, 0x00, 0x29,
+        /* ipv4 endpoint */ 0x00, 0x09, 0x04, 0x00,
+        192, 168, 0, 1, 0x00, 0x11, 0x77, 0x2d,
+        /* ipv4 multicast */ 0x00, 0x09, 0x14, 0x00,
+        239, 0, 0, 1, 0x00, 0x11, 0x77, 0x2e,
+        /* configuration */ 0x00, 0x06, 0x01, 0x00,
+        0x03, b'a', b'=', b'b', 0x00,
+        /* load balancing */ 0x00, 0x05, 0x02, 0x00,
+        0x00, 0x01, 0x00, 0x02,
+    ];
+
+    #[test]
+    fn test_someip_sd_decode() {
+        let header = [
+            &[0xff, 0xff, 0x81, 0x00],
+            &((SD_PAYLOAD.len() + 8) as u32).to_be_bytes(),
+            &[0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00][..],
+        ]
+        .concat();
+        let slice = [SOMEIP_NOTIFICATION, &header, SD_PAYLOAD].concat();
+        let message = trace(NetworkTraceType::Someip, vec![slice]);
+        let sd_messages = someip_sd::decode(&message)
+            .expect("decode")
+            .expect("someip trace");
+        assert_eq!(sd_messages.len(), 1);
+        let sd_message = &sd_messages[0];
+        assert!(sd_message.reboot && sd_message.unicast);
+        assert_eq!(
+            sd_message.entries,
+            vec![
+                SdEntry {
+                    entry_type: SdEntryType::OfferService,
+                    service_id: 0x1234,
+                    instance_id: 1,
+                    major_version: 1,
+                    ttl: 3,
+                    target: SdEntryTarget::Service { minor_version: 5 },
+                    option_runs: [(0, 1), (2, 1)],
+                },
+                SdEntry {
+                    entry_type: SdEntryType::StopSubscribeEventgroup,
+                    service_id: 0x1234,
+                    instance_id: 1,
+                    major_version: 1,
+                    ttl: 0,
+                    target: SdEntryTarget::Eventgroup {
+                        counter: 2,
+                        eventgroup_id: 7,
+                    },
+                    option_runs: [(1, 1), (0, 0)],
+                },
+            ]
+        );
+        let unicast = SdOption::Endpoint {
+            kind: EndpointKind::Unicast,
+            address: "192.168.0.1:30509".parse::<SocketAddr>().unwrap(),
+            protocol: TransportProtocol::Udp,
+        };
+        let configuration = SdOption::Configuration(vec!["a=b".to_string()]);
+        assert_eq!(
+            sd_message.entries[0]
+                .options(sd_message)
+                .collect::<Vec<_>>(),
+            vec![&unicast, &configuration]
+        );
+        assert_eq!(
+            sd_message.options[1],
+            SdOption::Endpoint {
+                kind: EndpointKind::Multicast,
+                address: "239.0.0.1:30510".parse::<SocketAddr>().unwrap(),
+                protocol: TransportProtocol::Udp,
+            }
+        );
+        assert_eq!(
+            sd_message.options[3],
+            SdOption::LoadBalancing {
+                priority: 1,
+                weight: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_someip_sd_invalid_entries() {
+        let mut payload = SD_PAYLOAD.to_vec();
+        payload[7] = 0x1f;
+        assert!(matches!(
+            SdMessage::parse(&payload),
+            Err(Error::Invalid { .. })
+        ));
+        assert!(matches!(
+            SdMessage::parse(&SD_PAYLOAD[..50]),
+            Err(Error::Incomplete { .. })
+        ));
+    }
+
+    #[test]
+    fn test_can_decode() {
+        let message = trace(
+            NetworkTraceType::Can,
+            vec![vec![0x01, 0x23], vec![0xde, 0xad]],
+        );
+        assert_eq!(
+            can::decode(&message),
+            Ok(Some(CanFrame {
+                id: 0x123,
+                flags: CanFlags::default(),
+                dlc: 2,
+                data: vec![0xde, 0xad],
+            }))
+        );
+
+        let frame = CanFrame {
+            id: 0x18da_f110,
+            flags: CanFlags {
+                extended: true,
+                fd: true,
+                bit_rate_switch: true,
+                ..CanFlags::default()
+            },
+            dlc: 13,
+            data: vec![0x55; 32],
+        };
+        let slices = frame.to_slices();
+        assert_eq!(slices[0], vec![0x98, 0xda, 0xf1, 0x10, 0x05]);
+        let message = trace(NetworkTraceType::Can, slices);
+        assert_eq!(can::decode(&message), Ok(Some(frame)));
+    }
+
+    #[test]
+    fn test_can_decode_errors() {
+        let too_long = trace(NetworkTraceType::Can, vec![vec![0x01, 0x23], vec![0; 9]]);
+        assert!(matches!(can::decode(&too_long), Err(Error::Invalid { .. })));
+        let single = trace(NetworkTraceType::Can, vec![vec![0x01, 0x23]]);
+        assert!(matches!(can::decode(&single), Err(Error::Invalid { .. })));
+        let someip = trace(NetworkTraceType::Someip, vec![vec![0x01, 0x23], vec![]]);
+        assert_eq!(can::decode(&someip), Ok(None));
+    }
+
+    #[test]
+    fn test_registry_decodes_user_defined_traces() {
+        let mut registry = NetworkTraceRegistry::new();
+        registry
+            .register(7, "LIN", |slices: &[Bytes]| match slices {
+                [header, data] if header.len() == 1 => Ok(vec![
+                    DecodedField::new("id", Value::U8(header[0])),
+                    DecodedField::new("data", Value::Raw(data.to_vec())),
+                ]),
+                _ => Err(Error::Invalid {
+                    what: "LIN trace",
+                    reason: "unexpected slices".to_string(),
+                }),
+            })
+            .expect("register");
+        assert!(registry
+            .register(6, "MOST", |_: &[Bytes]| Ok(vec![]))
+            .is_err());
+        assert_eq!(registry.protocol(7), Some("LIN"));
+
+        let lin = trace(
+            NetworkTraceType::UserDefined(7),
+            vec![vec![0x3c], vec![1, 2]],
+        );
+        let decoded = registry.process(lin.clone());
+        assert_eq!(decoded.message, lin);
+        assert_eq!(
+            decoded.trace,
+            Some(Ok(DecodedTrace {
+                protocol: "LIN".to_string(),
+                fields: vec![
+                    DecodedField::new("id", Value::U8(0x3c)),
+                    DecodedField::new("data", Value::Raw(vec![1, 2])),
+                ],
+            }))
+        );
+        let invalid = trace(NetworkTraceType::UserDefined(7), vec![vec![0x3c]]);
+        assert!(matches!(registry.decode(&invalid), Some(Err(_))));
+
+        let unknown = trace(NetworkTraceType::UserDefined(8), vec![vec![0x3c]]);
+        assert_eq!(registry.decode(&unknown), None);
+        let can = trace(NetworkTraceType::Can, vec![vec![0x3c], vec![]]);
+        assert_eq!(registry.decode(&can), None);
+
+        assert!(registry.unregister(7));
+        assert_eq!(registry.clone().process(lin).trace, None);
+    }
+
+    fn segment(values: Vec<Value>) -> Message {
+        let arguments = values
+            .into_iter()
+            .map(|value| {
+                let kind = match &value {
+                    Value::StringVal(_) => TypeInfoKind::StringType,
+                    Value::U16(_) => TypeInfoKind::Unsigned(TypeLength::BitLength16),
+                    Value::U32(_) => TypeInfoKind::Unsigned(TypeLength::BitLength32),
+                    _ => TypeInfoKind::Raw,
+                };
+                Argument {
+                    type_info: TypeInfo {
+                        kind,
+                        coding: StringCoding::UTF8,
+                        has_variable_info: false,
+                        has_trace_info: false,
+                    },
+                    name: None,
+                    unit: None,
+                    fixed_point: None,
+                    value,
+                }
+            })
+            .collect();
+        trace_message(NetworkTraceType::Someip, PayloadContent::Verbose(arguments))
+    }
+
+    fn start(handle: u32, length: u32, count: u16) -> Message {
+        segment(vec![
+            Value::StringVal(SEGMENT_START.to_string()),
+            Value::U32(handle),
+            Value::Raw(vec![0xff, 0x01]),
+            Value::U32(length),
+            Value::U16(count),
+            Value::U16(10),
+        ])
+    }
+
+    fn chunk(handle: u32, sequence: u16, data: &[u8]) -> Message {
+        segment(vec![
+            Value::StringVal(SEGMENT_CHUNK.to_string()),
+            Value::U32(handle),
+            Value::U16(sequence),
+            Value::Raw(data.to_vec()),
+        ])
+    }
+
+    fn end(handle: u32) -> Message {
+        segment(vec![
+            Value::StringVal(SEGMENT_END.to_string()),
+            Value::U32(handle),
+        ])
+    }
+
+    #[test]
+    fn test_network_trace_payload_classification() {
+        // only raw arguments are parsed as network trace slices
+        assert_eq!(
+            trace(NetworkTraceType::Ipc, vec![vec![1, 2], vec![3]]).payload,
+            PayloadContent::NetworkTrace(vec![
+                Bytes::from_static(&[1, 2]),
+                Bytes::from_static(&[3])
+            ])
+        );
+        // other arguments (e.g. of segments) would be lost as slices, so the
+        // payload is kept verbose
+        let message = chunk(1, 0, &[0xaa]);
+        match &message.payload {
+            PayloadContent::Verbose(arguments) => {
+                let values: Vec<&Value> = arguments.iter().map(|a| &a.value).collect();
+                assert_eq!(
+                    values,
+                    vec![
+                        &Value::StringVal(SEGMENT_CHUNK.to_string()),
+                        &Value::U32(1),
+                        &Value::U16(0),
+                        &Value::Raw(vec![0xaa]),
+                    ]
+                );
+            }
+            other => panic!("unexpected payload: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_segmented_trace_reassembly() {
+        let mut reassembler = SegmentReassembler::new();
+        let other = [0xaa; 20];
+        // chunks out of order, interleaved with a second stream
+        let messages = [
+            start(1, 24, 3),
+            start(2, 20, 2),
+            chunk(1, 1, &SOMEIP_NOTIFICATION[10..20]),
+            chunk(2, 0, &other[..10]),
+            chunk(1, 0, &SOMEIP_NOTIFICATION[..10]),
+            chunk(1, 2, &SOMEIP_NOTIFICATION[20..]),
+            trace(NetworkTraceType::Someip, vec![vec![1]]),
+        ];
+        for message in &messages {
+            assert!(is_segment(message) || message == &messages[6]);
+            assert_eq!(reassembler.push(message), Ok(None));
+        }
+        assert_eq!(reassembler.pending(), 2);
+
+        let complete = reassembler.push(&end(1)).expect("complete").expect("trace");
+        assert_eq!(
+            complete.payload,
+            PayloadContent::NetworkTrace(vec![
+                Bytes::from_static(&[0xff, 0x01]),
+                Bytes::from_static(SOMEIP_NOTIFICATION)
+            ])
+        );
+        let someip_messages = someip::decode(&complete).expect("decode").expect("someip");
+        assert_eq!(someip_messages[0].header.session_id, 0x2815);
+
+        assert_eq!(
+            reassembler.push(&end(2)),
+            Err(Error::Incomplete {
+                what: "segmented trace",
+                needed: 20,
+                available: 10,
+            })
+        );
+        assert_eq!(reassembler.pending(), 0);
+        assert!(reassembler.push(&chunk(3, 0, &other)).is_err());
+        reassembler.push(&start(4, 10, 1)).expect("start");
+        assert!(reassembler.push(&chunk(4, 1, &other)).is_err());
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn test_ipc_decode() {
+        let info = vec![0x0a, 0x00, 0x00, 0x02, 0x77, 0x2d, 0x11, 0x00, 0x00, 0x01];
+        let message = trace(
+            NetworkTraceType::Ipc,
+            vec![info, SOMEIP_NOTIFICATION.to_vec()],
+        );
+        let ipc_trace = ipc::decode(&message).expect("decode").expect("ipc trace");
+        assert_eq!(
+            ipc_trace.endpoint,
+            IpcEndpoint {
+                address: "10.0.0.2:30509".parse::<SocketAddrV4>().unwrap(),
+                protocol: TransportProtocol::Udp,
+                direction: Direction::Incoming,
+                instance_id: 1,
+            }
+        );
+        assert_eq!(ipc_trace.message, SOMEIP_NOTIFICATION);
+        let someip_message = ipc_trace.someip().expect("someip");
+        assert_eq!(someip_message.header.service_id, 0xf961);
+        assert_eq!(someip_message.payload.len(), 8);
+
+        let three_byte_instance = trace(
+            NetworkTraceType::Ipc,
+            vec![vec![0; 11], SOMEIP_NOTIFICATION.to_vec()],
+        );
+        assert!(matches!(
+            ipc::decode(&three_byte_instance),
+            Err(Error::Invalid { .. })
+        ));
+        let can = trace(NetworkTraceType::Can, vec![vec![0; 10], vec![]]);
+        assert_eq!(ipc::decode(&can), Ok(None));
+    }
+}
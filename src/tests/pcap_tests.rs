@@ -0,0 +1,174 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{dlt::*, parse::ParsedMessage, pcap::*, tests::TestMessage};
+
+    fn recorded(message: &Message, timestamp: DltTimeStamp) -> ParsedMessage {
+        let mut message = message.clone();
+        message.storage_header = Some(StorageHeader {
+            timestamp,
+            ecu_id: DltId::new("ECU1"),
+        });
+        ParsedMessage::Item(message)
+    }
+
+    /// Ethernet frame with an IPv4 packet of the given protocol
+    fn frame(protocol: u8, source_port: u16, segment_payload: &[u8]) -> Vec<u8> {
+        let transport = if protocol == 17 {
+            let mut udp = vec![];
+            udp.extend_from_slice(&source_port.to_be_bytes());
+            udp.extend_from_slice(&DLT_PORT.to_be_bytes());
+            udp.extend_from_slice(&(8 + segment_payload.len() as u16).to_be_bytes());
+            udp.extend_from_slice(&[0, 0]);
+            udp
+        } else {
+            let mut tcp = vec![];
+            tcp.extend_from_slice(&DLT_PORT.to_be_bytes());
+            tcp.extend_from_slice(&source_port.to_be_bytes());
+            tcp.extend_from_slice(&[0; 8]);
+            tcp.extend_from_slice(&[5 << 4, 0x18, 0, 0, 0, 0, 0, 0]);
+            tcp
+        };
+        let total_length = 20 + transport.len() + segment_payload.len();
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&(total_length as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+        frame.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 2]);
+        frame.extend_from_slice(&transport);
+        frame.extend_from_slice(segment_payload);
+        frame
+    }
+
+    fn legacy_pcap(frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut pcap = vec![];
+        pcap.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        pcap.extend_from_slice(&2u16.to_le_bytes());
+        pcap.extend_from_slice(&4u16.to_le_bytes());
+        pcap.extend_from_slice(&[0; 8]);
+        pcap.extend_from_slice(&65535u32.to_le_bytes());
+        pcap.extend_from_slice(&1u32.to_le_bytes());
+        for (seconds, frame) in frames {
+            pcap.extend_from_slice(&seconds.to_le_bytes());
+            pcap.extend_from_slice(&250u32.to_le_bytes());
+            pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(frame);
+        }
+        pcap
+    }
+
+    fn pcapng(frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let block = |block_type: u32, body: &[u8]| {
+            let length = 12 + body.len() as u32;
+            let mut block = vec![];
+            block.extend_from_slice(&block_type.to_le_bytes());
+            block.extend_from_slice(&length.to_le_bytes());
+            block.extend_from_slice(body);
+            block.extend_from_slice(&length.to_le_bytes());
+            block
+        };
+        let mut section = vec![];
+        section.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+        section.extend_from_slice(&1u16.to_le_bytes());
+        section.extend_from_slice(&0u16.to_le_bytes());
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut pcapng = block(0x0a0d_0d0a, &section);
+        let mut interface = vec![];
+        interface.extend_from_slice(&1u16.to_le_bytes());
+        interface.extend_from_slice(&0u16.to_le_bytes());
+        interface.extend_from_slice(&65535u32.to_le_bytes());
+        pcapng.extend(block(1, &interface));
+        for (seconds, frame) in frames {
+            let ticks = *seconds as u64 * 1_000_000 + 250;
+            let mut packet = vec![];
+            packet.extend_from_slice(&0u32.to_le_bytes());
+            packet.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+            packet.extend_from_slice(&(ticks as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(frame);
+            packet.resize(packet.len().div_ceil(4) * 4, 0);
+            pcapng.extend(block(6, &packet));
+        }
+        pcapng
+    }
+
+    #[test]
+    fn test_udp_and_tcp_messages() {
+        let messages: Vec<Message> = ["APP1", "APP2", "APP3", "APP4"]
+            .iter()
+            .map(|app_id| TestMessage::new(app_id).build())
+            .collect();
+        let udp_payload = [messages[0].as_bytes(), messages[1].as_bytes()].concat();
+        let tcp_payload = [messages[2].as_bytes(), messages[3].as_bytes()].concat();
+        let split = messages[2].as_bytes().len() + 3;
+        let frames = vec![
+            (1, frame(17, 50000, &udp_payload)),
+            (2, frame(6, 50001, &tcp_payload[..split])),
+            // not dlt traffic
+            (3, frame(17, 50000, &[])),
+            (4, frame(6, 50001, &tcp_payload[split..])),
+        ];
+        let expected = vec![
+            recorded(&messages[0], DltTimeStamp::from_us(1_000_250)),
+            recorded(&messages[1], DltTimeStamp::from_us(1_000_250)),
+            recorded(&messages[2], DltTimeStamp::from_us(2_000_250)),
+            recorded(&messages[3], DltTimeStamp::from_us(4_000_250)),
+        ];
+        for capture in [legacy_pcap(&frames), pcapng(&frames)] {
+            let reader = PcapMessageReader::new(capture.as_slice()).expect("reader");
+            let parsed: Vec<ParsedMessage> = reader.collect::<Result<_, _>>().expect("messages");
+            assert_eq!(parsed, expected);
+        }
+    }
+
+    #[test]
+    fn test_configured_ports() {
+        let capture = legacy_pcap(&[(
+            1,
+            frame(17, 50000, &TestMessage::new("APP1").build().as_bytes()),
+        )]);
+        let mut reader = PcapMessageReader::new(capture.as_slice()).expect("reader");
+        reader.set_ports([13400]);
+        assert!(reader.next_message().expect("end").is_none());
+    }
+
+    #[test]
+    fn test_tcp_segmentations() {
+        let messages: Vec<Message> = ["APP1", "APP2", "APP3"]
+            .iter()
+            .map(|app_id| TestMessage::new(app_id).build())
+            .collect();
+        let payload: Vec<u8> = messages.iter().flat_map(|m| m.as_bytes()).collect();
+        for size in [1, 3, 17, payload.len()] {
+            let frames: Vec<(u32, Vec<u8>)> = payload
+                .chunks(size)
+                .map(|segment| (1, frame(6, 50001, segment)))
+                .collect();
+            let capture = legacy_pcap(&frames);
+            let parsed: Vec<ParsedMessage> = PcapMessageReader::new(capture.as_slice())
+                .expect("reader")
+                .collect::<Result<_, _>>()
+                .expect("messages");
+            let expected: Vec<ParsedMessage> = messages
+                .iter()
+                .map(|message| recorded(message, DltTimeStamp::from_us(1_000_250)))
+                .collect();
+            assert_eq!(parsed, expected, "segment size {}", size);
+        }
+    }
+}
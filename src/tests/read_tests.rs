@@ -0,0 +1,622 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+mod tests {
+    use crate::{
+        dlt::*,
+        filtering::{DltFilterConfig, ProcessedDltFilterConfig},
+        parse::{DltParseError, ParsedInto, ParsedMessage},
+        progress::{CancellationToken, Progress},
+        read::*,
+        tests::TestMessage,
+    };
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+    };
+
+    fn log_message(app_id: &str, level: LogLevel, with_storage_header: bool) -> Message {
+        let message = TestMessage::new(app_id)
+            .endianness(Endianness::Little)
+            .level(level)
+            .verbose();
+        if with_storage_header {
+            message.stored(DltTimeStamp::from_ms(0), "ECU1")
+        } else {
+            message
+        }
+        .build()
+    }
+
+    fn messages(with_storage_header: bool) -> (Vec<Message>, Vec<u8>) {
+        let messages = vec![
+            log_message("APP1", LogLevel::Info, with_storage_header),
+            log_message("APP2", LogLevel::Debug, with_storage_header),
+            log_message("APP3", LogLevel::Info, with_storage_header),
+        ];
+        let bytes = messages.iter().flat_map(|m| m.as_bytes()).collect();
+        (messages, bytes)
+    }
+
+    #[test]
+    fn test_read_messages() {
+        for with_storage_header in [true, false] {
+            let (expected, bytes) = messages(with_storage_header);
+            let mut reader = DltMessageReader::new(Cursor::new(bytes), with_storage_header);
+            for message in expected {
+                assert_eq!(
+                    read_message(&mut reader, None).expect("read message"),
+                    Some(ParsedMessage::Item(message))
+                );
+            }
+            assert_eq!(read_message(&mut reader, None).expect("read eof"), None);
+        }
+    }
+
+    #[test]
+    fn test_read_incomplete_message() {
+        let (_, mut bytes) = messages(true);
+        bytes.truncate(bytes.len() - 3);
+        let mut reader = DltMessageReader::new(Cursor::new(bytes), true);
+        assert!(reader.next_message().expect("first").is_some());
+        assert!(reader.next_message().expect("second").is_some());
+        assert!(matches!(
+            reader.next_message(),
+            Err(DltParseError::IncompleteParse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_swap_filter_while_reading() {
+        let (_, bytes) = messages(true);
+        let mut reader = DltMessageReader::new(Cursor::new(bytes), true);
+        let handle = reader.filter_handle();
+
+        let only_app2: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .app_ids(["APP2"])
+            .build()
+            .expect("valid config")
+            .into();
+        handle.set(Some(only_app2));
+        assert!(matches!(
+            reader.next_message().expect("first"),
+            Some(ParsedMessage::FilteredOut(_))
+        ));
+        assert!(matches!(
+            reader.next_message().expect("second"),
+            Some(ParsedMessage::Item(_))
+        ));
+        handle.set(None);
+        assert!(matches!(
+            reader.next_message().expect("third"),
+            Some(ParsedMessage::Item(_))
+        ));
+        assert_eq!(reader.next_message().expect("eof"), None);
+    }
+
+    #[test]
+    fn test_progress_and_cancellation() {
+        let (_, bytes) = messages(true);
+        let total = bytes.len() as u64;
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        let reported = Arc::new(Mutex::new(vec![]));
+        let hook_reported = reported.clone();
+        reader.set_progress_hook(Some(total), move |p| hook_reported.lock().unwrap().push(p));
+        let token = CancellationToken::new();
+        reader.set_cancellation_token(token.clone());
+
+        assert!(reader.next_message().expect("first message").is_some());
+        let first = reader.bytes_processed();
+        assert!(first > 0 && first < total);
+        token.cancel();
+        assert_eq!(reader.next_message(), Err(DltParseError::Cancelled));
+        assert_eq!(reader.bytes_processed(), first);
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(
+            *reported,
+            vec![Progress {
+                processed: first,
+                total: Some(total)
+            }]
+        );
+        assert_eq!(reported[0].fraction(), Some(1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_skip_and_seek() {
+        for with_storage_header in [true, false] {
+            let (expected, bytes) = messages(with_storage_header);
+            let first_length = expected[0].as_bytes().len() as u64;
+            let mut reader = DltMessageReader::new(Cursor::new(bytes), with_storage_header);
+            assert_eq!(reader.skip_messages(2).expect("skip"), 2);
+            assert_eq!(reader.bytes_processed(), 2 * first_length);
+            assert_eq!(
+                reader.next_message().expect("third"),
+                Some(ParsedMessage::Item(expected[2].clone()))
+            );
+            assert_eq!(reader.skip_messages(5).expect("skip at end"), 0);
+
+            reader.seek_to_offset(first_length).expect("seek");
+            assert_eq!(
+                reader.next_message().expect("second"),
+                Some(ParsedMessage::Item(expected[1].clone()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_iterator() {
+        let (expected, bytes) = messages(true);
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        let app_ids: Vec<DltId> = reader
+            .messages()
+            .filter_map(|message| match message {
+                Ok(ParsedMessage::Item(m)) => m.extended_header.map(|h| h.application_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(app_ids, vec!["APP1", "APP2", "APP3"]);
+
+        let mut reader = DltMessageReader::new(&bytes[..bytes.len() - 3], true);
+        let mut read = vec![];
+        for message in &mut reader {
+            read.push(message);
+        }
+        assert_eq!(read.len(), 3);
+        assert_eq!(read[1], Ok(ParsedMessage::Item(expected[1].clone())));
+        assert!(matches!(
+            read[2],
+            Err(DltParseError::IncompleteParse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_multi_file_reader() {
+        let (expected, bytes) = messages(true);
+        let dir = std::env::temp_dir();
+        let first = dir.join(format!("dlt-core-multi-1-{}.dlt", std::process::id()));
+        let second = dir.join(format!("dlt-core-multi-2-{}.dlt", std::process::id()));
+        std::fs::write(&first, &bytes[..bytes.len() - 3]).expect("write first");
+        std::fs::write(&second, &bytes).expect("write second");
+
+        let mut reader = DltMultiFileReader::new([&first, &second], true);
+        let mut read = vec![];
+        loop {
+            match reader.next_message() {
+                Ok(Some(message)) => read.push(Ok(message)),
+                Ok(None) => break,
+                Err(e) => read.push(Err(e)),
+            }
+        }
+        std::fs::remove_file(&first).expect("remove first");
+        std::fs::remove_file(&second).expect("remove second");
+
+        assert_eq!(read.len(), 6);
+        assert!(matches!(
+            read[2],
+            Err(DltParseError::IncompleteParse { .. })
+        ));
+        let items: Vec<&ParsedMessage> = read.iter().filter_map(|m| m.as_ref().ok()).collect();
+        assert_eq!(items[1], &ParsedMessage::Item(expected[1].clone()));
+        assert_eq!(items[2], &ParsedMessage::Item(expected[0].clone()));
+        assert_eq!(items[4], &ParsedMessage::Item(expected[2].clone()));
+        assert_eq!(reader.current_file(), None);
+    }
+
+    #[test]
+    fn test_auto_detect_storage_header() {
+        for with_storage_header in [true, false] {
+            let (expected, bytes) = messages(with_storage_header);
+            let mut reader = DltMessageReader::auto_detect(bytes.as_slice()).expect("detect");
+            assert_eq!(reader.with_storage_header(), with_storage_header);
+            assert_eq!(
+                reader.next_message().expect("first"),
+                Some(ParsedMessage::Item(expected[0].clone()))
+            );
+        }
+        let mut empty = DltMessageReader::auto_detect(&[][..]).expect("detect");
+        assert!(!empty.with_storage_header());
+        assert_eq!(empty.next_message().expect("eof"), None);
+    }
+
+    #[test]
+    fn test_recover_from_corrupted_data() {
+        let (expected, _) = messages(true);
+        let raw: Vec<Vec<u8>> = expected.iter().map(|m| m.as_bytes()).collect();
+        let mut bogus_length = raw[2].clone();
+        let length = u16::from_be_bytes([bogus_length[18], bogus_length[19]]) + 4;
+        bogus_length[18..20].copy_from_slice(&length.to_be_bytes());
+        let bytes = [
+            b"abc".to_vec(),
+            raw[0].clone(),
+            b"JUNK!".to_vec(),
+            raw[1].clone(),
+            bogus_length,
+            raw[0].clone(),
+            b"DL".to_vec(),
+        ]
+        .concat();
+
+        let mut strict = DltMessageReader::new(bytes.as_slice(), true);
+        assert!(strict.next_message().is_err());
+
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_recovery(true);
+        let read: Vec<ParsedMessage> = reader
+            .messages()
+            .collect::<Result<_, _>>()
+            .expect("recovered messages");
+        assert_eq!(
+            read,
+            vec![
+                ParsedMessage::Item(expected[0].clone()),
+                ParsedMessage::Item(expected[1].clone()),
+                ParsedMessage::Item(expected[0].clone()),
+            ]
+        );
+        let (a, b, c) = (
+            raw[0].len() as u64,
+            raw[1].len() as u64,
+            raw[2].len() as u64,
+        );
+        assert_eq!(
+            reader.take_skipped_ranges(),
+            vec![
+                0..3,
+                3 + a..8 + a,
+                8 + a + b..8 + a + b + c,
+                8 + 2 * a + b + c..10 + 2 * a + b + c
+            ]
+        );
+        assert_eq!(reader.bytes_processed(), bytes.len() as u64);
+        assert!(reader.take_skipped_ranges().is_empty());
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let (expected, bytes) = messages(true);
+        let length = expected[0].as_bytes().len();
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_max_message_length(Some(length - 1));
+        assert_eq!(
+            reader.next_message(),
+            Err(DltParseError::MessageTooLarge { length, offset: 0 })
+        );
+        assert_eq!(
+            reader.next_message(),
+            Err(DltParseError::MessageTooLarge {
+                length,
+                offset: length as u64
+            })
+        );
+        reader.set_max_message_length(Some(length));
+        assert_eq!(
+            reader.next_message().expect("third"),
+            Some(ParsedMessage::Item(expected[2].clone()))
+        );
+
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_max_message_length(Some(length - 1));
+        reader.set_recovery(true);
+        assert_eq!(reader.next_message().expect("eof"), None);
+        assert_eq!(reader.take_skipped_ranges(), vec![0..bytes.len() as u64]);
+
+        // a corrupted length doesn't decide where reading continues
+        let mut corrupted = bytes.clone();
+        let overall_length = (length - STORAGE_HEADER_LENGTH as usize + 100) as u16;
+        corrupted[STORAGE_HEADER_LENGTH as usize + 2..STORAGE_HEADER_LENGTH as usize + 4]
+            .copy_from_slice(&overall_length.to_be_bytes());
+        let mut reader = DltMessageReader::new(corrupted.as_slice(), true);
+        reader.set_max_message_length(Some(length));
+        assert_eq!(
+            reader.next_message(),
+            Err(DltParseError::MessageTooLarge {
+                length: length + 100,
+                offset: 0
+            })
+        );
+        assert_eq!(reader.stats().bytes_skipped, length as u64);
+        assert_eq!(
+            reader.next_message().expect("second"),
+            Some(ParsedMessage::Item(expected[1].clone()))
+        );
+        assert_eq!(reader.bytes_processed(), 2 * length as u64);
+    }
+
+    #[test]
+    fn test_message_envelopes() {
+        let (expected, bytes) = messages(true);
+        let first = expected[0].as_bytes().len();
+        let bytes = [b"junk".to_vec(), bytes].concat();
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_recovery(true);
+        assert_eq!(
+            reader.next_message_envelope().expect("first"),
+            Some(MessageEnvelope {
+                offset: 4,
+                length: first,
+                message: ParsedMessage::Item(expected[0].clone()),
+            })
+        );
+        let second = read_message_envelope(&mut reader, None)
+            .expect("second")
+            .expect("not at end");
+        assert_eq!(second.offset, 4 + first as u64);
+        assert_eq!(
+            &bytes[second.offset as usize..second.offset as usize + second.length],
+            expected[1].as_bytes().as_slice()
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_read_compressed() {
+        use std::io::Write;
+
+        let (expected, bytes) = messages(true);
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(&bytes).expect("gzip");
+        let gzip = gzip.finish().expect("gzip");
+        let zstd = zstd::encode_all(bytes.as_slice(), 0).expect("zstd");
+
+        for source in [gzip, zstd, bytes] {
+            let mut reader =
+                DltMessageReader::decompressing(Cursor::new(source), true).expect("reader");
+            let read: Vec<ParsedMessage> = reader
+                .messages()
+                .collect::<Result<_, _>>()
+                .expect("messages");
+            assert_eq!(
+                read,
+                expected
+                    .iter()
+                    .cloned()
+                    .map(ParsedMessage::Item)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tail_growing_file() {
+        use std::{io::Write, time::Duration};
+
+        let (expected, bytes) = messages(true);
+        let path = std::env::temp_dir().join(format!("dlt-core-tail-{}.dlt", std::process::id()));
+        let mut file = std::fs::File::create(&path).expect("create");
+        let split = expected[0].as_bytes().len() + 5;
+        file.write_all(&bytes[..split]).expect("write start");
+
+        let mut reader = DltTailReader::open(&path, true).expect("open");
+        reader.set_poll_interval(Duration::from_millis(5));
+        let cancel = reader.cancellation_token();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            file.write_all(&bytes[split..]).expect("write rest");
+            file.flush().expect("flush");
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        for message in &expected {
+            assert_eq!(
+                reader.next_message().expect("message"),
+                ParsedMessage::Item(message.clone())
+            );
+        }
+        assert_eq!(reader.next_message(), Err(DltParseError::Cancelled));
+        writer.join().expect("writer");
+        std::fs::remove_file(&path).expect("remove");
+    }
+
+    #[test]
+    fn test_tail_large_source() {
+        let (expected, bytes) = messages(true);
+        let repetitions = 40_000;
+        let bytes = bytes.repeat(repetitions);
+        assert!(bytes.len() > 2 * 1024 * 1024);
+        let mut reader = DltTailReader::new(Cursor::new(bytes), true);
+        for i in 0..repetitions * expected.len() {
+            assert_eq!(
+                reader.next_message().expect("message"),
+                ParsedMessage::Item(expected[i % expected.len()].clone())
+            );
+        }
+        reader.cancellation_token().cancel();
+        assert_eq!(reader.next_message(), Err(DltParseError::Cancelled));
+    }
+
+    #[test]
+    fn test_reader_stats() {
+        let (expected, bytes) = messages(true);
+        let length = expected[0].as_bytes().len();
+        let bytes = [b"junk".to_vec(), bytes].concat();
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_recovery(true);
+        reader.set_filter(Some(
+            DltFilterConfig::builder()
+                .app_ids(["APP2"])
+                .build()
+                .expect("valid config")
+                .into(),
+        ));
+        assert_eq!(reader.messages().count(), 3);
+        assert_eq!(
+            reader.stats(),
+            &ReaderStats {
+                bytes_skipped: 4,
+                invalid_messages: 0,
+                filtered_messages: 2,
+            }
+        );
+
+        let (_, bytes) = messages(true);
+        let mut reader = DltMessageReader::new(bytes.as_slice(), true);
+        reader.set_max_message_length(Some(length - 1));
+        assert!(reader.next_message().is_err());
+        assert_eq!(reader.stats().bytes_skipped, length as u64);
+        assert_eq!(reader.stats().invalid_messages, 1);
+    }
+
+    #[test]
+    fn test_spawn_channel() {
+        let (expected, bytes) = messages(true);
+        let reader = DltMessageReader::new(std::io::Cursor::new(bytes), true);
+        let (receiver, handle) = reader.spawn_channel(1);
+        let received: Vec<ParsedMessage> = receiver
+            .iter()
+            .map(|result| result.expect("message"))
+            .collect();
+        assert_eq!(received.len(), expected.len());
+        let reader = handle.join().expect("reader thread");
+        assert_eq!(reader.stats(), &ReaderStats::default());
+    }
+
+    #[test]
+    fn test_push_parser_segmentations() {
+        for with_storage_header in [true, false] {
+            let (expected, bytes) = messages(with_storage_header);
+            let expected: Vec<ParsedMessage> =
+                expected.into_iter().map(ParsedMessage::Item).collect();
+            let parse = |pieces: Vec<&[u8]>| {
+                let mut parser = DltPushParser::new(with_storage_header);
+                let mut parsed = vec![];
+                for piece in pieces {
+                    parser.push(piece);
+                    while let Some(message) = parser.next_message(None).expect("message") {
+                        parsed.push(message);
+                    }
+                }
+                assert_eq!(parser.buffered(), 0);
+                parsed
+            };
+            // all messages coalesced into one piece
+            assert_eq!(parse(vec![&bytes]), expected);
+            // pieces of every size, down to single bytes
+            for size in 1..bytes.len() {
+                assert_eq!(
+                    parse(bytes.chunks(size).collect()),
+                    expected,
+                    "size {}",
+                    size
+                );
+            }
+            // every split of two pieces, including empty ones
+            for split in 0..=bytes.len() {
+                let (first, second) = bytes.split_at(split);
+                assert_eq!(parse(vec![first, &[], second]), expected, "split {}", split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_parser_out_of_sync() {
+        let (expected, bytes) = messages(false);
+        let mut parser = DltPushParser::new(false);
+        parser.push(&[0; 4]);
+        assert!(parser.next_message_slice().is_err());
+        assert_eq!(parser.buffered(), 0);
+        parser.push(&bytes);
+        assert_eq!(
+            parser.next_message(None).expect("message"),
+            Some(ParsedMessage::Item(expected[0].clone()))
+        );
+    }
+
+    #[test]
+    fn test_read_messages_into() {
+        let (expected, bytes) = messages(true);
+        let mut reader = DltMessageReader::new(Cursor::new(bytes), true);
+        reader.set_filter(Some(
+            DltFilterConfig::builder()
+                .app_ids(["APP1", "APP3"])
+                .build()
+                .expect("valid config")
+                .into(),
+        ));
+        let mut message = Message {
+            payload: PayloadContent::Verbose(Arguments::with_capacity(8)),
+            ..Message::default()
+        };
+        assert_eq!(
+            reader.next_message_into(&mut message).expect("first"),
+            Some(ParsedInto::Item)
+        );
+        assert_eq!(message, expected[0]);
+        // the allocation of the arguments is reused
+        assert!(matches!(&message.payload, PayloadContent::Verbose(args) if args.capacity() >= 8));
+        assert_eq!(
+            reader.next_message_into(&mut message).expect("second"),
+            Some(ParsedInto::FilteredOut(
+                expected[1].header.payload_length as usize
+            ))
+        );
+        assert!(matches!(&message.payload, PayloadContent::Verbose(args) if args.capacity() >= 8));
+        assert_eq!(
+            reader.next_message_into(&mut message).expect("third"),
+            Some(ParsedInto::Item)
+        );
+        assert_eq!(message, expected[2]);
+        assert_eq!(reader.next_message_into(&mut message).expect("eof"), None);
+        assert_eq!(reader.stats().filtered_messages, 1);
+    }
+
+    #[test]
+    fn test_invalid_message_length() {
+        let (expected, bytes) = messages(false);
+        let length = expected[0].as_bytes().len();
+        // a header that claims to be shorter than itself
+        let mut corrupted = bytes[..length].to_vec();
+        corrupted.extend_from_slice(&[0x21, 0x00, 0x00, 0x02]);
+        corrupted.extend_from_slice(&bytes[length..]);
+        let mut reader = DltMessageReader::new(corrupted.as_slice(), false);
+        let mut messages = reader.messages();
+        assert_eq!(
+            messages.next(),
+            Some(Ok(ParsedMessage::Item(expected[0].clone())))
+        );
+        assert!(matches!(
+            messages.next(),
+            Some(Err(DltParseError::Unrecoverable(_)))
+        ));
+        assert_eq!(messages.next(), None);
+        assert_eq!(reader.bytes_processed(), length as u64 + 4);
+        assert_eq!(reader.stats().invalid_messages, 1);
+    }
+
+    #[test]
+    fn test_read_owned_messages() {
+        use std::io::BufRead;
+
+        let (expected, bytes) = messages(true);
+        let mut source = std::io::BufReader::new(bytes.as_slice());
+        let mut read = vec![];
+        loop {
+            let buffer = source.fill_buf().expect("fill");
+            match read_owned_message(buffer, None, true).expect("message") {
+                Some((message, consumed)) => {
+                    source.consume(consumed);
+                    read.push(message);
+                }
+                None => break,
+            }
+        }
+        assert_eq!(
+            read,
+            expected
+                .into_iter()
+                .map(ParsedMessage::Item)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use crate::{parse::ParsedMessage, serial::*, tests::TestMessage};
+    use std::{
+        collections::VecDeque,
+        io::{self, Read},
+    };
+
+    /// Delivers the data in chunks, with a timeout before each chunk
+    struct SlowPort {
+        chunks: VecDeque<Vec<u8>>,
+        timed_out: bool,
+    }
+
+    impl Read for SlowPort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.timed_out = !self.timed_out;
+            if self.timed_out {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no data"));
+            }
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_serial_framing() {
+        let messages = [
+            TestMessage::new("APP1").build(),
+            TestMessage::new("APP2").build(),
+        ];
+        let bytes = [
+            b"noise DL".to_vec(),
+            DLS_PATTERN.to_vec(),
+            messages[0].as_bytes(),
+            // a serial header without a valid message
+            DLS_PATTERN.to_vec(),
+            vec![0; 4],
+            DLS_PATTERN.to_vec(),
+            messages[1].as_bytes(),
+            DLS_PATTERN.to_vec(),
+            messages[0].as_bytes()[..10].to_vec(),
+        ]
+        .concat();
+        let mut reader = DltSerialReader::new(SlowPort {
+            chunks: bytes.chunks(7).map(|chunk| chunk.to_vec()).collect(),
+            timed_out: false,
+        });
+        for message in &messages {
+            assert_eq!(
+                reader.next_message().expect("message"),
+                Some(ParsedMessage::Item(message.clone()))
+            );
+        }
+        assert_eq!(reader.next_message().expect("end"), None);
+        assert_eq!(reader.bytes_skipped(), 8 + 8 + 14);
+    }
+}
@@ -14,9 +14,49 @@
 #[cfg(test)]
 mod tests {
     use crate::{
-        dlt::LogLevel,
-        statistics::{LevelDistribution, StatisticInfo},
+        dlt::*,
+        fibex::{nonverbose_text, read_fibex_from_bytes, read_fibexes},
+        filtering::{DltFilterConfig, FilterVerdict, ProcessedDltFilterConfig},
+        parse::{dlt_message, dlt_message_headers, ParsedMessage},
+        read::DltMessageReader,
+        statistics::{
+            collect_statistics, collect_statistics_filtered,
+            common::{
+                MessageCount, MessageKey, NonVerboseIdCollector, SizeHistogram,
+                SizeHistogramCollector, SoftwareVersionCollector, TimeBucket, TimeBucketCollector,
+                TimestampRange, TimestampRangeCollector, TopMessagesCollector,
+            },
+            fibex::{FibexCoverage, FibexCoverageCollector, FibexSkeletonCollector},
+            LevelDistribution, MultiCollector, StatisticInfo,
+        },
+        tests::TestMessage,
     };
+    use std::{path::PathBuf, time::Duration};
+
+    fn message(app_id: &str, payload: PayloadContent) -> Vec<u8> {
+        message_at(app_id, payload, 0, None)
+    }
+
+    fn message_at(
+        app_id: &str,
+        payload: PayloadContent,
+        storage_ms: u64,
+        timestamp: Option<u32>,
+    ) -> Vec<u8> {
+        let message_type = match payload {
+            PayloadContent::ControlMsg(_, _) => MessageType::Control(ControlType::Request),
+            _ => MessageType::Log(LogLevel::Info),
+        };
+        TestMessage::new(app_id)
+            .context_id("CTX1")
+            .endianness(Endianness::Little)
+            .timestamp(timestamp)
+            .message_type(message_type)
+            .payload(payload)
+            .stored(DltTimeStamp::from_ms(storage_ms), "ECU1")
+            .build()
+            .as_bytes()
+    }
 
     fn get_stat_entities() -> Vec<(String, LevelDistribution)> {
         vec![
@@ -88,4 +128,566 @@ mod tests {
         assert_eq!(stat_a.ecu_ids[3].1.log_warning, 2);
         assert!(stat_a.contained_non_verbose);
     }
+
+    #[test]
+    fn test_fibex_coverage() {
+        let fibex = read_fibexes(vec![
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dlt-messages.xml")
+        ])
+        .expect("can't parse fibex");
+        let trace: Vec<u8> = [
+            message("DR", PayloadContent::NonVerbose(65, vec![0; 24].into())),
+            message("DR", PayloadContent::NonVerbose(65, vec![0; 24].into())),
+            // frame exists, but for another application
+            message("XX", PayloadContent::NonVerbose(64, vec![0; 16].into())),
+            message("DR", PayloadContent::NonVerbose(66, vec![].into())),
+            message("DR", PayloadContent::Verbose(Arguments::new())),
+            message(
+                "DR",
+                PayloadContent::ControlMsg(ControlType::Request, vec![].into()),
+            ),
+        ]
+        .concat();
+
+        let mut collector = FibexCoverageCollector::new(&fibex);
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(
+            collector.coverage(),
+            FibexCoverage {
+                seen: vec![(65, 2)],
+                unmatched: vec![(64, 1), (66, 1)],
+                unused: vec![64],
+            }
+        );
+    }
+
+    #[test]
+    fn test_fibex_skeleton() {
+        let fixed = message(
+            "APP",
+            PayloadContent::NonVerbose(65, vec![1, 0, 0, 0, 2, 0, 3].into()),
+        );
+        let trace: Vec<u8> = [
+            fixed.clone(),
+            fixed.clone(),
+            message("APP", PayloadContent::NonVerbose(66, vec![1, 2].into())),
+            message(
+                "APP",
+                PayloadContent::NonVerbose(66, vec![1, 2, 3, 4, 5].into()),
+            ),
+            message("APP", PayloadContent::Verbose(Arguments::new())),
+        ]
+        .concat();
+
+        let mut collector = FibexSkeletonCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        let frames: Vec<_> = collector.frames().collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].count, 2);
+        assert_eq!(
+            frames[0].signal_refs(),
+            vec!["S_UINT32", "S_UINT16", "S_UINT8"]
+        );
+        assert_eq!((frames[1].min_length, frames[1].max_length), (2, 5));
+        assert!(frames[1].signal_refs().is_empty());
+
+        let fibex = read_fibex_from_bytes(collector.fibex_xml().as_bytes()).expect("valid fibex");
+        assert_eq!(fibex.frame_ids(), vec![65, 66]);
+        let frame = fibex.frame(65).expect("frame");
+        assert_eq!(frame.application_id.as_deref(), Some("APP"));
+        assert_eq!(frame.log_level(), Some(LogLevel::Info));
+        assert_eq!(frame.byte_length, 7);
+        let message = match dlt_message(&fixed, None, true) {
+            Ok((_, ParsedMessage::Item(message))) => message,
+            _ => panic!("could not parse message"),
+        };
+        assert_eq!(
+            nonverbose_text(&fibex, &message),
+            Some("message 65: 1 2 3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_timestamp_range() {
+        let trace: Vec<u8> = [
+            message_at(
+                "APP",
+                PayloadContent::Verbose(Arguments::new()),
+                2_000,
+                Some(300),
+            ),
+            message_at(
+                "APP",
+                PayloadContent::Verbose(Arguments::new()),
+                1_500,
+                Some(100),
+            ),
+            message_at(
+                "APP",
+                PayloadContent::Verbose(Arguments::new()),
+                4_250,
+                None,
+            ),
+        ]
+        .concat();
+        let mut collector = TimestampRangeCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        let range = collector.range();
+        assert_eq!(
+            range,
+            &TimestampRange {
+                earliest_storage_timestamp: Some(DltTimeStamp::from_ms(1_500)),
+                latest_storage_timestamp: Some(DltTimeStamp::from_ms(4_250)),
+                earliest_header_timestamp: Some(100),
+                latest_header_timestamp: Some(300),
+                messages: 3,
+                without_storage_timestamp: 0,
+                without_header_timestamp: 1,
+            }
+        );
+        assert_eq!(range.duration(), Some(Duration::from_millis(2_750)));
+        assert_eq!(range.header_duration(), Some(Duration::from_millis(20)));
+
+        let mut collector = TimestampRangeCollector::new();
+        let mut reader = DltMessageReader::new(&[][..], true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(collector.range().duration(), None);
+    }
+
+    #[test]
+    fn test_size_histogram() {
+        let trace: Vec<u8> = [
+            message("APP", PayloadContent::NonVerbose(1, vec![].into())),
+            message("APP", PayloadContent::NonVerbose(1, vec![0; 4].into())),
+            message("APP", PayloadContent::NonVerbose(1, vec![0; 1000].into())),
+        ]
+        .concat();
+        let mut collector = SizeHistogramCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        let payload_sizes = collector.payload_sizes();
+        assert_eq!(payload_sizes.count, 3);
+        assert_eq!(payload_sizes.min, Some(4));
+        assert_eq!(payload_sizes.max, Some(1004));
+        assert_eq!(payload_sizes.total, 1016);
+        assert_eq!(payload_sizes.average(), Some(1016.0 / 3.0));
+        assert_eq!(payload_sizes.buckets[3], 1);
+        assert_eq!(payload_sizes.buckets[4], 1);
+        assert_eq!(payload_sizes.buckets[10], 1);
+        assert_eq!(payload_sizes.buckets.iter().sum::<usize>(), 3);
+        // standard header (4), ecu id (4), extended header (10)
+        assert_eq!(collector.message_sizes().min, Some(22));
+
+        assert_eq!(SizeHistogram::bucket_range(0), 0..1);
+        assert_eq!(SizeHistogram::bucket_range(1), 1..2);
+        assert_eq!(SizeHistogram::bucket_range(10), 512..1024);
+        assert_eq!(SizeHistogram::default().average(), None);
+    }
+
+    #[test]
+    fn test_time_buckets() {
+        let verbose =
+            |ms: u64| message_at("APP", PayloadContent::Verbose(Arguments::new()), ms, None);
+        let trace: Vec<u8> = [
+            verbose(1_000),
+            verbose(1_999),
+            verbose(2_500),
+            verbose(5_000),
+        ]
+        .concat();
+        let mut collector = TimeBucketCollector::new(Duration::from_secs(1));
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        let size = verbose(0).len() as u64 - 16;
+        assert_eq!(
+            collector.buckets(),
+            vec![
+                TimeBucket {
+                    start: DltTimeStamp::from_ms(1_000),
+                    messages: 2,
+                    bytes: 2 * size,
+                },
+                TimeBucket {
+                    start: DltTimeStamp::from_ms(2_000),
+                    messages: 1,
+                    bytes: size,
+                },
+                TimeBucket {
+                    start: DltTimeStamp::from_ms(5_000),
+                    messages: 1,
+                    bytes: size,
+                },
+            ]
+        );
+        assert_eq!(
+            collector.silent_gaps(),
+            vec![DltTimeStamp::from_ms(3_000)..DltTimeStamp::from_ms(5_000)]
+        );
+        assert_eq!(collector.without_timestamp(), 0);
+    }
+
+    #[test]
+    fn test_nonverbose_id_counts() {
+        let trace: Vec<u8> = [
+            message("APP", PayloadContent::NonVerbose(7, vec![1].into())),
+            message(
+                "APP",
+                PayloadContent::NonVerbose(0x0102_0304, vec![].into()),
+            ),
+            message(
+                "APP",
+                PayloadContent::NonVerbose(0x0102_0304, vec![2, 3].into()),
+            ),
+            message("APP", PayloadContent::NonVerbose(5, vec![].into())),
+            message("APP", PayloadContent::Verbose(Arguments::new())),
+        ]
+        .concat();
+        let mut collector = NonVerboseIdCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(collector.counts(), vec![(0x0102_0304, 2), (5, 1), (7, 1)]);
+        assert_eq!(collector.count(7), 1);
+        assert_eq!(collector.count(8), 0);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_collect_statistics_async() {
+        use crate::{statistics::collect_statistics_async, stream::DltStreamReader};
+
+        let trace: Vec<u8> = [
+            message("APP", PayloadContent::NonVerbose(7, vec![1].into())),
+            message("APP", PayloadContent::NonVerbose(7, vec![].into())),
+            message("APP", PayloadContent::Verbose(Arguments::new())),
+        ]
+        .concat();
+        let mut collector = NonVerboseIdCollector::new();
+        let mut reader = DltStreamReader::new(trace.as_slice(), true);
+        futures::executor::block_on(collect_statistics_async(&mut reader, &mut collector))
+            .expect("collect statistics");
+        assert_eq!(collector.counts(), vec![(7, 2)]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_collect_statistics_parallel() {
+        use crate::statistics::{chunk_starts, collect_dlt_stats, collect_statistics_parallel};
+
+        let trace: Vec<u8> = (0..1000u32)
+            .map(|i| {
+                // payloads that contain the storage header pattern
+                let payload =
+                    PayloadContent::NonVerbose(i, b"DLT\x01DLT\x01".repeat(i as usize % 7).into());
+                message(["APP1", "APP2", "APP3"][i as usize % 3], payload)
+            })
+            .collect::<Vec<Vec<u8>>>()
+            .concat();
+        let path =
+            std::env::temp_dir().join(format!("dlt-core-parallel-{}.dlt", std::process::id()));
+        std::fs::write(&path, &trace).expect("write trace");
+
+        let starts = chunk_starts(&path, trace.len() as u64, 4).expect("chunks");
+        assert_eq!(starts.len(), 4);
+        assert!(starts
+            .iter()
+            .all(|start| trace[*start as usize..].starts_with(b"DLT\x01")));
+
+        let sorted = |mut info: StatisticInfo| {
+            info.app_ids.sort_by(|a, b| a.0.cmp(&b.0));
+            format!("{:?}", info.app_ids)
+        };
+        let expected = sorted(collect_dlt_stats(&path).expect("stats"));
+        let parallel = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .expect("thread pool")
+            .install(|| collect_statistics_parallel(&path))
+            .expect("parallel stats");
+        std::fs::remove_file(&path).expect("remove trace");
+        assert!(parallel.contained_non_verbose);
+        assert_eq!(sorted(parallel), expected);
+    }
+
+    #[test]
+    fn test_multi_collector() {
+        let trace: Vec<u8> = [
+            message_at(
+                "APP",
+                PayloadContent::NonVerbose(7, vec![1].into()),
+                1000,
+                Some(10),
+            ),
+            message_at(
+                "APP",
+                PayloadContent::NonVerbose(7, vec![].into()),
+                3000,
+                Some(30),
+            ),
+            message_at(
+                "APP",
+                PayloadContent::NonVerbose(5, vec![].into()),
+                2000,
+                Some(20),
+            ),
+        ]
+        .concat();
+        let mut ids = NonVerboseIdCollector::new();
+        let mut range = TimestampRangeCollector::new();
+        {
+            let mut collector = MultiCollector::new();
+            collector.add(&mut ids).add(&mut range);
+            assert_eq!(collector.len(), 2);
+            let mut reader = DltMessageReader::new(trace.as_slice(), true);
+            collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        }
+        assert_eq!(ids.counts(), vec![(7, 2), (5, 1)]);
+
+        let mut single = TimestampRangeCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut single).expect("collect statistics");
+        assert_eq!(range.range(), single.range());
+        assert!(range.range().duration().is_some());
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let info = StatisticInfo {
+            app_ids: vec![(
+                "A,B".to_string(),
+                LevelDistribution::new(Some(LogLevel::Info)),
+            )],
+            context_ids: vec![],
+            ecu_ids: vec![("ECU1".to_string(), LevelDistribution::new(None))],
+            contained_non_verbose: false,
+        };
+        assert_eq!(
+            info.to_csv(),
+            "kind,id,non_log,fatal,error,warning,info,debug,verbose,invalid\n\
+             app,\"A,B\",0,0,0,0,1,0,0,0\n\
+             ecu,ECU1,1,0,0,0,0,0,0,0\n"
+        );
+
+        let mut histogram = SizeHistogram::default();
+        histogram.add(0);
+        histogram.add(3);
+        assert_eq!(histogram.to_csv(), "from,to,count\n0,1,1\n1,2,0\n2,4,1\n");
+
+        let trace: Vec<u8> = [
+            message("APP", PayloadContent::NonVerbose(7, vec![].into())),
+            message("APP", PayloadContent::NonVerbose(7, vec![].into())),
+            message("APP", PayloadContent::NonVerbose(5, vec![].into())),
+        ]
+        .concat();
+        let mut collector = NonVerboseIdCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(collector.to_csv(), "message_id,count\n7,2\n5,1\n");
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_export_json() {
+        let info = StatisticInfo {
+            app_ids: vec![(
+                "APP".to_string(),
+                LevelDistribution::new(Some(LogLevel::Warn)),
+            )],
+            context_ids: vec![],
+            ecu_ids: vec![],
+            contained_non_verbose: true,
+        };
+        let json = info.to_json().expect("json");
+        let parsed: StatisticInfo = serde_json::from_str(&json).expect("parse json");
+        assert_eq!(parsed.app_ids[0].0, "APP");
+        assert_eq!(parsed.app_ids[0].1.log_warning, 1);
+        assert!(parsed.contained_non_verbose);
+
+        let coverage = FibexCoverage {
+            seen: vec![(1, 2)],
+            unmatched: vec![],
+            unused: vec![3],
+        };
+        assert_eq!(
+            coverage.to_json().expect("json"),
+            r#"{"seen":[[1,2]],"unmatched":[],"unused":[3]}"#
+        );
+        assert_eq!(
+            coverage.to_csv(),
+            "message_id,status,count\n1,seen,2\n3,unused,0\n"
+        );
+    }
+
+    #[test]
+    fn test_sampling() {
+        use crate::statistics::{collect_statistics_every_nth, collect_statistics_sampled};
+
+        let trace: Vec<u8> = (0..100u32)
+            .map(|i| message("APP", PayloadContent::NonVerbose(i, vec![0; 12].into())))
+            .collect::<Vec<Vec<u8>>>()
+            .concat();
+        let message_length = trace.len() as u64 / 100;
+
+        let mut collector = NonVerboseIdCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics_every_nth(&mut reader, &mut collector, 10).expect("every nth");
+        let mut ids: Vec<u32> = collector.counts().into_iter().map(|(id, _)| id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..100).step_by(10).collect::<Vec<u32>>());
+
+        let path =
+            std::env::temp_dir().join(format!("dlt-core-sampled-{}.dlt", std::process::id()));
+        std::fs::write(&path, &trace).expect("write trace");
+        let mut collector = NonVerboseIdCollector::new();
+        let sampled = collect_statistics_sampled(&path, &mut collector, 4, 2 * message_length);
+        std::fs::remove_file(&path).expect("remove trace");
+        sampled.expect("sampled");
+        let mut ids: Vec<u32> = collector.counts().into_iter().map(|(id, _)| id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![0, 1, 25, 26, 50, 51, 75, 76]);
+    }
+
+    #[test]
+    fn test_software_versions() {
+        let response = |ecu_id: &str, status: u8, version: &str| {
+            let mut payload = vec![status];
+            payload.extend_from_slice(&(version.len() as u32 + 1).to_be_bytes());
+            payload.extend_from_slice(version.as_bytes());
+            payload.push(0);
+            Message::new(
+                MessageConfig {
+                    version: 1,
+                    counter: 0,
+                    endianness: Endianness::Big,
+                    ecu_id: Some(ecu_id.to_string()),
+                    session_id: None,
+                    timestamp: None,
+                    payload: PayloadContent::NonVerbose(0x13, payload.into()),
+                    extended_header_info: Some(ExtendedHeaderConfig {
+                        message_type: MessageType::Control(ControlType::Response),
+                        app_id: "DA1".to_string(),
+                        context_id: "DC1".to_string(),
+                    }),
+                },
+                None,
+            )
+            .as_bytes()
+        };
+        let trace: Vec<u8> = [
+            response("ECU1", 0, "1.2.3"),
+            response("ECU2", 0, "2.0, beta"),
+            response("ECU1", 0, "1.2.3"),
+            // not supported
+            response("ECU1", 2, "0.0.0"),
+        ]
+        .concat();
+        let mut collector = SoftwareVersionCollector::new();
+        let mut reader = DltMessageReader::new(trace.as_slice(), false);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(collector.versions_of("ECU1"), vec!["1.2.3"]);
+        assert_eq!(collector.versions_of("ECU2"), vec!["2.0, beta"]);
+        assert_eq!(collector.versions()["ECU1"]["1.2.3"], 2);
+        assert_eq!(
+            collector.to_csv(),
+            "ecu,version,count\nECU1,1.2.3,2\nECU2,\"2.0, beta\",1\n"
+        );
+    }
+
+    #[test]
+    fn test_top_messages() {
+        let text = |text: &str| {
+            PayloadContent::Verbose(
+                [Argument {
+                    type_info: TypeInfo {
+                        kind: TypeInfoKind::StringType,
+                        coding: StringCoding::UTF8,
+                        has_variable_info: false,
+                        has_trace_info: false,
+                    },
+                    name: None,
+                    unit: None,
+                    fixed_point: None,
+                    value: Value::StringVal(text.to_string()),
+                }]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let trace: Vec<u8> = [
+            message("APP1", text("spam")),
+            message("APP1", PayloadContent::NonVerbose(9, vec![].into())),
+            message("APP1", text("spam")),
+            message("APP2", text("spam")),
+            message("APP1", text("spam")),
+            message("APP2", text("once")),
+            message("APP1", PayloadContent::NonVerbose(9, vec![1].into())),
+        ]
+        .concat();
+        let mut collector = TopMessagesCollector::new(2);
+        let mut reader = DltMessageReader::new(trace.as_slice(), true);
+        collect_statistics(&mut reader, &mut collector).expect("collect statistics");
+        assert_eq!(
+            collector.top(),
+            vec![
+                MessageCount {
+                    application_id: Some(DltId::new("APP1")),
+                    context_id: Some(DltId::new("CTX1")),
+                    message: MessageKey::Text("spam".to_string()),
+                    count: 3,
+                },
+                MessageCount {
+                    application_id: Some(DltId::new("APP1")),
+                    context_id: Some(DltId::new("CTX1")),
+                    message: MessageKey::NonVerbose(9),
+                    count: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            collector.to_csv(),
+            "app,context,message_id,text,count\nAPP1,CTX1,,spam,3\nAPP1,CTX1,9,,2\n"
+        );
+    }
+
+    #[test]
+    fn test_collect_statistics_filtered() {
+        let trace: Vec<u8> = [
+            message("APP1", PayloadContent::NonVerbose(1, vec![].into())),
+            message("APP2", PayloadContent::NonVerbose(2, vec![].into())),
+            message("APP1", PayloadContent::NonVerbose(3, vec![].into())),
+        ]
+        .concat();
+        let collect = |filter: &ProcessedDltFilterConfig| {
+            let mut collector = NonVerboseIdCollector::new();
+            let mut reader = DltMessageReader::new(trace.as_slice(), true);
+            collect_statistics_filtered(&mut reader, filter, &mut collector)
+                .expect("collect statistics");
+            collector.counts()
+        };
+
+        let by_app: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .build()
+            .expect("valid filter")
+            .into();
+        assert_eq!(collect(&by_app), vec![(1, 1), (3, 1)]);
+        let (rest, headers) =
+            dlt_message_headers(&trace, Some(&by_app), true).expect("valid message");
+        assert_eq!(headers.verdict, FilterVerdict::Matches);
+        let (_, headers) = dlt_message_headers(rest, Some(&by_app), true).expect("valid message");
+        assert_eq!(headers.verdict, FilterVerdict::FilteredOut);
+
+        let by_message_id: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .message_ids([3])
+            .build()
+            .expect("valid filter")
+            .into();
+        assert_eq!(collect(&by_message_id), vec![(3, 1)]);
+        let (_, headers) =
+            dlt_message_headers(&trace, Some(&by_message_id), true).expect("valid message");
+        assert_eq!(headers.verdict, FilterVerdict::NeedsPayload);
+    }
 }
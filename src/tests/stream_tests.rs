@@ -0,0 +1,263 @@
+// Copyright 2021 by Accenture ESR
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+mod tests {
+    use crate::{
+        dlt::*,
+        parse::{DltParseError, ParsedMessage},
+        stream::*,
+        tests::TestMessage,
+    };
+    use futures::{executor::block_on, StreamExt, TryStreamExt};
+
+    fn messages(with_storage_header: bool) -> (Vec<Message>, Vec<u8>) {
+        let messages: Vec<Message> = ["APP1", "APP2"]
+            .iter()
+            .map(|app_id| {
+                let message = TestMessage::new(app_id).verbose();
+                if with_storage_header {
+                    message.stored(DltTimeStamp::from_ms(0), "ECU1")
+                } else {
+                    message
+                }
+                .build()
+            })
+            .collect();
+        let bytes = messages.iter().flat_map(|m| m.as_bytes()).collect();
+        (messages, bytes)
+    }
+
+    #[test]
+    fn test_read_messages() {
+        for with_storage_header in [true, false] {
+            let (expected, bytes) = messages(with_storage_header);
+            let mut reader = DltStreamReader::new(bytes.as_slice(), with_storage_header);
+            block_on(async {
+                for message in expected {
+                    assert_eq!(
+                        read_message(&mut reader, None).await.expect("read message"),
+                        Some(ParsedMessage::Item(message))
+                    );
+                }
+                assert_eq!(read_message(&mut reader, None).await.expect("eof"), None);
+            });
+        }
+    }
+
+    #[test]
+    fn test_read_incomplete_message() {
+        let (_, mut bytes) = messages(true);
+        bytes.truncate(bytes.len() - 3);
+        let mut reader = DltStreamReader::new(bytes.as_slice(), true);
+        block_on(async {
+            assert!(read_message(&mut reader, None)
+                .await
+                .expect("first")
+                .is_some());
+            assert!(matches!(
+                read_message(&mut reader, None).await,
+                Err(DltParseError::IncompleteParse { .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn test_skip_and_seek() {
+        let (expected, bytes) = messages(true);
+        let first_length = expected[0].as_bytes().len() as u64;
+        let mut reader = DltStreamReader::new(futures::io::Cursor::new(bytes), true);
+        block_on(async {
+            assert_eq!(reader.skip_messages(3).await.expect("skip"), 2);
+            assert_eq!(read_message(&mut reader, None).await.expect("eof"), None);
+            reader.seek_to_offset(first_length).await.expect("seek");
+            assert_eq!(
+                read_message(&mut reader, None).await.expect("second"),
+                Some(ParsedMessage::Item(expected[1].clone()))
+            );
+        });
+    }
+
+    #[test]
+    fn test_messages_stream() {
+        let (expected, bytes) = messages(false);
+        let mut reader = DltStreamReader::new(bytes.as_slice(), false);
+        let parsed: Vec<ParsedMessage> =
+            block_on(reader.messages().try_collect()).expect("messages");
+        assert_eq!(
+            parsed,
+            expected
+                .into_iter()
+                .map(ParsedMessage::Item)
+                .collect::<Vec<_>>()
+        );
+
+        let (_, mut bytes) = messages(true);
+        bytes.truncate(bytes.len() - 3);
+        let mut reader = DltStreamReader::new(bytes.as_slice(), true);
+        block_on(async {
+            let mut stream = reader.messages();
+            fn assert_send<T: Send>(_: &T) {}
+            assert_send(&stream);
+            assert!(matches!(stream.next().await, Some(Ok(_))));
+            assert!(matches!(
+                stream.next().await,
+                Some(Err(DltParseError::IncompleteParse { .. }))
+            ));
+            assert!(stream.next().await.is_none());
+        });
+
+        // an invalid length ends the stream
+        let (_, bytes) = messages(false);
+        let corrupted = [&[0x21, 0x00, 0x00, 0x02], bytes.as_slice()].concat();
+        let mut reader = DltStreamReader::new(corrupted.as_slice(), false);
+        block_on(async {
+            let mut stream = reader.messages();
+            assert!(matches!(
+                stream.next().await,
+                Some(Err(DltParseError::Unrecoverable(_)))
+            ));
+            assert!(stream.next().await.is_none());
+        });
+        assert_eq!(reader.stats().invalid_messages, 1);
+    }
+
+    #[test]
+    fn test_into_channel() {
+        let (expected, bytes) = messages(true);
+        let reader = DltStreamReader::new(futures::io::Cursor::new(bytes), true);
+        let (receiver, producer) = reader.into_channel(1);
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&producer);
+        let (_, received) = block_on(futures::future::join(
+            producer,
+            receiver.try_collect::<Vec<ParsedMessage>>(),
+        ));
+        assert_eq!(
+            received.expect("messages"),
+            expected
+                .iter()
+                .cloned()
+                .map(ParsedMessage::Item)
+                .collect::<Vec<_>>()
+        );
+
+        // the producer uses the filter of the reader
+        let (_, bytes) = messages(true);
+        let reader = DltStreamReader::new(futures::io::Cursor::new(bytes), true);
+        let only_app1 = crate::filtering::DltFilterConfig::builder()
+            .app_ids(["APP1"])
+            .build()
+            .expect("valid config")
+            .into();
+        reader.set_filter(Some(only_app1));
+        let (receiver, producer) = reader.into_channel(1);
+        let (reader, received) = block_on(futures::future::join(
+            producer,
+            receiver.try_collect::<Vec<ParsedMessage>>(),
+        ));
+        let received = received.expect("messages");
+        assert_eq!(received[0], ParsedMessage::Item(expected[0].clone()));
+        assert!(matches!(received[1], ParsedMessage::FilteredOut(_)));
+        assert_eq!(reader.stats().filtered_messages, 1);
+    }
+
+    #[test]
+    fn test_tail_growing_file() {
+        use std::io::Write;
+
+        let (expected, bytes) = messages(true);
+        let path =
+            std::env::temp_dir().join(format!("dlt-core-stream-tail-{}.dlt", std::process::id()));
+        let mut file = std::fs::File::create(&path).expect("create");
+        let split = expected[0].as_bytes().len() + 5;
+        file.write_all(&bytes[..split]).expect("write start");
+
+        let source = futures::io::AllowStdIo::new(std::fs::File::open(&path).expect("open"));
+        let (notify, notifications) = futures::channel::mpsc::unbounded();
+        let mut reader = DltStreamTailReader::new(source, true, notifications);
+        let writer = std::thread::spawn(move || {
+            file.write_all(&bytes[split..]).expect("write rest");
+            file.flush().expect("flush");
+            notify.unbounded_send(()).expect("notify");
+        });
+        block_on(async {
+            for message in &expected {
+                assert_eq!(
+                    reader.next_message().await.expect("message"),
+                    Some(ParsedMessage::Item(message.clone()))
+                );
+            }
+            assert_eq!(reader.next_message().await.expect("end"), None);
+        });
+        writer.join().expect("writer");
+        std::fs::remove_file(&path).expect("remove");
+    }
+
+    #[test]
+    fn test_tail_large_source() {
+        let (expected, bytes) = messages(true);
+        let repetitions = 40_000;
+        let bytes = bytes.repeat(repetitions);
+        assert!(bytes.len() > 2 * 1024 * 1024);
+        let mut reader =
+            DltStreamTailReader::new(bytes.as_slice(), true, futures::stream::empty::<()>());
+        block_on(async {
+            for i in 0..repetitions * expected.len() {
+                assert_eq!(
+                    reader.next_message().await.expect("message"),
+                    Some(ParsedMessage::Item(expected[i % expected.len()].clone()))
+                );
+            }
+            assert_eq!(reader.next_message().await.expect("end"), None);
+        });
+    }
+
+    #[test]
+    fn test_swap_filter_while_streaming() {
+        use crate::filtering::{DltFilterConfig, ProcessedDltFilterConfig};
+        let (_, bytes) = messages(true);
+        let bytes = [bytes.clone(), bytes].concat();
+        let mut reader = DltStreamReader::new(bytes.as_slice(), true);
+        let handle = reader.filter_handle();
+
+        let only_app2: ProcessedDltFilterConfig = DltFilterConfig::builder()
+            .app_ids(["APP2"])
+            .build()
+            .expect("valid config")
+            .into();
+        reader.set_filter(Some(only_app2));
+        block_on(async {
+            assert!(matches!(
+                reader.next_message().await.expect("first"),
+                Some(ParsedMessage::FilteredOut(_))
+            ));
+            assert!(matches!(
+                reader.next_message().await.expect("second"),
+                Some(ParsedMessage::Item(_))
+            ));
+            // the stream of messages uses the current filter as well
+            let mut stream = reader.messages();
+            assert!(matches!(
+                stream.next().await,
+                Some(Ok(ParsedMessage::FilteredOut(_)))
+            ));
+            handle.set(None);
+            assert!(matches!(
+                stream.next().await,
+                Some(Ok(ParsedMessage::Item(_)))
+            ));
+            assert!(stream.next().await.is_none());
+        });
+        assert_eq!(reader.stats().filtered_messages, 2);
+    }
+}